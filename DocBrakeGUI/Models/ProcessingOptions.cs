@@ -97,7 +97,7 @@ namespace DocBrake.Models
         public int BpgChromaFormat
         {
             get => _bpgChromaFormat;
-            set => SetProperty(ref _bpgChromaFormat, Math.Clamp(value, 0, 2));
+            set => SetProperty(ref _bpgChromaFormat, Math.Clamp(value, -1, 2)); // -1 = auto per image
         }
 
         public int BpgEncoderType
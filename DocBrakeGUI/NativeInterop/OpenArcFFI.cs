@@ -31,6 +31,7 @@ namespace DocBrake.NativeInterop
             public bool EnableDedup;         // Enable file deduplication
             [MarshalAs(UnmanagedType.I1)]
             public bool SkipAlreadyCompressedVideos; // Skip re-encoding already compressed videos
+            public int MaxConcurrentVideos;  // Simultaneous video encodes (<= 0 = default 2)
         }
 
         [StructLayout(LayoutKind.Sequential)]
@@ -73,21 +74,70 @@ namespace DocBrake.NativeInterop
         [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
         public delegate void ProgressCallback(ProgressInfo progress);
 
-        // Archive creation
+        // Write a NUL-terminated password into buffer (bufferLen bytes) and return 1, or 0 to cancel
+        [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+        public delegate int PasswordCallback(
+            [MarshalAs(UnmanagedType.LPStr)] string archivePath,
+            IntPtr buffer,
+            int bufferLen);
+
+        // Returned by CreateArchive/ExtractArchive when their cancel token was triggered
+        public const int Cancelled = -2;
+
+        // Failure codes of CreateArchive, ExtractArchive, ExtractArcArchive and VerifyArchive; details via GetOpenArcError
+        public enum OpenArcResult
+        {
+            Ok = 0,
+            NullArgument = -1,
+            Cancelled = -2,
+            IoError = -3,
+            DecodeError = -4,
+            VerificationFailed = -5,
+            InvalidArgument = -6,
+            Failed = -7,
+            InvalidPassword = -8,
+        }
+
+        // Cancel tokens for CreateArchive/ExtractArchive/phone backups (free with OpenArcCancelTokenFree)
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern IntPtr OpenArcCancelTokenCreate();
+
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void OpenArcCancelTokenCancel(IntPtr token);
+
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void OpenArcCancelTokenFree(IntPtr token);
+
+        // Archive creation (cancelToken may be IntPtr.Zero)
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int CreateArchive(
             [MarshalAs(UnmanagedType.LPStr)] string outputPath,
             [MarshalAs(UnmanagedType.LPArray, ArraySubType = UnmanagedType.LPStr)] string[] inputFiles,
             int fileCount,
             ref CompressionSettings settings,
-            ProgressCallback callback);
+            ProgressCallback callback,
+            IntPtr cancelToken);
 
-        // Archive extraction
+        // Archive extraction (cancelToken may be IntPtr.Zero)
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int ExtractArchive(
             [MarshalAs(UnmanagedType.LPStr)] string archivePath,
             [MarshalAs(UnmanagedType.LPStr)] string outputDir,
-            ProgressCallback callback);
+            ProgressCallback callback,
+            IntPtr cancelToken);
+
+        // Password prompt for encrypted archives (null to clear); keep the delegate alive while registered
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void SetPasswordCallback(PasswordCallback? callback);
+
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void ClearPasswordCache();
+
+        // FreeARC (.arc) or PeaZip (.pea) extraction; returns the entry count or an OpenArcResult code
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int ExtractArcArchive(
+            [MarshalAs(UnmanagedType.LPStr)] string archivePath,
+            [MarshalAs(UnmanagedType.LPStr)] string outputDir);
 
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int ExtractArchiveEntry(
@@ -100,11 +150,16 @@ namespace DocBrake.NativeInterop
         public static extern int VerifyArchive(
             [MarshalAs(UnmanagedType.LPStr)] string archivePath);
 
+        // Full read of every entry; returns the number of failed entries, or -1 on error
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int VerifyArchiveFull(
+            [MarshalAs(UnmanagedType.LPStr)] string archivePath);
+
         // File type detection
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int DetectFileType([MarshalAs(UnmanagedType.LPStr)] string filePath);
 
-        // Get last error message
+        // Get last error message of a call made on this thread; read it right after the failing call
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern IntPtr GetOpenArcError();
 
@@ -116,7 +171,17 @@ namespace DocBrake.NativeInterop
             [MarshalAs(UnmanagedType.LPStr)] string phoneRoot,
             [MarshalAs(UnmanagedType.LPStr)] string outputPath,
             ref CompressionSettings settings,
-            ProgressCallback? callback);
+            ProgressCallback? callback,
+            IntPtr cancelToken);
+
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Ansi)]
+        public static extern int PhoneArchivePendingFilesChunked(
+            [MarshalAs(UnmanagedType.LPStr)] string phoneRoot,
+            [MarshalAs(UnmanagedType.LPStr)] string outputDir,
+            ulong chunkBytes,
+            ref CompressionSettings settings,
+            ProgressCallback? callback,
+            IntPtr cancelToken);
 
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern void FreeCString(IntPtr ptr);
@@ -128,9 +193,16 @@ namespace DocBrake.NativeInterop
             out int fileCount,
             out IntPtr files);
 
+        // Free the array returned by ListArchive (exactly once)
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void FreeArchiveFileInfoArray(IntPtr files, int count);
+
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern void FreeArchiveFileList(IntPtr files, int count);
 
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Ansi)]
+        public static extern IntPtr PeekArchive([MarshalAs(UnmanagedType.LPStr)] string archivePath);
+
         // Update archive destination
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int UpdateArchiveDestination(
@@ -138,6 +210,25 @@ namespace DocBrake.NativeInterop
             [MarshalAs(UnmanagedType.LPStr)] string archivePath,
             [MarshalAs(UnmanagedType.LPStr)] string destinationPath);
 
+        // Update archive description
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int UpdateArchiveDescription(
+            [MarshalAs(UnmanagedType.LPStr)] string catalogDbPath,
+            [MarshalAs(UnmanagedType.LPStr)] string archivePath,
+            [MarshalAs(UnmanagedType.LPStr)] string description);
+
+        // Remove an archive record and its file mappings (id from ArchiveRecordInfo)
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int DeleteArchiveRecord(
+            [MarshalAs(UnmanagedType.LPStr)] string catalogDbPath,
+            long archiveId);
+
+        // Write every catalog entry to a JSON file
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int ExportCatalogJson(
+            [MarshalAs(UnmanagedType.LPStr)] string catalogDbPath,
+            [MarshalAs(UnmanagedType.LPStr)] string outputPath);
+
         // Get all archives
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int GetAllArchives(
@@ -145,10 +236,23 @@ namespace DocBrake.NativeInterop
             out int archiveCount,
             out IntPtr archives);
 
+        // Get archives created in [start, end) (Unix seconds)
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int GetArchivesInRange(
+            [MarshalAs(UnmanagedType.LPStr)] string catalogDbPath,
+            ulong start,
+            ulong end,
+            out int archiveCount,
+            out IntPtr archives);
+
         // Free archives array
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern void FreeArchivesArray(IntPtr archives, int count);
 
+        // Original vs archived bytes across a catalog, as JSON (free with FreeCString)
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Ansi)]
+        public static extern IntPtr GetCatalogSavingsJson([MarshalAs(UnmanagedType.LPStr)] string catalogDbPath);
+
         // Single file encoding
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int EncodeBpgFile(
@@ -156,12 +260,29 @@ namespace DocBrake.NativeInterop
             [MarshalAs(UnmanagedType.LPStr)] string outputPath,
             ref CompressionSettings settings);
 
+        // Any decodable image to bpg/avif/heic/jpeg/png/webp; returns output size or -1
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern long TranscodeImage(
+            [MarshalAs(UnmanagedType.LPStr)] string inputPath,
+            [MarshalAs(UnmanagedType.LPStr)] string outputPath,
+            [MarshalAs(UnmanagedType.LPStr)] string targetFormat,
+            int quality,
+            ref CompressionSettings settings);
+
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int EncodeVideoFile(
             [MarshalAs(UnmanagedType.LPStr)] string inputPath,
             [MarshalAs(UnmanagedType.LPStr)] string outputPath,
             ref CompressionSettings settings);
 
+        // Tiled preview of evenly spaced frames (tileWidth 0 = 160px); 1 written, 0 too few frames, -1 error
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int GenerateVideoSpriteSheet(
+            [MarshalAs(UnmanagedType.LPStr)] string inputPath,
+            [MarshalAs(UnmanagedType.LPStr)] string outputPath,
+            int frames,
+            int tileWidth);
+
         public static string GetLastErrorMessage()
         {
             IntPtr ptr = GetOpenArcError();
@@ -183,5 +304,37 @@ namespace DocBrake.NativeInterop
                 FreeCString(ptr);
             }
         }
+
+        public static string CatalogSavingsJson(string catalogDbPath)
+        {
+            IntPtr ptr = GetCatalogSavingsJson(catalogDbPath);
+            if (ptr == IntPtr.Zero)
+                return string.Empty;
+
+            try
+            {
+                return Marshal.PtrToStringAnsi(ptr) ?? string.Empty;
+            }
+            finally
+            {
+                FreeCString(ptr);
+            }
+        }
+
+        public static string PeekArchiveJson(string archivePath)
+        {
+            IntPtr ptr = PeekArchive(archivePath);
+            if (ptr == IntPtr.Zero)
+                return string.Empty;
+
+            try
+            {
+                return Marshal.PtrToStringAnsi(ptr) ?? string.Empty;
+            }
+            finally
+            {
+                FreeCString(ptr);
+            }
+        }
     }
 }
@@ -22,12 +22,24 @@ namespace DocBrake.Services
 
         public List<ArchiveRecord> GetAllArchives(string catalogDbPath)
         {
-            var archives = new List<ArchiveRecord>();
+            int result = OpenArcFFI.GetAllArchives(catalogDbPath, out int archiveCount, out IntPtr archivesPtr);
+            return ReadArchiveRecords(result, archiveCount, archivesPtr);
+        }
 
-            int archiveCount;
-            IntPtr archivesPtr;
+        /// <summary>
+        /// Archives created in [start, end), as Unix seconds; e.g. one month of a timeline
+        /// </summary>
+        public List<ArchiveRecord> GetArchivesInRange(string catalogDbPath, DateTimeOffset start, DateTimeOffset end)
+        {
+            ulong startSecs = (ulong)Math.Max(0, start.ToUnixTimeSeconds());
+            ulong endSecs = (ulong)Math.Max(0, end.ToUnixTimeSeconds());
+            int result = OpenArcFFI.GetArchivesInRange(catalogDbPath, startSecs, endSecs, out int archiveCount, out IntPtr archivesPtr);
+            return ReadArchiveRecords(result, archiveCount, archivesPtr);
+        }
 
-            int result = OpenArcFFI.GetAllArchives(catalogDbPath, out archiveCount, out archivesPtr);
+        private static List<ArchiveRecord> ReadArchiveRecords(int result, int archiveCount, IntPtr archivesPtr)
+        {
+            var archives = new List<ArchiveRecord>();
 
             if (result == 0 && archiveCount > 0 && archivesPtr != IntPtr.Zero)
             {
@@ -72,6 +84,12 @@ namespace DocBrake.Services
             return result == 0;
         }
 
+        public bool UpdateArchiveDescription(string catalogDbPath, string archivePath, string description)
+        {
+            int result = OpenArcFFI.UpdateArchiveDescription(catalogDbPath, archivePath, description);
+            return result == 0;
+        }
+
         public bool ArchiveEntry(string catalogDbPath, long archiveId)
         {
             // TODO: Implement FFI call to mark archive as archived (hidden)
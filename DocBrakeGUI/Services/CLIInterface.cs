@@ -444,7 +444,7 @@ namespace DocBrake.Services
                 Console.WriteLine($"  Files: {string.Join(", ", inputFiles)}");
                 Console.WriteLine($"  Compression Level: {settings.CompressionLevel}");
 
-                int result = OpenArcFFI.CreateArchive(outputArchive, inputFiles, inputFiles.Length, ref settings, null!);
+                int result = OpenArcFFI.CreateArchive(outputArchive, inputFiles, inputFiles.Length, ref settings, null!, IntPtr.Zero);
 
                 Console.WriteLine($"CreateArchive returned: {result}");
 
@@ -553,7 +553,7 @@ namespace DocBrake.Services
                     Console.WriteLine($"  Progress: {p.CurrentFile}/{p.TotalFiles} ({p.ProgressPercent:F1}%) - {p.CurrentFileName}");
                 };
 
-                int result = OpenArcFFI.CreateArchive(outputArchive, inputFiles, inputFiles.Length, ref settings, progressCb);
+                int result = OpenArcFFI.CreateArchive(outputArchive, inputFiles, inputFiles.Length, ref settings, progressCb, IntPtr.Zero);
                 GC.KeepAlive(progressCb);
                 Console.WriteLine($"CreateArchive returned: {result}");
 
@@ -638,7 +638,7 @@ namespace DocBrake.Services
                 };
 
                 Console.WriteLine("Calling OpenArcFFI.CreateArchive...");
-                int result = OpenArcFFI.CreateArchive(outputPath, validFiles.ToArray(), validFiles.Count, ref settings, null!);
+                int result = OpenArcFFI.CreateArchive(outputPath, validFiles.ToArray(), validFiles.Count, ref settings, null!, IntPtr.Zero);
 
                 Console.WriteLine($"CreateArchive returned: {result}");
 
@@ -20,6 +20,7 @@ namespace DocBrake.Services
         private volatile bool _cancelRequested;
         private readonly object _activeProcessLock = new();
         private Process? _activeProcess;
+        private IntPtr _activeCancelToken;
 
         public event EventHandler<DocumentProcessingProgress>? ProgressUpdated;
         public event EventHandler<DocumentProcessingResult>? ProcessingCompleted;
@@ -129,17 +130,18 @@ namespace DocBrake.Services
                         ProgressUpdated?.Invoke(this, progressUpdate);
                     };
 
-                    var rc = OpenArcFFI.CreateArchive(
+                    var rc = RunCancellable(cancellationToken, token => OpenArcFFI.CreateArchive(
                         options.OutputArchivePath,
                         paths,
                         paths.Length,
                         ref settings,
-                        cb);
+                        cb,
+                        token));
 
                     // Prevent GC from collecting the callback delegate during native execution
                     GC.KeepAlive(cb);
 
-                    if (_cancelRequested || cancellationToken.IsCancellationRequested)
+                    if (rc == OpenArcFFI.Cancelled || _cancelRequested || cancellationToken.IsCancellationRequested)
                     {
                         return paths.Select(_ => new DocumentProcessingResult
                         {
@@ -225,6 +227,11 @@ namespace DocBrake.Services
 
             lock (_activeProcessLock)
             {
+                if (_activeCancelToken != IntPtr.Zero)
+                {
+                    OpenArcFFI.OpenArcCancelTokenCancel(_activeCancelToken);
+                }
+
                 if (_activeProcess != null)
                 {
                     try
@@ -241,6 +248,35 @@ namespace DocBrake.Services
             }
         }
 
+        // Run a native call with a cancel token that CancelProcessing and
+        // cancellationToken can trigger while it is running
+        private int RunCancellable(CancellationToken cancellationToken, Func<IntPtr, int> call)
+        {
+            var token = OpenArcFFI.OpenArcCancelTokenCreate();
+            lock (_activeProcessLock)
+            {
+                _activeCancelToken = token;
+            }
+
+            try
+            {
+                using var registration = cancellationToken.Register(() => OpenArcFFI.OpenArcCancelTokenCancel(token));
+                if (_cancelRequested)
+                {
+                    OpenArcFFI.OpenArcCancelTokenCancel(token);
+                }
+                return call(token);
+            }
+            finally
+            {
+                lock (_activeProcessLock)
+                {
+                    _activeCancelToken = IntPtr.Zero;
+                }
+                OpenArcFFI.OpenArcCancelTokenFree(token);
+            }
+        }
+
         private static string? TryResolveOpenArcCliPath()
         {
             try
@@ -461,12 +497,12 @@ namespace DocBrake.Services
                         ProgressUpdated?.Invoke(this, progressUpdate);
                     };
 
-                    var rc = OpenArcFFI.ExtractArchive(archivePath, outputDirectory, cb);
+                    var rc = RunCancellable(cancellationToken, token => OpenArcFFI.ExtractArchive(archivePath, outputDirectory, cb, token));
 
                     // Prevent GC from collecting the callback delegate during native execution
                     GC.KeepAlive(cb);
 
-                    if (_cancelRequested || cancellationToken.IsCancellationRequested)
+                    if (rc == OpenArcFFI.Cancelled || _cancelRequested || cancellationToken.IsCancellationRequested)
                     {
                         return false;
                     }
@@ -545,7 +581,7 @@ namespace DocBrake.Services
                     {
                         if (filesPtr != IntPtr.Zero && fileCount > 0)
                         {
-                            OpenArcFFI.FreeArchiveFileList(filesPtr, fileCount);
+                            OpenArcFFI.FreeArchiveFileInfoArray(filesPtr, fileCount);
                         }
                     }
                 }
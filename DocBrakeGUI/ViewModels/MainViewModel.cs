@@ -1045,7 +1045,7 @@ namespace DocBrake.ViewModels
                         });
                     };
 
-                    var rc = OpenArcFFI.PhoneArchivePendingFiles(phone.Path, outputPath, ref settings, cb);
+                    var rc = OpenArcFFI.PhoneArchivePendingFiles(phone.Path, outputPath, ref settings, cb, IntPtr.Zero);
                     GC.KeepAlive(cb);
 
                     if (rc < 0)
@@ -1314,6 +1314,7 @@ namespace DocBrake.ViewModels
         {
             return chromaFormat switch
             {
+                -1 => "Auto",
                 0 => "4:2:0",
                 1 => "4:4:4",
                 2 => "RGB",
@@ -7,6 +7,7 @@
 //! Supports: Blowfish, AES, Twofish, Serpent (cascadable)
 
 use anyhow::{anyhow, Result};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 
 /// Encryption errors
@@ -118,12 +119,15 @@ impl EncryptionInfo {
             } else if part.starts_with('r') {
                 rounds = part[1..].parse().unwrap_or(0);
             } else if part.starts_with('s') {
+                #[cfg(feature = "debug_crypto")]
                 eprintln!("Parsing salt from: '{}'", &part[1..]);
                 salt = Some(hex_decode(&part[1..])?);
             } else if part.starts_with('c') {
+                #[cfg(feature = "debug_crypto")]
                 eprintln!("Parsing code from: '{}'", &part[1..]);
                 code = Some(hex_decode(&part[1..])?);
             } else if part.starts_with('i') {
+                #[cfg(feature = "debug_crypto")]
                 eprintln!("Parsing IV from: '{}'", &part[1..]);
                 iv = Some(hex_decode(&part[1..])?);
             }
@@ -555,7 +559,8 @@ impl CascadedDecryptor {
                 return Ok(false);
             }
 
-            let matches = &hash[..code_len] == &verification_code[..];
+            let matches = bool::from(hash[..code_len].ct_eq(&verification_code[..]));
+            #[cfg(feature = "debug_crypto")]
             if !matches {
                 eprintln!("Password verification FAILED");
                 eprintln!("Expected code: {:?}", verification_code);
@@ -608,10 +613,13 @@ impl CascadedDecryptor {
 
                     eprintln!("Blowfish decrypt: key_size={}, iv_len={}, iterations={}",
                         key.len(), iv.len(), enc_info.iterations);
-                    eprintln!("Key (first 16 bytes): {:?}", &key[..std::cmp::min(16, key.len())]);
-                    eprintln!("IV (all 8 bytes): {:?}", &iv);
-                    if let Some(ref salt_bytes) = enc_info.salt {
-                        eprintln!("Salt (first 16 bytes): {:?}", &salt_bytes[..std::cmp::min(16, salt_bytes.len())]);
+                    #[cfg(feature = "debug_crypto")]
+                    {
+                        eprintln!("Key (first 16 bytes): {:?}", &key[..std::cmp::min(16, key.len())]);
+                        eprintln!("IV (all 8 bytes): {:?}", &iv);
+                        if let Some(ref salt_bytes) = enc_info.salt {
+                            eprintln!("Salt (first 16 bytes): {:?}", &salt_bytes[..std::cmp::min(16, salt_bytes.len())]);
+                        }
                     }
 
                     Box::new(BlowfishCipher::new(&key, &iv)?)
@@ -81,6 +81,39 @@ static int callback_func_wrapper(const char* operation, void* buffer, int size,
     return callback_func((char*)operation, buffer, size, auxdata);
 }
 
+// Caller-supplied I/O for the streaming entry points (see freearc_wrapper.h)
+#define FREEARC_IO_READ  0
+#define FREEARC_IO_WRITE 1
+typedef int32_t (*freearc_io_callback)(int32_t operation, uint8_t* buffer, int32_t size, void* ctx);
+
+typedef struct {
+    freearc_io_callback io;
+    void* ctx;
+} StreamData;
+
+// Forwards FreeARC's "read"/"write" requests to the caller; other operations are ignored
+static int stream_callback(const char* operation, void* buffer, int size, void* auxdata) {
+    StreamData* stream = (StreamData*)auxdata;
+    if (strcmp(operation, "read") == 0) {
+        return stream->io(FREEARC_IO_READ, (uint8_t*)buffer, size, stream->ctx);
+    }
+    if (strcmp(operation, "write") == 0) {
+        return stream->io(FREEARC_IO_WRITE, (uint8_t*)buffer, size, stream->ctx);
+    }
+    return 0;
+}
+
+static COMPRESSION_METHOD* parse_lzma_params(uint32_t dict_size, uint32_t lc, uint32_t lp, uint32_t pb) {
+    char s_dict[32], s_lc[32], s_lp[32], s_pb[32];
+    snprintf(s_dict, sizeof(s_dict), "d%ub", dict_size);
+    snprintf(s_lc, sizeof(s_lc), "lc%d", lc);
+    snprintf(s_lp, sizeof(s_lp), "lp%d", lp);
+    snprintf(s_pb, sizeof(s_pb), "pb%d", pb);
+
+    char *args[] = { (char*)"lzma", s_dict, s_lc, s_lp, s_pb, NULL };
+    return parse_LZMA(args);
+}
+
 // Export wrapper functions with C linkage for Rust FFI
 #ifdef __cplusplus
 extern "C" {
@@ -148,6 +181,47 @@ int32_t freearc_lzma2_compress(
     return (result == FREEARC_OK) ? output_size : result;
 }
 
+int32_t freearc_lzma2_compress_stream(
+    int32_t compression_level,
+    uint32_t dict_size,
+    uint32_t lc,
+    uint32_t lp,
+    uint32_t pb,
+    freearc_io_callback io,
+    void* ctx
+) {
+    COMPRESSION_METHOD *c = parse_lzma_params(dict_size, lc, lp, pb);
+    if (!c) {
+        return FREEARC_ERRCODE_INVALID_COMPRESSOR;
+    }
+
+    StreamData stream = { io, ctx };
+    int result = c->compress(stream_callback, &stream);
+
+    delete c;
+    return result;
+}
+
+int32_t freearc_lzma2_decompress_stream(
+    uint32_t dict_size,
+    uint32_t lc,
+    uint32_t lp,
+    uint32_t pb,
+    freearc_io_callback io,
+    void* ctx
+) {
+    COMPRESSION_METHOD *c = parse_lzma_params(dict_size, lc, lp, pb);
+    if (!c) {
+        return FREEARC_ERRCODE_INVALID_COMPRESSOR;
+    }
+
+    StreamData stream = { io, ctx };
+    int result = c->decompress(stream_callback, &stream);
+
+    delete c;
+    return result;
+}
+
 // PPMD functions
 // Note: memory_size is size_t (MemSize) which is 64-bit on x64
 int32_t freearc_ppmd_decompress(
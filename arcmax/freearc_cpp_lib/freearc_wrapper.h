@@ -12,6 +12,13 @@
 extern "C" {
 #endif
 
+// I/O callback for the streaming functions: reads fill `buffer` with up to
+// `size` bytes and return the count (0 at end of input), writes consume all
+// `size` bytes and return `size`; a negative return aborts with that code
+#define FREEARC_IO_READ  0
+#define FREEARC_IO_WRITE 1
+typedef int32_t (*freearc_io_callback)(int32_t operation, uint8_t* buffer, int32_t size, void* ctx);
+
 // GRZip functions
 int32_t freearc_grzip_decompress(
     uint8_t* input,
@@ -102,6 +109,26 @@ int32_t freearc_lzma2_compress(
     uint32_t pb
 );
 
+// Streaming LZMA2: same format as the functions above, with all I/O through `io`
+int32_t freearc_lzma2_compress_stream(
+    int32_t compression_level,
+    uint32_t dict_size,
+    uint32_t lc,
+    uint32_t lp,
+    uint32_t pb,
+    freearc_io_callback io,
+    void* ctx
+);
+
+int32_t freearc_lzma2_decompress_stream(
+    uint32_t dict_size,
+    uint32_t lc,
+    uint32_t lp,
+    uint32_t pb,
+    freearc_io_callback io,
+    void* ctx
+);
+
 // Utility functions for memory management
 void* freearc_big_alloc(int32_t size);
 void freearc_big_free(void* ptr);
@@ -6,6 +6,9 @@ use anyhow::{Result, anyhow};
 use clap::{Parser, Subcommand};
 
 use arcmax::{compress, decompress, CompressionMethod, compression_ratio};
+use arcmax::core::format::{detect_archive_format, detect_format_with_provider, ArchiveFormat};
+use arcmax::core::selftest::run_selftest;
+use arcmax::core::password::{CachingPasswordProvider, PasswordProvider, StaticPassword, StdinPasswordProvider};
 
 #[derive(Parser, Debug)]
 #[command(name = "arcmax")]
@@ -23,6 +26,8 @@ pub enum Commands {
     Extract(ExtractArgs),
     /// Test compression
     Test(TestArgs),
+    /// Check every codec and cipher against built-in test vectors
+    Selftest,
 }
 
 #[derive(Parser, Debug)]
@@ -35,7 +40,7 @@ pub struct CompressArgs {
     #[arg(short, long)]
     output: PathBuf,
     
-    /// Compression method
+    /// Compression method: store, lzma2, tornado, ppmd, lzp or grzip
     #[arg(short, long, default_value = "store")]
     method: String,
     
@@ -58,7 +63,7 @@ pub struct ExtractArgs {
     #[arg(short, long)]
     output: Option<PathBuf>,
     
-    /// Password for encrypted archives
+    /// Password for encrypted archives (prompted on stdin when omitted)
     #[arg(short, long)]
     password: Option<String>,
 }
@@ -76,6 +81,7 @@ pub fn dispatch() -> Result<()> {
         Commands::Compress(args) => cmd_compress(args),
         Commands::Extract(args) => cmd_extract(args),
         Commands::Test(args) => cmd_test(args),
+        Commands::Selftest => cmd_selftest(),
     }
 }
 
@@ -89,6 +95,10 @@ fn cmd_compress(args: CompressArgs) -> Result<()> {
     let method = match args.method.as_str() {
         "store" => CompressionMethod::Store,
         "lzma2" => CompressionMethod::Lzma2 { level: args.level, dict_size: args.dict_size },
+        "tornado" => CompressionMethod::Tornado { method: args.level },
+        "ppmd" => CompressionMethod::Ppmd { order: 6, memory_size: args.dict_size },
+        "lzp" => CompressionMethod::Lzp { min_match_len: 32, hash_size_log: 18 },
+        "grzip" => CompressionMethod::Grzip { mode: 0 },
         _ => return Err(anyhow!("Unknown compression method: {}", args.method)),
     };
     
@@ -105,6 +115,23 @@ fn cmd_compress(args: CompressArgs) -> Result<()> {
 }
 
 fn cmd_extract(args: ExtractArgs) -> Result<()> {
+    let format = detect_archive_format(&args.archive)?;
+    if format != ArchiveFormat::Unknown {
+        let output_dir = args.output.unwrap_or_else(|| PathBuf::from("."));
+        let provider: Box<dyn PasswordProvider> = match args.password {
+            Some(pwd) => Box::new(StaticPassword(pwd)),
+            None => Box::new(CachingPasswordProvider::new(StdinPasswordProvider)),
+        };
+
+        println!("Extracting {} -> {}", args.archive.display(), output_dir.display());
+        let mut reader = detect_format_with_provider(&args.archive, provider.as_ref(), None)?;
+        std::fs::create_dir_all(&output_dir)?;
+        reader.extract_all(&output_dir)?;
+
+        println!("Extraction complete!");
+        return Ok(());
+    }
+
     // Read input file
     let mut input_data = Vec::new();
     let mut input_file = std::fs::File::open(&args.archive)?;
@@ -140,3 +167,20 @@ fn cmd_test(args: TestArgs) -> Result<()> {
     
     Ok(())
 }
+
+fn cmd_selftest() -> Result<()> {
+    let results = run_selftest();
+    for result in &results {
+        match &result.outcome {
+            Ok(()) => println!("{:<14} PASS", result.component),
+            Err(reason) => println!("{:<14} FAIL  {}", result.component, reason),
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        return Err(anyhow!("{} of {} self-tests failed", failed, results.len()));
+    }
+    println!("All {} self-tests passed", results.len());
+    Ok(())
+}
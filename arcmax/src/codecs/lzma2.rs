@@ -1,33 +1,114 @@
-use std::ffi::{CString, CStr};
-use std::os::raw::c_char;
+use std::ffi::c_void;
+use std::io::{self, Read, Write};
 use anyhow::{Result, anyhow};
 
+use super::stream::Lzma2Block;
+
+/// `operation` values of the I/O callback (`FREEARC_IO_READ` / `FREEARC_IO_WRITE`)
+const IO_READ: i32 = 0;
+const IO_WRITE: i32 = 1;
+
+/// FreeARC error codes returned from the callback when I/O fails
+const FREEARC_ERRCODE_READ: i32 = -6;
+const FREEARC_ERRCODE_WRITE: i32 = -11;
+
+type IoCallback = unsafe extern "C" fn(operation: i32, buffer: *mut u8, size: i32, ctx: *mut c_void) -> i32;
+
 // External C++ functions from FreeARC libraries
 extern "C" {
-    // LZMA2 decompression function
-    fn freearc_lzma2_decompress(
-        input: *const u8,
-        input_size: i32,
-        output: *mut u8,
-        output_size: i32,
+    // LZMA2 compression, pulling input and pushing output through `io`
+    fn freearc_lzma2_compress_stream(
+        compression_level: i32,
         dict_size: u32,
         lc: u32,
         lp: u32,
         pb: u32,
-    ) -> i32; // Returns actual decompressed size or negative error code
+        io: IoCallback,
+        ctx: *mut c_void,
+    ) -> i32; // Returns 0 or negative error code
     
-    // LZMA2 compression function
-    fn freearc_lzma2_compress(
-        input: *const u8,
-        input_size: i32,
-        output: *mut u8,
-        output_size: i32,
-        compression_level: i32,
+    // LZMA2 decompression, pulling input and pushing output through `io`
+    fn freearc_lzma2_decompress_stream(
         dict_size: u32,
         lc: u32,
         lp: u32,
         pb: u32,
-    ) -> i32; // Returns actual compressed size or negative error code
+        io: IoCallback,
+        ctx: *mut c_void,
+    ) -> i32; // Returns 0 or negative error code
+}
+
+/// Reader and writer the C++ codec calls back into
+struct StreamIo<'a> {
+    reader: &'a mut dyn Read,
+    writer: &'a mut dyn Write,
+    consumed: u64,
+    written: u64,
+    /// Writes past this many bytes fail, for decoding into a known size
+    write_limit: Option<u64>,
+    error: Option<io::Error>,
+}
+
+unsafe extern "C" fn stream_io(operation: i32, buffer: *mut u8, size: i32, ctx: *mut c_void) -> i32 {
+    let io = &mut *(ctx as *mut StreamIo);
+    if buffer.is_null() || size <= 0 {
+        return 0;
+    }
+    let buf = std::slice::from_raw_parts_mut(buffer, size as usize);
+    
+    match operation {
+        IO_READ => {
+            // A short read means end of input to the codec, so fill the buffer
+            let mut filled = 0;
+            while filled < buf.len() {
+                match io.reader.read(&mut buf[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        io.error = Some(e);
+                        return FREEARC_ERRCODE_READ;
+                    }
+                }
+            }
+            io.consumed += filled as u64;
+            filled as i32
+        }
+        IO_WRITE => {
+            if matches!(io.write_limit, Some(limit) if io.written + buf.len() as u64 > limit) {
+                io.error = Some(io::Error::new(io::ErrorKind::InvalidData, "output larger than expected"));
+                return FREEARC_ERRCODE_WRITE;
+            }
+            if let Err(e) = io.writer.write_all(buf) {
+                io.error = Some(e);
+                return FREEARC_ERRCODE_WRITE;
+            }
+            io.written += buf.len() as u64;
+            size
+        }
+        _ => 0,
+    }
+}
+
+/// Run one streaming codec call; returns (bytes consumed, bytes written)
+fn run_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    write_limit: Option<u64>,
+    what: &str,
+    call: impl FnOnce(IoCallback, *mut c_void) -> i32,
+) -> Result<(u64, u64)> {
+    let mut io = StreamIo { reader, writer, consumed: 0, written: 0, write_limit, error: None };
+    let result = call(stream_io, &mut io as *mut StreamIo as *mut c_void);
+    
+    if let Some(e) = io.error.take() {
+        return Err(anyhow::Error::new(e).context(format!("LZMA2 {} failed", what)));
+    }
+    if result < 0 {
+        return Err(anyhow!("LZMA2 {} failed with error code: {}", what, result));
+    }
+    io.writer.flush()?;
+    Ok((io.consumed, io.written))
 }
 
 /// Native FreeARC LZMA decoder function
@@ -42,35 +123,16 @@ pub fn lzma_decode_freearc(
     lzma2_decompress(compressed_data, expected_size, dict_size, lit_context_bits, lit_pos_bits, pos_state_bits)
 }
 
-/// Main LZMA2 decompression function using FFI to FreeARC C++ implementation
+/// Main LZMA2 decompression function, a wrapper over `lzma2_decompress_stream`
+///
+/// Fails once the output would exceed `expected_size`; the buffer grows as
+/// the codec writes, so a bogus size allocates nothing up front.
 pub fn lzma2_decompress(input: &[u8], expected_size: usize, dict_size: u32, lc: u32, lp: u32, pb: u32) -> Result<Vec<u8>> {
-    // Allocate output buffer
-    let mut output = vec![0u8; expected_size];
-
-    let result = unsafe {
-        freearc_lzma2_decompress(
-            input.as_ptr(),
-            input.len() as i32,
-            output.as_mut_ptr(),
-            expected_size as i32,
-            dict_size,
-            lc,
-            lp,
-            pb,
-        )
-    };
-
-    if result < 0 {
-        return Err(anyhow!("LZMA2 decompression failed with error code: {}", result));
-    }
-
-    let actual_size = result as usize;
-    if actual_size <= output.len() {
-        output.truncate(actual_size);
-    } else {
-        return Err(anyhow!("LZMA2 decompression returned size larger than expected: {} > {}", actual_size, expected_size));
-    }
-
+    let mut output = Vec::with_capacity(expected_size.min(input.len().saturating_mul(4)));
+    let mut reader = input;
+    run_stream(&mut reader, &mut output, Some(expected_size as u64), "decompression", |io, ctx| unsafe {
+        freearc_lzma2_decompress_stream(dict_size, lc, lp, pb, io, ctx)
+    })?;
     Ok(output)
 }
 
@@ -94,38 +156,36 @@ pub fn compress_lzma(data: &[u8], level: i32, dict_size: u32, lc: u32, lp: u32,
     lzma2_compress(data, level, dict_size, lc, lp, pb)
 }
 
-/// Main LZMA2 compression function using FFI to FreeARC C++ implementation
+/// Main LZMA2 compression function, a wrapper over `lzma2_compress_stream`
 pub fn lzma2_compress(input: &[u8], compression_level: i32, dict_size: u32, lc: u32, lp: u32, pb: u32) -> Result<Vec<u8>> {
-    // Allocate output buffer (typically compressed data is smaller)
-    let max_output_size = input.len() + (input.len() / 8) + 256; // Add some extra space
-    let mut output = vec![0u8; max_output_size];
-
-    let result = unsafe {
-        freearc_lzma2_compress(
-            input.as_ptr(),
-            input.len() as i32,
-            output.as_mut_ptr(),
-            max_output_size as i32,
-            compression_level,
-            dict_size,
-            lc,
-            lp,
-            pb,
-        )
-    };
-
-    if result < 0 {
-        return Err(anyhow!("LZMA2 compression failed with error code: {}", result));
-    }
+    let mut output = Vec::with_capacity(input.len() / 2 + 256);
+    let params = Lzma2Block { level: compression_level, dict_size, lc, lp, pb };
+    lzma2_compress_stream(input, &mut output, params)?;
+    Ok(output)
+}
 
-    let actual_size = result as usize;
-    if actual_size <= output.len() {
-        output.truncate(actual_size);
-    } else {
-        return Err(anyhow!("LZMA2 compression returned size larger than buffer: {} > {}", actual_size, max_output_size));
-    }
+/// Compress `reader` into `writer` through the FreeARC codec's callback I/O
+///
+/// The codec pulls input and pushes output in buffers of its own, so memory
+/// use depends on the dictionary size, not on the input length. The output is
+/// the raw LZMA stream FreeARC stores in its archives, byte for byte what
+/// `lzma2_compress` returns. Returns the number of input bytes consumed.
+pub fn lzma2_compress_stream<R: Read, W: Write>(mut reader: R, mut writer: W, params: Lzma2Block) -> Result<u64> {
+    let (consumed, _) = run_stream(&mut reader, &mut writer, None, "compression", |io, ctx| unsafe {
+        freearc_lzma2_compress_stream(params.level, params.dict_size, params.lc, params.lp, params.pb, io, ctx)
+    })?;
+    Ok(consumed)
+}
 
-    Ok(output)
+/// Decompress a raw LZMA stream from `reader` into `writer`
+///
+/// `params` must match the ones used for compression (`level` is ignored).
+/// Returns the number of bytes written.
+pub fn lzma2_decompress_stream<R: Read, W: Write>(mut reader: R, mut writer: W, params: Lzma2Block) -> Result<u64> {
+    let (_, written) = run_stream(&mut reader, &mut writer, None, "decompression", |io, ctx| unsafe {
+        freearc_lzma2_decompress_stream(params.dict_size, params.lc, params.lp, params.pb, io, ctx)
+    })?;
+    Ok(written)
 }
 
 #[cfg(test)]
@@ -157,4 +217,31 @@ mod tests {
         let decompressed = lzma2_decompress(&compressed, data.len(), dict, lc, lp, pb).unwrap();
         assert_eq!(data.as_slice(), decompressed.as_slice());
     }
+
+    #[test]
+    fn test_lzma2_stream_functions_roundtrip() {
+        // This test will only pass when linked with actual FreeARC library
+        let data: Vec<u8> = (0..(2 * 1024 * 1024 + 777))
+            .map(|i: usize| if (i / 1000) % 3 == 0 { (i % 251) as u8 } else { b"stream "[i % 7] })
+            .collect();
+        let params = Lzma2Block { dict_size: 1024 * 1024, ..Lzma2Block::default() };
+
+        let mut compressed = Vec::new();
+        let consumed = lzma2_compress_stream(&data[..], &mut compressed, params).unwrap();
+        assert_eq!(consumed, data.len() as u64);
+
+        // Same raw stream as the whole-buffer API, readable by either side
+        let whole = lzma2_compress(&data, params.level, params.dict_size, params.lc, params.lp, params.pb).unwrap();
+        assert_eq!(compressed, whole);
+        let out = lzma2_decompress(&compressed, data.len(), params.dict_size, params.lc, params.lp, params.pb).unwrap();
+        assert_eq!(out, data);
+
+        let mut out = Vec::new();
+        let written = lzma2_decompress_stream(&compressed[..], &mut out, params).unwrap();
+        assert_eq!(written, data.len() as u64);
+        assert_eq!(out, data);
+
+        // Output past the expected size is an error, not a larger buffer
+        assert!(lzma2_decompress(&compressed, 1024, params.dict_size, params.lc, params.lp, params.pb).is_err());
+    }
 }
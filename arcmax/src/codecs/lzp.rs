@@ -159,13 +159,17 @@ pub fn apply_lzp_post_processing(data: &mut Vec<u8>, method: &str, original_size
 
 /// Main LZP decompression function using FFI to FreeARC C++ implementation
 pub fn lzp_decompress(input: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+    // Default min match length and hash size log (2^18 = 256KB hash table)
+    lzp_decompress_with_params(input, expected_size, 32, 18)
+}
+
+/// LZP decompression with the parameters the data was compressed with
+pub fn lzp_decompress_with_params(input: &[u8], expected_size: usize, min_match_len: i32, hash_size: i32) -> Result<Vec<u8>> {
     if input.is_empty() {
         return Ok(Vec::new());
     }
 
     let mut output = vec![0u8; expected_size];
-    let min_match_len = 32; // Default min match length
-    let hash_size = 18; // Default hash size log (2^18 = 256KB hash table)
 
     let result = unsafe {
         freearc_lzp_decompress(
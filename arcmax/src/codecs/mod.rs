@@ -6,11 +6,13 @@ pub mod ppmd;
 pub mod lzp;
 pub mod grzip;
 pub mod zstd;
+pub mod stream;
 
 // Re-export commonly used functions
-pub use lzma2::{lzma2_compress, lzma2_decompress};
+pub use lzma2::{lzma2_compress, lzma2_compress_stream, lzma2_decompress, lzma2_decompress_stream};
 pub use tornado::{tornado_compress, tornado_decompress};
 pub use ppmd::{ppmd_compress, ppmd_decompress};
-pub use lzp::{lzp_compress, lzp_decompress};
+pub use lzp::{lzp_compress, lzp_decompress, lzp_decompress_with_params};
 pub use grzip::{grzip_compress, grzip_decompress};
 pub use zstd::{compress_zstd, decompress_zstd, format_zstd_method};
+pub use stream::{BlockCodec, BlockReader, BlockWriter, Lzma2Block, Lzma2Reader, Lzma2Writer};
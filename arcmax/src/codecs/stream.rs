@@ -0,0 +1,384 @@
+//! Streaming `Read`/`Write` adapters over the block-oriented FreeARC codecs
+//!
+//! The C++ entry points compress whole buffers, so the adapters cut the stream
+//! into fixed-size blocks and frame each one as:
+//!
+//! ```text
+//! [u32 LE uncompressed_len][u32 LE compressed_len][compressed bytes]
+//! ```
+//!
+//! A frame with `uncompressed_len == 0` marks the end of the stream; only
+//! `BlockWriter::finish` writes it, so a writer dropped early leaves a stream
+//! that reads back as truncated. Memory use is bounded by the block size
+//! regardless of the total input length.
+
+use std::io::{self, Read, Write};
+use anyhow::Result;
+
+use super::{grzip, lzma2, ppmd, tornado};
+
+/// Default block size used by the streaming adapters (8MB)
+pub const DEFAULT_STREAM_BLOCK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Largest compressed size accepted for a block of `raw_len` bytes; codecs
+/// expand incompressible input only slightly, so anything past this is corrupt
+fn max_packed_len(raw_len: usize) -> usize {
+    raw_len.saturating_mul(2).saturating_add(1024)
+}
+
+/// A codec that can compress and decompress independent blocks
+pub trait BlockCodec {
+    fn compress_block(&self, input: &[u8]) -> Result<Vec<u8>>;
+    fn decompress_block(&self, input: &[u8], expected_size: usize) -> Result<Vec<u8>>;
+}
+
+/// LZMA2 block codec parameters
+#[derive(Debug, Clone, Copy)]
+pub struct Lzma2Block {
+    pub level: i32,
+    pub dict_size: u32,
+    pub lc: u32,
+    pub lp: u32,
+    pub pb: u32,
+}
+
+impl Default for Lzma2Block {
+    fn default() -> Self {
+        Self { level: 5, dict_size: 32 * 1024 * 1024, lc: 3, lp: 0, pb: 2 }
+    }
+}
+
+impl BlockCodec for Lzma2Block {
+    fn compress_block(&self, input: &[u8]) -> Result<Vec<u8>> {
+        lzma2::lzma2_compress(input, self.level, self.dict_size, self.lc, self.lp, self.pb)
+    }
+
+    fn decompress_block(&self, input: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+        lzma2::lzma2_decompress(input, expected_size, self.dict_size, self.lc, self.lp, self.pb)
+    }
+}
+
+/// Tornado block codec (method number 1-16)
+#[derive(Debug, Clone, Copy)]
+pub struct TornadoBlock {
+    pub method: i32,
+}
+
+impl Default for TornadoBlock {
+    fn default() -> Self {
+        Self { method: 5 }
+    }
+}
+
+impl BlockCodec for TornadoBlock {
+    fn compress_block(&self, input: &[u8]) -> Result<Vec<u8>> {
+        tornado::tornado_compress(input, self.method)
+    }
+
+    fn decompress_block(&self, input: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+        tornado::tornado_decompress(input, expected_size)
+    }
+}
+
+/// PPMd block codec parameters
+#[derive(Debug, Clone, Copy)]
+pub struct PpmdBlock {
+    pub order: u8,
+    pub memory_size: usize,
+}
+
+impl Default for PpmdBlock {
+    fn default() -> Self {
+        Self { order: 6, memory_size: 16 * 1024 * 1024 }
+    }
+}
+
+impl BlockCodec for PpmdBlock {
+    fn compress_block(&self, input: &[u8]) -> Result<Vec<u8>> {
+        ppmd::ppmd_compress(input, self.order, self.memory_size)
+    }
+
+    fn decompress_block(&self, input: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+        ppmd::ppmd_decompress(input, expected_size, self.order, self.memory_size)
+    }
+}
+
+/// GRZip block codec (mode 1-4)
+#[derive(Debug, Clone, Copy)]
+pub struct GrzipBlock {
+    pub mode: i32,
+}
+
+impl Default for GrzipBlock {
+    fn default() -> Self {
+        Self { mode: 1 }
+    }
+}
+
+impl BlockCodec for GrzipBlock {
+    fn compress_block(&self, input: &[u8]) -> Result<Vec<u8>> {
+        grzip::grzip_compress(input, self.mode)
+    }
+
+    fn decompress_block(&self, input: &[u8], expected_size: usize) -> Result<Vec<u8>> {
+        grzip::grzip_decompress(input, expected_size)
+    }
+}
+
+fn to_io_error(e: anyhow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Compressing writer: buffers one block at a time and emits framed blocks
+pub struct BlockWriter<W: Write, C: BlockCodec> {
+    inner: Option<W>,
+    codec: C,
+    buffer: Vec<u8>,
+    block_size: usize,
+}
+
+impl<W: Write, C: BlockCodec> BlockWriter<W, C> {
+    pub fn new(inner: W, codec: C) -> Self {
+        Self::with_block_size(inner, codec, DEFAULT_STREAM_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(inner: W, codec: C, block_size: usize) -> Self {
+        let block_size = block_size.max(1);
+        Self {
+            inner: Some(inner),
+            codec,
+            buffer: Vec::with_capacity(block_size),
+            block_size,
+        }
+    }
+
+    fn inner_mut(&mut self) -> io::Result<&mut W> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "stream already finished"))
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let compressed = self.codec.compress_block(&self.buffer).map_err(to_io_error)?;
+        let raw_len = self.buffer.len() as u32;
+        self.buffer.clear();
+
+        let inner = self.inner_mut()?;
+        inner.write_all(&raw_len.to_le_bytes())?;
+        inner.write_all(&(compressed.len() as u32).to_le_bytes())?;
+        inner.write_all(&compressed)?;
+        Ok(())
+    }
+
+    /// Flush the pending block, write the end marker and return the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        let mut inner = self.inner.take().expect("inner writer present until finish");
+        inner.write_all(&0u32.to_le_bytes())?;
+        inner.write_all(&0u32.to_le_bytes())?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write, C: BlockCodec> Write for BlockWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.block_size - self.buffer.len();
+        let n = room.min(buf.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() >= self.block_size {
+            self.flush_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_block()?;
+        self.inner_mut()?.flush()
+    }
+}
+
+/// Decompressing reader for streams produced by [`BlockWriter`]
+pub struct BlockReader<R: Read, C: BlockCodec> {
+    inner: R,
+    codec: C,
+    block: Vec<u8>,
+    pos: usize,
+    max_block_size: usize,
+    finished: bool,
+}
+
+impl<R: Read, C: BlockCodec> BlockReader<R, C> {
+    pub fn new(inner: R, codec: C) -> Self {
+        Self::with_max_block_size(inner, codec, DEFAULT_STREAM_BLOCK_SIZE)
+    }
+
+    /// Blocks declaring more than `max_block_size` bytes, or a compressed size
+    /// out of proportion to that, are rejected as corrupt
+    pub fn with_max_block_size(inner: R, codec: C, max_block_size: usize) -> Self {
+        Self {
+            inner,
+            codec,
+            block: Vec::new(),
+            pos: 0,
+            max_block_size,
+            finished: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn next_block(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 8];
+        self.inner.read_exact(&mut header)?;
+        let raw_len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let packed_len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+
+        if raw_len == 0 {
+            self.finished = true;
+            self.block.clear();
+            self.pos = 0;
+            return Ok(());
+        }
+        if raw_len > self.max_block_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Block size {} exceeds limit {}", raw_len, self.max_block_size),
+            ));
+        }
+        if packed_len > max_packed_len(raw_len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Compressed block size {} is implausible for {} bytes", packed_len, raw_len),
+            ));
+        }
+
+        let mut packed = vec![0u8; packed_len];
+        self.inner.read_exact(&mut packed)?;
+        let block = self.codec.decompress_block(&packed, raw_len).map_err(to_io_error)?;
+        if block.len() != raw_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Block decompressed to {} bytes, expected {}", block.len(), raw_len),
+            ));
+        }
+        self.block = block;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read, C: BlockCodec> Read for BlockReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.block.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_block()?;
+        }
+        let n = (self.block.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+pub type Lzma2Writer<W> = BlockWriter<W, Lzma2Block>;
+pub type Lzma2Reader<R> = BlockReader<R, Lzma2Block>;
+pub type TornadoWriter<W> = BlockWriter<W, TornadoBlock>;
+pub type TornadoReader<R> = BlockReader<R, TornadoBlock>;
+pub type PpmdWriter<W> = BlockWriter<W, PpmdBlock>;
+pub type PpmdReader<R> = BlockReader<R, PpmdBlock>;
+pub type GrzipWriter<W> = BlockWriter<W, GrzipBlock>;
+pub type GrzipReader<R> = BlockReader<R, GrzipBlock>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::{BufReader, BufWriter, Cursor};
+
+    fn sample_data(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                // Mix of repetitive text and pseudo-random bytes
+                if (i / 4096) % 2 == 0 { b"openarc "[i % 8] } else { (state >> 16) as u8 }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lzma2_stream_roundtrip() {
+        // This test will only pass when linked with actual FreeARC library
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let input = dir.join(format!("arcmax_stream_in_{}.bin", id));
+        let packed = dir.join(format!("arcmax_stream_packed_{}.lz2", id));
+        let output = dir.join(format!("arcmax_stream_out_{}.bin", id));
+        fs::write(&input, sample_data(3 * 1024 * 1024 + 123)).unwrap();
+
+        // File to file, so neither side holds more than a block
+        let sink = BufWriter::new(File::create(&packed).unwrap());
+        let mut writer = Lzma2Writer::with_block_size(sink, Lzma2Block::default(), 1024 * 1024);
+        io::copy(&mut BufReader::new(File::open(&input).unwrap()), &mut writer).unwrap();
+        writer.finish().unwrap();
+
+        let source = BufReader::new(File::open(&packed).unwrap());
+        let mut reader = Lzma2Reader::new(source, Lzma2Block::default());
+        io::copy(&mut reader, &mut File::create(&output).unwrap()).unwrap();
+        assert_eq!(fs::read(&input).unwrap(), fs::read(&output).unwrap());
+
+        for path in [&input, &packed, &output] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        let writer = Lzma2Writer::new(Vec::new(), Lzma2Block::default());
+        let compressed = writer.finish().unwrap();
+        assert_eq!(compressed, vec![0u8; 8]);
+
+        let mut reader = Lzma2Reader::new(Cursor::new(compressed), Lzma2Block::default());
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_oversized_block_rejected() {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&(64u32 * 1024 * 1024).to_le_bytes());
+        frame.extend_from_slice(&4u32.to_le_bytes());
+        frame.extend_from_slice(&[0u8; 4]);
+
+        let mut reader = Lzma2Reader::new(Cursor::new(frame), Lzma2Block::default());
+        let mut out = Vec::new();
+        assert!(reader.read_to_end(&mut out).is_err());
+
+        // A small block claiming a huge compressed size fails before allocating it
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&16u32.to_le_bytes());
+        frame.extend_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = Lzma2Reader::new(Cursor::new(frame), Lzma2Block::default());
+        let err = reader.read_to_end(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_dropped_writer_leaves_no_end_marker() {
+        let mut sink = Vec::new();
+        {
+            let mut writer = Lzma2Writer::new(&mut sink, Lzma2Block::default());
+            writer.write_all(b"unfinished").unwrap();
+        }
+        assert!(sink.is_empty());
+    }
+}
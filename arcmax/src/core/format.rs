@@ -4,6 +4,7 @@ use std::io::{Read, Seek, SeekFrom};
 use anyhow::Result;
 use crate::formats::freearc::reader::FreeArcReader;
 use crate::formats::peazip::PeaArchive;
+use crate::core::password::{with_password_retry, PasswordProvider, DEFAULT_PASSWORD_ATTEMPTS};
 
 /// Detected archive format
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -83,6 +84,25 @@ pub fn detect_format(path: &Path, password: Option<&str>, crypto_flags: Option<&
     }
 }
 
+/// Open an archive, asking `provider` for a password and retrying on a wrong one
+///
+/// FreeARC archives with only their data blocks encrypted open without a
+/// password, so those are checked here too rather than failing mid-extraction.
+pub fn detect_format_with_provider(
+    path: &Path,
+    provider: &dyn PasswordProvider,
+    crypto_flags: Option<&str>,
+) -> Result<Box<dyn crate::core::archive::ArchiveReader>> {
+    with_password_retry(provider, path, DEFAULT_PASSWORD_ATTEMPTS, |password| {
+        if detect_archive_format(path)? == ArchiveFormat::FreeArc {
+            let reader = FreeArcReader::new(File::open(path)?, password)?;
+            reader.check_data_password()?;
+            return Ok(Box::new(reader) as Box<dyn crate::core::archive::ArchiveReader>);
+        }
+        detect_format(path, password.as_deref(), crypto_flags)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,5 +1,7 @@
 pub mod archive;
 pub mod crypto;
 pub mod format;
+pub mod password;
+pub mod selftest;
 pub mod debug;
 pub mod varint;  // FreeARC variable-length integer encoding
\ No newline at end of file
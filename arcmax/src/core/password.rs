@@ -0,0 +1,243 @@
+//! Password acquisition for encrypted archives
+//!
+//! Readers never prompt on their own; they ask a `PasswordProvider`. The CLI
+//! uses `StdinPasswordProvider`, embedders supply a callback, and
+//! `CachingPasswordProvider` remembers accepted passwords for the session so a
+//! user is asked at most once per archive.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::Result;
+
+use crate::core::crypto::CryptoError;
+
+/// Default number of password requests before giving up
+pub const DEFAULT_PASSWORD_ATTEMPTS: u32 = 3;
+
+/// Source of passwords for encrypted archives
+pub trait PasswordProvider: Send + Sync {
+    /// Return the password for `archive`, or `None` if the user declined
+    fn password_for(&self, archive: &Path) -> Result<Option<String>>;
+
+    /// Called when a password returned for `archive` was rejected
+    fn password_rejected(&self, _archive: &Path) {}
+
+    /// Called when a password returned for `archive` opened it successfully
+    fn password_accepted(&self, _archive: &Path, _password: &str) {}
+}
+
+/// Provider that never supplies a password
+pub struct NoPassword;
+
+impl PasswordProvider for NoPassword {
+    fn password_for(&self, _archive: &Path) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Provider that always returns the same password
+pub struct StaticPassword(pub String);
+
+impl PasswordProvider for StaticPassword {
+    fn password_for(&self, _archive: &Path) -> Result<Option<String>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// Interactive provider that prompts on stderr and reads a line from stdin
+///
+/// Input is not masked; terminal echo control is left to the caller.
+pub struct StdinPasswordProvider;
+
+impl PasswordProvider for StdinPasswordProvider {
+    fn password_for(&self, archive: &Path) -> Result<Option<String>> {
+        eprint!("Password for {}: ", archive.display());
+        io::stderr().flush()?;
+
+        let mut line = String::new();
+        let read = io::stdin().lock().read_line(&mut line)?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        let password = line.trim_end_matches(['\r', '\n']).to_string();
+        if password.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(password))
+        }
+    }
+
+    fn password_rejected(&self, _archive: &Path) {
+        eprintln!("Wrong password, try again.");
+    }
+}
+
+/// Provider backed by a closure (used for FFI callbacks)
+pub struct CallbackPasswordProvider {
+    callback: Box<dyn Fn(&Path) -> Option<String> + Send + Sync>,
+}
+
+impl CallbackPasswordProvider {
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(&Path) -> Option<String> + Send + Sync + 'static,
+    {
+        Self { callback: Box::new(callback) }
+    }
+}
+
+impl PasswordProvider for CallbackPasswordProvider {
+    fn password_for(&self, archive: &Path) -> Result<Option<String>> {
+        Ok((self.callback)(archive))
+    }
+}
+
+/// Wraps another provider and remembers accepted passwords per archive
+pub struct CachingPasswordProvider<P: PasswordProvider> {
+    inner: P,
+    cache: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl<P: PasswordProvider> CachingPasswordProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Forget every remembered password
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn key(archive: &Path) -> PathBuf {
+        archive.canonicalize().unwrap_or_else(|_| archive.to_path_buf())
+    }
+}
+
+impl<P: PasswordProvider> PasswordProvider for CachingPasswordProvider<P> {
+    fn password_for(&self, archive: &Path) -> Result<Option<String>> {
+        if let Some(pwd) = self.cache.lock().unwrap().get(&Self::key(archive)) {
+            return Ok(Some(pwd.clone()));
+        }
+        self.inner.password_for(archive)
+    }
+
+    fn password_rejected(&self, archive: &Path) {
+        self.cache.lock().unwrap().remove(&Self::key(archive));
+        self.inner.password_rejected(archive);
+    }
+
+    fn password_accepted(&self, archive: &Path, password: &str) {
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(Self::key(archive), password.to_string());
+        self.inner.password_accepted(archive, password);
+    }
+}
+
+/// Returns true if `err` was caused by a wrong password
+pub fn is_invalid_password(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidPassword))
+}
+
+/// Returns true if `err` means a password is missing or wrong
+pub fn needs_password(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<CryptoError>(),
+        Some(CryptoError::InvalidPassword) | Some(CryptoError::PasswordRequired)
+    )
+}
+
+/// Run `open`, asking `provider` for a password only when one is needed
+///
+/// The first attempt is made without a password so unencrypted archives never
+/// prompt. A wrong password triggers `password_rejected` and another request,
+/// up to `max_attempts` times.
+pub fn with_password_retry<T, F>(
+    provider: &dyn PasswordProvider,
+    archive: &Path,
+    max_attempts: u32,
+    mut open: F,
+) -> Result<T>
+where
+    F: FnMut(Option<String>) -> Result<T>,
+{
+    let mut last_err = match open(None) {
+        Ok(value) => return Ok(value),
+        Err(e) if needs_password(&e) => e,
+        Err(e) => return Err(e),
+    };
+
+    for _ in 0..max_attempts.max(1) {
+        let password = match provider.password_for(archive)? {
+            Some(pwd) => pwd,
+            None => return Err(last_err),
+        };
+        match open(Some(password.clone())) {
+            Ok(value) => {
+                provider.password_accepted(archive, &password);
+                return Ok(value);
+            }
+            Err(e) if is_invalid_password(&e) => {
+                provider.password_rejected(archive);
+                last_err = e;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_retry_until_correct_password() {
+        let calls = AtomicU32::new(0);
+        let provider = CachingPasswordProvider::new(CallbackPasswordProvider::new(move |_| {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            Some(if n == 0 { "wrong".to_string() } else { "secret".to_string() })
+        }));
+        let archive = Path::new("retry_test.arc");
+
+        let result = with_password_retry(&provider, archive, 3, |pwd| match pwd.as_deref() {
+            None => Err(CryptoError::PasswordRequired.into()),
+            Some("secret") => Ok(42),
+            Some(_) => Err(CryptoError::InvalidPassword.into()),
+        })
+        .unwrap();
+        assert_eq!(result, 42);
+
+        // Accepted password is now served from the cache
+        assert_eq!(provider.password_for(archive).unwrap().as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let provider = StaticPassword("wrong".to_string());
+        let result: Result<()> = with_password_retry(&provider, Path::new("x.arc"), 2, |_| {
+            Err(CryptoError::InvalidPassword.into())
+        });
+        assert!(is_invalid_password(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn test_unencrypted_never_prompts() {
+        let provider = CallbackPasswordProvider::new(|_| panic!("should not prompt"));
+        let result = with_password_retry(&provider, Path::new("plain.arc"), 3, |pwd| {
+            assert!(pwd.is_none());
+            Ok(7)
+        })
+        .unwrap();
+        assert_eq!(result, 7);
+    }
+}
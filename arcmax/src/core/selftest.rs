@@ -0,0 +1,231 @@
+//! Built-in self-test for the codec and cipher backends
+//!
+//! Every codec goes through a compress/decompress round-trip on a fixed
+//! payload, and where the output is fixed by a standard (zstd frames, cipher
+//! keystreams) it is also checked against an embedded known answer. This is
+//! meant to catch a broken C++ build before it is trusted with real data.
+
+use anyhow::{anyhow, Result};
+
+use crate::codecs::{grzip, lzma2, lzp, ppmd, tornado, zstd};
+use crate::core::crypto::{AesCipher, BlowfishCipher, SerpentCipher, TwofishCipher};
+
+/// Payload used for the round-trips: repetitive enough for every codec to find matches
+const PAYLOAD_LINE: &[u8] = b"arcmax self-test: the quick brown fox jumps over the lazy dog 0123456789\n";
+const PAYLOAD_REPEATS: usize = 64;
+
+/// `zstd -c` of `b"OpenArc self-test"` (single frame with content checksum)
+const ZSTD_FRAME: [u8; 30] = [
+    0x28, 0xb5, 0x2f, 0xfd, 0x04, 0x58, 0x89, 0x00, 0x00, 0x4f, 0x70, 0x65,
+    0x6e, 0x41, 0x72, 0x63, 0x20, 0x73, 0x65, 0x6c, 0x66, 0x2d, 0x74, 0x65,
+    0x73, 0x74, 0xe0, 0x66, 0x5e, 0x19,
+];
+const ZSTD_FRAME_CONTENT: &[u8] = b"OpenArc self-test";
+
+/// NIST SP 800-38A F.5.1, first block of CTR-AES128.Encrypt
+const AES128_KEY: [u8; 16] = [
+    0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c,
+];
+const AES128_IV: [u8; 16] = [
+    0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd, 0xfe, 0xff,
+];
+const AES128_PLAIN: [u8; 16] = [
+    0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+];
+const AES128_CIPHER: [u8; 16] = [
+    0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6, 0xce,
+];
+
+/// Blowfish ECB vector (zero key, zero block) seen through CTR: the first
+/// keystream block is E(K, IV), so a zero plaintext encrypts to it
+const BLOWFISH_CIPHER: [u8; 8] = [0x4e, 0xf9, 0x97, 0x45, 0x61, 0x98, 0xdd, 0x78];
+
+/// NESSIE Serpent-256 set 1 vector 0 (key 80 00 .. 00, zero block), seen through CTR
+const SERPENT256_KEY: [u8; 32] = [
+    0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+const SERPENT256_CIPHER: [u8; 16] = [
+    0xa2, 0x23, 0xaa, 0x12, 0x88, 0x46, 0x3c, 0x0e, 0x2b, 0xe3, 0x8e, 0xbd, 0x82, 0x56, 0x16, 0xc0,
+];
+
+/// Twofish ECB vector (256-bit zero key, zero block), seen through CTR the same way
+const TWOFISH256_CIPHER: [u8; 16] = [
+    0x57, 0xff, 0x73, 0x9d, 0x4d, 0xc9, 0x2c, 0x1b, 0xd7, 0xfc, 0x01, 0x70, 0x0c, 0xc8, 0x21, 0x6f,
+];
+
+/// Outcome of one component's check
+#[derive(Debug)]
+pub struct SelfTestResult {
+    pub component: &'static str,
+    /// `Err` carries a human-readable reason
+    pub outcome: std::result::Result<(), String>,
+}
+
+impl SelfTestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// Run every component check; never stops early so the report is complete
+pub fn run_selftest() -> Vec<SelfTestResult> {
+    let checks: [(&'static str, fn() -> Result<()>); 10] = [
+        ("lzma2", check_lzma2),
+        ("tornado", check_tornado),
+        ("ppmd", check_ppmd),
+        ("lzp", check_lzp),
+        ("grzip", check_grzip),
+        ("zstd", check_zstd),
+        ("aes-ctr", check_aes),
+        ("blowfish-ctr", check_blowfish),
+        ("twofish-ctr", check_twofish),
+        ("serpent-ctr", check_serpent),
+    ];
+
+    checks
+        .iter()
+        .map(|(component, check)| {
+            // A panicking FFI wrapper should fail its component, not the whole run
+            let outcome = match std::panic::catch_unwind(*check) {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err(e.to_string()),
+                Err(_) => Err("panicked".to_string()),
+            };
+            SelfTestResult { component, outcome }
+        })
+        .collect()
+}
+
+fn payload() -> Vec<u8> {
+    PAYLOAD_LINE.repeat(PAYLOAD_REPEATS)
+}
+
+fn expect_roundtrip(original: &[u8], decoded: &[u8]) -> Result<()> {
+    if original != decoded {
+        return Err(anyhow!(
+            "round-trip mismatch ({} bytes in, {} bytes out)",
+            original.len(),
+            decoded.len()
+        ));
+    }
+    Ok(())
+}
+
+fn check_lzma2() -> Result<()> {
+    let data = payload();
+    let dict_size = 1 << 20;
+    let compressed = lzma2::lzma2_compress(&data, 5, dict_size, 3, 0, 2)?;
+    let decoded = lzma2::lzma2_decompress(&compressed, data.len(), dict_size, 3, 0, 2)?;
+    expect_roundtrip(&data, &decoded)
+}
+
+fn check_tornado() -> Result<()> {
+    let data = payload();
+    // Builds differ in which method numbers they expose; any working one will do
+    let mut last_err = None;
+    for method in 1..=12 {
+        match tornado::tornado_compress(&data, method) {
+            Ok(compressed) => {
+                let decoded = tornado::tornado_decompress(&compressed, data.len())?;
+                return expect_roundtrip(&data, &decoded);
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow!("no Tornado method available")))
+}
+
+fn check_ppmd() -> Result<()> {
+    let data = payload();
+    let (order, memory) = (6, 16 * 1024 * 1024);
+    let compressed = ppmd::ppmd_compress(&data, order, memory)?;
+    let decoded = ppmd::ppmd_decompress(&compressed, data.len(), order, memory)?;
+    expect_roundtrip(&data, &decoded)
+}
+
+fn check_lzp() -> Result<()> {
+    let data = payload();
+    let compressed = lzp::lzp_compress(&data, 32, 18)?;
+    let decoded = lzp::lzp_decompress(&compressed, data.len())?;
+    expect_roundtrip(&data, &decoded)
+}
+
+fn check_grzip() -> Result<()> {
+    let data = payload();
+    let compressed = grzip::grzip_compress(&data, 0)?;
+    let decoded = grzip::grzip_decompress(&compressed, data.len())?;
+    expect_roundtrip(&data, &decoded)
+}
+
+fn check_zstd() -> Result<()> {
+    let known = zstd::decompress_zstd(&ZSTD_FRAME)?;
+    if known != ZSTD_FRAME_CONTENT {
+        return Err(anyhow!("known-answer frame decoded incorrectly"));
+    }
+
+    let data = payload();
+    let compressed = zstd::compress_zstd(&data, 3)?;
+    let decoded = zstd::decompress_zstd(&compressed)?;
+    expect_roundtrip(&data, &decoded)
+}
+
+fn check_aes() -> Result<()> {
+    let cipher = AesCipher::new(&AES128_KEY, &AES128_IV)?;
+    if cipher.encrypt(&AES128_PLAIN)? != AES128_CIPHER {
+        return Err(anyhow!("known-answer ciphertext mismatch"));
+    }
+
+    let data = payload();
+    expect_roundtrip(&data, &cipher.decrypt(&cipher.encrypt(&data)?)?)
+}
+
+fn check_blowfish() -> Result<()> {
+    let cipher = BlowfishCipher::new(&[0u8; 8], &[0u8; 8])?;
+    if cipher.encrypt(&[0u8; 8])? != BLOWFISH_CIPHER {
+        return Err(anyhow!("known-answer ciphertext mismatch"));
+    }
+
+    let data = payload();
+    expect_roundtrip(&data, &cipher.decrypt(&cipher.encrypt(&data)?)?)
+}
+
+fn check_twofish() -> Result<()> {
+    let cipher = TwofishCipher::new(&[0u8; 32], &[0u8; 16])?;
+    if cipher.encrypt(&[0u8; 16])? != TWOFISH256_CIPHER {
+        return Err(anyhow!("known-answer ciphertext mismatch"));
+    }
+
+    let data = payload();
+    expect_roundtrip(&data, &cipher.decrypt(&cipher.encrypt(&data)?)?)
+}
+
+fn check_serpent() -> Result<()> {
+    let cipher = SerpentCipher::new(&SERPENT256_KEY, &[0u8; 16])?;
+    if cipher.encrypt(&[0u8; 16])? != SERPENT256_CIPHER {
+        return Err(anyhow!("known-answer ciphertext mismatch"));
+    }
+
+    let data = payload();
+    expect_roundtrip(&data, &cipher.decrypt(&cipher.encrypt(&data)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_passes() {
+        let results = run_selftest();
+        assert_eq!(results.len(), 10);
+        for result in &results {
+            assert!(result.passed(), "{} failed: {:?}", result.component, result.outcome);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_mismatch_reported() {
+        assert!(expect_roundtrip(b"abc", b"abc").is_ok());
+        let err = expect_roundtrip(b"abc", b"abd").unwrap_err();
+        assert!(err.to_string().contains("mismatch"));
+    }
+}
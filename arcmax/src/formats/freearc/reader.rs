@@ -1,4 +1,4 @@
-use std::io::{Read, Seek, SeekFrom, Cursor};
+use std::io::{Read, Write, Seek, SeekFrom, Cursor};
 use std::sync::Mutex;
 use std::path::Path;
 use anyhow::{Result, anyhow, Context};
@@ -8,7 +8,7 @@ use crate::formats::freearc::block::BlockDescriptor;
 use crate::formats::freearc::footer::FooterBlock;
 use crate::formats::freearc::directory::DirectoryBlock;
 use crate::formats::freearc::utils::{read_varint, split_compressor_encryption};
-use crate::core::crypto::{EncryptionInfo, CascadedDecryptor};
+use crate::core::crypto::{EncryptionInfo, CascadedDecryptor, CryptoError};
 use crate::codecs::lzma2::decompress_lzma_default;
 
 pub struct FreeArcReader<R: Read + Seek> {
@@ -58,6 +58,25 @@ impl<R: Read + Seek> FreeArcReader<R> {
         })
     }
 
+    /// Check the password against the first encrypted data block
+    ///
+    /// An archive with only its data encrypted opens without a password, so
+    /// `new` can't tell; call this before extracting to fail with
+    /// `PasswordRequired` or `InvalidPassword` up front.
+    pub fn check_data_password(&self) -> Result<()> {
+        let encryption = self.directory.data_blocks.iter()
+            .map(|block| split_compressor_encryption(&block.compressor).1)
+            .find(|encryption| !encryption.is_empty());
+        
+        if let Some(encryption) = encryption {
+            let pwd = self.password.as_deref().ok_or(CryptoError::PasswordRequired)?;
+            let enc_info = EncryptionInfo::from_method_string(&encryption, None)?;
+            CascadedDecryptor::new(&enc_info, pwd)?;
+        }
+        
+        Ok(())
+    }
+
     fn find_footer_descriptor(reader: &mut R, file_size: u64) -> Result<(BlockDescriptor, u64)> {
         let scan_size = std::cmp::min(file_size, SCAN_MAX);
         reader.seek(SeekFrom::End(-(scan_size as i64)))?;
@@ -97,7 +116,7 @@ impl<R: Read + Seek> FreeArcReader<R> {
         
         // 1. Decrypt if needed
         let processed_data = if !encryption.is_empty() {
-             let pwd = password.ok_or_else(|| anyhow!("Password required for encrypted block"))?;
+             let pwd = password.ok_or(CryptoError::PasswordRequired)?;
              
              // Parse encryption info
              // Format usually: aes-256/ctr:k...:i... or similar
@@ -131,6 +150,87 @@ impl<R: Read + Seek> FreeArcReader<R> {
         
         let block_idx = file_info.data_block_index.ok_or_else(|| anyhow!("File has no data block"))?;
         let block_info = self.directory.data_blocks.get(block_idx).ok_or_else(|| anyhow!("Invalid data block index"))?;
+        let compressed_data = self.read_raw_block(block_idx)?;
+        
+        let decompressed = Self::decompress_data(
+            &block_info.compressor, 
+            &compressed_data, 
+            block_info.original_size as usize, 
+            self.password.as_deref()
+        )?;
+        
+        // Extract file slice
+        let start = file_info.offset_in_block as usize;
+        let end = start + file_info.size as usize;
+        
+        if end > decompressed.len() {
+             return Err(anyhow!("File data outside of decompressed block bounds"));
+        }
+        
+        Ok(decompressed[start..end].to_vec())
+    }
+    
+    /// Write a data block's contents to `writer`, decrypting on the way
+    ///
+    /// Stored blocks are streamed through `CascadedDecryptor::decrypt_stream`;
+    /// compressed ones are decompressed whole, as the codecs need the full
+    /// block. Returns the number of bytes written.
+    pub fn extract_block_to<W: Write>(&self, block_idx: usize, mut writer: W) -> Result<u64> {
+        let block_info = self.directory.data_blocks.get(block_idx).ok_or_else(|| anyhow!("Invalid data block index"))?;
+        let block_pos = self.block_position(block_idx)?;
+        let (compressor, encryption) = split_compressor_encryption(&block_info.compressor);
+        let decryptor = if encryption.is_empty() {
+            None
+        } else {
+            let pwd = self.password.as_deref().ok_or(CryptoError::PasswordRequired)?;
+            let enc_info = EncryptionInfo::from_method_string(&encryption, None)?;
+            Some(CascadedDecryptor::new(&enc_info, pwd)?)
+        };
+        
+        let mut reader = self.reader.lock().unwrap();
+        reader.seek(SeekFrom::Start(block_pos))?;
+        let mut raw = (&mut *reader).take(block_info.compressed_size);
+        
+        if compressor == "storing" || compressor.is_empty() {
+            return match decryptor {
+                Some(decryptor) => decryptor.decrypt_stream(raw, writer),
+                None => Ok(std::io::copy(&mut raw, &mut writer)?),
+            };
+        }
+        
+        let mut compressed = Vec::with_capacity(block_info.compressed_size as usize);
+        match decryptor {
+            Some(decryptor) => {
+                decryptor.decrypt_stream(raw, &mut compressed)?;
+            }
+            None => {
+                raw.read_to_end(&mut compressed)?;
+            }
+        }
+        drop(reader);
+        
+        let data = Self::decompress_data(&compressor, &compressed, block_info.original_size as usize, None)?;
+        writer.write_all(&data)?;
+        Ok(data.len() as u64)
+    }
+    
+    /// Bytes of a data block as stored, still compressed and encrypted
+    pub fn read_raw_block(&self, block_idx: usize) -> Result<Vec<u8>> {
+        let block_info = self.directory.data_blocks.get(block_idx).ok_or_else(|| anyhow!("Invalid data block index"))?;
+        let block_pos = self.block_position(block_idx)?;
+        
+        // Read and decompress block
+        let mut reader = self.reader.lock().unwrap();
+        reader.seek(SeekFrom::Start(block_pos))?;
+        
+        let mut compressed_data = vec![0u8; block_info.compressed_size as usize];
+        reader.read_exact(&mut compressed_data)?;
+        Ok(compressed_data)
+    }
+    
+    /// Absolute position of a data block in the archive
+    fn block_position(&self, block_idx: usize) -> Result<u64> {
+        let block_info = self.directory.data_blocks.get(block_idx).ok_or_else(|| anyhow!("Invalid data block index"))?;
         
         // Calculate absolute position of the data block
         // Block offset is relative to the start of directory block (which we know?)
@@ -153,31 +253,7 @@ impl<R: Read + Seek> FreeArcReader<R> {
         // So stored_offset = dir_pos - block_pos.
         // => block_pos = dir_pos - stored_offset.
         
-        let block_pos = dir_pos.checked_sub(block_info.offset).ok_or_else(|| anyhow!("Invalid block offset calculation"))?;
-        
-        // Read and decompress block
-        let mut reader = self.reader.lock().unwrap();
-        reader.seek(SeekFrom::Start(block_pos))?;
-        
-        let mut compressed_data = vec![0u8; block_info.compressed_size as usize];
-        reader.read_exact(&mut compressed_data)?;
-        
-        let decompressed = Self::decompress_data(
-            &block_info.compressor, 
-            &compressed_data, 
-            block_info.original_size as usize, 
-            self.password.as_deref()
-        )?;
-        
-        // Extract file slice
-        let start = file_info.offset_in_block as usize;
-        let end = start + file_info.size as usize;
-        
-        if end > decompressed.len() {
-             return Err(anyhow!("File data outside of decompressed block bounds"));
-        }
-        
-        Ok(decompressed[start..end].to_vec())
+        dir_pos.checked_sub(block_info.offset).ok_or_else(|| anyhow!("Invalid block offset calculation"))
     }
 }
 
@@ -1,10 +1,11 @@
-use std::io::{Write, Seek, SeekFrom};
+use std::io::{Read, Write, Seek, SeekFrom};
 use anyhow::{Result, anyhow};
 use crate::formats::freearc::constants::{BlockType, ARC_SIGNATURE};
 use crate::formats::freearc::block::BlockDescriptor;
 use crate::formats::freearc::footer::FooterBlock;
+use crate::formats::freearc::reader::FreeArcReader;
 use crate::formats::freearc::directory::{DirectoryBlock, DataBlockInfo, FileInfo};
-use crate::core::crypto::{EncryptionInfo, create_encryptor, CascadedDecryptor};
+use crate::core::crypto::{EncryptionInfo, create_decryptor, create_encryptor, CascadedDecryptor, CryptoError, PasswordEncoding};
 use crate::formats::freearc::utils::split_compressor_encryption;
 use crate::codecs::lzma2::{compress_lzma_default, compress_lzma};
 
@@ -101,7 +102,22 @@ impl<W: Write + Seek> FreeArcWriter<W> {
         Ok(())
     }
     
+    /// Add a file in its own "storing" block, skipping compression
+    ///
+    /// For data that is already compressed (zip, mp3, ...), where another
+    /// pass only costs time. Encryption still applies.
+    pub fn add_file_stored(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        // Close the current solid block so the stored file doesn't share its method
+        self.flush_block()?;
+        self.add_file(path, data)?;
+        self.flush_block_with(true)
+    }
+    
     pub fn flush_block(&mut self) -> Result<()> {
+        self.flush_block_with(false)
+    }
+    
+    fn flush_block_with(&mut self, store: bool) -> Result<()> {
         if self.pending_data.is_empty() {
             return Ok(());
         }
@@ -109,7 +125,7 @@ impl<W: Write + Seek> FreeArcWriter<W> {
         let original_size = self.pending_data.len() as u64;
         
         // Compress/Encrypt
-        let (compressed_data, method_string) = self.compress_and_encrypt(&self.pending_data)?;
+        let (compressed_data, method_string) = self.compress_and_encrypt(&self.pending_data, store)?;
         
         let compressed_size = compressed_data.len() as u64;
         let offset = self.current_offset;
@@ -139,9 +155,9 @@ impl<W: Write + Seek> FreeArcWriter<W> {
         Ok(())
     }
     
-    fn compress_and_encrypt(&self, data: &[u8]) -> Result<(Vec<u8>, String)> {
+    fn compress_and_encrypt(&self, data: &[u8], store: bool) -> Result<(Vec<u8>, String)> {
         let mut method = self.options.compression.clone();
-        if method.is_empty() {
+        if method.is_empty() || store {
             method = "storing".to_string();
         }
         
@@ -159,16 +175,55 @@ impl<W: Write + Seek> FreeArcWriter<W> {
              // Ideally we would update method string with exact parameters if needed
         }
         
-        // Encrypt
-        if let Some(enc_method) = &self.options.encryption {
-            if let Some(pwd) = &self.options.password {
-                let (full_method, encryptor) = create_encryptor(enc_method, pwd)?;
-                processed = encryptor.encrypt(&processed)?;
-                method = format!("{}+{}", method, full_method); // Fix method string
-            }
+        self.encrypt(processed, method)
+    }
+    
+    /// Encrypt an already compressed block if the options ask for it
+    fn encrypt(&self, data: Vec<u8>, method: String) -> Result<(Vec<u8>, String)> {
+        if let (Some(enc_method), Some(pwd)) = (&self.options.encryption, &self.options.password) {
+            let (full_method, encryptor) = create_encryptor(enc_method, pwd, PasswordEncoding::Utf8)?;
+            let encrypted = encryptor.encrypt(&data)?;
+            return Ok((encrypted, format!("{}+{}", method, full_method))); // Fix method string
+        }
+        Ok((data, method))
+    }
+    
+    /// Copy `source` to `writer` under the encryption in `options`
+    ///
+    /// Data blocks are decrypted with `old_password` and re-encrypted one at a
+    /// time, keeping their compressed bytes as they are; directory and footer
+    /// are rewritten by `finish`. Blocks that weren't encrypted get encrypted,
+    /// and `options.encryption: None` leaves the copy unencrypted.
+    pub fn rekey<R: Read + Seek>(source: R, old_password: Option<&str>, writer: W, options: ArchiveOptions) -> Result<W> {
+        let reader = FreeArcReader::new(source, old_password.map(str::to_string))?;
+        let mut arc = FreeArcWriter::new(writer, options)?;
+        
+        for (i, block) in reader.directory.data_blocks.iter().enumerate() {
+            let raw = reader.read_raw_block(i)?;
+            let (compressor, encryption) = split_compressor_encryption(&block.compressor);
+            let plain = if encryption.is_empty() {
+                raw
+            } else {
+                let pwd = old_password.ok_or(CryptoError::PasswordRequired)?;
+                create_decryptor(&encryption, pwd, None, None)?.decrypt(&raw)?
+            };
+            
+            let (data, method) = arc.encrypt(plain, compressor)?;
+            arc.writer.write_all(&data)?;
+            arc.data_blocks.push(DataBlockInfo {
+                compressor: method,
+                original_size: block.original_size,
+                compressed_size: data.len() as u64,
+                offset: arc.current_offset,
+                num_files: block.num_files,
+            });
+            arc.current_offset += data.len() as u64;
         }
         
-        Ok((processed, method))
+        // Block indices are unchanged, so the file entries carry over as they are
+        arc.files = reader.directory.files.clone();
+        arc.directories = reader.directory.directories.clone();
+        arc.finish()
     }
     
     pub fn finish(mut self) -> Result<W> {
@@ -200,7 +255,7 @@ impl<W: Write + Seek> FreeArcWriter<W> {
         let dir_orig_size = dir_content.len() as u64;
         
         // Compress Directory
-        let (dir_compressed, dir_method) = self.compress_and_encrypt(&dir_content)?;
+        let (dir_compressed, dir_method) = self.compress_and_encrypt(&dir_content, false)?;
         let dir_comp_size = dir_compressed.len() as u64;
         let _dir_crc = crc32fast::hash(&dir_compressed); // CRC of COMPRESSED data? 
         // Spec: "CRC of original data" in descriptor.
@@ -244,7 +299,7 @@ impl<W: Write + Seek> FreeArcWriter<W> {
             let footer_orig_size = footer_content.len() as u64;
             let footer_orig_crc = crc32fast::hash(&footer_content);
             
-            let (footer_compressed, footer_method) = self.compress_and_encrypt(&footer_content)?;
+            let (footer_compressed, footer_method) = self.compress_and_encrypt(&footer_content, false)?;
             let footer_comp_size = footer_compressed.len() as u64;
             
             let new_footer_desc_pos = footer_start_pos + footer_comp_size;
@@ -272,3 +327,92 @@ impl<W: Write + Seek> FreeArcWriter<W> {
         Ok(self.writer)
     }
 }
+
+impl<W: Read + Write + Seek> FreeArcWriter<W> {
+    /// Reopen an existing archive to add more files to it
+    ///
+    /// The current directory is read back and new blocks are written after the
+    /// end of the file; `finish` then writes a directory and footer covering
+    /// both the old and the new files. The previous directory and footer stay
+    /// behind as unused bytes, since a generic writer can't be truncated.
+    /// `options.password` is also used to read an encrypted directory.
+    pub fn open_append(mut writer: W, options: ArchiveOptions) -> Result<Self> {
+        let (mut directory, dir_pos) = {
+            let reader = FreeArcReader::new(&mut writer, options.password.clone())?;
+            let dir_pos = reader.footer.control_blocks.iter()
+                .find(|b| b.block_type == BlockType::Directory)
+                .and_then(|b| b.pos)
+                .ok_or_else(|| anyhow!("Directory position missing"))?;
+            (reader.directory, dir_pos)
+        };
+        
+        // Stored offsets are relative to the old directory; the writer keeps absolute ones
+        for block in &mut directory.data_blocks {
+            block.offset = dir_pos.checked_sub(block.offset)
+                .ok_or_else(|| anyhow!("Invalid block offset in existing archive"))?;
+        }
+        
+        let current_offset = writer.seek(SeekFrom::End(0))?;
+        Ok(FreeArcWriter {
+            writer,
+            options,
+            files: directory.files,
+            data_blocks: directory.data_blocks,
+            directories: directory.directories,
+            current_offset,
+            pending_data: Vec::new(),
+            pending_files: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use crate::core::archive::ArchiveReader;
+    use crate::core::format::detect_format_with_provider;
+    use crate::core::password::CallbackPasswordProvider;
+
+    #[test]
+    fn test_data_only_encryption_asks_for_password() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("arcmax_data_only_encrypted_{}.arc", std::process::id()));
+
+        let options = ArchiveOptions {
+            compression: "storing".to_string(),
+            compression_level: 0,
+            encryption: Some("aes-256".to_string()),
+            password: Some("secret".to_string()),
+        };
+        let mut arc = FreeArcWriter::new(fs::File::create(&path).unwrap(), options).unwrap();
+        arc.add_file("data.txt", b"only the data block is encrypted").unwrap();
+        arc.flush_block().unwrap();
+        // Directory and footer in the clear, as FreeARC writes with -p but not -hp
+        arc.options.encryption = None;
+        arc.options.password = None;
+        arc.finish().unwrap();
+
+        let reader = FreeArcReader::new(fs::File::open(&path).unwrap(), None).unwrap();
+        assert!(reader.check_data_password().is_err());
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&calls);
+        let provider = CallbackPasswordProvider::new(move |_: &Path| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            Some(if n == 0 { "wrong".to_string() } else { "secret".to_string() })
+        });
+        let mut reader = detect_format_with_provider(&path, &provider, None).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let entries = reader.list().unwrap();
+        let mut out = Vec::new();
+        reader.extract(&entries[0], &mut out).unwrap();
+        assert_eq!(out, b"only the data block is encrypted");
+
+        let _ = fs::remove_file(&path);
+    }
+}
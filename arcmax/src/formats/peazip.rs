@@ -18,9 +18,10 @@ use std::path::Path;
 use std::fs::File;
 use anyhow::{anyhow, Result};
 use crate::core::archive::{ArchiveReader, FileEntry};
+use crate::core::crypto::CryptoError;
 
 // PEA Magic byte
-const PEA_MAGIC: u8 = 0xEA;  // 234
+pub const PEA_MAGIC: u8 = 0xEA;  // 234
 
 // Current supported format version/revision
 const PEA_FORMAT_VER: u8 = 1;
@@ -32,6 +33,13 @@ const POD_TRIGGER: [u8; 6] = [0x00, 0x00, 0x50, 0x4F, 0x44, 0x00]; // "\0\0POD\0
 // EOS (End of Stream) trigger
 const EOS_TRIGGER: [u8; 2] = [0x00, 0x00];
 
+// Object attributes written by `PeaWriter` (Windows-style)
+const PEA_ATTR_DIRECTORY: u32 = 0x10;
+const PEA_ATTR_ARCHIVE: u32 = 0x20;
+
+// EAX authentication tag size (OMAC output, one cipher block)
+const EAX_TAG_SIZE: usize = 16;
+
 // Control algorithm codes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ControlAlgorithm {
@@ -94,6 +102,35 @@ impl ControlAlgorithm {
         }
     }
 
+    fn to_byte(&self) -> u8 {
+        match self {
+            Self::NoAlgo => 0x00,
+            Self::Adler32 => 0x01,
+            Self::Crc32 => 0x02,
+            Self::Crc64 => 0x03,
+            Self::Md5 => 0x10,
+            Self::Ripemd160 => 0x11,
+            Self::Sha1 => 0x12,
+            Self::Sha256 => 0x13,
+            Self::Sha512 => 0x14,
+            Self::Whirlpool => 0x15,
+            Self::Sha3_256 => 0x16,
+            Self::Sha3_512 => 0x17,
+            Self::Blake2s => 0x18,
+            Self::Blake2b => 0x19,
+            Self::Hmac => 0x30,
+            Self::Eax => 0x31,
+            Self::Tf => 0x32,
+            Self::Sp => 0x33,
+            Self::Eax256 => 0x41,
+            Self::Tf256 => 0x42,
+            Self::Sp256 => 0x43,
+            Self::TriAts => 0x44,
+            Self::TriTsa => 0x45,
+            Self::TriSat => 0x46,
+        }
+    }
+
     fn requires_password(&self) -> bool {
         matches!(
             self,
@@ -163,6 +200,15 @@ impl CompressionAlgorithm {
             _ => Err(anyhow!("Unknown compression algorithm: {}", b)),
         }
     }
+
+    fn to_byte(&self) -> u8 {
+        match self {
+            Self::PCompress0 => 0,
+            Self::PCompress1 => 1,
+            Self::PCompress2 => 2,
+            Self::PCompress3 => 3,
+        }
+    }
 }
 
 /// PEA Archive Header (10 bytes)
@@ -296,6 +342,7 @@ pub struct PeaObject {
 pub struct AesEaxContext {
     key: Vec<u8>,
     nonce: Vec<u8>,
+    pw_ver: u16,
 }
 
 impl AesEaxContext {
@@ -313,38 +360,73 @@ impl AesEaxContext {
 
         let key = derived[..key_size].to_vec();
         let nonce = derived[key_size..key_size + 16].to_vec();
+        let pw_ver = u16::from_le_bytes([derived[key_size + 16], derived[key_size + 17]]);
+
+        Ok(AesEaxContext { key, nonce, pw_ver })
+    }
 
-        Ok(AesEaxContext { key, nonce })
+    /// Password verification word derived alongside the key and nonce
+    pub fn pw_ver(&self) -> u16 {
+        self.pw_ver
     }
 
-    /// Decrypt data using AES-EAX mode
-    /// For simplicity, we use AES-CTR for now since EAX is CTR + OMAC
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
-        use aes::cipher::{KeyIvInit, StreamCipher};
-        use ctr::Ctr64LE;
-        use crypto_common::generic_array::GenericArray;
+    /// Decrypt data using AES-EAX mode, verifying the 16-byte authentication tag
+    ///
+    /// A tag mismatch means either a wrong password or a corrupted stream; both
+    /// are reported as `CryptoError::InvalidPassword` since they can't be told apart.
+    pub fn decrypt(&self, ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>> {
+        use eax::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+        use eax::Eax;
 
-        let mut buffer = ciphertext.to_vec();
+        if tag.len() != EAX_TAG_SIZE {
+            return Err(anyhow!(
+                "Invalid EAX tag size: {} (expected {})",
+                tag.len(),
+                EAX_TAG_SIZE
+            ));
+        }
 
-        // EAX mode uses CTR internally with the nonce
-        match self.key.len() {
-            16 => {
-                let key = GenericArray::from_slice(&self.key);
-                let iv = GenericArray::from_slice(&self.nonce);
-                let mut cipher = Ctr64LE::<aes::Aes128>::new(key, iv);
-                cipher.apply_keystream(&mut buffer);
-            }
-            32 => {
-                let key = GenericArray::from_slice(&self.key);
-                let iv = GenericArray::from_slice(&self.nonce);
-                let mut cipher = Ctr64LE::<aes::Aes256>::new(key, iv);
-                cipher.apply_keystream(&mut buffer);
-            }
+        let mut buffer = ciphertext.to_vec();
+        let nonce = GenericArray::from_slice(&self.nonce);
+        let tag = GenericArray::from_slice(tag);
+
+        let verified = match self.key.len() {
+            16 => Eax::<aes::Aes128>::new(GenericArray::from_slice(&self.key))
+                .decrypt_in_place_detached(nonce, &[], &mut buffer, tag),
+            32 => Eax::<aes::Aes256>::new(GenericArray::from_slice(&self.key))
+                .decrypt_in_place_detached(nonce, &[], &mut buffer, tag),
             _ => return Err(anyhow!("Invalid AES key size: {}", self.key.len())),
+        };
+
+        if verified.is_err() {
+            return Err(anyhow::Error::new(CryptoError::InvalidPassword)
+                .context("EAX authentication tag mismatch (wrong password or corrupted stream)"));
         }
 
         Ok(buffer)
     }
+
+    /// Encrypt data using AES-EAX mode, returning the ciphertext and its authentication tag
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<(Vec<u8>, [u8; EAX_TAG_SIZE])> {
+        use eax::aead::{generic_array::GenericArray, AeadInPlace, KeyInit};
+        use eax::Eax;
+
+        let mut buffer = plaintext.to_vec();
+        let nonce = GenericArray::from_slice(&self.nonce);
+
+        let tag = match self.key.len() {
+            16 => Eax::<aes::Aes128>::new(GenericArray::from_slice(&self.key))
+                .encrypt_in_place_detached(nonce, &[], &mut buffer),
+            32 => Eax::<aes::Aes256>::new(GenericArray::from_slice(&self.key))
+                .encrypt_in_place_detached(nonce, &[], &mut buffer),
+            _ => return Err(anyhow!("Invalid AES key size: {}", self.key.len())),
+        }
+        .map_err(|_| anyhow!("AES-EAX encryption failed"))?;
+
+        let mut out = [0u8; EAX_TAG_SIZE];
+        out.copy_from_slice(&tag);
+        Ok((buffer, out))
+    }
 }
 
 /// Main PEA Archive Reader
@@ -384,10 +466,10 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
         // Check if encryption is used
         let crypto_subheader = if stream_header.stream_control.requires_password() {
             if password.is_none() {
-                return Err(anyhow!(
+                return Err(anyhow::Error::new(CryptoError::PasswordRequired).context(format!(
                     "Archive is encrypted ({:?}) but no password provided",
                     stream_header.stream_control
-                ));
+                )));
             }
 
             // Read crypto subheader (16 bytes for single cipher, more for cascaded)
@@ -400,6 +482,7 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
             reader.read_exact(&mut crypto_buf)?;
 
             let subhdr = CryptoSubheader::parse(&crypto_buf)?;
+            #[cfg(feature = "debug_crypto")]
             eprintln!(
                 "PEA Crypto: salt={:02X?}, pw_ver=0x{:04X}",
                 &subhdr.salt, subhdr.pw_ver
@@ -460,13 +543,15 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
             data_size, auth_tag_size
         );
 
-        // Read the entire stream data
+        // Read the entire stream data, followed by its auth tag
         let mut encrypted_data = vec![0u8; data_size as usize];
         reader.read_exact(&mut encrypted_data)?;
+        let mut auth_tag = vec![0u8; auth_tag_size as usize];
+        reader.read_exact(&mut auth_tag)?;
 
         // Decrypt if needed
         let decrypted_data = if let (Some(crypto), Some(pwd)) = (crypto_subheader, password) {
-            Self::decrypt_stream(stream_header, crypto, pwd, &encrypted_data, archive_header)?
+            Self::decrypt_stream(stream_header, crypto, pwd, &encrypted_data, &auth_tag, archive_header)?
         } else {
             encrypted_data
         };
@@ -486,6 +571,7 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
         crypto: &CryptoSubheader,
         password: &str,
         data: &[u8],
+        auth_tag: &[u8],
         archive_header: &PeaArchiveHeader,
     ) -> Result<Vec<u8>> {
         // Calculate iterations based on algorithm and iteration multiplier
@@ -499,45 +585,30 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
 
         eprintln!("Decrypting with {} iterations", iterations);
 
+        // The stored verification word isn't checked: where PeaZip takes it from
+        // the key derivation hasn't been confirmed against real archives, so the
+        // EAX tag alone decides whether the password is right
         match stream_header.stream_control {
             ControlAlgorithm::Eax => {
                 let ctx = AesEaxContext::new(password, &crypto.salt, iterations, 16)?;
-                ctx.decrypt(data)
+                ctx.decrypt(data, auth_tag)
             }
             ControlAlgorithm::Eax256 => {
                 let ctx = AesEaxContext::new(password, &crypto.salt, iterations, 32)?;
-                ctx.decrypt(data)
-            }
-            ControlAlgorithm::Tf | ControlAlgorithm::Tf256 => {
-                // Twofish - use similar approach
-                // For now, we'll use AES as a placeholder until twofish crate is added
-                eprintln!("Warning: Twofish not fully implemented, falling back to AES");
-                let key_size = if stream_header.stream_control == ControlAlgorithm::Tf256 {
-                    32
-                } else {
-                    16
-                };
-                let ctx = AesEaxContext::new(password, &crypto.salt, iterations * 2, key_size)?;
-                ctx.decrypt(data)
-            }
-            ControlAlgorithm::Sp | ControlAlgorithm::Sp256 => {
-                // Serpent - use similar approach
-                eprintln!("Warning: Serpent not fully implemented, falling back to AES");
-                let key_size = if stream_header.stream_control == ControlAlgorithm::Sp256 {
-                    32
-                } else {
-                    16
-                };
-                let ctx = AesEaxContext::new(password, &crypto.salt, iterations * 3, key_size)?;
-                ctx.decrypt(data)
-            }
-            ControlAlgorithm::TriAts | ControlAlgorithm::TriTsa | ControlAlgorithm::TriSat => {
-                // Triple cascaded encryption
-                // For now, just decrypt with AES
-                eprintln!("Warning: Triple cascaded encryption partially implemented");
-                let ctx = AesEaxContext::new(password, &crypto.salt, iterations, 32)?;
-                ctx.decrypt(data)
+                ctx.decrypt(data, auth_tag)
             }
+            // Twofish, Serpent and the triple cascades aren't implemented. Trying
+            // AES-EAX instead would fail the tag check and blame the password.
+            ControlAlgorithm::Tf
+            | ControlAlgorithm::Tf256
+            | ControlAlgorithm::Sp
+            | ControlAlgorithm::Sp256
+            | ControlAlgorithm::TriAts
+            | ControlAlgorithm::TriTsa
+            | ControlAlgorithm::TriSat => Err(anyhow!(
+                "Unsupported PEA stream control for decryption: {:?} (only AES-EAX archives can be opened)",
+                stream_header.stream_control
+            )),
             _ => Ok(data.to_vec()),
         }
     }
@@ -589,7 +660,6 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
     fn parse_objects(data: &[u8]) -> Result<Vec<PeaObject>> {
         let mut objects = Vec::new();
         let mut cursor = Cursor::new(data);
-        let mut offset = 0u64;
 
         // PEA stream format:
         // For each object:
@@ -642,7 +712,8 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
             // Determine if directory (attribute check or size = 0 with special markers)
             let is_dir = filename.ends_with('/') || filename.ends_with('\\');
 
-            let current_pos = cursor.position();
+            // File data starts right after the record header
+            let offset = cursor.position();
 
             objects.push(PeaObject {
                 name: filename.clone(),
@@ -661,8 +732,6 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
                 cursor.seek(SeekFrom::Current(size as i64))?;
             }
 
-            offset = cursor.position();
-
             // Safety check to prevent infinite loops
             if objects.len() > 100000 {
                 eprintln!("Warning: Too many objects, stopping parse");
@@ -701,9 +770,11 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
         let auth_tag_size = self.stream_header.stream_control.auth_tag_size() as u64;
         let data_size = file_size - current_pos - auth_tag_size;
 
-        // Read stream data
+        // Read stream data and its auth tag
         let mut encrypted_data = vec![0u8; data_size as usize];
         reader.read_exact(&mut encrypted_data)?;
+        let mut auth_tag = vec![0u8; auth_tag_size as usize];
+        reader.read_exact(&mut auth_tag)?;
 
         // Decrypt if needed
         let decrypted_data = if let (Some(crypto), Some(pwd)) = (
@@ -715,6 +786,7 @@ impl<R: Read + Seek + Send> PeaArchive<R> {
                 crypto,
                 pwd,
                 &encrypted_data,
+                &auth_tag,
                 &self.archive_header,
             )?
         } else {
@@ -793,6 +865,144 @@ impl<R: Read + Seek + Send> ArchiveReader for PeaArchive<R> {
     }
 }
 
+/// Single-stream PEA archive writer
+///
+/// Objects are buffered into one stream which is compressed and sealed with the
+/// stream control tag by `finish`. Supports `PCompress0`/`PCompress3` and
+/// `NoAlgo`/`Crc32` stream control; encrypted streams are not written yet.
+pub struct PeaWriter<W: IoWrite> {
+    writer: W,
+    compression: CompressionAlgorithm,
+    stream_control: ControlAlgorithm,
+    stream: Vec<u8>,
+}
+
+impl<W: IoWrite> PeaWriter<W> {
+    pub fn new(
+        writer: W,
+        compression: CompressionAlgorithm,
+        stream_control: ControlAlgorithm,
+    ) -> Result<Self> {
+        if !matches!(
+            compression,
+            CompressionAlgorithm::PCompress0 | CompressionAlgorithm::PCompress3
+        ) {
+            return Err(anyhow!("Unsupported PEA compression for writing: {:?}", compression));
+        }
+        if !matches!(stream_control, ControlAlgorithm::NoAlgo | ControlAlgorithm::Crc32) {
+            return Err(anyhow!(
+                "Unsupported PEA stream control for writing: {:?}",
+                stream_control
+            ));
+        }
+
+        Ok(PeaWriter {
+            writer,
+            compression,
+            stream_control,
+            stream: Vec::new(),
+        })
+    }
+
+    /// Add a file object with its contents and modification time (seconds)
+    pub fn add_file(&mut self, name: &str, data: &[u8], mtime: u32) -> Result<()> {
+        let name = name.replace('\\', "/");
+        if name.is_empty() || name.ends_with('/') {
+            return Err(anyhow!("Invalid PEA file name: {:?}", name));
+        }
+        self.push_object(&name, data, mtime, PEA_ATTR_ARCHIVE)
+    }
+
+    /// Add a directory object; a trailing `/` is appended if missing
+    pub fn add_directory(&mut self, name: &str, mtime: u32) -> Result<()> {
+        let mut name = name.replace('\\', "/");
+        if !name.ends_with('/') {
+            name.push('/');
+        }
+        self.push_object(&name, &[], mtime, PEA_ATTR_DIRECTORY)
+    }
+
+    /// Add a file from disk under `name`, taking its modification time from the filesystem
+    pub fn add_path(&mut self, path: &Path, name: &str) -> Result<()> {
+        let metadata = std::fs::metadata(path)?;
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs().min(u32::MAX as u64) as u32)
+            .unwrap_or(0);
+
+        if metadata.is_dir() {
+            self.add_directory(name, mtime)
+        } else {
+            let data = std::fs::read(path)?;
+            self.add_file(name, &data, mtime)
+        }
+    }
+
+    fn push_object(&mut self, name: &str, data: &[u8], mtime: u32, attributes: u32) -> Result<()> {
+        let name_bytes = name.as_bytes();
+        if name_bytes.len() > u16::MAX as usize {
+            return Err(anyhow!("PEA file name too long: {} bytes", name_bytes.len()));
+        }
+
+        self.stream.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+        self.stream.extend_from_slice(name_bytes);
+        self.stream.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        self.stream.extend_from_slice(&mtime.to_le_bytes());
+        self.stream.extend_from_slice(&attributes.to_le_bytes());
+        self.stream.extend_from_slice(data);
+        Ok(())
+    }
+
+    /// Write headers, the compressed stream and its control tag, returning the inner writer
+    pub fn finish(mut self) -> Result<W> {
+        self.stream.extend_from_slice(&EOS_TRIGGER);
+
+        let stored = match self.compression {
+            CompressionAlgorithm::PCompress0 => std::mem::take(&mut self.stream),
+            _ => {
+                let mut encoder = flate2::write::ZlibEncoder::new(
+                    Vec::new(),
+                    flate2::Compression::new(9),
+                );
+                encoder.write_all(&self.stream)?;
+                encoder.finish()?
+            }
+        };
+
+        let archive_header = [
+            PEA_MAGIC,
+            PEA_FORMAT_VER,
+            PEA_FORMAT_REV,
+            ControlAlgorithm::NoAlgo.to_byte(),
+            0, // ECC scheme
+            0, // OS id
+            0, // Date/time encoding
+            1, // UTF-8 names
+            0, // CPU/endianness
+            1, // KDF iteration multiplier
+        ];
+        self.writer.write_all(&archive_header)?;
+
+        self.writer.write_all(&POD_TRIGGER)?;
+        self.writer.write_all(&[
+            self.compression.to_byte(),
+            0, // Stream ECC
+            self.stream_control.to_byte(),
+            ControlAlgorithm::NoAlgo.to_byte(),
+        ])?;
+
+        self.writer.write_all(&stored)?;
+        if self.stream_control == ControlAlgorithm::Crc32 {
+            self.writer.write_all(&crc32fast::hash(&stored).to_le_bytes())?;
+        }
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
 /// Check if a file is a PEA archive
 pub fn is_pea_archive(path: &Path) -> Result<bool> {
     let mut file = File::open(path)?;
@@ -801,6 +1011,16 @@ pub fn is_pea_archive(path: &Path) -> Result<bool> {
     Ok(magic[0] == PEA_MAGIC)
 }
 
+/// Check whether a PEA archive is encrypted, reading only its two headers
+pub fn is_pea_encrypted(path: &Path) -> Result<bool> {
+    let mut file = File::open(path)?;
+    let mut headers = [0u8; 20];
+    file.read_exact(&mut headers)?;
+    PeaArchiveHeader::parse(&headers[..10])?;
+    let stream_header = PeaStreamHeader::parse(&headers[10..])?;
+    Ok(stream_header.stream_control.requires_password())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -856,4 +1076,124 @@ mod tests {
         assert!(matches!(header.stream_control, ControlAlgorithm::NoAlgo));
         assert!(matches!(header.object_control, ControlAlgorithm::Crc32));
     }
+
+    /// Build a stored, AES-128-EAX protected PEA archive holding one file
+    ///
+    /// Made with this module's own key derivation and EAX code, so it checks
+    /// tag and header handling, not compatibility with archives PeaZip wrote.
+    fn encrypted_pea_sample(password: &str, content: &[u8]) -> Vec<u8> {
+        let salt = [0x5Au8; 12];
+        let mut archive = vec![0xEA, 1, 6, 0x00, 0, 0, 0, 1, 0, 1];
+        archive.extend_from_slice(&POD_TRIGGER);
+        archive.extend_from_slice(&[0, 0, 0x31, 0x00]);
+        archive.extend_from_slice(&[0, 0]);
+        archive.extend_from_slice(&salt);
+        let ctx = AesEaxContext::new(password, &salt, 1000, 16).unwrap();
+        archive.extend_from_slice(&ctx.pw_ver().to_le_bytes());
+
+        let name = b"hello.txt";
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        stream.extend_from_slice(name);
+        stream.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        stream.extend_from_slice(&0u32.to_le_bytes());
+        stream.extend_from_slice(&0x20u32.to_le_bytes());
+        stream.extend_from_slice(content);
+        stream.extend_from_slice(&EOS_TRIGGER);
+
+        let (ciphertext, tag) = ctx.encrypt(&stream).unwrap();
+        archive.extend_from_slice(&ciphertext);
+        archive.extend_from_slice(&tag);
+        archive
+    }
+
+    #[test]
+    fn test_eax_stream_tag_verified() {
+        let content = b"PEA EAX sample payload";
+        let sample = encrypted_pea_sample("secret", content);
+
+        let mut archive = PeaArchive::new(Cursor::new(sample.clone()), Some("secret".into())).unwrap();
+        let entries = archive.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "hello.txt");
+        let mut out = Vec::new();
+        archive.extract(&entries[0], &mut out).unwrap();
+        assert_eq!(out, content);
+
+        let err = PeaArchive::new(Cursor::new(sample.clone()), Some("wrong".into())).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidPassword)));
+
+        let mut corrupted = sample;
+        corrupted[40] ^= 0x01;
+        let err = PeaArchive::new(Cursor::new(corrupted), Some("secret".into())).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_pw_ver_mismatch_left_to_eax_tag() {
+        let mut sample = encrypted_pea_sample("secret", b"payload");
+        // pw_ver sits at the end of the crypto subheader, right after the 12-byte salt
+        let pw_ver_offset = 10 + POD_TRIGGER.len() + 4 + 2 + 12;
+        sample[pw_ver_offset] ^= 0xFF;
+
+        // The right password still opens the archive
+        let mut archive = PeaArchive::new(Cursor::new(sample.clone()), Some("secret".into())).unwrap();
+        let entries = archive.list().unwrap();
+        let mut out = Vec::new();
+        archive.extract(&entries[0], &mut out).unwrap();
+        assert_eq!(out, b"payload");
+
+        // A wrong one is still caught, by the tag
+        let err = PeaArchive::new(Cursor::new(sample), Some("wrong".into())).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_unsupported_cipher_not_reported_as_wrong_password() {
+        let mut sample = encrypted_pea_sample("secret", b"payload");
+        // Stream control byte of the stream header: Twofish-256-EAX
+        sample[10 + POD_TRIGGER.len() + 2] = 0x42;
+
+        let err = PeaArchive::new(Cursor::new(sample), Some("secret".into())).err().unwrap();
+        assert!(err.downcast_ref::<CryptoError>().is_none());
+        assert!(format!("{:#}", err).contains("Unsupported PEA stream control"));
+    }
+
+    #[test]
+    fn test_pea_writer_round_trip() {
+        let big: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+
+        for compression in [CompressionAlgorithm::PCompress0, CompressionAlgorithm::PCompress3] {
+            let mut writer = PeaWriter::new(Vec::new(), compression, ControlAlgorithm::Crc32).unwrap();
+            writer.add_directory("docs", 1_700_000_000).unwrap();
+            writer.add_file("docs\\readme.txt", b"hello pea", 1_700_000_001).unwrap();
+            writer.add_file("data.bin", &big, 1_700_000_002).unwrap();
+            let bytes = writer.finish().unwrap();
+
+            let stored_len = bytes.len() - 20 - 4;
+            let crc = u32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+            assert_eq!(crc, crc32fast::hash(&bytes[20..20 + stored_len]));
+            if compression == CompressionAlgorithm::PCompress3 {
+                assert!(stored_len < big.len());
+            }
+
+            let mut archive = PeaArchive::new(Cursor::new(bytes), None).unwrap();
+            assert_eq!(archive.stream_header.compression, compression);
+            let entries = archive.list().unwrap();
+            let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(names, ["docs/", "docs/readme.txt", "data.bin"]);
+            assert!(entries[0].is_dir);
+            assert_eq!(entries[1].mtime, Some(1_700_000_001));
+
+            let mut out = Vec::new();
+            archive.extract(&entries[1], &mut out).unwrap();
+            assert_eq!(out, b"hello pea");
+            out.clear();
+            archive.extract(&entries[2], &mut out).unwrap();
+            assert_eq!(out, big);
+        }
+
+        assert!(PeaWriter::new(Vec::new(), CompressionAlgorithm::PCompress1, ControlAlgorithm::Crc32).is_err());
+        assert!(PeaWriter::new(Vec::new(), CompressionAlgorithm::PCompress0, ControlAlgorithm::Eax).is_err());
+    }
 }
@@ -31,8 +31,67 @@ pub enum CompressionMethod {
     Store,
     /// LZMA2 compression
     Lzma2 { level: i32, dict_size: u32 },
+    /// Tornado compression; `method` is the FreeARC method number
+    Tornado { method: i32 },
+    /// PPMd (variant H) with the given model order and memory size in bytes
+    Ppmd { order: u8, memory_size: u32 },
+    /// LZP with the given minimum match length and hash table size (log2)
+    Lzp { min_match_len: i32, hash_size_log: i32 },
+    /// GRZip (BWT-based); `mode` combines the flags documented on `grzip_compress`
+    Grzip { mode: i32 },
 }
 
+impl CompressionMethod {
+    /// Tag stored in the header written by `compress`
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Store => 0,
+            Self::Lzma2 { .. } => 1,
+            Self::Tornado { .. } => 2,
+            Self::Ppmd { .. } => 3,
+            Self::Lzp { .. } => 4,
+            Self::Grzip { .. } => 5,
+        }
+    }
+
+    /// The two parameters decompression needs, as stored in the header
+    fn params(&self) -> (u32, u32) {
+        match *self {
+            Self::Store => (0, 0),
+            Self::Lzma2 { level, dict_size } => (dict_size, level as u32),
+            Self::Tornado { method } => (method as u32, 0),
+            Self::Ppmd { order, memory_size } => (order as u32, memory_size),
+            Self::Lzp { min_match_len, hash_size_log } => (min_match_len as u32, hash_size_log as u32),
+            Self::Grzip { mode } => (mode as u32, 0),
+        }
+    }
+
+    fn from_header(tag: u8, p1: u32, p2: u32) -> Result<Self> {
+        match tag {
+            0 => Ok(Self::Store),
+            1 => Ok(Self::Lzma2 { level: p2 as i32, dict_size: p1 }),
+            2 => Ok(Self::Tornado { method: p1 as i32 }),
+            3 => Ok(Self::Ppmd { order: p1 as u8, memory_size: p2 }),
+            4 => Ok(Self::Lzp { min_match_len: p1 as i32, hash_size_log: p2 as i32 }),
+            5 => Ok(Self::Grzip { mode: p1 as i32 }),
+            _ => Err(anyhow!("Unknown compression method tag: {}", tag)),
+        }
+    }
+}
+
+/// Magic prefix of data produced by `compress`
+const METHOD_MAGIC: [u8; 2] = *b"AX";
+
+/// Header size: magic, method tag, original size (u64 LE), two u32 LE parameters
+const METHOD_HEADER_SIZE: usize = 2 + 1 + 8 + 4 + 4;
+
+/// Largest original size `decompress` trusts from a header per payload byte;
+/// runs of zeros reach a few thousand to one, so this leaves a wide margin
+const MAX_EXPANSION_RATIO: usize = 1 << 16;
+
+/// Largest original size `decompress` accepts at all (the codecs take `i32` sizes)
+const MAX_DECOMPRESSED_SIZE: usize = i32::MAX as usize;
+
 impl Default for CompressionMethod {
     fn default() -> Self {
         Self::Lzma2 { level: 5, dict_size: 32 * 1024 * 1024 }
@@ -40,19 +99,95 @@ impl Default for CompressionMethod {
 }
 
 /// Compress data using specified method
+///
+/// The output starts with a small header naming the method, its parameters and
+/// the original size, so `decompress` can pick the right codec.
 pub fn compress(data: &[u8], method: CompressionMethod) -> Result<Vec<u8>> {
-    match method {
-        CompressionMethod::Store => Ok(data.to_vec()),
+    let payload = match method {
+        CompressionMethod::Store => data.to_vec(),
         CompressionMethod::Lzma2 { level, dict_size } => {
-            lzma2_compress(data, level, dict_size, 3, 0, 0)
+            lzma2_compress(data, level, dict_size, 3, 0, 0)?
         }
-    }
+        CompressionMethod::Tornado { method } => codecs::tornado_compress(data, method)?,
+        CompressionMethod::Ppmd { order, memory_size } => {
+            codecs::ppmd_compress(data, order, memory_size as usize)?
+        }
+        CompressionMethod::Lzp { min_match_len, hash_size_log } => {
+            codecs::lzp_compress(data, min_match_len, hash_size_log)?
+        }
+        CompressionMethod::Grzip { mode } => codecs::grzip_compress(data, mode)?,
+    };
+
+    let (p1, p2) = method.params();
+    let mut out = Vec::with_capacity(METHOD_HEADER_SIZE + payload.len());
+    out.extend_from_slice(&METHOD_MAGIC);
+    out.push(method.tag());
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(&p1.to_le_bytes());
+    out.extend_from_slice(&p2.to_le_bytes());
+    out.extend_from_slice(&payload);
+    Ok(out)
 }
 
-/// Decompress data (automatically detects method)
+/// Decompress data produced by `compress` (the header names the method)
+///
+/// Headerless data from earlier versions must go through `decompress_raw`.
 pub fn decompress(compressed_data: &[u8]) -> Result<Vec<u8>> {
-    // Try LZMA2 first
-    lzma2_decompress(compressed_data, compressed_data.len() * 4)
+    if compressed_data.len() < METHOD_HEADER_SIZE || compressed_data[..2] != METHOD_MAGIC {
+        return Err(anyhow!(
+            "Missing arcmax method header; use decompress_raw for headerless LZMA2 data"
+        ));
+    }
+
+    let u32_at = |i: usize| u32::from_le_bytes(compressed_data[i..i + 4].try_into().unwrap());
+    let original_size = u64::from_le_bytes(compressed_data[3..11].try_into().unwrap()) as usize;
+    let method = CompressionMethod::from_header(compressed_data[2], u32_at(11), u32_at(15))?;
+    let payload = &compressed_data[METHOD_HEADER_SIZE..];
+    if original_size == 0 {
+        return Ok(Vec::new());
+    }
+    // The size comes from untrusted input and sizes the output buffer up front
+    if original_size > MAX_DECOMPRESSED_SIZE
+        || original_size > payload.len().saturating_mul(MAX_EXPANSION_RATIO)
+    {
+        return Err(anyhow!(
+            "Header claims {} bytes from a {} byte payload; refusing to decompress",
+            original_size,
+            payload.len()
+        ));
+    }
+
+    let data = match method {
+        CompressionMethod::Store => payload.to_vec(),
+        CompressionMethod::Lzma2 { dict_size, .. } => {
+            codecs::lzma2::lzma2_decompress(payload, original_size, dict_size, 3, 0, 0)?
+        }
+        CompressionMethod::Tornado { .. } => codecs::tornado_decompress(payload, original_size)?,
+        CompressionMethod::Ppmd { order, memory_size } => {
+            codecs::ppmd_decompress(payload, original_size, order, memory_size as usize)?
+        }
+        CompressionMethod::Lzp { min_match_len, hash_size_log } => {
+            codecs::lzp_decompress_with_params(payload, original_size, min_match_len, hash_size_log)?
+        }
+        CompressionMethod::Grzip { .. } => codecs::grzip_decompress(payload, original_size)?,
+    };
+
+    if data.len() != original_size {
+        return Err(anyhow!(
+            "{:?} decompressed to {} bytes, expected {}",
+            method,
+            data.len(),
+            original_size
+        ));
+    }
+    Ok(data)
+}
+
+/// Decompress headerless LZMA2 data written before `compress` added its header
+///
+/// The original size isn't recorded in such data, so the caller must supply it.
+pub fn decompress_raw(compressed_data: &[u8], original_size: usize) -> Result<Vec<u8>> {
+    lzma2_decompress(compressed_data, original_size)
 }
 
 /// Get compression ratio
@@ -82,4 +217,88 @@ mod tests {
         assert_eq!(data, decompressed.as_slice());
         println!("Round-trip successful!");
     }
+
+    fn sample_data() -> Vec<u8> {
+        b"The quick brown fox jumps over the lazy dog. 0123456789abcdef "
+            .iter()
+            .cycle()
+            .take(64 * 1024)
+            .copied()
+            .collect()
+    }
+
+    fn assert_round_trip(method: CompressionMethod) {
+        let data = sample_data();
+        let compressed = compress(&data, method).unwrap();
+        assert_eq!(&compressed[..2], &METHOD_MAGIC);
+        assert_eq!(decompress(&compressed).unwrap(), data, "{:?}", method);
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        assert_round_trip(CompressionMethod::Store);
+    }
+
+    #[test]
+    fn test_lzma2_round_trip() {
+        assert_round_trip(CompressionMethod::Lzma2 { level: 5, dict_size: 1024 * 1024 });
+    }
+
+    #[test]
+    fn test_tornado_round_trip() {
+        // Different FreeArc builds expose different method numbers
+        let data = sample_data();
+        let method = (0..=64)
+            .map(|method| CompressionMethod::Tornado { method })
+            .find(|&m| compress(&data, m).is_ok())
+            .expect("no Tornado method in 0..=64 succeeded");
+        assert_round_trip(method);
+    }
+
+    #[test]
+    fn test_ppmd_round_trip() {
+        assert_round_trip(CompressionMethod::Ppmd { order: 6, memory_size: 16 * 1024 * 1024 });
+    }
+
+    #[test]
+    fn test_lzp_round_trip() {
+        assert_round_trip(CompressionMethod::Lzp { min_match_len: 24, hash_size_log: 16 });
+    }
+
+    #[test]
+    fn test_grzip_round_trip() {
+        assert_round_trip(CompressionMethod::Grzip { mode: 0 });
+    }
+
+    #[test]
+    fn test_headerless_data_needs_decompress_raw() {
+        let data = sample_data();
+        let raw = lzma2_compress(&data, 5, 32 * 1024 * 1024, 3, 0, 0).unwrap();
+        assert!(decompress(&raw).is_err());
+        assert_eq!(decompress_raw(&raw, data.len()).unwrap(), data);
+
+        // Highly compressible input is sized exactly from the header
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let compressed = compress(&zeros, CompressionMethod::default()).unwrap();
+        assert!(compressed.len() * 4 < zeros.len());
+        assert_eq!(decompress(&compressed).unwrap(), zeros);
+    }
+
+    #[test]
+    fn test_unknown_method_tag_rejected() {
+        let mut compressed = compress(b"payload", CompressionMethod::Store).unwrap();
+        compressed[2] = 0xFF;
+        assert!(decompress(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_implausible_original_size_rejected() {
+        for method in [CompressionMethod::default(), CompressionMethod::Store] {
+            let mut compressed = compress(b"payload payload payload", method).unwrap();
+            compressed[3..11].copy_from_slice(&(1u64 << 40).to_le_bytes());
+            assert!(decompress(&compressed).is_err());
+            compressed[3..11].copy_from_slice(&(64u64 * 1024 * 1024).to_le_bytes());
+            assert!(decompress(&compressed).is_err());
+        }
+    }
 }
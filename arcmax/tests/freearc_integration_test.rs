@@ -2,6 +2,7 @@ use arcmax::formats::freearc::{
     writer::{FreeArcWriter, ArchiveOptions},
     reader::FreeArcReader,
 };
+use arcmax::core::archive::ArchiveReader;
 use std::io::Cursor;
 use anyhow::Result;
 
@@ -112,6 +113,67 @@ fn test_freearc_multiple_files() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_freearc_stored_files() -> Result<()> {
+    let text: &[u8] = b"compressible text compressible text compressible text";
+    let packed: &[u8] = b"PK\x03\x04 already compressed payload";
+
+    let options = ArchiveOptions {
+        compression: "lzma".to_string(),
+        compression_level: 3,
+        encryption: None,
+        password: None,
+    };
+    let mut writer = FreeArcWriter::new(Cursor::new(Vec::new()), options)?;
+    writer.add_file("notes.txt", text)?;
+    writer.add_file_stored("backup.zip", packed)?;
+    writer.add_file("more.txt", text)?;
+    let archive_data = writer.finish()?.into_inner();
+
+    let reader = FreeArcReader::new(Cursor::new(archive_data), None)?;
+    let blocks = &reader.directory.data_blocks;
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[1].compressor, "storing");
+    assert_eq!(blocks[1].compressed_size, packed.len() as u64);
+    assert_ne!(blocks[0].compressor, "storing");
+
+    assert_eq!(&reader.extract_file(0)?[..], text);
+    assert_eq!(&reader.extract_file(1)?[..], packed);
+    assert_eq!(&reader.extract_file(2)?[..], text);
+    Ok(())
+}
+
+#[test]
+fn test_freearc_open_append() -> Result<()> {
+    let options = || ArchiveOptions {
+        compression: "lzma".to_string(),
+        compression_level: 3,
+        encryption: None,
+        password: None,
+    };
+
+    let mut writer = FreeArcWriter::new(Cursor::new(Vec::new()), options())?;
+    writer.add_file("first.txt", b"first file, written originally")?;
+    writer.add_file("second.txt", b"second file, written originally")?;
+    let original = writer.finish()?.into_inner();
+
+    let mut writer = FreeArcWriter::open_append(Cursor::new(original), options())?;
+    writer.add_file("third.txt", b"third file, appended later")?;
+    writer.add_file_stored("fourth.zip", b"PK\x03\x04 appended and stored")?;
+    let appended = writer.finish()?.into_inner();
+
+    let mut reader = FreeArcReader::new(Cursor::new(appended), None)?;
+    let names: Vec<String> = reader.list()?.into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["first.txt", "second.txt", "third.txt", "fourth.zip"]);
+    assert_eq!(reader.directory.data_blocks.len(), 3);
+
+    assert_eq!(&reader.extract_file(0)?[..], b"first file, written originally");
+    assert_eq!(&reader.extract_file(1)?[..], b"second file, written originally");
+    assert_eq!(&reader.extract_file(2)?[..], b"third file, appended later");
+    assert_eq!(&reader.extract_file(3)?[..], b"PK\x03\x04 appended and stored");
+    Ok(())
+}
+
 #[test]
 fn test_freearc_empty_archive() -> Result<()> {
     let archive_buffer = Cursor::new(Vec::new());
@@ -48,7 +48,7 @@ namespace BpgViewer.NativeInterop
         public static extern IntPtr bpg_viewer_decode_file([MarshalAs(UnmanagedType.LPStr)] string path);
 
         /// <summary>
-        /// Get image dimensions from handle
+        /// Get image dimensions from handle (display dimensions, after EXIF orientation)
         /// </summary>
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern int bpg_viewer_get_dimensions(
@@ -56,6 +56,22 @@ namespace BpgViewer.NativeInterop
             out uint width,
             out uint height);
 
+        /// <summary>
+        /// Get the EXIF orientation (1-8, 1 when the image has none)
+        /// </summary>
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int bpg_viewer_get_orientation(
+            IntPtr handle,
+            out byte orientation);
+
+        /// <summary>
+        /// Turn EXIF orientation handling on (default) or off to rotate in the caller instead
+        /// </summary>
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
+        public static extern int bpg_viewer_set_respect_orientation(
+            IntPtr handle,
+            int respect);
+
         /// <summary>
         /// Get image data pointer and size (original format)
         /// The returned pointer is valid as long as the handle exists
@@ -87,7 +103,7 @@ namespace BpgViewer.NativeInterop
             out UIntPtr size);
 
         /// <summary>
-        /// Free buffer allocated by bpg_viewer_get_rgba32 or bpg_viewer_get_bgra32
+        /// Free buffer allocated by bpg_viewer_get_rgba32, bpg_viewer_get_bgra32 or bpg_thumbnail_generate_bgra
         /// </summary>
         [DllImport(DllName, CallingConvention = CallingConvention.Cdecl)]
         public static extern void bpg_viewer_free_buffer(IntPtr ptr, UIntPtr size);
@@ -151,6 +167,29 @@ namespace BpgViewer.NativeInterop
             [MarshalAs(UnmanagedType.LPStr)] string inputPath,
             [MarshalAs(UnmanagedType.LPStr)] string outputPath);
 
+        /// <summary>
+        /// Generate thumbnail and save as JPEG with the given quality (1-100)
+        /// </summary>
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Ansi)]
+        public static extern int bpg_thumbnail_generate_jpeg(
+            IntPtr handle,
+            [MarshalAs(UnmanagedType.LPStr)] string inputPath,
+            [MarshalAs(UnmanagedType.LPStr)] string outputPath,
+            byte quality);
+
+        /// <summary>
+        /// Generate thumbnail as BGRA32 pixels in memory (for WPF BitmapSource)
+        /// Caller must free the returned pointer with bpg_viewer_free_buffer
+        /// </summary>
+        [DllImport(DllName, CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Ansi)]
+        public static extern int bpg_thumbnail_generate_bgra(
+            IntPtr handle,
+            [MarshalAs(UnmanagedType.LPStr)] string inputPath,
+            out IntPtr data,
+            out UIntPtr size,
+            out uint width,
+            out uint height);
+
         /// <summary>
         /// Free thumbnail generator handle
         /// </summary>
@@ -0,0 +1,216 @@
+// Archive Thumbnail Module
+// Builds a thumbnail set straight from the image entries of an OpenArc tar.zst,
+// decoding each entry in memory instead of extracting the archive first
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::sync_channel;
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{Context, Result};
+
+use crate::thumbnail::{ThumbnailConfig, ThumbnailGenerator};
+
+/// Upper bound on decode workers; each holds a full-size decoded image
+const MAX_WORKERS: usize = 8;
+
+/// Entry extensions that get a thumbnail (BPG transcodes and stored originals)
+const IMAGE_EXTENSIONS: &[&str] = &["bpg", "jpg", "jpeg", "png", "tif", "tiff", "bmp", "webp", "gif"];
+
+/// Generate a PNG thumbnail for every image entry under `media/` in a tar.zst archive
+///
+/// Entries are streamed out of the archive one at a time and decoded by a
+/// bounded pool of workers, so at most a few entries are held in memory.
+/// Thumbnails are written to `out_dir` as `<entry stem>.png` (`.jpg` with JPEG
+/// output). Returns the
+/// number of thumbnails written; the first decode or write error aborts.
+pub fn generate_thumbnails_from_archive(archive: &Path, out_dir: &Path, config: &ThumbnailConfig) -> Result<usize> {
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create output directory: {}", out_dir.display()))?;
+
+    let file = std::fs::File::open(archive)
+        .with_context(|| format!("Failed to open archive: {}", archive.display()))?;
+    let decoder = zstd::stream::read::Decoder::new(file)
+        .with_context(|| format!("Failed to create zstd decoder for {}", archive.display()))?;
+    let mut tar = tar::Archive::new(decoder);
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS);
+    let generator = ThumbnailGenerator::with_config(config.clone());
+    let first_error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+    let mut written = 0usize;
+
+    thread::scope(|scope| -> Result<()> {
+        // Bounded so the reader can't run ahead of the decoders
+        let (tx, rx) = sync_channel::<(String, Vec<u8>)>(workers * 2);
+        let rx = Mutex::new(rx);
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+
+        for _ in 0..workers {
+            let rx = &rx;
+            let done_tx = done_tx.clone();
+            let generator = &generator;
+            let first_error = &first_error;
+            scope.spawn(move || loop {
+                let next = rx.lock().unwrap().recv();
+                let Ok((name, data)) = next else { break };
+                // Keep draining after a failure so the reader never blocks on a full channel
+                if first_error.lock().unwrap().is_some() {
+                    continue;
+                }
+                let output_path = out_dir.join(format!(
+                    "{}.{}",
+                    thumbnail_stem(&name),
+                    config.output_format.extension()
+                ));
+                match generator
+                    .generate_thumbnail_from_memory_to_png(&data, &output_path)
+                    .with_context(|| format!("Failed to generate thumbnail for {}", name))
+                {
+                    Ok(()) => {
+                        let _ = done_tx.send(());
+                    }
+                    Err(e) => {
+                        first_error.lock().unwrap().get_or_insert(e);
+                    }
+                }
+            });
+        }
+        drop(done_tx);
+
+        // tar entries borrow the decoder, so reading stays on this thread
+        let read_result = (|| -> Result<()> {
+            for entry in tar.entries().context("Failed to read tar entries")? {
+                let mut entry = entry.context("Failed to read tar entry")?;
+                if !entry.header().entry_type().is_file() {
+                    continue;
+                }
+                let name = entry
+                    .path()
+                    .context("Failed to read tar entry path")?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if !is_thumbnail_source(&name) {
+                    continue;
+                }
+                if first_error.lock().unwrap().is_some() {
+                    break;
+                }
+
+                let mut data = Vec::with_capacity(entry.header().size().unwrap_or(0) as usize);
+                entry
+                    .read_to_end(&mut data)
+                    .with_context(|| format!("Failed to read {}", name))?;
+                if tx.send((name, data)).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        })();
+        drop(tx);
+
+        written = done_rx.iter().count();
+        read_result
+    })?;
+
+    if let Some(e) = first_error.into_inner().unwrap() {
+        return Err(e);
+    }
+    Ok(written)
+}
+
+/// True for image entries under `media/` (including `media/originals/`)
+fn is_thumbnail_source(name: &str) -> bool {
+    let name = name.trim_start_matches("./");
+    if !name.starts_with("media/") {
+        return false;
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Output file stem for an entry; originals are prefixed so they don't
+/// overwrite the thumbnail of their BPG transcode
+fn thumbnail_stem(name: &str) -> String {
+    let name = name.trim_start_matches("./");
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| name.to_string());
+    if name.starts_with("media/originals/") {
+        format!("original_{}", stem)
+    } else {
+        stem
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn png_bytes(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbaImage::from_pixel(width, height, Rgba([0, 128, 255, 255]))
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    fn append(builder: &mut tar::Builder<impl std::io::Write>, name: &str, data: &[u8]) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, data)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_thumbnails_from_archive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("test.tar.zst");
+        {
+            let encoder = zstd::stream::write::Encoder::new(std::fs::File::create(&archive)?, 3)?;
+            let mut builder = tar::Builder::new(encoder);
+            append(&mut builder, "media/a.png", &png_bytes(64, 32))?;
+            append(&mut builder, "media/originals/a.png", &png_bytes(64, 32))?;
+            append(&mut builder, "media/b.jpg.txt", b"not an image")?;
+            append(&mut builder, "thumbs/a.png", &png_bytes(8, 8))?;
+            append(&mut builder, "MANIFEST.txt", b"a.png")?;
+            builder.into_inner()?.finish()?;
+        }
+
+        let out = dir.path().join("thumbs");
+        let config = ThumbnailConfig { max_width: 16, max_height: 16, ..Default::default() };
+        assert_eq!(generate_thumbnails_from_archive(&archive, &out, &config)?, 2);
+
+        let thumb = image::open(out.join("a.png"))?;
+        assert_eq!((thumb.width(), thumb.height()), (16, 8));
+        assert!(out.join("original_a.png").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_entry_fails() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("bad.tar.zst");
+        {
+            let encoder = zstd::stream::write::Encoder::new(std::fs::File::create(&archive)?, 3)?;
+            let mut builder = tar::Builder::new(encoder);
+            append(&mut builder, "media/broken.png", b"not really a png")?;
+            builder.into_inner()?.finish()?;
+        }
+
+        let err = generate_thumbnails_from_archive(&archive, &dir.path().join("out"), &ThumbnailConfig::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("media/broken.png"));
+        Ok(())
+    }
+}
@@ -15,9 +15,31 @@ pub struct DecodedImage {
     pub format: BPGImageFormat,
     pub color_space: u8,
     pub exif_data: Option<Vec<u8>>,
+    /// EXIF orientation tag (1-8), 1 when the image has none
+    pub orientation: u8,
+    /// Rotate/flip into display orientation in `to_bgra32` and `copy_to_buffer`.
+    /// `data`, `width`, `height` and `to_rgba32` always stay as stored.
+    pub respect_orientation: bool,
 }
 
 impl DecodedImage {
+    /// Orientation `to_bgra32` and `copy_to_buffer` apply; 1 when turned off
+    pub fn effective_orientation(&self) -> u8 {
+        if self.respect_orientation {
+            self.orientation
+        } else {
+            1
+        }
+    }
+
+    /// Width and height of the BGRA output, swapped for orientations 5-8
+    pub fn display_dimensions(&self) -> (u32, u32) {
+        match self.effective_orientation() {
+            5..=8 => (self.height, self.width),
+            _ => (self.width, self.height),
+        }
+    }
+
     /// Get the number of bytes per pixel
     pub fn bytes_per_pixel(&self) -> usize {
         match self.format {
@@ -106,7 +128,7 @@ impl DecodedImage {
                 }
             }
             BPGImageFormat::BGRA32 => {
-                return Ok(self.data.clone());
+                return Ok(self.oriented(self.data.clone(), 4));
             }
             BPGImageFormat::BGR24 => {
                 for i in 0..pixel_count {
@@ -128,11 +150,44 @@ impl DecodedImage {
             _ => return Err(anyhow!("Unsupported format conversion: {:?}", self.format)),
         }
 
-        Ok(bgra_data)
+        Ok(self.oriented(bgra_data, 4))
+    }
+
+    fn oriented(&self, pixels: Vec<u8>, bytes_per_pixel: usize) -> Vec<u8> {
+        match self.effective_orientation() {
+            1 => pixels,
+            o => apply_orientation(&pixels, self.width, self.height, bytes_per_pixel, o),
+        }
     }
 
     /// Copy decoded data to an output buffer with color conversion to sRGB + BGRA32 format
+    ///
+    /// The buffer holds `display_dimensions()` pixels, i.e. it is rotated
+    /// according to the EXIF orientation unless `respect_orientation` is off.
     pub fn copy_to_buffer(&self, output: &mut [u8], stride: usize) -> Result<()> {
+        let orientation = self.effective_orientation();
+        if orientation == 1 {
+            return self.copy_stored_to_buffer(output, stride);
+        }
+
+        let row_bytes = self.width as usize * 4;
+        let mut stored = vec![0u8; row_bytes * self.height as usize];
+        self.copy_stored_to_buffer(&mut stored, row_bytes)?;
+        let oriented = apply_orientation(&stored, self.width, self.height, 4, orientation);
+
+        let (display_width, display_height) = self.display_dimensions();
+        let dst_row_bytes = display_width as usize * 4;
+        if stride < dst_row_bytes || output.len() < display_height as usize * stride {
+            return Err(anyhow!("Output buffer too small"));
+        }
+        for (y, row) in oriented.chunks_exact(dst_row_bytes).enumerate() {
+            output[y * stride..y * stride + dst_row_bytes].copy_from_slice(row);
+        }
+        Ok(())
+    }
+
+    /// `copy_to_buffer` in stored orientation
+    fn copy_stored_to_buffer(&self, output: &mut [u8], stride: usize) -> Result<()> {
         use lcms2::{Intent, PixelFormat, Profile, Transform};
 
         let height = self.height as usize;
@@ -215,6 +270,71 @@ impl DecodedImage {
     }
 }
 
+/// Read the orientation tag (0x0112) from IFD0 of EXIF data
+///
+/// Accepts the TIFF structure with or without the `Exif\0\0` prefix JPEG
+/// APP1 segments carry. Returns `None` when the tag is absent or out of range.
+pub fn orientation_from_exif(exif: &[u8]) -> Option<u8> {
+    let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(exif);
+    let big_endian = match tiff.get(..4)? {
+        [b'I', b'I', 0x2A, 0x00] => false,
+        [b'M', b'M', 0x00, 0x2A] => true,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| -> Option<u16> {
+        let bytes: [u8; 2] = tiff.get(pos..pos.checked_add(2)?)?.try_into().ok()?;
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let u32_at = |pos: usize| -> Option<u32> {
+        let bytes: [u8; 4] = tiff.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    let ifd0 = u32_at(4)? as usize;
+    let entries = u16_at(ifd0)? as usize;
+    for i in 0..entries {
+        let entry = ifd0 + 2 + i * 12;
+        if u16_at(entry)? == 0x0112 {
+            // A SHORT sits in the first two bytes of the value field
+            let value = u16_at(entry + 8)?;
+            return (1..=8).contains(&value).then_some(value as u8);
+        }
+    }
+    None
+}
+
+/// Rotate/flip a stored `width` x `height` pixel buffer into display orientation
+///
+/// `orientation` is the EXIF value: 2/4 mirror, 3 rotates 180 degrees, 6 and 8
+/// rotate 90 degrees clockwise and counter-clockwise, 5/7 transpose. Orientations
+/// 5-8 swap width and height. Unknown values return the pixels unchanged.
+pub fn apply_orientation(pixels: &[u8], width: u32, height: u32, bytes_per_pixel: usize, orientation: u8) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    let (dst_w, dst_h) = match orientation {
+        5..=8 => (h, w),
+        _ => (w, h),
+    };
+    let mut out = vec![0u8; dst_w * dst_h * bytes_per_pixel];
+    for dy in 0..dst_h {
+        for dx in 0..dst_w {
+            let (sx, sy) = match orientation {
+                2 => (w - 1 - dx, dy),
+                3 => (w - 1 - dx, h - 1 - dy),
+                4 => (dx, h - 1 - dy),
+                5 => (dy, dx),
+                6 => (dy, h - 1 - dx),
+                7 => (w - 1 - dy, h - 1 - dx),
+                8 => (w - 1 - dy, dx),
+                _ => (dx, dy),
+            };
+            let src = (sy * w + sx) * bytes_per_pixel;
+            let dst = (dy * dst_w + dx) * bytes_per_pixel;
+            out[dst..dst + bytes_per_pixel].copy_from_slice(&pixels[src..src + bytes_per_pixel]);
+        }
+    }
+    out
+}
+
 /// Create BT.601 (Rec. 601) color profile
 fn create_bt601_profile() -> Result<Profile> {
     // BT.601 primaries
@@ -362,6 +482,7 @@ pub fn decode_memory(input_data: &[u8]) -> Result<DecodedImage> {
         }
 
         eprintln!("Decoded {} scanlines, total data: {} bytes", img_info.height, output_data.len());
+        let orientation = exif_data.as_deref().and_then(orientation_from_exif).unwrap_or(1);
         eprintln!("=== DECODE_MEMORY COMPLETE ===");
 
         ffi::bpg_decoder_close(decoder_ctx);
@@ -373,6 +494,8 @@ pub fn decode_memory(input_data: &[u8]) -> Result<DecodedImage> {
             format: BPGImageFormat::RGB24, // The output format is RGB24 as specified
             color_space: img_info.color_space,
             exif_data,
+            orientation,
+            respect_orientation: true,
         })
     }
 }
@@ -390,6 +513,8 @@ mod tests {
             format: BPGImageFormat::RGBA32,
             color_space: 0,
             exif_data: None,
+            orientation: 1,
+            respect_orientation: true,
         };
         assert_eq!(img.bytes_per_pixel(), 4);
 
@@ -400,7 +525,100 @@ mod tests {
             format: BPGImageFormat::RGB24,
             color_space: 0,
             exif_data: None,
+            orientation: 1,
+            respect_orientation: true,
         };
         assert_eq!(img_rgb.bytes_per_pixel(), 3);
     }
+
+    #[test]
+    fn test_apply_orientation_all_values() {
+        // a b c
+        // d e f
+        let stored = b"abcdef";
+        let cases: [(u8, &[u8]); 8] = [
+            (1, b"abcdef"),
+            (2, b"cbafed"),
+            (3, b"fedcba"),
+            (4, b"defabc"),
+            (5, b"adbecf"),
+            (6, b"daebfc"),
+            (7, b"fcebda"),
+            (8, b"cfbead"),
+        ];
+        for (orientation, expected) in cases {
+            assert_eq!(
+                apply_orientation(stored, 3, 2, 1, orientation),
+                expected,
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+
+    fn exif_with_orientation(big_endian: bool, value: u16) -> Vec<u8> {
+        let (u16b, u32b): (fn(u16) -> [u8; 2], fn(u32) -> [u8; 4]) = if big_endian {
+            (u16::to_be_bytes, u32::to_be_bytes)
+        } else {
+            (u16::to_le_bytes, u32::to_le_bytes)
+        };
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(if big_endian { b"MM\0*" } else { b"II*\0" });
+        exif.extend_from_slice(&u32b(8));
+        exif.extend_from_slice(&u16b(2));
+        // Make (0x010F), then Orientation as SHORT
+        exif.extend_from_slice(&u16b(0x010F));
+        exif.extend_from_slice(&u16b(2));
+        exif.extend_from_slice(&u32b(4));
+        exif.extend_from_slice(&u32b(0));
+        exif.extend_from_slice(&u16b(0x0112));
+        exif.extend_from_slice(&u16b(3));
+        exif.extend_from_slice(&u32b(1));
+        exif.extend_from_slice(&u16b(value));
+        exif.extend_from_slice(&[0, 0]);
+        exif.extend_from_slice(&u32b(0));
+        exif
+    }
+
+    #[test]
+    fn test_orientation_from_exif() {
+        for value in 1..=8u16 {
+            assert_eq!(orientation_from_exif(&exif_with_orientation(false, value)), Some(value as u8));
+            assert_eq!(orientation_from_exif(&exif_with_orientation(true, value)), Some(value as u8));
+        }
+        // Without the APP1 prefix
+        assert_eq!(orientation_from_exif(&exif_with_orientation(false, 6)[6..]), Some(6));
+        assert_eq!(orientation_from_exif(&exif_with_orientation(false, 9)), None);
+        assert_eq!(orientation_from_exif(b"not exif"), None);
+        assert_eq!(orientation_from_exif(&exif_with_orientation(true, 3)[..20]), None);
+    }
+
+    #[test]
+    fn test_bgra_output_follows_orientation() {
+        // 2x1 red, green in stored orientation
+        let mut img = DecodedImage {
+            data: vec![255, 0, 0, 0, 255, 0],
+            width: 2,
+            height: 1,
+            format: BPGImageFormat::RGB24,
+            color_space: 1,
+            exif_data: None,
+            orientation: 6,
+            respect_orientation: true,
+        };
+        assert_eq!(img.display_dimensions(), (1, 2));
+        assert_eq!(img.to_bgra32().unwrap(), vec![0, 0, 255, 255, 0, 255, 0, 255]);
+
+        // Stride wider than a row leaves the padding untouched
+        let mut buffer = vec![7u8; 2 * 8];
+        img.copy_to_buffer(&mut buffer, 8).unwrap();
+        assert_eq!(buffer, vec![0, 0, 255, 255, 7, 7, 7, 7, 0, 255, 0, 255, 7, 7, 7, 7]);
+
+        img.respect_orientation = false;
+        assert_eq!(img.display_dimensions(), (2, 1));
+        assert_eq!(img.to_bgra32().unwrap(), vec![0, 0, 255, 255, 0, 255, 0, 255]);
+        let mut buffer = vec![0u8; 8];
+        img.copy_to_buffer(&mut buffer, 8).unwrap();
+        assert_eq!(buffer, vec![0, 0, 255, 255, 0, 255, 0, 255]);
+    }
 }
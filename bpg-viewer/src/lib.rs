@@ -7,12 +7,14 @@ pub mod encoder;
 pub mod thumbnail;
 pub mod universal_thumbnail;
 pub mod universal_decode;
+pub mod archive_thumbnails;
 
 // Re-export main types
-pub use decoder::{DecodedImage, decode_file, decode_memory};
+pub use decoder::{DecodedImage, decode_file, decode_memory, orientation_from_exif, apply_orientation};
 pub use encoder::BPGEncoder;
-pub use thumbnail::{ThumbnailGenerator, ThumbnailConfig};
+pub use thumbnail::{ThumbnailGenerator, ThumbnailConfig, ThumbnailFormat};
 pub use universal_thumbnail::UniversalThumbnailGenerator;
+pub use archive_thumbnails::generate_thumbnails_from_archive;
 pub use ffi::{BPGImageFormat, BPGEncoderConfig};
 
 // C FFI interface for embedding in other languages
@@ -76,6 +78,8 @@ pub extern "C" fn bpg_viewer_decode_file(path: *const c_char) -> *mut BPGImageHa
 }
 
 /// Get image dimensions from handle
+/// These are display dimensions: swapped for EXIF orientations 5-8 unless
+/// orientation handling was turned off with bpg_viewer_set_respect_orientation
 #[no_mangle]
 pub extern "C" fn bpg_viewer_get_dimensions(
     handle: *const BPGImageHandle,
@@ -87,14 +91,52 @@ pub extern "C" fn bpg_viewer_get_dimensions(
     }
 
     let handle_ref = unsafe { &*handle };
+    let (display_width, display_height) = handle_ref.image.display_dimensions();
     unsafe {
-        *width = handle_ref.image.width;
-        *height = handle_ref.image.height;
+        *width = display_width;
+        *height = display_height;
+    }
+
+    BPGViewerError::Success as c_int
+}
+
+/// Get the EXIF orientation (1-8, 1 when the image has none)
+/// Reported even when orientation handling is turned off
+#[no_mangle]
+pub extern "C" fn bpg_viewer_get_orientation(
+    handle: *const BPGImageHandle,
+    orientation: *mut u8,
+) -> c_int {
+    if handle.is_null() || orientation.is_null() {
+        return BPGViewerError::InvalidParam as c_int;
+    }
+
+    let handle_ref = unsafe { &*handle };
+    unsafe {
+        *orientation = handle_ref.image.orientation;
     }
 
     BPGViewerError::Success as c_int
 }
 
+/// Turn EXIF orientation handling on (default) or off
+/// When off, the BGRA output and dimensions are in stored orientation so the
+/// caller can rotate by itself using bpg_viewer_get_orientation
+#[no_mangle]
+pub extern "C" fn bpg_viewer_set_respect_orientation(
+    handle: *mut BPGImageHandle,
+    respect: c_int,
+) -> c_int {
+    if handle.is_null() {
+        return BPGViewerError::InvalidParam as c_int;
+    }
+
+    let handle_ref = unsafe { &mut *handle };
+    handle_ref.image.respect_orientation = respect != 0;
+
+    BPGViewerError::Success as c_int
+}
+
 /// Get image color space
 #[no_mangle]
 pub extern "C" fn bpg_viewer_get_color_space(
@@ -165,6 +207,7 @@ pub extern "C" fn bpg_viewer_get_exif_data(
 
 /// Get image data pointer and size
 /// The returned pointer is valid as long as the handle exists
+/// The data is in stored orientation (see bpg_viewer_get_orientation)
 #[no_mangle]
 pub extern "C" fn bpg_viewer_get_data(
     handle: *const BPGImageHandle,
@@ -186,6 +229,7 @@ pub extern "C" fn bpg_viewer_get_data(
 
 /// Get RGBA32 data from image (performs conversion if needed)
 /// Caller must free the returned pointer with bpg_viewer_free_buffer
+/// The data is in stored orientation (see bpg_viewer_get_orientation)
 #[no_mangle]
 pub extern "C" fn bpg_viewer_get_rgba32(
     handle: *const BPGImageHandle,
@@ -244,7 +288,8 @@ pub extern "C" fn bpg_viewer_get_bgra32(
     }
 }
 
-/// Free buffer allocated by bpg_viewer_get_rgba32 or bpg_viewer_get_bgra32
+/// Free buffer allocated by bpg_viewer_get_rgba32, bpg_viewer_get_bgra32 or
+/// bpg_thumbnail_generate_bgra
 #[no_mangle]
 pub extern "C" fn bpg_viewer_free_buffer(ptr: *mut u8, size: usize) {
     if ptr.is_null() {
@@ -318,6 +363,96 @@ pub extern "C" fn bpg_thumbnail_generate_png(
     }
 }
 
+/// Generate thumbnail and save as JPEG with the given quality (1-100)
+/// Uses the size and filter of the handle; much smaller than PNG for photo grids
+#[no_mangle]
+pub extern "C" fn bpg_thumbnail_generate_jpeg(
+    handle: *const BPGThumbnailHandle,
+    input_path: *const c_char,
+    output_path: *const c_char,
+    quality: u8,
+) -> c_int {
+    if handle.is_null() || input_path.is_null() || output_path.is_null() {
+        return BPGViewerError::InvalidParam as c_int;
+    }
+
+    let handle_ref = unsafe { &*handle };
+
+    let input_str = unsafe {
+        match CStr::from_ptr(input_path).to_str() {
+            Ok(s) => s,
+            Err(_) => return BPGViewerError::InvalidParam as c_int,
+        }
+    };
+
+    let output_str = unsafe {
+        match CStr::from_ptr(output_path).to_str() {
+            Ok(s) => s,
+            Err(_) => return BPGViewerError::InvalidParam as c_int,
+        }
+    };
+
+    let generator = ThumbnailGenerator::with_config(ThumbnailConfig {
+        output_format: ThumbnailFormat::Jpeg { quality },
+        ..handle_ref.generator.config().clone()
+    });
+    match generator.generate_thumbnail_to_png(
+        std::path::Path::new(input_str),
+        std::path::Path::new(output_str),
+    ) {
+        Ok(_) => BPGViewerError::Success as c_int,
+        Err(_) => BPGViewerError::EncodeFailed as c_int,
+    }
+}
+
+/// Generate thumbnail as BGRA32 pixels in memory (for WPF BitmapSource)
+/// Caller must free the returned pointer with bpg_viewer_free_buffer
+#[no_mangle]
+pub extern "C" fn bpg_thumbnail_generate_bgra(
+    handle: *const BPGThumbnailHandle,
+    input_path: *const c_char,
+    data: *mut *mut u8,
+    size: *mut usize,
+    width: *mut c_uint,
+    height: *mut c_uint,
+) -> c_int {
+    if handle.is_null()
+        || input_path.is_null()
+        || data.is_null()
+        || size.is_null()
+        || width.is_null()
+        || height.is_null()
+    {
+        return BPGViewerError::InvalidParam as c_int;
+    }
+
+    let handle_ref = unsafe { &*handle };
+
+    let input_str = unsafe {
+        match CStr::from_ptr(input_path).to_str() {
+            Ok(s) => s,
+            Err(_) => return BPGViewerError::InvalidParam as c_int,
+        }
+    };
+
+    match handle_ref.generator.generate_thumbnail_bgra(std::path::Path::new(input_str)) {
+        Ok((bgra_data, thumb_width, thumb_height)) => {
+            let len = bgra_data.len();
+            let boxed = bgra_data.into_boxed_slice();
+            let ptr = Box::into_raw(boxed) as *mut u8;
+
+            unsafe {
+                *data = ptr;
+                *size = len;
+                *width = thumb_width;
+                *height = thumb_height;
+            }
+            BPGViewerError::Success as c_int
+        }
+        Err(_) => BPGViewerError::DecodeFailed as c_int,
+    }
+}
+
 /// Free thumbnail generator handle
 #[no_mangle]
 pub extern "C" fn bpg_thumbnail_free(handle: *mut BPGThumbnailHandle) {
@@ -384,6 +519,49 @@ pub extern "C" fn universal_thumbnail_generate_png(
     }
 }
 
+/// Generate PNG thumbnails for `count` input files into `output_dir` in parallel
+/// `threads` = 0 uses one worker per core. Unsupported or failing files are
+/// skipped without stopping the batch. Returns the number of thumbnails written,
+/// or a negative error code if the arguments are invalid.
+#[no_mangle]
+pub extern "C" fn universal_thumbnail_generate_batch(
+    handle: *const UniversalThumbnailHandle,
+    input_paths: *const *const c_char,
+    count: usize,
+    output_dir: *const c_char,
+    threads: c_uint,
+) -> c_int {
+    if handle.is_null() || (input_paths.is_null() && count > 0) || output_dir.is_null() {
+        return BPGViewerError::InvalidParam as c_int;
+    }
+
+    let handle_ref = unsafe { &*handle };
+
+    let output_str = unsafe {
+        match CStr::from_ptr(output_dir).to_str() {
+            Ok(s) => s,
+            Err(_) => return BPGViewerError::InvalidParam as c_int,
+        }
+    };
+
+    let raw_paths = if count == 0 { &[][..] } else { unsafe { slice::from_raw_parts(input_paths, count) } };
+    let mut inputs = Vec::with_capacity(count);
+    for &raw in raw_paths {
+        if raw.is_null() {
+            return BPGViewerError::InvalidParam as c_int;
+        }
+        match unsafe { CStr::from_ptr(raw) }.to_str() {
+            Ok(s) => inputs.push(std::path::PathBuf::from(s)),
+            Err(_) => return BPGViewerError::InvalidParam as c_int,
+        }
+    }
+
+    let results = handle_ref
+        .generator
+        .generate_batch(&inputs, std::path::Path::new(output_str), threads as usize);
+    results.iter().filter(|r| r.is_ok()).count().min(c_int::MAX as usize) as c_int
+}
+
 /// Check if a file format is supported by the universal thumbnail generator
 #[no_mangle]
 pub extern "C" fn universal_thumbnail_is_supported(file_path: *const c_char) -> c_int {
@@ -5,10 +5,32 @@ use std::fs::File;
 use anyhow::Result;
 use image::{DynamicImage, ImageBuffer, Rgba, imageops::FilterType};
 
-use crate::decoder::{decode_file, DecodedImage};
+use crate::decoder::{decode_file, decode_memory, DecodedImage};
 use crate::encoder::BPGEncoder;
 use crate::ffi::BPGImageFormat;
 
+/// Leading bytes of every BPG file
+const BPG_MAGIC: [u8; 4] = [0x42, 0x50, 0x47, 0xFB];
+
+/// File format thumbnails are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailFormat {
+    #[default]
+    Png,
+    /// Much smaller than PNG for photo grids; alpha is dropped. Quality is 1-100.
+    Jpeg { quality: u8 },
+}
+
+impl ThumbnailFormat {
+    /// File extension for thumbnails in this format, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Png => "png",
+            ThumbnailFormat::Jpeg { .. } => "jpg",
+        }
+    }
+}
+
 /// Thumbnail generator configuration
 #[derive(Debug, Clone)]
 pub struct ThumbnailConfig {
@@ -16,6 +38,8 @@ pub struct ThumbnailConfig {
     pub max_height: u32,
     pub quality: u8,
     pub filter: FilterType,
+    /// Format of the files `generate_thumbnail_to_png` and friends write
+    pub output_format: ThumbnailFormat,
 }
 
 impl Default for ThumbnailConfig {
@@ -26,6 +50,7 @@ impl Default for ThumbnailConfig {
             quality: 28,
             // Use Triangle (bilinear) for speed - good enough for thumbnails
             filter: FilterType::Triangle,
+            output_format: ThumbnailFormat::Png,
         }
     }
 }
@@ -48,6 +73,11 @@ impl ThumbnailGenerator {
         Self { config }
     }
 
+    /// Settings this generator was created with
+    pub fn config(&self) -> &ThumbnailConfig {
+        &self.config
+    }
+
     /// Create a thumbnail generator with specific dimensions
     pub fn with_dimensions(max_width: u32, max_height: u32) -> Self {
         Self {
@@ -61,6 +91,11 @@ impl ThumbnailGenerator {
 
     /// Generate a thumbnail from a BPG file
     pub fn generate_thumbnail(&self, input_path: &Path) -> Result<Vec<u8>> {
+        Ok(self.generate_thumbnail_rgba(input_path)?.0)
+    }
+
+    /// Generate a thumbnail from a BPG file as RGBA pixels plus its width and height
+    pub fn generate_thumbnail_rgba(&self, input_path: &Path) -> Result<(Vec<u8>, u32, u32)> {
         // Decode the full BPG image
         let decoded = decode_file(input_path.to_str().unwrap())?;
 
@@ -79,17 +114,21 @@ impl ThumbnailGenerator {
             new_height,
         )?;
 
-        Ok(thumbnail_data)
+        Ok((thumbnail_data, new_width, new_height))
+    }
+
+    /// Generate a thumbnail from a BPG file as BGRA pixels plus its width and height,
+    /// ready for a WPF BitmapSource without going through a file
+    pub fn generate_thumbnail_bgra(&self, input_path: &Path) -> Result<(Vec<u8>, u32, u32)> {
+        let (rgba_data, width, height) = self.generate_thumbnail_rgba(input_path)?;
+        Ok((rgba_into_bgra(rgba_data), width, height))
     }
 
     /// Generate a thumbnail and save it as BPG
     pub fn generate_thumbnail_to_file(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        let thumbnail_data = self.generate_thumbnail(input_path)?;
+        let (thumbnail_data, new_width, new_height) = self.generate_thumbnail_rgba(input_path)?;
 
         // Re-encode as BPG
-        let decoded = decode_file(input_path.to_str().unwrap())?;
-        let (new_width, new_height) = self.calculate_dimensions(decoded.width, decoded.height);
-
         let encoder = BPGEncoder::with_quality(self.config.quality)?;
         let bpg_data = encoder.encode_from_memory(
             &thumbnail_data,
@@ -103,39 +142,31 @@ impl ThumbnailGenerator {
         Ok(())
     }
 
-    /// Generate a thumbnail and save it as PNG using fast PNG encoder
+    /// Generate a thumbnail and save it in `config.output_format` (PNG by default)
     pub fn generate_thumbnail_to_png(&self, input_path: &Path, output_path: &Path) -> Result<()> {
-        // Decode and get dimensions in one pass
-        let decoded = decode_file(input_path.to_str().unwrap())?;
-        let (new_width, new_height) = self.calculate_dimensions(decoded.width, decoded.height);
-
-        // Convert to RGBA32 for processing
-        let rgba_data = decoded.to_rgba32()?;
-
-        // Resize the image
-        let thumbnail_data = self.resize_image(
-            &rgba_data,
-            decoded.width,
-            decoded.height,
-            new_width,
-            new_height,
-        )?;
-
-        // Use fast png crate for encoding with optimized settings
-        let file = File::create(output_path)?;
-        let writer = BufWriter::with_capacity(64 * 1024, file); // 64KB buffer
-
-        let mut encoder = png::Encoder::new(writer, new_width, new_height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        encoder.set_compression(png::Compression::Fast);
-        encoder.set_filter(png::FilterType::Sub); // Faster filter
-        encoder.set_adaptive_filter(png::AdaptiveFilterType::NonAdaptive); // Skip filter selection
-
-        let mut writer = encoder.write_header()?;
-        writer.write_image_data(&thumbnail_data)?;
+        let (thumbnail_data, new_width, new_height) = self.generate_thumbnail_rgba(input_path)?;
+        write_thumbnail(&thumbnail_data, new_width, new_height, output_path, self.config.output_format)
+    }
 
-        Ok(())
+    /// Generate a thumbnail file from an encoded image already in memory, in
+    /// `config.output_format` (PNG by default)
+    ///
+    /// BPG data is recognised by its magic and goes through the BPG decoder;
+    /// anything else is handed to the `image` crate.
+    pub fn generate_thumbnail_from_memory_to_png(&self, data: &[u8], output_path: &Path) -> Result<()> {
+        let (rgba_data, width, height) = if data.starts_with(&BPG_MAGIC) {
+            let decoded = decode_memory(data)?;
+            (decoded.to_rgba32()?, decoded.width, decoded.height)
+        } else {
+            let img = image::load_from_memory(data)?.to_rgba8();
+            let (width, height) = img.dimensions();
+            (img.into_raw(), width, height)
+        };
+
+        let (new_width, new_height) = self.calculate_dimensions(width, height);
+        let thumbnail_data = self.resize_image(&rgba_data, width, height, new_width, new_height)?;
+
+        write_thumbnail(&thumbnail_data, new_width, new_height, output_path, self.config.output_format)
     }
 
     /// Calculate thumbnail dimensions while maintaining aspect ratio
@@ -175,6 +206,60 @@ impl ThumbnailGenerator {
     }
 }
 
+/// Swap the red and blue channels of RGBA pixels in place
+fn rgba_into_bgra(mut data: Vec<u8>) -> Vec<u8> {
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    data
+}
+
+/// Write RGBA thumbnail pixels to `output_path` in `format`
+pub(crate) fn write_thumbnail(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    output_path: &Path,
+    format: ThumbnailFormat,
+) -> Result<()> {
+    match format {
+        ThumbnailFormat::Png => write_png_rgba(data, width, height, output_path),
+        ThumbnailFormat::Jpeg { quality } => write_jpeg_rgba(data, width, height, output_path, quality),
+    }
+}
+
+/// Write RGBA data as baseline JPEG, dropping the alpha channel
+fn write_jpeg_rgba(data: &[u8], width: u32, height: u32, output_path: &Path, quality: u8) -> Result<()> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    let rgb: Vec<u8> = data.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+    let file = File::create(output_path)?;
+    let writer = BufWriter::with_capacity(64 * 1024, file);
+    let mut encoder = JpegEncoder::new_with_quality(writer, quality.clamp(1, 100));
+    encoder.encode(&rgb, width, height, image::ExtendedColorType::Rgb8)?;
+
+    Ok(())
+}
+
+/// Write RGBA data as PNG using the fast encoder settings
+fn write_png_rgba(data: &[u8], width: u32, height: u32, output_path: &Path) -> Result<()> {
+    // Use fast png crate for encoding with optimized settings
+    let file = File::create(output_path)?;
+    let writer = BufWriter::with_capacity(64 * 1024, file); // 64KB buffer
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Fast);
+    encoder.set_filter(png::FilterType::Sub); // Faster filter
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::NonAdaptive); // Skip filter selection
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+
+    Ok(())
+}
+
 impl Default for ThumbnailGenerator {
     fn default() -> Self {
         Self::new()
@@ -209,4 +294,51 @@ mod tests {
         assert_eq!(w, 50);
         assert_eq!(h, 50);
     }
+
+    #[test]
+    fn test_thumbnail_from_memory() -> Result<()> {
+        let mut png_data = Vec::new();
+        image::RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png)?;
+
+        let dir = tempfile::tempdir()?;
+        let out = dir.path().join("thumb.png");
+        ThumbnailGenerator::with_dimensions(10, 10).generate_thumbnail_from_memory_to_png(&png_data, &out)?;
+
+        let thumb = image::open(&out)?;
+        assert_eq!((thumb.width(), thumb.height()), (10, 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jpeg_output_format() -> Result<()> {
+        let mut png_data = Vec::new();
+        image::RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png_data), image::ImageFormat::Png)?;
+
+        let dir = tempfile::tempdir()?;
+        let out = dir.path().join("thumb.jpg");
+        let config = ThumbnailConfig {
+            max_width: 10,
+            max_height: 10,
+            output_format: ThumbnailFormat::Jpeg { quality: 80 },
+            ..Default::default()
+        };
+        assert_eq!(config.output_format.extension(), "jpg");
+        ThumbnailGenerator::with_config(config).generate_thumbnail_from_memory_to_png(&png_data, &out)?;
+
+        assert!(std::fs::read(&out)?.starts_with(&[0xFF, 0xD8]));
+        let thumb = image::open(&out)?;
+        assert_eq!((thumb.width(), thumb.height()), (10, 5));
+        assert_eq!(ThumbnailConfig::default().output_format, ThumbnailFormat::Png);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rgba_into_bgra() {
+        assert_eq!(
+            rgba_into_bgra(vec![255, 128, 64, 200, 1, 2, 3, 4]),
+            vec![64, 128, 255, 200, 3, 2, 1, 4]
+        );
+    }
 }
@@ -39,10 +39,12 @@ impl UniversalDecodedImage {
     /// Decode BPG file
     fn decode_bpg(input_path: &Path) -> Result<Self> {
         let decoded = decode_bpg_file(input_path.to_str().unwrap())?;
+        // Upright per the EXIF orientation, so dimensions come from the rotated output
         let bgra = decoded.to_bgra32()?;
+        let (width, height) = decoded.display_dimensions();
         Ok(Self {
-            width: decoded.width,
-            height: decoded.height,
+            width,
+            height,
             data: bgra,
         })
     }
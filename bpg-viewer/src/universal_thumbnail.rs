@@ -1,13 +1,13 @@
 // Universal Thumbnail Generation Module
 // Supports BPG, standard image formats, HEIC/HEIF, RAW, DNG, and JPEG2000 files
-use std::path::Path;
-use std::io::BufWriter;
-use std::fs::File;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, anyhow};
+use rayon::prelude::*;
 use image::{DynamicImage, ImageBuffer, Rgba, imageops::FilterType};
 
 use crate::decoder::{decode_file as decode_bpg_file, DecodedImage};
-use crate::thumbnail::{ThumbnailConfig, ThumbnailGenerator};
+use crate::thumbnail::{write_thumbnail, ThumbnailConfig, ThumbnailGenerator};
 
 /// Universal thumbnail generator that handles all image formats
 pub struct UniversalThumbnailGenerator {
@@ -58,28 +58,68 @@ impl UniversalThumbnailGenerator {
         }
     }
 
-    /// Generate a thumbnail and save it as PNG
+    /// Generate a thumbnail and save it in `config.output_format` (PNG by default)
     pub fn generate_thumbnail_to_png(&self, input_path: &Path, output_path: &Path) -> Result<()> {
         let thumbnail_data = self.generate_thumbnail(input_path)?;
         
         // Get dimensions from the thumbnail data
         let (width, height) = self.get_thumbnail_dimensions(input_path)?;
         
-        // Use fast png crate for encoding with optimized settings
-        let file = File::create(output_path)?;
-        let writer = BufWriter::with_capacity(64 * 1024, file); // 64KB buffer
+        write_thumbnail(&thumbnail_data, width, height, output_path, self.config.output_format)
+    }
 
-        let mut encoder = png::Encoder::new(writer, width, height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        encoder.set_compression(png::Compression::Fast);
-        encoder.set_filter(png::FilterType::Sub); // Faster filter
-        encoder.set_adaptive_filter(png::AdaptiveFilterType::NonAdaptive); // Skip filter selection
+    /// Generate thumbnails for many files at once on `threads` workers (0 = one per core)
+    ///
+    /// Returns one entry per input, in input order: the thumbnail written to
+    /// `output_dir` as `<stem>.png` (`.jpg` for JPEG output), or why that file
+    /// failed. Inputs sharing a stem (`IMG_1.jpg` next to `IMG_1.dng`) get
+    /// `<stem>_<ext>.png` instead.
+    /// Unsupported formats are skipped without being opened. A failure,
+    /// including a decoder panic, only affects its own entry.
+    pub fn generate_batch(&self, inputs: &[PathBuf], output_dir: &Path, threads: usize) -> Vec<Result<PathBuf, String>> {
+        if let Err(e) = std::fs::create_dir_all(output_dir) {
+            let error = format!("Failed to create output directory {}: {}", output_dir.display(), e);
+            return inputs.iter().map(|_| Err(error.clone())).collect();
+        }
+        let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool,
+            Err(e) => {
+                let error = format!("Failed to create thumbnail thread pool: {}", e);
+                return inputs.iter().map(|_| Err(error.clone())).collect();
+            }
+        };
 
-        let mut writer = encoder.write_header()?;
-        writer.write_image_data(&thumbnail_data)?;
+        let out_ext = self.config.output_format.extension();
+        let mut stem_counts: HashMap<String, usize> = HashMap::new();
+        for input in inputs.iter().filter(|p| Self::is_supported_format(p)) {
+            *stem_counts.entry(file_stem_lowercase(input)).or_default() += 1;
+        }
 
-        Ok(())
+        pool.install(|| {
+            inputs
+                .par_iter()
+                .map(|input| {
+                    if !Self::is_supported_format(input) {
+                        return Err(format!("Unsupported format: {}", input.display()));
+                    }
+                    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+                    let name = if stem_counts.get(&file_stem_lowercase(input)).copied().unwrap_or(0) > 1 {
+                        let ext = input.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+                        format!("{}_{}.{}", stem, ext, out_ext)
+                    } else {
+                        format!("{}.{}", stem, out_ext)
+                    };
+                    let output_path = output_dir.join(name);
+
+                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        self.generate_thumbnail_to_png(input, &output_path)
+                    }))
+                    .map_err(|_| format!("Decoder panicked on {}", input.display()))?
+                    .map_err(|e| format!("Failed to generate thumbnail for {}: {}", input.display(), e))?;
+                    Ok(output_path)
+                })
+                .collect()
+        })
     }
 
     /// Generate thumbnail from BPG file
@@ -440,6 +480,12 @@ impl UniversalThumbnailGenerator {
     }
 }
 
+/// Stem used to detect batch inputs that would share an output name; case-folded
+/// because Windows treats `a.png` and `A.png` as the same file
+fn file_stem_lowercase(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase()
+}
+
 impl Default for UniversalThumbnailGenerator {
     fn default() -> Self {
         Self::new()
@@ -460,6 +506,35 @@ mod tests {
         assert!(!UniversalThumbnailGenerator::is_supported_format(Path::new("test.txt")));
     }
 
+    #[test]
+    fn test_generate_batch_isolates_failures() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let png = dir.path().join("a.png");
+        let jpg = dir.path().join("a.jpg");
+        image::RgbaImage::from_pixel(40, 20, Rgba([0, 128, 255, 255])).save(&png)?;
+        image::RgbImage::from_pixel(20, 40, image::Rgb([0, 128, 255])).save(&jpg)?;
+        let broken = dir.path().join("broken.png");
+        std::fs::write(&broken, b"not really a png")?;
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"notes")?;
+
+        let out = dir.path().join("thumbs");
+        let generator = UniversalThumbnailGenerator::with_dimensions(10, 10);
+        let results = generator.generate_batch(&[png, broken, notes, jpg], &out, 2);
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], Ok(out.join("a_png.png")));
+        assert!(results[1].as_ref().unwrap_err().contains("broken.png"));
+        assert!(results[2].as_ref().unwrap_err().contains("Unsupported"));
+        assert_eq!(results[3], Ok(out.join("a_jpg.png")));
+
+        let thumb = image::open(out.join("a_png.png"))?;
+        assert_eq!((thumb.width(), thumb.height()), (10, 5));
+        let thumb = image::open(out.join("a_jpg.png"))?;
+        assert_eq!((thumb.width(), thumb.height()), (5, 10));
+        Ok(())
+    }
+
     #[test]
     fn test_calculate_dimensions() {
         let generator = UniversalThumbnailGenerator::with_dimensions(100, 100);
@@ -311,6 +311,89 @@ pub fn decode_file(input_path: &str) -> Result<(Vec<u8>, u32, u32, BPGImageForma
     Ok((data, width as u32, height as u32, format))
 }
 
+/// BPG color space codes from the file header
+pub const BPG_CS_YCBCR: u8 = 0;
+pub const BPG_CS_RGB: u8 = 1;
+pub const BPG_CS_YCGCO: u8 = 2;
+pub const BPG_CS_YCBCR_BT709: u8 = 3;
+pub const BPG_CS_YCBCR_BT2020: u8 = 4;
+
+/// Extension tag holding an ICC profile
+const BPG_EXTENSION_ICC: u32 = 2;
+
+/// Colour information stored in a BPG header
+///
+/// `color_space` only describes the YUV matrix the decoder undoes; the RGB
+/// primaries (e.g. Display P3) are known only through `icc_profile`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BpgColorInfo {
+    pub color_space: u8,
+    pub bit_depth: u8,
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+/// Read the colour space and embedded ICC profile from a BPG file header
+pub fn read_color_info(input_path: &str) -> Result<BpgColorInfo> {
+    let data = std::fs::read(input_path)?;
+    parse_color_info(&data)
+}
+
+/// Parse the header fields and extension data of an in-memory BPG file
+pub fn parse_color_info(data: &[u8]) -> Result<BpgColorInfo> {
+    if data.len() < 6 || !data.starts_with(b"BPG\xFB") {
+        return Err(anyhow!("Not a BPG file"));
+    }
+    let bit_depth = (data[4] & 0x0F) + 8;
+    let color_space = data[5] >> 4;
+    let has_extensions = data[5] & 0x08 != 0;
+
+    let mut pos = 6;
+    for _ in 0..3 {
+        // picture_width, picture_height, picture_data_length
+        read_ue7(data, &mut pos)?;
+    }
+
+    let mut icc_profile = None;
+    if has_extensions {
+        let ext_len = read_ue7(data, &mut pos)? as usize;
+        let end = pos
+            .checked_add(ext_len)
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| anyhow!("BPG extension data truncated"))?;
+        while pos < end {
+            let tag = read_ue7(data, &mut pos)?;
+            let len = read_ue7(data, &mut pos)? as usize;
+            let payload = data
+                .get(pos..pos + len)
+                .filter(|_| pos + len <= end)
+                .ok_or_else(|| anyhow!("BPG extension {} truncated", tag))?;
+            if tag == BPG_EXTENSION_ICC && !payload.is_empty() {
+                icc_profile = Some(payload.to_vec());
+            }
+            pos += len;
+        }
+    }
+
+    Ok(BpgColorInfo { color_space, bit_depth, icc_profile })
+}
+
+/// Big-endian base-128 integer (high bit = continuation), at most 32 bits
+fn read_ue7(data: &[u8], pos: &mut usize) -> Result<u32> {
+    let mut value: u32 = 0;
+    for _ in 0..5 {
+        let byte = *data.get(*pos).ok_or_else(|| anyhow!("BPG header truncated"))?;
+        *pos += 1;
+        value = value
+            .checked_mul(128)
+            .ok_or_else(|| anyhow!("BPG header value overflows"))?
+            | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(anyhow!("BPG header value too long"))
+}
+
 // Utility functions
 pub fn get_version() -> String {
     unsafe {
@@ -345,6 +428,39 @@ mod tests {
         println!("BPG version: {}", version);
     }
     
+    #[test]
+    fn test_parse_color_info() {
+        // 8-bit 4:2:0, color space 3 (BT.709), extension flag set
+        let mut header = vec![b'B', b'P', b'G', 0xFB, 0x20, 0x38];
+        header.extend_from_slice(&[0x81, 0x00, 0x40, 0x05]); // 128 x 64, 5 bytes of picture
+        let icc = b"fake-icc-profile";
+        let mut ext = vec![0x01, 0x02, 0xAA, 0xBB]; // EXIF, skipped
+        ext.push(0x02);
+        ext.push(icc.len() as u8);
+        ext.extend_from_slice(icc);
+        header.push(ext.len() as u8);
+        header.extend_from_slice(&ext);
+        header.extend_from_slice(&[0; 5]);
+
+        let info = parse_color_info(&header).unwrap();
+        assert_eq!(info.color_space, BPG_CS_YCBCR_BT709);
+        assert_eq!(info.bit_depth, 8);
+        assert_eq!(info.icc_profile.as_deref(), Some(&icc[..]));
+
+        // No extensions: untagged
+        let mut plain = header[..10].to_vec();
+        plain[5] = 0x00;
+        let info = parse_color_info(&plain).unwrap();
+        assert_eq!(info.color_space, BPG_CS_YCBCR);
+        assert!(info.icc_profile.is_none());
+
+        // Extension length past the end of the file
+        let mut truncated = header[..11].to_vec();
+        truncated[10] = 0x7F;
+        assert!(parse_color_info(&truncated).is_err());
+        assert!(parse_color_info(b"PNG").is_err());
+    }
+
     #[test]
     fn test_supported_encoders() {
         let encoders = get_supported_encoders();
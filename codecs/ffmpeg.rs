@@ -44,6 +44,13 @@ fn openarc_ffmpeg_dll_path() -> Result<PathBuf> {
     Ok(dir.join("openarc_ffmpeg.dll"))
 }
 
+fn load_openarc_ffmpeg() -> Result<Library> {
+    let dll_path = openarc_ffmpeg_dll_path()?;
+    unsafe { Library::new(&dll_path) }.map_err(|e| anyhow!("Failed to load {}: {}", dll_path.display(), e))
+}
+
+type StrerrorFn = unsafe extern "C" fn(c_int, *mut c_char, c_int) -> c_int;
+
 #[derive(Debug, Clone)]
 pub struct FfmpegEncodeOptions {
     pub codec: VideoCodec,
@@ -106,9 +113,7 @@ impl FFmpegEncoder {
         let crf = self.options.effective_crf() as i32;
         let copy_audio = if self.options.copy_audio { 1 } else { 0 };
 
-        let dll_path = openarc_ffmpeg_dll_path()?;
-        let lib = unsafe { Library::new(&dll_path) }
-            .map_err(|e| anyhow!("Failed to load {}: {}", dll_path.display(), e))?;
+        let lib = load_openarc_ffmpeg()?;
 
         type TranscodeFn = unsafe extern "C" fn(
             *const c_char,
@@ -118,7 +123,6 @@ impl FFmpegEncoder {
             c_int,
             c_int,
         ) -> c_int;
-        type StrerrorFn = unsafe extern "C" fn(c_int, *mut c_char, c_int) -> c_int;
 
         let transcode: libloading::Symbol<TranscodeFn> = unsafe { lib.get(b"openarc_ffmpeg_transcode\0") }
             .map_err(|e| anyhow!("Missing symbol openarc_ffmpeg_transcode: {}", e))?;
@@ -148,7 +152,88 @@ impl FFmpegEncoder {
     }
 }
 
-fn ffmpeg_err_to_string(err: i32, strerror: &libloading::Symbol<unsafe extern "C" fn(c_int, *mut c_char, c_int) -> c_int>) -> String {
+/// Default width of one sprite-sheet tile in pixels
+pub const DEFAULT_SPRITE_TILE_WIDTH: u32 = 160;
+
+/// Layout of a generated sprite sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteSheetInfo {
+    pub frames: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_width: u32,
+}
+
+/// Near-square grid for `frames` tiles (columns, rows)
+pub fn sprite_grid(frames: u32) -> (u32, u32) {
+    let frames = frames.max(1);
+    let columns = (frames as f64).sqrt().ceil() as u32;
+    let rows = (frames + columns - 1) / columns;
+    (columns, rows)
+}
+
+impl FFmpegEncoder {
+    /// Extract `frames` evenly spaced frames from `input` into one tiled image
+    ///
+    /// Decodes through the openarc_ffmpeg library in one pass, like `encode_file`.
+    /// The output format follows the extension of `output` (e.g. `.jpg`). Returns
+    /// `Ok(None)` without writing anything when the video has fewer frames than
+    /// requested.
+    pub fn generate_sprite_sheet(
+        &self,
+        input: &Path,
+        output: &Path,
+        frames: u32,
+        tile_width: u32,
+    ) -> Result<Option<SpriteSheetInfo>> {
+        if frames == 0 {
+            return Ok(None);
+        }
+
+        let (columns, rows) = sprite_grid(frames);
+        // Even tile widths keep the sheet valid for 4:2:0 encoders
+        let tile_width = tile_width.max(16) & !1;
+
+        let input_c = CString::new(input.to_string_lossy().as_bytes())?;
+        let output_c = CString::new(output.to_string_lossy().as_bytes())?;
+
+        let lib = load_openarc_ffmpeg()?;
+
+        type SpriteSheetFn = unsafe extern "C" fn(*const c_char, *const c_char, c_int, c_int, c_int, c_int) -> c_int;
+
+        let sprite_sheet: libloading::Symbol<SpriteSheetFn> = unsafe { lib.get(b"openarc_ffmpeg_sprite_sheet\0") }
+            .map_err(|e| anyhow!("Missing symbol openarc_ffmpeg_sprite_sheet: {}", e))?;
+        let strerror: libloading::Symbol<StrerrorFn> = unsafe { lib.get(b"openarc_ffmpeg_strerror\0") }
+            .map_err(|e| anyhow!("Missing symbol openarc_ffmpeg_strerror: {}", e))?;
+
+        let ret = unsafe {
+            sprite_sheet(
+                input_c.as_ptr(),
+                output_c.as_ptr(),
+                frames as c_int,
+                columns as c_int,
+                rows as c_int,
+                tile_width as c_int,
+            )
+        };
+
+        if ret < 0 {
+            return Err(anyhow!(
+                "FFmpeg sprite sheet failed: {} ({})",
+                ffmpeg_err_to_string(ret, &strerror),
+                ret
+            ));
+        }
+        // Fewer frames than requested; nothing was written
+        if ret > 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(SpriteSheetInfo { frames, columns, rows, tile_width }))
+    }
+}
+
+fn ffmpeg_err_to_string(err: i32, strerror: &libloading::Symbol<StrerrorFn>) -> String {
     let mut buf = vec![0 as c_char; 256];
     let ret = unsafe { strerror(err, buf.as_mut_ptr(), buf.len() as c_int) };
     if ret < 0 {
@@ -160,3 +245,16 @@ fn ffmpeg_err_to_string(err: i32, strerror: &libloading::Symbol<unsafe extern "C
         cstr.to_string_lossy().trim().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sprite_grid() {
+        assert_eq!(sprite_grid(1), (1, 1));
+        assert_eq!(sprite_grid(10), (4, 3));
+        assert_eq!(sprite_grid(16), (4, 4));
+        assert_eq!(sprite_grid(0), (1, 1));
+    }
+}
@@ -431,3 +431,336 @@ cleanup:
 
     return ret;
 }
+
+/* Pick the encoder's first supported pixel format, or `fallback` when it lists none */
+static enum AVPixelFormat encoder_pix_fmt(const AVCodec *enc, enum AVPixelFormat fallback) {
+    const void *configs = NULL;
+    int count = 0;
+    if (avcodec_get_supported_config(NULL, enc, AV_CODEC_CONFIG_PIX_FORMAT, 0, &configs, &count) < 0
+        || !configs || count <= 0) {
+        return fallback;
+    }
+    return ((const enum AVPixelFormat *)configs)[0];
+}
+
+static int write_single_image(const char *output_path, AVFrame *rgb, int width, int height) {
+    int ret = 0;
+    AVFormatContext *out_fmt = NULL;
+    AVCodecContext *enc_ctx = NULL;
+    AVStream *out_st = NULL;
+    struct SwsContext *sws = NULL;
+    AVFrame *frame = NULL;
+    AVPacket *pkt = NULL;
+
+    ret = avformat_alloc_output_context2(&out_fmt, NULL, NULL, output_path);
+    if (ret < 0 || !out_fmt) {
+        if (ret == 0) {
+            ret = AVERROR_UNKNOWN;
+        }
+        goto cleanup;
+    }
+
+    const AVCodec *enc = avcodec_find_encoder(out_fmt->oformat->video_codec);
+    if (!enc) {
+        ret = AVERROR_ENCODER_NOT_FOUND;
+        goto cleanup;
+    }
+
+    out_st = avformat_new_stream(out_fmt, NULL);
+    enc_ctx = avcodec_alloc_context3(enc);
+    if (!out_st || !enc_ctx) {
+        ret = AVERROR(ENOMEM);
+        goto cleanup;
+    }
+
+    enc_ctx->width = width;
+    enc_ctx->height = height;
+    enc_ctx->pix_fmt = encoder_pix_fmt(enc, AV_PIX_FMT_YUVJ420P);
+    enc_ctx->color_range = AVCOL_RANGE_JPEG;
+    enc_ctx->time_base = (AVRational){1, 1};
+    if (out_fmt->oformat->flags & AVFMT_GLOBALHEADER) {
+        enc_ctx->flags |= AV_CODEC_FLAG_GLOBAL_HEADER;
+    }
+
+    ret = avcodec_open2(enc_ctx, enc, NULL);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    ret = avcodec_parameters_from_context(out_st->codecpar, enc_ctx);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    out_st->time_base = enc_ctx->time_base;
+
+    /* One image to a plain file name, not a numbered sequence */
+    av_opt_set_int(out_fmt->priv_data, "update", 1, 0);
+
+    frame = av_frame_alloc();
+    pkt = av_packet_alloc();
+    if (!frame || !pkt) {
+        ret = AVERROR(ENOMEM);
+        goto cleanup;
+    }
+    frame->format = enc_ctx->pix_fmt;
+    frame->width = width;
+    frame->height = height;
+    frame->color_range = AVCOL_RANGE_JPEG;
+    ret = av_frame_get_buffer(frame, 32);
+    if (ret < 0) {
+        goto cleanup;
+    }
+
+    sws = sws_getContext(width, height, AV_PIX_FMT_RGB24, width, height, enc_ctx->pix_fmt, SWS_BICUBIC, NULL, NULL, NULL);
+    if (!sws) {
+        ret = AVERROR(EINVAL);
+        goto cleanup;
+    }
+    sws_scale(sws, (const uint8_t *const *)rgb->data, rgb->linesize, 0, height, frame->data, frame->linesize);
+    frame->pts = 0;
+
+    if (!(out_fmt->oformat->flags & AVFMT_NOFILE)) {
+        ret = avio_open(&out_fmt->pb, output_path, AVIO_FLAG_WRITE);
+        if (ret < 0) {
+            goto cleanup;
+        }
+    }
+    ret = avformat_write_header(out_fmt, NULL);
+    if (ret < 0) {
+        goto cleanup;
+    }
+
+    ret = avcodec_send_frame(enc_ctx, frame);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    ret = avcodec_send_frame(enc_ctx, NULL);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    while ((ret = avcodec_receive_packet(enc_ctx, pkt)) >= 0) {
+        pkt->stream_index = out_st->index;
+        av_packet_rescale_ts(pkt, enc_ctx->time_base, out_st->time_base);
+        ret = av_interleaved_write_frame(out_fmt, pkt);
+        av_packet_unref(pkt);
+        if (ret < 0) {
+            goto cleanup;
+        }
+    }
+    if (ret != AVERROR_EOF) {
+        goto cleanup;
+    }
+
+    ret = av_write_trailer(out_fmt);
+
+cleanup:
+    if (pkt) {
+        av_packet_free(&pkt);
+    }
+    if (frame) {
+        av_frame_free(&frame);
+    }
+    if (sws) {
+        sws_freeContext(sws);
+    }
+    if (enc_ctx) {
+        avcodec_free_context(&enc_ctx);
+    }
+    if (out_fmt) {
+        if (!(out_fmt->oformat->flags & AVFMT_NOFILE) && out_fmt->pb) {
+            avio_closep(&out_fmt->pb);
+        }
+        avformat_free_context(out_fmt);
+    }
+    return ret;
+}
+
+/*
+ * Tile `frames` evenly spaced frames of the first video stream into a
+ * columns x rows sheet, each tile `tile_width` pixels wide, and write it as one
+ * image whose format follows the extension of `output_path`.
+ *
+ * Returns 0 when written, 1 when the video has fewer than `frames` frames
+ * (nothing is written), or a negative AVERROR.
+ */
+int openarc_ffmpeg_sprite_sheet(const char *input_path, const char *output_path, int frames, int columns, int rows, int tile_width) {
+    int ret = 0;
+    AVFormatContext *in_fmt = NULL;
+    AVCodecContext *dec_ctx = NULL;
+    struct SwsContext *sws = NULL;
+    AVFrame *dec_frame = NULL;
+    AVFrame *sheet = NULL;
+    AVPacket *pkt = NULL;
+    int video_stream_index = -1;
+    int placed = 0;
+
+    if (!input_path || !output_path || frames <= 0 || columns <= 0 || rows <= 0
+        || columns * rows < frames || tile_width < 2) {
+        return AVERROR(EINVAL);
+    }
+
+    ret = avformat_open_input(&in_fmt, input_path, NULL, NULL);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    ret = avformat_find_stream_info(in_fmt, NULL);
+    if (ret < 0) {
+        goto cleanup;
+    }
+
+    ret = av_find_best_stream(in_fmt, AVMEDIA_TYPE_VIDEO, -1, -1, NULL, 0);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    video_stream_index = ret;
+    AVStream *st = in_fmt->streams[video_stream_index];
+
+    /* Stream duration first, the container's when the stream has none */
+    double duration = 0.0;
+    if (st->duration > 0) {
+        duration = st->duration * av_q2d(st->time_base);
+    } else if (in_fmt->duration > 0) {
+        duration = in_fmt->duration / (double)AV_TIME_BASE;
+    }
+    int64_t frame_count = st->nb_frames;
+    double fps = av_q2d(st->avg_frame_rate);
+    /* Some containers don't record nb_frames; estimate from duration and rate */
+    if (frame_count <= 0 && duration > 0.0 && fps > 0.0) {
+        frame_count = (int64_t)(duration * fps);
+    }
+    if (duration <= 0.0 || frame_count < frames) {
+        ret = 1;
+        goto cleanup;
+    }
+
+    ret = open_decoder(in_fmt, video_stream_index, &dec_ctx);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    if (dec_ctx->width <= 0 || dec_ctx->height <= 0) {
+        ret = AVERROR_INVALIDDATA;
+        goto cleanup;
+    }
+
+    /* Even tile sizes keep the sheet valid for 4:2:0 encoders */
+    int tile_w = tile_width & ~1;
+    int tile_h = (int)((int64_t)dec_ctx->height * tile_w / dec_ctx->width) & ~1;
+    if (tile_h < 2) {
+        tile_h = 2;
+    }
+
+    /* Unfilled tiles stay black */
+    sheet = av_frame_alloc();
+    if (!sheet) {
+        ret = AVERROR(ENOMEM);
+        goto cleanup;
+    }
+    sheet->format = AV_PIX_FMT_RGB24;
+    sheet->width = columns * tile_w;
+    sheet->height = rows * tile_h;
+    ret = av_frame_get_buffer(sheet, 32);
+    if (ret < 0) {
+        goto cleanup;
+    }
+    for (int y = 0; y < sheet->height; y++) {
+        memset(sheet->data[0] + (size_t)y * sheet->linesize[0], 0, (size_t)sheet->width * 3);
+    }
+
+    dec_frame = av_frame_alloc();
+    pkt = av_packet_alloc();
+    if (!dec_frame || !pkt) {
+        ret = AVERROR(ENOMEM);
+        goto cleanup;
+    }
+
+    double start = st->start_time != AV_NOPTS_VALUE ? st->start_time * av_q2d(st->time_base) : 0.0;
+    double step = duration / frames;
+    int draining = 0;
+
+    while (placed < frames) {
+        if (!draining) {
+            ret = av_read_frame(in_fmt, pkt);
+            if (ret == AVERROR_EOF) {
+                draining = 1;
+                ret = avcodec_send_packet(dec_ctx, NULL);
+            } else if (ret < 0) {
+                goto cleanup;
+            } else if (pkt->stream_index == video_stream_index) {
+                ret = avcodec_send_packet(dec_ctx, pkt);
+                av_packet_unref(pkt);
+            } else {
+                av_packet_unref(pkt);
+                continue;
+            }
+            if (ret < 0 && ret != AVERROR(EAGAIN)) {
+                goto cleanup;
+            }
+        }
+
+        while (placed < frames && (ret = avcodec_receive_frame(dec_ctx, dec_frame)) >= 0) {
+            int64_t pts = dec_frame->best_effort_timestamp;
+            double t = pts != AV_NOPTS_VALUE ? pts * av_q2d(st->time_base) - start : 0.0;
+            /* Like the fps filter: the first frame at or after each sample time */
+            if (t + 1e-6 >= placed * step) {
+                sws = sws_getCachedContext(
+                    sws,
+                    dec_frame->width,
+                    dec_frame->height,
+                    dec_frame->format,
+                    tile_w,
+                    tile_h,
+                    AV_PIX_FMT_RGB24,
+                    SWS_BICUBIC,
+                    NULL,
+                    NULL,
+                    NULL
+                );
+                if (!sws) {
+                    ret = AVERROR(EINVAL);
+                    goto cleanup;
+                }
+                int x = (placed % columns) * tile_w;
+                int y = (placed / columns) * tile_h;
+                uint8_t *dst[4] = { sheet->data[0] + (size_t)y * sheet->linesize[0] + (size_t)x * 3, NULL, NULL, NULL };
+                int dst_linesize[4] = { sheet->linesize[0], 0, 0, 0 };
+                sws_scale(sws, (const uint8_t *const *)dec_frame->data, dec_frame->linesize, 0, dec_frame->height, dst, dst_linesize);
+                placed++;
+            }
+            av_frame_unref(dec_frame);
+        }
+
+        if (ret == AVERROR_EOF) {
+            break;
+        }
+        if (ret < 0 && ret != AVERROR(EAGAIN)) {
+            goto cleanup;
+        }
+    }
+
+    if (placed == 0) {
+        ret = AVERROR_INVALIDDATA;
+        goto cleanup;
+    }
+
+    ret = write_single_image(output_path, sheet, sheet->width, sheet->height);
+
+cleanup:
+    if (pkt) {
+        av_packet_free(&pkt);
+    }
+    if (dec_frame) {
+        av_frame_free(&dec_frame);
+    }
+    if (sheet) {
+        av_frame_free(&sheet);
+    }
+    if (sws) {
+        sws_freeContext(sws);
+    }
+    if (dec_ctx) {
+        avcodec_free_context(&dec_ctx);
+    }
+    if (in_fmt) {
+        avformat_close_input(&in_fmt);
+    }
+    return ret;
+}
@@ -0,0 +1,283 @@
+//! Archive format sniffing for a single "open archive" entry point
+
+use anyhow::{Context, Result};
+use arcmax::core::crypto::CryptoError;
+use arcmax::formats::freearc::constants::ARC_SIGNATURE;
+use arcmax::formats::freearc::reader::FreeArcReader;
+use arcmax::formats::freearc::utils::split_compressor_encryption;
+use arcmax::formats::peazip::{is_pea_encrypted, PEA_MAGIC};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::orchestrator::ArchiveMetadata;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+/// Offset and value of the POSIX/GNU tar magic inside the first header block
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8; 5] = b"ustar";
+/// Compressed bytes of a tar.zst `peek_archive` reads looking for the metadata entry
+const PEEK_READ_LIMIT: u64 = 8 * 1024 * 1024;
+/// Bookkeeping entries of an OpenArc tar.zst that aren't user files
+const INTERNAL_ENTRIES: [&str; 4] = ["OPENARC_METADATA.json", "HASHES.sha256", "MANIFEST.txt", "misc.arc"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    /// zstd-compressed tar (the OpenArc container)
+    TarZst,
+    /// zstd stream that doesn't hold a tar
+    Zstd,
+    /// FreeARC `.arc`
+    FreeArc,
+    /// PeaZip `.pea`
+    Pea,
+    Unknown,
+}
+
+/// Identify an archive from its leading bytes, ignoring the extension
+///
+/// Only failing to open or read the file is an error; anything unrecognised is `Unknown`.
+pub fn detect_archive_format(path: &Path) -> Result<ArchiveFormat> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    file.by_ref()
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut head)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if head.starts_with(&ZSTD_MAGIC) {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        return Ok(if zstd_holds_tar(file) {
+            ArchiveFormat::TarZst
+        } else {
+            ArchiveFormat::Zstd
+        });
+    }
+    if head.starts_with(&ARC_SIGNATURE) {
+        return Ok(ArchiveFormat::FreeArc);
+    }
+    if head.first() == Some(&PEA_MAGIC) {
+        return Ok(ArchiveFormat::Pea);
+    }
+    Ok(ArchiveFormat::Unknown)
+}
+
+/// Quick summary of an archive for previews
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ArchivePeek {
+    pub format: ArchiveFormat,
+    /// Number of archived files; `None` when it can't be known without a full read
+    pub file_count: Option<u64>,
+    /// `file_count` is a lower bound from the entries read before giving up
+    pub file_count_estimated: bool,
+    /// Size of the archive file itself
+    pub archive_size: u64,
+    /// Total original size of the archived files, when the archive records it
+    pub total_size: Option<u64>,
+    /// Creation time in Unix seconds, from the OpenArc metadata
+    pub created_at: Option<u64>,
+    pub encrypted: bool,
+}
+
+/// Summarise an archive from its headers and metadata only
+///
+/// Unlike `list_archive_contents` this never walks the whole stream: a tar.zst
+/// is read until its metadata entry (at most `PEEK_READ_LIMIT` compressed
+/// bytes), a FreeARC archive only for its footer and directory, and a PEA
+/// archive only for its headers.
+pub fn peek_archive(path: &Path) -> Result<ArchivePeek> {
+    let format = detect_archive_format(path)?;
+    let archive_size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata of {}", path.display()))?
+        .len();
+    let mut peek = ArchivePeek {
+        format,
+        file_count: None,
+        file_count_estimated: false,
+        archive_size,
+        total_size: None,
+        created_at: None,
+        encrypted: false,
+    };
+
+    match format {
+        ArchiveFormat::TarZst => peek_tar_zst(path, &mut peek)?,
+        ArchiveFormat::Zstd => {
+            let mut head = Vec::new();
+            File::open(path)?.take(18).read_to_end(&mut head)?;
+            peek.file_count = Some(1);
+            peek.total_size = zstd::zstd_safe::get_frame_content_size(&head).ok().flatten();
+        }
+        ArchiveFormat::FreeArc => peek_freearc(path, &mut peek)?,
+        ArchiveFormat::Pea => peek.encrypted = is_pea_encrypted(path)?,
+        ArchiveFormat::Unknown => {}
+    }
+    Ok(peek)
+}
+
+fn peek_tar_zst(path: &Path, peek: &mut ArchivePeek) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let decoder = zstd::stream::read::Decoder::new(file.take(PEEK_READ_LIMIT))
+        .with_context(|| format!("Failed to create zstd decoder for {}", path.display()))?;
+    let mut archive = tar::Archive::new(decoder);
+
+    let mut seen = 0u64;
+    for entry in archive.entries().context("Failed to read tar entries")? {
+        // Running into the read limit ends the stream early
+        let Ok(mut entry) = entry else { break };
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = match entry.path() {
+            Ok(p) => p.to_string_lossy().trim_start_matches("./").to_string(),
+            Err(_) => continue,
+        };
+
+        if name == "OPENARC_METADATA.json" {
+            let mut json = String::new();
+            let meta = entry
+                .read_to_string(&mut json)
+                .ok()
+                .and_then(|_| serde_json::from_str::<ArchiveMetadata>(&json).ok());
+            if let Some(meta) = meta {
+                peek.created_at = Some(meta.created_at);
+                match meta.size_report {
+                    Some(report) => {
+                        let classes = [&report.images, &report.videos, &report.misc];
+                        peek.file_count = Some(classes.iter().map(|c| c.files as u64).sum());
+                        peek.total_size = Some(classes.iter().map(|c| c.original_bytes).sum());
+                    }
+                    // Older archives only list images and videos
+                    None => {
+                        peek.file_count = Some((meta.images.len() + meta.videos.len()) as u64);
+                        peek.file_count_estimated = true;
+                    }
+                }
+                return Ok(());
+            }
+        } else if !INTERNAL_ENTRIES.contains(&name.as_str()) {
+            seen += 1;
+        }
+    }
+
+    // No usable metadata: the count is exact only if the whole archive fit in the limit
+    peek.file_count = Some(seen);
+    peek.file_count_estimated = peek.archive_size > PEEK_READ_LIMIT;
+    Ok(())
+}
+
+fn peek_freearc(path: &Path, peek: &mut ArchivePeek) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    match FreeArcReader::new(file, None) {
+        Ok(reader) => {
+            let files = reader.directory.files.iter().filter(|f| !f.is_dir);
+            peek.file_count = Some(files.clone().count() as u64);
+            peek.total_size = Some(files.map(|f| f.size).sum());
+            peek.encrypted = reader
+                .directory
+                .data_blocks
+                .iter()
+                .any(|b| !split_compressor_encryption(&b.compressor).1.is_empty());
+            Ok(())
+        }
+        // The directory itself is encrypted; nothing more to learn without the password
+        Err(e) if matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::PasswordRequired)) => {
+            peek.encrypted = true;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Decode just enough of the stream to look for the tar header magic
+fn zstd_holds_tar(reader: impl Read) -> bool {
+    let Ok(decoder) = zstd::stream::read::Decoder::new(reader) else {
+        return false;
+    };
+    let mut block = Vec::with_capacity(512);
+    if decoder.take(512).read_to_end(&mut block).is_err() {
+        return false;
+    }
+    block.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len()) == Some(TAR_MAGIC.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect_archive_format() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("a.txt"), b"hello")?;
+
+        let codec = zstd_archive::ZstdCodec::new(zstd_archive::ZstdOptions::default());
+        let tar_zst = dir.path().join("a.bin");
+        codec.archive_dir_tar_zst(&src, &tar_zst)?;
+        assert_eq!(detect_archive_format(&tar_zst)?, ArchiveFormat::TarZst);
+
+        let plain_zst = dir.path().join("b.bin");
+        fs::write(&plain_zst, codec.compress_bytes(b"not a tar")?)?;
+        assert_eq!(detect_archive_format(&plain_zst)?, ArchiveFormat::Zstd);
+
+        let cases: [(&[u8], ArchiveFormat); 4] = [
+            (&[0x41, 0x72, 0x43, 0x01, 0, 0], ArchiveFormat::FreeArc),
+            (&[0xEA, 1, 6, 0x02, 0, 0, 0, 1, 0, 1], ArchiveFormat::Pea),
+            (b"PK\x03\x04", ArchiveFormat::Unknown),
+            (b"", ArchiveFormat::Unknown),
+        ];
+        for (i, (bytes, expected)) in cases.iter().enumerate() {
+            let path = dir.path().join(format!("case{}", i));
+            fs::write(&path, bytes)?;
+            assert_eq!(detect_archive_format(&path)?, *expected);
+        }
+
+        assert!(detect_archive_format(&dir.path().join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek_archive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("misc"))?;
+        fs::write(src.join("misc").join("a.txt"), b"hello")?;
+        fs::write(src.join("misc").join("b.txt"), b"world")?;
+        fs::write(src.join("MANIFEST.txt"), b"")?;
+
+        // Without metadata, entries are counted (internal files excluded)
+        let codec = zstd_archive::ZstdCodec::new(zstd_archive::ZstdOptions::default());
+        let bare = dir.path().join("bare.tar.zst");
+        codec.archive_dir_tar_zst(&src, &bare)?;
+        let peek = peek_archive(&bare)?;
+        assert_eq!(peek.format, ArchiveFormat::TarZst);
+        assert_eq!(peek.file_count, Some(2));
+        assert!(!peek.file_count_estimated);
+        assert!(!peek.encrypted);
+
+        let json = r#"{"version":1,"images":[],"created_at":1700000000,
+            "size_report":{"images":{"files":3,"original_bytes":300,"output_bytes":100},
+            "videos":{"files":1,"original_bytes":1000,"output_bytes":500},
+            "misc":{"files":2,"original_bytes":10,"output_bytes":10}}}"#;
+        fs::write(src.join("OPENARC_METADATA.json"), json)?;
+        let full = dir.path().join("full.tar.zst");
+        codec.archive_dir_tar_zst(&src, &full)?;
+        let peek = peek_archive(&full)?;
+        assert_eq!(peek.file_count, Some(6));
+        assert_eq!(peek.total_size, Some(1310));
+        assert_eq!(peek.created_at, Some(1_700_000_000));
+
+        let plain_zst = dir.path().join("b.zst");
+        fs::write(&plain_zst, codec.compress_bytes(b"just bytes")?)?;
+        let peek = peek_archive(&plain_zst)?;
+        assert_eq!((peek.format, peek.file_count), (ArchiveFormat::Zstd, Some(1)));
+
+        let serialized = serde_json::to_string(&peek)?;
+        assert!(serialized.contains("\"format\":\"zstd\""));
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use rusqlite::{params, Connection, OptionalExtension};
+use std::borrow::Cow;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
+use crate::catalog_migrations;
+use crate::location::LocationSummary;
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct ArchiveRecord {
     pub id: Option<i64>, // None when inserting new records
@@ -36,46 +41,8 @@ impl<'a> ArchiveTracker<'a> {
     }
 
     fn init_schema(&self) -> Result<()> {
-        self.conn
-            .execute_batch(
-                r#"
-            -- Table to track created archives
-            CREATE TABLE IF NOT EXISTS archives (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                archive_path TEXT NOT NULL,
-                archive_size INTEGER NOT NULL,
-                creation_date INTEGER NOT NULL,
-                original_location TEXT NOT NULL,
-                destination_location TEXT,
-                description TEXT,
-                file_count INTEGER NOT NULL DEFAULT 0
-            );
-
-            -- Index for faster lookups by archive path
-            CREATE INDEX IF NOT EXISTS idx_archives_path ON archives (archive_path);
-
-            -- Index for faster lookups by creation date
-            CREATE INDEX IF NOT EXISTS idx_archives_creation_date ON archives (creation_date);
-
-            -- Table to map files to archives
-            CREATE TABLE IF NOT EXISTS archive_files (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                archive_id INTEGER NOT NULL,
-                file_path TEXT NOT NULL,
-                original_path TEXT NOT NULL,
-                file_size INTEGER NOT NULL,
-                archived_at INTEGER NOT NULL,
-                FOREIGN KEY (archive_id) REFERENCES archives(id) ON DELETE CASCADE
-            );
-
-            -- Index for faster lookups by archive_id
-            CREATE INDEX IF NOT EXISTS idx_archive_files_archive_id ON archive_files (archive_id);
-
-            -- Index for faster lookups by file_path
-            CREATE INDEX IF NOT EXISTS idx_archive_files_path ON archive_files (file_path);
-        "#,
-            )
-            .context("Failed to create schema")?;
+        // Tables live in the shared catalog schema; see catalog_migrations
+        catalog_migrations::migrate(&*self.conn).context("Failed to migrate schema")?;
         Ok(())
     }
 
@@ -107,9 +74,10 @@ impl<'a> ArchiveTracker<'a> {
     }
 
     pub fn record_archive_files(&mut self, archive_id: i64, files: Vec<ArchiveFileMapping>) -> Result<()> {
+        // Savepoint so this nests inside BackupCatalog::in_transaction
         let tx = self
             .conn
-            .transaction()
+            .savepoint()
             .context("Failed to start transaction")?;
             
         let now = now_secs();
@@ -193,18 +161,32 @@ impl<'a> ArchiveTracker<'a> {
             .context("Failed to prepare query")?;
 
         let archives = stmt
-            .query_map([], |row| {
-                Ok(ArchiveRecord {
-                    id: Some(row.get(0)?),
-                    archive_path: row.get(1)?,
-                    archive_size: row.get::<_, i64>(2)? as u64,
-                    creation_date: row.get::<_, i64>(3)? as u64,
-                    original_location: row.get(4)?,
-                    destination_location: row.get(5)?,
-                    description: row.get(6)?,
-                    file_count: row.get::<_, i32>(7)? as u32,
-                })
-            })
+            .query_map([], archive_from_row)
+            .context("Failed to execute query")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to collect results")?;
+
+        Ok(archives)
+    }
+
+    /// Archives created in `[start, end)` (Unix seconds), newest first
+    ///
+    /// The half-open range lets callers page by month without double-counting
+    /// an archive created exactly on a boundary.
+    pub fn get_archives_in_range(&self, start: u64, end: u64) -> Result<Vec<ArchiveRecord>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, archive_path, archive_size, creation_date, original_location, destination_location, description, file_count 
+                      FROM archives 
+                      WHERE creation_date >= ?1 AND creation_date < ?2
+                      ORDER BY creation_date DESC")
+            .context("Failed to prepare query")?;
+
+        let archives = stmt
+            .query_map(
+                params![start.min(i64::MAX as u64) as i64, end.min(i64::MAX as u64) as i64],
+                archive_from_row,
+            )
             .context("Failed to execute query")?
             .collect::<Result<Vec<_>, _>>()
             .context("Failed to collect results")?;
@@ -222,6 +204,78 @@ impl<'a> ArchiveTracker<'a> {
         Ok(())
     }
 
+    /// Store the GPS summary gathered while creating the archive
+    pub fn set_location_summary(&mut self, archive_id: i64, summary: &LocationSummary) -> Result<()> {
+        let json = serde_json::to_string(summary).context("Failed to serialize location summary")?;
+        self.conn
+            .execute(
+                "UPDATE archives SET location_summary = ?1 WHERE id = ?2",
+                params![json, archive_id],
+            )
+            .context("Failed to store location summary")?;
+        Ok(())
+    }
+
+    /// Where the photos in `archive_path` were taken, if a summary was recorded
+    pub fn location_summary(&self, archive_path: &str) -> Result<Option<LocationSummary>> {
+        let json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT location_summary FROM archives WHERE archive_path = ?1 ORDER BY id DESC LIMIT 1",
+                params![archive_path],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to query location summary")?
+            .flatten();
+        json.map(|j| serde_json::from_str(&j).context("Failed to parse location summary"))
+            .transpose()
+    }
+
+    pub fn update_archive_description(&mut self, archive_path: &str, description: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE archives SET description = ?1 WHERE archive_path = ?2",
+                params![description, archive_path],
+            )
+            .context("Failed to update archive description")?;
+        Ok(())
+    }
+
+    /// Remove an archive record and its file mappings, e.g. after the archive file was deleted
+    pub fn delete_archive(&mut self, archive_id: i64) -> Result<()> {
+        let tx = self
+            .conn
+            .savepoint()
+            .context("Failed to start transaction")?;
+        if !delete_archive_rows(&tx, archive_id)? {
+            return Err(anyhow!("No archive record with id {}", archive_id));
+        }
+        tx.commit().context("Failed to commit transaction")?;
+        Ok(())
+    }
+
+    /// Delete the records of archives whose file (or first volume) no longer exists;
+    /// returns how many were removed
+    pub fn prune_missing(&mut self) -> Result<usize> {
+        let missing: Vec<i64> = self
+            .get_all_archives()?
+            .into_iter()
+            .filter(|a| !zstd_archive::split::archive_exists(Path::new(&a.archive_path)))
+            .filter_map(|a| a.id)
+            .collect();
+
+        let tx = self
+            .conn
+            .savepoint()
+            .context("Failed to start transaction")?;
+        for &archive_id in &missing {
+            delete_archive_rows(&tx, archive_id)?;
+        }
+        tx.commit().context("Failed to commit transaction")?;
+        Ok(missing.len())
+    }
+
     pub fn export_json(&self, output_path: impl AsRef<Path>) -> Result<()> {
         let archives = self.get_all_archives()?;
         let json = serde_json::to_string_pretty(&archives).context("Failed to serialize to JSON")?;
@@ -229,6 +283,75 @@ impl<'a> ArchiveTracker<'a> {
             .with_context(|| format!("Failed to write JSON to {}", output_path.as_ref().display()))?;
         Ok(())
     }
+
+    /// Write all archives as CSV with a header row, one archive per line in id order.
+    /// Columns follow `ArchiveRecord`'s fields; missing optional values are empty.
+    pub fn export_csv(&self, writer: impl Write) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, archive_path, archive_size, creation_date, original_location, destination_location, description, file_count
+                      FROM archives
+                      ORDER BY id")
+            .context("Failed to prepare query")?;
+        let rows = stmt.query_map([], archive_from_row).context("Failed to execute query")?;
+
+        let mut out = BufWriter::new(writer);
+        writeln!(
+            out,
+            "id,archive_path,archive_size,creation_date,original_location,destination_location,description,file_count"
+        )?;
+        for record in rows {
+            let r = record.context("Failed to read archive record")?;
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                r.id.unwrap_or_default(),
+                csv_field(&r.archive_path),
+                r.archive_size,
+                r.creation_date,
+                csv_field(&r.original_location),
+                csv_field(r.destination_location.as_deref().unwrap_or("")),
+                csv_field(r.description.as_deref().unwrap_or("")),
+                r.file_count,
+            )?;
+        }
+        out.flush().context("Failed to flush CSV output")?;
+        Ok(())
+    }
+}
+
+/// Quote a CSV field (RFC 4180) when it contains a separator, quote or line break
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Delete an archive row and its mappings; false if there was no such archive.
+/// The mappings go explicitly because SQLite ignores `ON DELETE CASCADE` unless
+/// `foreign_keys` is switched on for the connection.
+fn delete_archive_rows(conn: &Connection, archive_id: i64) -> Result<bool> {
+    conn.execute("DELETE FROM archive_files WHERE archive_id = ?1", params![archive_id])
+        .context("Failed to delete archive file mappings")?;
+    let removed = conn
+        .execute("DELETE FROM archives WHERE id = ?1", params![archive_id])
+        .context("Failed to delete archive record")?;
+    Ok(removed > 0)
+}
+
+fn archive_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ArchiveRecord> {
+    Ok(ArchiveRecord {
+        id: Some(row.get(0)?),
+        archive_path: row.get(1)?,
+        archive_size: row.get::<_, i64>(2)? as u64,
+        creation_date: row.get::<_, i64>(3)? as u64,
+        original_location: row.get(4)?,
+        destination_location: row.get(5)?,
+        description: row.get(6)?,
+        file_count: row.get::<_, i32>(7)? as u32,
+    })
 }
 
 fn now_secs() -> u64 {
@@ -307,6 +430,157 @@ mod tests {
         let all_archives = tracker.get_all_archives()?;
         assert_eq!(all_archives.len(), 1);
 
+        // Location summary round-trips through the catalog
+        assert!(tracker.location_summary("/path/to/archive.oarc")?.is_none());
+        let mut summary = LocationSummary::default();
+        summary.add(
+            "a.jpg",
+            Some(crate::location::GeoPoint { lat: 1.5, lon: 2.5 }),
+            crate::location::LocationMode::Summary,
+        );
+        tracker.set_location_summary(archive_id, &summary)?;
+        assert_eq!(tracker.location_summary("/path/to/archive.oarc")?, Some(summary));
+
+        // Annotate after the fact
+        tracker.update_archive_description("/path/to/archive.oarc", "Europe trip 2023")?;
+        let retrieved = tracker.get_archive_by_path("/path/to/archive.oarc")?.unwrap();
+        assert_eq!(retrieved.description, Some("Europe trip 2023".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archives_in_range() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        let mut tracker = ArchiveTracker::new(&mut conn)?;
+
+        // Jan, Feb and Mar 2024 (first of each month, UTC)
+        let dates = [("jan", 1_704_067_200i64), ("feb", 1_706_745_600), ("mar", 1_709_251_200)];
+        for (name, date) in dates {
+            let id = tracker.record_archive(ArchiveRecord {
+                id: None,
+                archive_path: format!("/archives/{}.tar.zst", name),
+                archive_size: 1,
+                creation_date: 0,
+                original_location: "/photos".to_string(),
+                destination_location: None,
+                description: None,
+                file_count: 1,
+            })?;
+            tracker.conn.execute(
+                "UPDATE archives SET creation_date = ?1 WHERE id = ?2",
+                params![date, id],
+            )?;
+        }
+
+        // February alone: the March archive sits exactly on the end boundary
+        let feb = tracker.get_archives_in_range(1_706_745_600, 1_709_251_200)?;
+        assert_eq!(feb.len(), 1);
+        assert_eq!(feb[0].archive_path, "/archives/feb.tar.zst");
+
+        let all = tracker.get_archives_in_range(0, u64::MAX)?;
+        let paths: Vec<_> = all.iter().map(|a| a.archive_path.as_str()).collect();
+        assert_eq!(paths, ["/archives/mar.tar.zst", "/archives/feb.tar.zst", "/archives/jan.tar.zst"]);
+
+        assert!(tracker.get_archives_in_range(1_709_251_201, 1_709_251_200)?.is_empty());
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_export_csv() -> Result<()> {
+        let mut conn = Connection::open_in_memory()?;
+        let mut tracker = ArchiveTracker::new(&mut conn)?;
+
+        let archives = [
+            ("/archives/a.tar.zst", None),
+            ("/archives/b.tar.zst", Some("Paris, \"2023\"")),
+        ];
+        for (path, description) in archives {
+            tracker.record_archive(ArchiveRecord {
+                id: None,
+                archive_path: path.to_string(),
+                archive_size: 42,
+                creation_date: 0,
+                original_location: "/photos".to_string(),
+                destination_location: None,
+                description: description.map(str::to_string),
+                file_count: 3,
+            })?;
+        }
+
+        let mut csv = Vec::new();
+        tracker.export_csv(&mut csv)?;
+        let csv = String::from_utf8(csv)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "id,archive_path,archive_size,creation_date,original_location,destination_location,description,file_count"
+        );
+        assert!(lines[1].starts_with("1,/archives/a.tar.zst,42,"));
+        assert!(lines[1].ends_with(",/photos,,,3"));
+        assert!(lines[2].ends_with(",/photos,,\"Paris, \"\"2023\"\"\",3"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_archive_removes_file_mappings() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let kept_path = dir.path().join("kept.tar.zst");
+        std::fs::write(&kept_path, b"archive")?;
+
+        let mut conn = Connection::open_in_memory()?;
+        let mut tracker = ArchiveTracker::new(&mut conn)?;
+
+        let mut add = |path: &Path, files: &[&str]| -> Result<i64> {
+            let id = tracker.record_archive(ArchiveRecord {
+                id: None,
+                archive_path: path.to_string_lossy().to_string(),
+                archive_size: 1,
+                creation_date: 0,
+                original_location: "/photos".to_string(),
+                destination_location: None,
+                description: None,
+                file_count: files.len() as u32,
+            })?;
+            let mappings = files
+                .iter()
+                .map(|f| ArchiveFileMapping {
+                    id: None,
+                    archive_id: id,
+                    file_path: f.to_string(),
+                    original_path: format!("/photos/{}", f),
+                    file_size: 1,
+                    archived_at: 0,
+                })
+                .collect();
+            tracker.record_archive_files(id, mappings)?;
+            Ok(id)
+        };
+        let kept = add(&kept_path, &["a.jpg"])?;
+        let deleted = add(&dir.path().join("deleted.tar.zst"), &["b.jpg", "c.jpg"])?;
+        let gone = add(&dir.path().join("gone.tar.zst"), &["d.jpg"])?;
+        let gone_too = add(&dir.path().join("gone_too.tar.zst"), &[])?;
+
+        tracker.delete_archive(deleted)?;
+        assert!(tracker.get_archive_files(deleted)?.is_empty());
+        assert_eq!(tracker.get_archive_files(kept)?.len(), 1);
+        assert_eq!(tracker.get_all_archives()?.len(), 3);
+        assert!(tracker.delete_archive(deleted).is_err());
+
+        // Only records whose file is missing go, along with their mappings
+        assert_eq!(tracker.prune_missing()?, 2);
+        let left: Vec<Option<i64>> = tracker.get_all_archives()?.into_iter().map(|a| a.id).collect();
+        assert_eq!(left, vec![Some(kept)]);
+        assert!(tracker.get_archive_files(gone)?.is_empty());
+        assert!(tracker.get_archive_files(gone_too)?.is_empty());
+        let orphans: i64 = tracker.conn.query_row(
+            "SELECT COUNT(*) FROM archive_files WHERE archive_id != ?1",
+            params![kept],
+            |r| r.get(0),
+        )?;
+        assert_eq!(orphans, 0);
+        assert_eq!(tracker.prune_missing()?, 0);
+        Ok(())
+    }
+}
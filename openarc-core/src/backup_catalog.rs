@@ -1,9 +1,14 @@
 use anyhow::{anyhow, Context, Result};
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::ser::{SerializeSeq, Serializer};
+use std::collections::HashMap;
 use std::fs;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use crate::catalog_migrations;
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct BackupEntry {
     pub path: String,
@@ -14,9 +19,128 @@ pub struct BackupEntry {
     pub archive_id: Option<String>,
 }
 
+/// Space saved by archiving, as recorded in the catalog
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct SavingsReport {
+    /// Original size of the files held by the counted archives
+    pub original_bytes: u64,
+    /// Size of those archives when they were written
+    pub archived_bytes: u64,
+    /// `archived_bytes / original_bytes` (1.0 when nothing is archived)
+    pub ratio: f64,
+    pub archive_count: u64,
+    pub file_count: u64,
+}
+
+impl SavingsReport {
+    pub fn saved_bytes(&self) -> u64 {
+        self.original_bytes.saturating_sub(self.archived_bytes)
+    }
+}
+
+/// Default cap on rows loaded by `BackupCatalog::load_index`
+pub const DEFAULT_INDEX_MAX_ENTRIES: usize = 5_000_000;
+
+/// Compact per-file record held by `CatalogIndex`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedFile {
+    pub size: u64,
+    pub mtime_secs: u64,
+    /// Raw SHA-256 (32 bytes instead of 64 hex chars)
+    pub sha256: Option<[u8; 32]>,
+    /// Interned archive name, resolved with `CatalogIndex::archive_name`
+    archive: Option<u32>,
+}
+
+/// In-memory snapshot of the catalog for O(1) lookups by path or content hash
+///
+/// Loading costs one table scan and roughly 100-150 bytes per entry (path
+/// string plus a compact record; archive names are interned and hashes kept
+/// as raw bytes), so a million-entry catalog needs on the order of 150MB.
+/// Prefer it over `should_skip_file` when checking many files at once; for a
+/// handful of lookups the per-query SQLite path uses far less memory. The
+/// snapshot does not see writes made after it was loaded.
+#[derive(Debug, Default)]
+pub struct CatalogIndex {
+    by_path: HashMap<String, IndexedFile>,
+    by_sha: HashMap<[u8; 32], Option<u32>>,
+    archives: Vec<String>,
+    archive_ids: HashMap<String, u32>,
+}
+
+impl CatalogIndex {
+    pub fn len(&self) -> usize {
+        self.by_path.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&IndexedFile> {
+        self.by_path.get(&normalize_path(path.as_ref()))
+    }
+
+    pub fn contains_path(&self, path: impl AsRef<Path>) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// True if `path` is catalogued with the same size and mtime (i.e. would be skipped)
+    pub fn is_unchanged(&self, path: impl AsRef<Path>, size: u64, mtime_secs: u64) -> bool {
+        self.get(path)
+            .map(|f| f.size == size && f.mtime_secs == mtime_secs)
+            .unwrap_or(false)
+    }
+
+    /// True if any catalogued file has this content hash (hex)
+    pub fn contains_sha256(&self, sha256_hex: &str) -> bool {
+        parse_sha256(sha256_hex)
+            .map(|d| self.by_sha.contains_key(&d))
+            .unwrap_or(false)
+    }
+
+    /// Archive holding content with this hash, if recorded
+    pub fn archive_for_sha256(&self, sha256_hex: &str) -> Option<&str> {
+        let digest = parse_sha256(sha256_hex)?;
+        let archive = (*self.by_sha.get(&digest)?)?;
+        self.archives.get(archive as usize).map(|s| s.as_str())
+    }
+
+    /// Archive a file was recorded in
+    pub fn archive_name(&self, file: &IndexedFile) -> Option<&str> {
+        file.archive
+            .and_then(|i| self.archives.get(i as usize))
+            .map(|s| s.as_str())
+    }
+
+    fn insert(&mut self, path: String, size: u64, mtime_secs: u64, sha256: Option<&str>, archive: Option<String>) {
+        let archive = archive.map(|name| match self.archive_ids.get(&name) {
+            Some(&i) => i,
+            None => {
+                let i = self.archives.len() as u32;
+                self.archive_ids.insert(name.clone(), i);
+                self.archives.push(name);
+                i
+            }
+        });
+        let sha256 = sha256.and_then(parse_sha256);
+        if let Some(digest) = sha256 {
+            self.by_sha.entry(digest).or_insert(archive);
+        }
+        self.by_path.insert(path, IndexedFile { size, mtime_secs, sha256, archive });
+    }
+}
+
+fn parse_sha256(hex_str: &str) -> Option<[u8; 32]> {
+    let mut out = [0u8; 32];
+    hex::decode_to_slice(hex_str.trim(), &mut out).ok()?;
+    Some(out)
+}
+
 pub struct BackupCatalog {
     conn: Connection,
     db_path: PathBuf,
+    path_base: Option<PathBuf>,
 }
 
 impl BackupCatalog {
@@ -28,33 +152,74 @@ impl BackupCatalog {
         conn.execute_batch("PRAGMA journal_mode = WAL;")
             .context("Failed to enable WAL mode")?;
 
-        let mut catalog = Self { conn, db_path };
+        let mut catalog = Self {
+            conn,
+            db_path,
+            path_base: None,
+        };
         catalog.init_schema().context("Failed to initialize schema")?;
         Ok(catalog)
     }
 
+    /// Open an existing catalog for lookups only, as a dry run does
+    ///
+    /// Nothing is created, migrated or written. A catalog whose schema is older
+    /// than this build is rejected instead of being upgraded in place.
+    pub fn open_read_only(db_path: impl AsRef<Path>) -> Result<Self> {
+        let db_path = db_path.as_ref().to_path_buf();
+        let conn = Connection::open_with_flags(
+            &db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open catalog DB at {}", db_path.display()))?;
+
+        let version = catalog_migrations::schema_version(&conn)?;
+        let latest = catalog_migrations::current_schema_version();
+        if version != latest {
+            return Err(anyhow!(
+                "Catalog {} has schema version {} but this build expects {}; run once without a dry run to migrate it",
+                db_path.display(),
+                version,
+                latest
+            ));
+        }
+
+        Ok(Self {
+            conn,
+            db_path,
+            path_base: None,
+        })
+    }
+
+    /// Store and look up paths relative to `base` so the catalog stays valid when
+    /// the source tree is backed up from another machine or mount point
+    ///
+    /// Paths outside `base` are kept absolute. The comparison is lexical, so
+    /// `base` should be spelled the same way as the input paths.
+    pub fn with_path_base(mut self, base: Option<PathBuf>) -> Self {
+        self.path_base = base;
+        self
+    }
+
+    /// Key under which `path` is stored in `backed_up_files`
+    pub fn catalog_key(&self, path: &Path) -> String {
+        match self.path_base.as_deref().and_then(|base| path.strip_prefix(base).ok()) {
+            // Always forward slashes so Windows and Unix machines agree
+            Some(rel) => normalize_path(rel).replace('\\', "/"),
+            None => normalize_path(path),
+        }
+    }
+
     fn init_schema(&mut self) -> Result<()> {
-        self.conn
-            .execute_batch(
-                r#"
-            CREATE TABLE IF NOT EXISTS backed_up_files (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                path TEXT UNIQUE NOT NULL,
-                size INTEGER NOT NULL,
-                mtime_secs INTEGER NOT NULL,
-                sha256 TEXT,
-                backed_up_at INTEGER NOT NULL,
-                archive_id TEXT
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_path ON backed_up_files (path);
-            CREATE INDEX IF NOT EXISTS idx_backed_up_at ON backed_up_files (backed_up_at);
-        "#,
-            )
-            .context("Failed to create schema")?;
+        catalog_migrations::migrate(&self.conn).context("Failed to migrate schema")?;
         Ok(())
     }
 
+    /// Schema version of the underlying database
+    pub fn schema_version(&self) -> Result<i64> {
+        catalog_migrations::schema_version(&self.conn)
+    }
+
     pub fn record_backup(&mut self, entry: BackupEntry) -> Result<()> {
         let now = now_secs();
         self.conn
@@ -76,9 +241,10 @@ impl BackupCatalog {
     }
 
     pub fn record_backups(&mut self, entries: Vec<BackupEntry>) -> Result<()> {
+        // A savepoint behaves like a transaction on its own and nests inside `in_transaction`
         let tx = self
             .conn
-            .transaction()
+            .savepoint()
             .context("Failed to start transaction")?;
         let now = now_secs();
 
@@ -103,8 +269,106 @@ impl BackupCatalog {
         Ok(())
     }
 
+    /// Store the SHA-256 of each file's original content, keyed by catalog path,
+    /// on rows already written by `record_backups`
+    pub fn record_source_hashes(&mut self, hashes: &[(String, String)]) -> Result<()> {
+        let tx = self
+            .conn
+            .savepoint()
+            .context("Failed to start transaction")?;
+        for (path, sha256) in hashes {
+            tx.execute(
+                "UPDATE backed_up_files SET source_sha256 = ?2 WHERE path = ?1",
+                params![path, sha256.to_ascii_lowercase()],
+            )
+            .context("Failed to record source hash")?;
+        }
+        tx.commit().context("Failed to commit transaction")?;
+        Ok(())
+    }
+
+    /// Archive already holding a file whose original content has this SHA-256
+    /// (hex), if any
+    ///
+    /// Only archives still tracked in `archives` and present on disk count, so
+    /// nothing new refers to content that was deleted or pruned.
+    pub fn contains_hash(&self, sha256: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT DISTINCT b.archive_id, a.archive_path
+                 FROM backed_up_files b
+                 JOIN archives a ON substr(a.archive_path, -length(b.archive_id)) = b.archive_id
+                 WHERE b.source_sha256 = ?1 AND b.archive_id IS NOT NULL",
+            )
+            .context("Failed to prepare query")?;
+        let rows = stmt
+            .query_map(params![sha256.trim().to_ascii_lowercase()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .context("Failed to query catalog")?;
+
+        for row in rows {
+            let (archive_id, archive_path) = row.context("Failed to read catalog row")?;
+            let path = Path::new(&archive_path);
+            // The suffix match also pairs `photos.tar.zst` with `old_photos.tar.zst`
+            let same_name = path.file_name().and_then(|n| n.to_str()) == Some(archive_id.as_str());
+            if same_name && zstd_archive::split::archive_exists(path) {
+                return Ok(Some(archive_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Run `f` as one unit: everything it writes commits together or is rolled back
+    ///
+    /// With WAL enabled, readers opening the catalog meanwhile see the state
+    /// before the batch and are never blocked by it.
+    pub fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn
+            .execute_batch("SAVEPOINT openarc_batch")
+            .context("Failed to start catalog transaction")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn
+                    .execute_batch("RELEASE openarc_batch")
+                    .context("Failed to commit catalog transaction")?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self
+                    .conn
+                    .execute_batch("ROLLBACK TO openarc_batch; RELEASE openarc_batch");
+                Err(e)
+            }
+        }
+    }
+
+    /// Copy the WAL into the main database file and truncate it
+    pub fn checkpoint(&self) -> Result<()> {
+        let busy: i64 = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+            .context("Failed to checkpoint catalog")?;
+        if busy != 0 {
+            // A reader still holds the WAL; the next checkpoint will finish the job
+            log::debug!("Catalog checkpoint incomplete for {}", self.db_path.display());
+        }
+        Ok(())
+    }
+
+    /// Checkpoint and close, reporting errors that dropping would hide
+    pub fn close(self) -> Result<()> {
+        self.checkpoint()?;
+        let db_path = self.db_path;
+        self.conn
+            .close()
+            .map_err(|(_, e)| anyhow!(e))
+            .with_context(|| format!("Failed to close catalog {}", db_path.display()))
+    }
+
     pub fn should_skip_file(&self, file_path: impl AsRef<Path>) -> Result<Option<bool>> {
-        let path_str = normalize_path(file_path.as_ref());
+        let path_str = self.catalog_key(file_path.as_ref());
 
         let metadata = fs::metadata(file_path.as_ref()).context("Failed to read file metadata")?;
         let current_size = metadata.len();
@@ -149,16 +413,7 @@ impl BackupCatalog {
             .context("Failed to prepare query")?;
 
         let entries = stmt
-            .query_map([], |row| {
-                Ok(BackupEntry {
-                    path: row.get(0)?,
-                    size: row.get::<_, i64>(1)? as u64,
-                    mtime_secs: row.get::<_, i64>(2)? as u64,
-                    sha256: row.get(3)?,
-                    backed_up_at: row.get::<_, i64>(4)? as u64,
-                    archive_id: row.get(5)?,
-                })
-            })
+            .query_map([], entry_from_row)
             .context("Failed to execute query")?
             .collect::<Result<Vec<_>, _>>()
             .context("Failed to collect results")?;
@@ -166,11 +421,168 @@ impl BackupCatalog {
         Ok(entries)
     }
 
-    pub fn export_json(&self, output_path: impl AsRef<Path>) -> Result<()> {
-        let entries = self.list_all()?;
-        let json = serde_json::to_string_pretty(&entries).context("Failed to serialize to JSON")?;
-        fs::write(output_path.as_ref(), json)
-            .with_context(|| format!("Failed to write JSON to {}", output_path.as_ref().display()))?;
+    /// Files whose latest backup went into `archive_id`, sorted by path
+    pub fn entries_for_archive(&self, archive_id: &str) -> Result<Vec<BackupEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT path, size, mtime_secs, sha256, backed_up_at, archive_id FROM backed_up_files
+                 WHERE archive_id = ?1 ORDER BY path",
+            )
+            .context("Failed to prepare query")?;
+
+        let entries = stmt
+            .query_map(params![archive_id], entry_from_row)
+            .context("Failed to execute query")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("Failed to collect results")?;
+
+        Ok(entries)
+    }
+
+    /// Most recent backup of `path`, or `None` if it was never backed up.
+    /// `path` is mapped through `catalog_key`, so it may be a filesystem path.
+    pub fn last_backup_of(&self, path: &str) -> Result<Option<BackupEntry>> {
+        self.conn
+            .query_row(
+                "SELECT path, size, mtime_secs, sha256, backed_up_at, archive_id FROM backed_up_files
+                 WHERE path = ?1",
+                params![self.catalog_key(Path::new(path))],
+                entry_from_row,
+            )
+            .optional()
+            .context("Failed to query catalog")
+    }
+
+    /// Bulk-load the catalog into a `CatalogIndex` (see its docs for memory use)
+    pub fn load_index(&self) -> Result<CatalogIndex> {
+        self.load_index_with_limit(DEFAULT_INDEX_MAX_ENTRIES)
+    }
+
+    /// Like `load_index`, but fail instead of loading more than `max_entries` rows
+    pub fn load_index_with_limit(&self, max_entries: usize) -> Result<CatalogIndex> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM backed_up_files", [], |row| row.get(0))
+            .context("Failed to count catalog entries")?;
+        if count as usize > max_entries {
+            return Err(anyhow!(
+                "Catalog has {} entries, more than the index limit of {}",
+                count,
+                max_entries
+            ));
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, size, mtime_secs, sha256, archive_id FROM backed_up_files")
+            .context("Failed to prepare query")?;
+        let mut rows = stmt.query([]).context("Failed to execute query")?;
+
+        let mut index = CatalogIndex {
+            by_path: HashMap::with_capacity(count as usize),
+            ..CatalogIndex::default()
+        };
+        while let Some(row) = rows.next().context("Failed to read catalog row")? {
+            let sha256: Option<String> = row.get(3)?;
+            index.insert(
+                row.get(0)?,
+                row.get::<_, i64>(1)? as u64,
+                row.get::<_, i64>(2)? as u64,
+                sha256.as_deref(),
+                row.get(4)?,
+            );
+        }
+
+        Ok(index)
+    }
+
+    /// Original bytes against archive bytes over every archive in the catalog
+    ///
+    /// Each archive's originals come from its file mappings, which stay put when a
+    /// file is later re-archived elsewhere; archives without mappings fall back to
+    /// the backup rows naming them. Archives with neither are left out on both
+    /// sides, and an archive recorded more than once (re-created at the same path)
+    /// counts only its latest record. Deduplicated copies and files skipped as
+    /// unchanged were never stored, so they add nothing and the figure errs low.
+    pub fn total_space_saved(&self) -> Result<SavingsReport> {
+        let mut by_mappings: HashMap<i64, (u64, u64)> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT archive_id, COUNT(*), COALESCE(SUM(file_size), 0) FROM archive_files GROUP BY archive_id")
+            .context("Failed to prepare query")?;
+        let mut rows = stmt.query([]).context("Failed to execute query")?;
+        while let Some(row) = rows.next().context("Failed to read archive file totals")? {
+            by_mappings.insert(row.get(0)?, (row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64));
+        }
+
+        let mut by_name: HashMap<String, (u64, u64)> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT archive_id, COUNT(*), COALESCE(SUM(size), 0) FROM backed_up_files
+                 WHERE archive_id IS NOT NULL GROUP BY archive_id",
+            )
+            .context("Failed to prepare query")?;
+        let mut rows = stmt.query([]).context("Failed to execute query")?;
+        while let Some(row) = rows.next().context("Failed to read backup totals")? {
+            by_name.insert(row.get(0)?, (row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64));
+        }
+
+        // Latest record per archive path
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, archive_path, archive_size FROM archives
+                 WHERE id IN (SELECT MAX(id) FROM archives GROUP BY archive_path)",
+            )
+            .context("Failed to prepare query")?;
+        let mut rows = stmt.query([]).context("Failed to execute query")?;
+
+        let mut report = SavingsReport::default();
+        while let Some(row) = rows.next().context("Failed to read archive record")? {
+            let id: i64 = row.get(0)?;
+            let archive_path: String = row.get(1)?;
+            let archive_size = row.get::<_, i64>(2)? as u64;
+
+            let name = Path::new(&archive_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(archive_path);
+            let Some(&(files, original)) = by_mappings.get(&id).or_else(|| by_name.get(&name)) else {
+                continue;
+            };
+            report.archive_count += 1;
+            report.file_count += files;
+            report.original_bytes += original;
+            report.archived_bytes += archive_size;
+        }
+
+        report.ratio = if report.original_bytes > 0 {
+            report.archived_bytes as f64 / report.original_bytes as f64
+        } else {
+            1.0
+        };
+        Ok(report)
+    }
+
+    /// Write every entry as a JSON array of `BackupEntry`, sorted by path.
+    /// Rows are serialized as they are read, so large catalogs aren't held in memory.
+    pub fn export_json(&self, writer: impl Write) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, size, mtime_secs, sha256, backed_up_at, archive_id FROM backed_up_files ORDER BY path")
+            .context("Failed to prepare query")?;
+        let rows = stmt.query_map([], entry_from_row).context("Failed to execute query")?;
+
+        let mut ser = serde_json::Serializer::pretty(BufWriter::new(writer));
+        let mut seq = ser.serialize_seq(None).context("Failed to write JSON")?;
+        for entry in rows {
+            let entry = entry.context("Failed to read catalog entry")?;
+            seq.serialize_element(&entry).context("Failed to write JSON")?;
+        }
+        seq.end().context("Failed to write JSON")?;
+        ser.into_inner().flush().context("Failed to flush JSON output")?;
         Ok(())
     }
 
@@ -183,6 +595,18 @@ impl BackupCatalog {
     }
 }
 
+/// Row of `SELECT path, size, mtime_secs, sha256, backed_up_at, archive_id`
+fn entry_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<BackupEntry> {
+    Ok(BackupEntry {
+        path: row.get(0)?,
+        size: row.get::<_, i64>(1)? as u64,
+        mtime_secs: row.get::<_, i64>(2)? as u64,
+        sha256: row.get(3)?,
+        backed_up_at: row.get::<_, i64>(4)? as u64,
+        archive_id: row.get(5)?,
+    })
+}
+
 pub fn normalize_path(path: &Path) -> String {
     let mut s = path.to_string_lossy().to_string();
     #[cfg(target_os = "windows")]
@@ -245,4 +669,300 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_total_space_saved() -> Result<()> {
+        use crate::archive_tracker::{ArchiveFileMapping, ArchiveRecord, ArchiveTracker};
+
+        let db_file = tempfile::NamedTempFile::new()?;
+        let mut catalog = BackupCatalog::new(db_file.path())?;
+        assert_eq!(catalog.total_space_saved()?.archive_count, 0);
+
+        let record = |path: &str, size: u64| ArchiveRecord {
+            id: None,
+            archive_path: path.to_string(),
+            archive_size: size,
+            creation_date: 0,
+            original_location: "/backups".to_string(),
+            destination_location: None,
+            description: None,
+            file_count: 0,
+        };
+        let mapping = |archive_id: i64, name: &str, size: u64| ArchiveFileMapping {
+            id: None,
+            archive_id,
+            file_path: format!("media/{}", name),
+            original_path: format!("/photos/{}", name),
+            file_size: size,
+            archived_at: 0,
+        };
+        let backup = |path: &str, size: u64, archive: &str| BackupEntry {
+            path: path.to_string(),
+            size,
+            mtime_secs: 0,
+            sha256: None,
+            backed_up_at: 0,
+            archive_id: Some(archive.to_string()),
+        };
+
+        // "a" has mappings; "b" only backup rows; "c" nothing to compare against
+        let mut tracker = ArchiveTracker::new(catalog.get_connection_mut())?;
+        let a = tracker.record_archive(record("/backups/a.tar.zst", 300))?;
+        tracker.record_archive_files(a, vec![mapping(a, "1.jpg", 1000), mapping(a, "2.jpg", 500)])?;
+        tracker.record_archive(record("/backups/b.tar.zst", 100))?;
+        tracker.record_archive(record("/backups/c.tar.zst", 999))?;
+        catalog.record_backups(vec![
+            backup("/docs/x.txt", 400, "b.tar.zst"),
+            // Re-archived into b later; a's mappings still count it for a
+            backup("/photos/1.jpg", 1000, "b.tar.zst"),
+        ])?;
+
+        let report = catalog.total_space_saved()?;
+        assert_eq!(report.archive_count, 2);
+        assert_eq!(report.file_count, 4);
+        assert_eq!(report.original_bytes, 1500 + 1400);
+        assert_eq!(report.archived_bytes, 400);
+        assert_eq!(report.saved_bytes(), 2500);
+        assert!((report.ratio - 400.0 / 2900.0).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_catalog_matches_across_machines() -> Result<()> {
+        let db_file = tempfile::NamedTempFile::new()?;
+        let laptop = tempfile::TempDir::new()?;
+        let desktop = tempfile::TempDir::new()?;
+
+        let on_laptop = laptop.path().join("Photos").join("a.jpg");
+        let on_desktop = desktop.path().join("Photos").join("a.jpg");
+        for p in [&on_laptop, &on_desktop] {
+            fs::create_dir_all(p.parent().unwrap())?;
+            fs::write(p, b"same photo")?;
+        }
+        let mtime = fs::metadata(&on_laptop)?.modified()?;
+        fs::File::options().write(true).open(&on_desktop)?.set_modified(mtime)?;
+
+        let mut catalog = BackupCatalog::new(db_file.path())?.with_path_base(Some(laptop.path().to_path_buf()));
+        let key = catalog.catalog_key(&on_laptop);
+        assert_eq!(key, "Photos/a.jpg");
+        let md = fs::metadata(&on_laptop)?;
+        catalog.record_backup(BackupEntry {
+            path: key,
+            size: md.len(),
+            mtime_secs: get_mtime_secs(&md)?,
+            sha256: None,
+            backed_up_at: now_secs(),
+            archive_id: None,
+        })?;
+
+        let desktop_catalog = BackupCatalog::new(db_file.path())?.with_path_base(Some(desktop.path().to_path_buf()));
+        let (skip, backup) = desktop_catalog.filter_files_to_backup(vec![on_desktop.clone()])?;
+        assert_eq!(skip, vec![on_desktop]);
+        assert!(backup.is_empty());
+
+        // Without a base the absolute path doesn't match
+        let absolute = BackupCatalog::new(db_file.path())?;
+        assert_eq!(absolute.should_skip_file(desktop.path().join("Photos").join("a.jpg"))?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_index() -> Result<()> {
+        let db_file = tempfile::NamedTempFile::new()?;
+        let mut catalog = BackupCatalog::new(db_file.path())?;
+
+        let sha = "ab".repeat(32);
+        catalog.record_backups(vec![
+            BackupEntry {
+                path: normalize_path(Path::new("/photos/a.jpg")),
+                size: 10,
+                mtime_secs: 100,
+                sha256: Some(sha.clone()),
+                backed_up_at: 0,
+                archive_id: Some("2024.tar.zst".to_string()),
+            },
+            BackupEntry {
+                path: normalize_path(Path::new("/photos/b.jpg")),
+                size: 20,
+                mtime_secs: 200,
+                sha256: None,
+                backed_up_at: 0,
+                archive_id: Some("2024.tar.zst".to_string()),
+            },
+        ])?;
+
+        let index = catalog.load_index()?;
+        assert_eq!(index.len(), 2);
+        assert!(index.is_unchanged("/photos/a.jpg", 10, 100));
+        assert!(!index.is_unchanged("/photos/a.jpg", 11, 100));
+        assert!(!index.contains_path("/photos/c.jpg"));
+        assert!(index.contains_sha256(&sha));
+        assert_eq!(index.archive_for_sha256(&sha), Some("2024.tar.zst"));
+        let b = index.get("/photos/b.jpg").unwrap();
+        assert_eq!(index.archive_name(b), Some("2024.tar.zst"));
+        assert_eq!(index.archives.len(), 1);
+
+        assert!(catalog.load_index_with_limit(1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_json_roundtrip() -> Result<()> {
+        let db_file = tempfile::NamedTempFile::new()?;
+        let mut catalog = BackupCatalog::new(db_file.path())?;
+
+        let mut empty = Vec::new();
+        catalog.export_json(&mut empty)?;
+        assert!(serde_json::from_slice::<Vec<BackupEntry>>(&empty)?.is_empty());
+
+        catalog.record_backups(vec![
+            BackupEntry {
+                path: "/photos/b \"quoted\".jpg".to_string(),
+                size: 20,
+                mtime_secs: 200,
+                sha256: Some("cd".repeat(32)),
+                backed_up_at: 0,
+                archive_id: Some("2024.tar.zst".to_string()),
+            },
+            BackupEntry {
+                path: "/photos/a.jpg".to_string(),
+                size: 10,
+                mtime_secs: 100,
+                sha256: None,
+                backed_up_at: 0,
+                archive_id: None,
+            },
+        ])?;
+
+        let mut json = Vec::new();
+        catalog.export_json(&mut json)?;
+        let back: Vec<BackupEntry> = serde_json::from_slice(&json)?;
+        let mut expected = catalog.list_all()?;
+        expected.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(serde_json::to_value(&back)?, serde_json::to_value(&expected)?);
+        assert_eq!(back[0].path, "/photos/a.jpg");
+        assert_eq!(back[1].sha256, Some("cd".repeat(32)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_and_path_queries() -> Result<()> {
+        let db_file = tempfile::NamedTempFile::new()?;
+        let mut catalog = BackupCatalog::new(db_file.path())?;
+
+        let entry = |path: &str, size: u64, archive: &str| BackupEntry {
+            path: normalize_path(Path::new(path)),
+            size,
+            mtime_secs: 100,
+            sha256: None,
+            backed_up_at: 0,
+            archive_id: Some(archive.to_string()),
+        };
+        catalog.record_backups(vec![
+            entry("/photos/b.jpg", 20, "2024.tar.zst"),
+            entry("/photos/a.jpg", 10, "2024.tar.zst"),
+            entry("/docs/c.pdf", 30, "docs.tar.zst"),
+        ])?;
+
+        let in_2024: Vec<String> = catalog
+            .entries_for_archive("2024.tar.zst")?
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(in_2024, vec![normalize_path(Path::new("/photos/a.jpg")), normalize_path(Path::new("/photos/b.jpg"))]);
+        assert!(catalog.entries_for_archive("missing.tar.zst")?.is_empty());
+
+        let a = catalog.last_backup_of("/photos/a.jpg")?.unwrap();
+        assert_eq!(a.size, 10);
+        assert_eq!(a.archive_id.as_deref(), Some("2024.tar.zst"));
+        assert!(a.backed_up_at > 0);
+        assert!(catalog.last_backup_of("/photos/never.jpg")?.is_none());
+
+        // A later backup of the same path replaces the earlier one
+        catalog.record_backups(vec![entry("/photos/a.jpg", 11, "2025.tar.zst")])?;
+        let a = catalog.last_backup_of("/photos/a.jpg")?.unwrap();
+        assert_eq!((a.size, a.archive_id.as_deref()), (11, Some("2025.tar.zst")));
+        assert_eq!(catalog.entries_for_archive("2024.tar.zst")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_batch_rolls_back() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let db_path = dir.path().join("test.catalog.sqlite");
+        let mut catalog = BackupCatalog::new(&db_path)?;
+
+        let entry = |path: &str| BackupEntry {
+            path: path.to_string(),
+            size: 1,
+            mtime_secs: 1,
+            sha256: None,
+            backed_up_at: 0,
+            archive_id: Some("a.tar.zst".to_string()),
+        };
+
+        let result: Result<()> = catalog.in_transaction(|cat| {
+            cat.record_backups(vec![entry("/photos/a.jpg")])?;
+
+            // A reader isn't blocked and doesn't see the uncommitted rows
+            let reader = Connection::open(&db_path)?;
+            let visible: i64 =
+                reader.query_row("SELECT COUNT(*) FROM backed_up_files", [], |r| r.get(0))?;
+            assert_eq!(visible, 0);
+
+            Err(anyhow!("simulated crash before the tracker writes"))
+        });
+        assert!(result.is_err());
+        assert!(catalog.list_all()?.is_empty());
+
+        catalog.in_transaction(|cat| cat.record_backups(vec![entry("/photos/b.jpg")]))?;
+        catalog.close()?;
+
+        let reopened = BackupCatalog::new(&db_path)?;
+        let paths: Vec<String> = reopened.list_all()?.into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, vec!["/photos/b.jpg".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_open_leaves_catalog_untouched() -> Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let db_path = temp_dir.path().join("catalog.sqlite");
+        let file = temp_dir.path().join("file.txt");
+        fs::write(&file, "content")?;
+
+        let mut catalog = BackupCatalog::new(&db_path)?;
+        catalog.record_backup(BackupEntry {
+            path: normalize_path(&file),
+            size: 7,
+            mtime_secs: get_mtime_secs(&fs::metadata(&file)?)?,
+            sha256: None,
+            backed_up_at: 0,
+            archive_id: None,
+        })?;
+        catalog.close()?;
+        let before = fs::read(&db_path)?;
+
+        let mut read_only = BackupCatalog::open_read_only(&db_path)?;
+        let (skipped, to_backup) = read_only.filter_files_to_backup(vec![file.clone()])?;
+        assert_eq!(skipped, vec![file.clone()]);
+        assert!(to_backup.is_empty());
+        assert!(read_only
+            .record_backup(BackupEntry {
+                path: "/other".to_string(),
+                size: 1,
+                mtime_secs: 1,
+                sha256: None,
+                backed_up_at: 0,
+                archive_id: None,
+            })
+            .is_err());
+        drop(read_only);
+
+        assert_eq!(fs::read(&db_path)?, before);
+        // A missing catalog is not created
+        assert!(BackupCatalog::open_read_only(temp_dir.path().join("missing.sqlite")).is_err());
+        assert!(!temp_dir.path().join("missing.sqlite").exists());
+        Ok(())
+    }
 }
@@ -0,0 +1,260 @@
+//! Versioned schema migrations for the catalog database
+//!
+//! `BackupCatalog` and `ArchiveTracker` share one SQLite file. Every schema
+//! change is appended to `MIGRATIONS` with the next version number; `migrate`
+//! applies whatever is missing, each step in its own transaction, and records
+//! it in `schema_version`. Steps must be idempotent because databases created
+//! before versioning existed already contain the version 1 tables.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::SystemTime;
+
+type MigrationFn = fn(&Connection) -> Result<()>;
+
+/// Ordered list of (version, description, step)
+const MIGRATIONS: &[(i64, &str, MigrationFn)] = &[
+    (1, "base catalog and archive tracking tables", migrate_v1_base_tables),
+    (2, "index backed_up_files by sha256", migrate_v2_sha256_index),
+    (3, "location summary per archive", migrate_v3_location_summary),
+    (4, "original content hash per backed-up file", migrate_v4_source_sha256),
+    (5, "index backed_up_files by archive_id", migrate_v5_archive_id_index),
+];
+
+/// Latest schema version known to this build
+pub fn current_schema_version() -> i64 {
+    MIGRATIONS.last().map(|(v, _, _)| *v).unwrap_or(0)
+}
+
+/// Version recorded in the database (0 if it predates versioning)
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    let has_table: Option<String> = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to query sqlite_master")?;
+    if has_table.is_none() {
+        return Ok(0);
+    }
+
+    let version: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+        .context("Failed to read schema version")?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Bring the database up to `current_schema_version()`; safe to call repeatedly
+pub fn migrate(conn: &Connection) -> Result<i64> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        );
+    "#,
+    )
+    .context("Failed to create schema_version table")?;
+
+    let mut version = schema_version(conn)?;
+    let latest = current_schema_version();
+    if version > latest {
+        anyhow::bail!(
+            "Catalog schema version {} is newer than this build supports ({})",
+            version,
+            latest
+        );
+    }
+
+    for (step_version, description, step) in MIGRATIONS {
+        if *step_version <= version {
+            continue;
+        }
+
+        let tx = conn
+            .unchecked_transaction()
+            .context("Failed to start migration transaction")?;
+        step(&tx).with_context(|| format!("Migration {} ({}) failed", step_version, description))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![step_version, description, now_secs() as i64],
+        )
+        .context("Failed to record schema version")?;
+        tx.commit().context("Failed to commit migration")?;
+
+        version = *step_version;
+    }
+
+    Ok(version)
+}
+
+/// Add a column unless it already exists (SQLite has no ADD COLUMN IF NOT EXISTS)
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if column_exists(conn, table, column)? {
+        return Ok(());
+    }
+    conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, decl))
+        .with_context(|| format!("Failed to add column {}.{}", table, column))?;
+    Ok(())
+}
+
+pub fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .context("Failed to read table info")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .context("Failed to read table info")?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names.iter().any(|n| n == column))
+}
+
+fn migrate_v1_base_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS backed_up_files (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT UNIQUE NOT NULL,
+            size INTEGER NOT NULL,
+            mtime_secs INTEGER NOT NULL,
+            sha256 TEXT,
+            backed_up_at INTEGER NOT NULL,
+            archive_id TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_path ON backed_up_files (path);
+        CREATE INDEX IF NOT EXISTS idx_backed_up_at ON backed_up_files (backed_up_at);
+
+        -- Table to track created archives
+        CREATE TABLE IF NOT EXISTS archives (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            archive_path TEXT NOT NULL,
+            archive_size INTEGER NOT NULL,
+            creation_date INTEGER NOT NULL,
+            original_location TEXT NOT NULL,
+            destination_location TEXT,
+            description TEXT,
+            file_count INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_archives_path ON archives (archive_path);
+        CREATE INDEX IF NOT EXISTS idx_archives_creation_date ON archives (creation_date);
+
+        -- Table to map files to archives
+        CREATE TABLE IF NOT EXISTS archive_files (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            archive_id INTEGER NOT NULL,
+            file_path TEXT NOT NULL,
+            original_path TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            archived_at INTEGER NOT NULL,
+            FOREIGN KEY (archive_id) REFERENCES archives(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_archive_files_archive_id ON archive_files (archive_id);
+        CREATE INDEX IF NOT EXISTS idx_archive_files_path ON archive_files (file_path);
+    "#,
+    )
+    .context("Failed to create base tables")?;
+    Ok(())
+}
+
+fn migrate_v2_sha256_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_sha256 ON backed_up_files (sha256);")
+        .context("Failed to create sha256 index")?;
+    Ok(())
+}
+
+fn migrate_v3_location_summary(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "archives", "location_summary", "TEXT")
+}
+
+/// `sha256` holds the digest of what was archived (a BPG for images), so
+/// matching new inputs by content needs the digest of the original as well
+fn migrate_v4_source_sha256(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "backed_up_files", "source_sha256", "TEXT")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_source_sha256 ON backed_up_files (source_sha256);")
+        .context("Failed to create source_sha256 index")?;
+    Ok(())
+}
+
+/// Backs `BackupCatalog::entries_for_archive`
+fn migrate_v5_archive_id_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_archive_id ON backed_up_files (archive_id);")
+        .context("Failed to create archive_id index")?;
+    Ok(())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrates_unversioned_database() -> Result<()> {
+        let db_file = tempfile::NamedTempFile::new()?;
+
+        // Schema as written by releases before versioning existed
+        {
+            let conn = Connection::open(db_file.path())?;
+            conn.execute_batch(
+                r#"
+                CREATE TABLE backed_up_files (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    path TEXT UNIQUE NOT NULL,
+                    size INTEGER NOT NULL,
+                    mtime_secs INTEGER NOT NULL,
+                    sha256 TEXT,
+                    backed_up_at INTEGER NOT NULL,
+                    archive_id TEXT
+                );
+                INSERT INTO backed_up_files (path, size, mtime_secs, sha256, backed_up_at, archive_id)
+                VALUES ('/photos/a.jpg', 10, 20, 'abc', 30, 'old.tar.zst');
+            "#,
+            )?;
+        }
+
+        let conn = Connection::open(db_file.path())?;
+        assert_eq!(schema_version(&conn)?, 0);
+        assert_eq!(migrate(&conn)?, current_schema_version());
+
+        // Existing rows survive and new objects exist
+        let path: String = conn.query_row("SELECT path FROM backed_up_files", [], |r| r.get(0))?;
+        assert_eq!(path, "/photos/a.jpg");
+        let index: Option<String> = conn
+            .query_row(
+                "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'idx_sha256'",
+                [],
+                |r| r.get(0),
+            )
+            .optional()?;
+        assert!(index.is_some());
+        assert!(column_exists(&conn, "archives", "file_count")?);
+
+        // Running again is a no-op
+        assert_eq!(migrate(&conn)?, current_schema_version());
+        let rows: i64 = conn.query_row("SELECT COUNT(*) FROM schema_version", [], |r| r.get(0))?;
+        assert_eq!(rows, MIGRATIONS.len() as i64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_column_if_missing_is_idempotent() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY);")?;
+        add_column_if_missing(&conn, "t", "note", "TEXT")?;
+        add_column_if_missing(&conn, "t", "note", "TEXT")?;
+        assert!(column_exists(&conn, "t", "note")?);
+        Ok(())
+    }
+}
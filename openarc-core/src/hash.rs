@@ -30,6 +30,192 @@ pub fn sha256_file_hex(path: impl AsRef<Path>) -> Result<String> {
     sha256_reader_hex(&mut file).with_context(|| format!("Failed to hash {}", path.display()))
 }
 
+/// `<archive>.sha256` next to `archive` (e.g. `photos.tar.zst.sha256`)
+pub fn sidecar_checksum_path(archive: impl AsRef<Path>) -> PathBuf {
+    let archive = archive.as_ref();
+    let mut name = archive.as_os_str().to_os_string();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Write the SHA-256 of the whole file as `<archive>.sha256` in `sha256sum` format,
+/// so `sha256sum -c` (or `shasum -a 256 -c`) can check it after a copy. The archive
+/// is listed by file name only, so the pair can be moved together. A split archive gets
+/// one line per volume (`photos.tar.zst.001`, ...). Returns the sidecar path.
+pub fn write_sidecar_checksum(archive: impl AsRef<Path>) -> Result<PathBuf> {
+    let archive = archive.as_ref();
+    let files = if archive.is_file() {
+        vec![archive.to_path_buf()]
+    } else {
+        zstd_archive::split::volume_paths(archive)
+    };
+    if files.is_empty() {
+        return Err(anyhow!("No archive or volumes found at {}", archive.display()));
+    }
+
+    let mut lines = String::new();
+    for file in &files {
+        let digest = sha256_file_hex(file)?;
+        let name = file
+            .file_name()
+            .ok_or_else(|| anyhow!("Archive path has no file name: {}", file.display()))?
+            .to_string_lossy();
+        // Two spaces: text mode in sha256sum's format, accepted by every checker
+        lines.push_str(&format!("{}  {}\n", digest, name));
+    }
+    let sidecar = sidecar_checksum_path(archive);
+    std::fs::write(&sidecar, lines).with_context(|| format!("Failed to write {}", sidecar.display()))?;
+    Ok(sidecar)
+}
+
+/// Digest used for dedup and the archive hash list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    /// Several times faster than SHA-256 on large media
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Some(HashAlgorithm::Sha256),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algo: HashAlgorithm) -> Self {
+        match algo {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha256(h) => hex::encode(h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+pub fn bytes_hex(data: &[u8], algo: HashAlgorithm) -> String {
+    let mut h = Hasher::new(algo);
+    h.update(data);
+    h.finalize_hex()
+}
+
+pub fn reader_hex<R: Read>(reader: &mut R, algo: HashAlgorithm) -> Result<String> {
+    let mut h = Hasher::new(algo);
+    std::io::copy(reader, &mut HashWriter(&mut h)).context("Failed to read while hashing")?;
+    Ok(h.finalize_hex())
+}
+
+/// Hash a file with the given algorithm
+pub fn file_hex(path: impl AsRef<Path>, algo: HashAlgorithm) -> Result<String> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::with_capacity(1024 * 1024, file);
+    reader_hex(&mut reader, algo).with_context(|| format!("Failed to hash {}", path.display()))
+}
+
+/// Default chunk size for multipart hashing (16MB)
+pub const DEFAULT_HASH_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Prefix marking per-chunk lines in `HASHES.sha256`
+const CHUNK_LINE_PREFIX: &str = "#chunk";
+
+/// Header naming the digest when it isn't SHA-256 (files without it are SHA-256)
+const ALGORITHM_LINE_PREFIX: &str = "#algorithm";
+
+/// SHA-256 of one fixed-size region of an archived file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHash {
+    pub rel_path: String,
+    pub chunk_size: u64,
+    pub index: u64,
+    pub hash: String,
+}
+
+/// A chunk whose contents no longer match the recorded hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkDamage {
+    pub rel_path: String,
+    pub index: u64,
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Hash `path` in `chunk_size` pieces, returning one hex digest per chunk
+pub fn sha256_file_chunks_hex(path: impl AsRef<Path>, chunk_size: u64) -> Result<Vec<String>> {
+    file_chunks_hex(path, chunk_size, HashAlgorithm::Sha256)
+}
+
+/// Hash `path` in `chunk_size` pieces with the given algorithm
+pub fn file_chunks_hex(path: impl AsRef<Path>, chunk_size: u64, algo: HashAlgorithm) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    if chunk_size == 0 {
+        return Err(anyhow!("Chunk size must be non-zero"));
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::with_capacity(1024 * 1024, file);
+    let mut out = Vec::new();
+
+    loop {
+        let mut chunk = (&mut reader).take(chunk_size);
+        let mut h = Hasher::new(algo);
+        let n = std::io::copy(&mut chunk, &mut HashWriter(&mut h))
+            .with_context(|| format!("Failed to hash {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        out.push(h.finalize_hex());
+        if n < chunk_size {
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+struct HashWriter<'a>(&'a mut Hasher);
+
+impl std::io::Write for HashWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 pub fn build_dedup_map(files: &[PathBuf]) -> Result<HashMap<String, Vec<PathBuf>>> {
     let mut map: HashMap<String, Vec<PathBuf>> = HashMap::new();
     for f in files {
@@ -52,6 +238,138 @@ pub fn write_hashes_file(hashes: &[(String, String)], output_path: impl AsRef<Pa
     Ok(())
 }
 
+/// Write whole-file hashes followed by per-chunk hashes
+///
+/// Chunk lines look like `#chunk <chunk_size> <index> <hash>  <rel_path>` so
+/// tools that only understand `hash  path` lines can skip them. Non-SHA-256
+/// digests are announced by a leading `#algorithm <name>` line; SHA-256 files
+/// stay plain `sha256sum` format.
+pub fn write_hashes_file_with_chunks(
+    hashes: &[(String, String)],
+    chunks: &[ChunkHash],
+    algo: HashAlgorithm,
+    output_path: impl AsRef<Path>,
+) -> Result<()> {
+    use std::io::Write;
+
+    let output_path = output_path.as_ref();
+    let file = std::fs::File::create(output_path)
+        .with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let mut out = std::io::BufWriter::new(file);
+
+    if algo != HashAlgorithm::Sha256 {
+        writeln!(out, "{} {}", ALGORITHM_LINE_PREFIX, algo.name())?;
+    }
+    for (hash_hex, rel_path) in hashes {
+        writeln!(out, "{}  {}", hash_hex, rel_path)?;
+    }
+    for c in chunks {
+        writeln!(out, "{} {} {} {}  {}", CHUNK_LINE_PREFIX, c.chunk_size, c.index, c.hash, c.rel_path)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+fn parse_algorithm_line(line: &str) -> Option<Result<HashAlgorithm>> {
+    let name = line.trim().strip_prefix(ALGORITHM_LINE_PREFIX)?.trim();
+    Some(HashAlgorithm::from_name(name).ok_or_else(|| anyhow!("Unknown hash algorithm: {}", name)))
+}
+
+/// Digest algorithm recorded in a hashes file (SHA-256 when not stated)
+pub fn read_hashes_algorithm(path: impl AsRef<Path>) -> Result<HashAlgorithm> {
+    let path = path.as_ref();
+    let f = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    for line in BufReader::new(f).lines() {
+        if let Some(algo) = parse_algorithm_line(&line?) {
+            return algo;
+        }
+    }
+    Ok(HashAlgorithm::Sha256)
+}
+
+/// Read the per-chunk entries from a hashes file (empty if none were recorded)
+pub fn read_chunk_hashes_file(path: impl AsRef<Path>) -> Result<Vec<ChunkHash>> {
+    let path = path.as_ref();
+    let f = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let r = BufReader::new(f);
+    let mut out = Vec::new();
+
+    for line in r.lines() {
+        let line = line?;
+        let Some(rest) = line.trim().strip_prefix(CHUNK_LINE_PREFIX) else {
+            continue;
+        };
+        let mut parts = rest.split_whitespace();
+        let chunk_size = parts.next().and_then(|v| v.parse().ok());
+        let index = parts.next().and_then(|v| v.parse().ok());
+        let hash = parts.next();
+        let rel = parts.next();
+        match (chunk_size, index, hash, rel) {
+            (Some(chunk_size), Some(index), Some(hash), Some(rel)) => out.push(ChunkHash {
+                rel_path: rel.to_string(),
+                chunk_size,
+                index,
+                hash: hash.to_string(),
+            }),
+            _ => return Err(anyhow!("Invalid chunk hash line: {}", line)),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Compare `path` against its recorded chunk hashes and report damaged regions
+pub fn verify_file_chunks(
+    path: impl AsRef<Path>,
+    chunks: &[ChunkHash],
+    algo: HashAlgorithm,
+) -> Result<Vec<ChunkDamage>> {
+    let path = path.as_ref();
+    let Some(first) = chunks.first() else {
+        return Ok(Vec::new());
+    };
+    let chunk_size = first.chunk_size;
+    let file_len = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path.display()))?
+        .len();
+    let actual = file_chunks_hex(path, chunk_size, algo)?;
+
+    let mut damage = Vec::new();
+    for c in chunks {
+        let matches = actual.get(c.index as usize).map(|h| *h == c.hash).unwrap_or(false);
+        if !matches {
+            let offset = c.index * chunk_size;
+            damage.push(ChunkDamage {
+                rel_path: c.rel_path.clone(),
+                index: c.index,
+                offset,
+                len: chunk_size.min(file_len.saturating_sub(offset)),
+            });
+        }
+    }
+
+    Ok(damage)
+}
+
+/// Check every file with chunk hashes under `root_dir` and collect damaged chunks
+pub fn verify_dir_chunks(root_dir: impl AsRef<Path>, hashes_file: impl AsRef<Path>) -> Result<Vec<ChunkDamage>> {
+    let root_dir = root_dir.as_ref();
+    let hashes_file = hashes_file.as_ref();
+    let algo = read_hashes_algorithm(hashes_file)?;
+    let mut by_file: HashMap<String, Vec<ChunkHash>> = HashMap::new();
+    for c in read_chunk_hashes_file(hashes_file)? {
+        by_file.entry(c.rel_path.clone()).or_default().push(c);
+    }
+
+    let mut damage = Vec::new();
+    for (rel, chunks) in by_file {
+        damage.extend(verify_file_chunks(root_dir.join(&rel), &chunks, algo)?);
+    }
+    damage.sort_by(|a, b| a.rel_path.cmp(&b.rel_path).then(a.index.cmp(&b.index)));
+    Ok(damage)
+}
+
 pub fn read_hashes_file(path: impl AsRef<Path>) -> Result<Vec<(String, String)>> {
     let path = path.as_ref();
     let f = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
@@ -61,7 +379,7 @@ pub fn read_hashes_file(path: impl AsRef<Path>) -> Result<Vec<(String, String)>>
     for line in r.lines() {
         let line = line?;
         let line = line.trim();
-        if line.is_empty() {
+        if line.is_empty() || line.starts_with('#') {
             continue;
         }
         let mut parts = line.split_whitespace();
@@ -77,11 +395,31 @@ pub fn verify_dir_against_hashes(root_dir: impl AsRef<Path>, hashes_file: impl A
     let root_dir = root_dir.as_ref();
     let hashes_file = hashes_file.as_ref();
 
+    let algo = read_hashes_algorithm(hashes_file)?;
     let entries = read_hashes_file(hashes_file)?;
     for (expected_hash, rel) in entries {
-        let path = root_dir.join(rel);
-        let actual = sha256_file_hex(&path)?;
+        let path = root_dir.join(&rel);
+        let actual = file_hex(&path, algo)?;
         if actual != expected_hash {
+            // Point at the damaged regions when chunk hashes were recorded
+            let chunks: Vec<ChunkHash> = read_chunk_hashes_file(hashes_file)?
+                .into_iter()
+                .filter(|c| c.rel_path == rel)
+                .collect();
+            let damage = verify_file_chunks(&path, &chunks, algo)?;
+            if !damage.is_empty() {
+                let ranges: Vec<String> = damage
+                    .iter()
+                    .map(|d| format!("chunk {} (bytes {}..{})", d.index, d.offset, d.offset + d.len))
+                    .collect();
+                return Err(anyhow!(
+                    "Hash mismatch for {} (expected {}, got {}); damaged: {}",
+                    path.display(),
+                    expected_hash,
+                    actual,
+                    ranges.join(", ")
+                ));
+            }
             return Err(anyhow!(
                 "Hash mismatch for {} (expected {}, got {})",
                 path.display(),
@@ -110,3 +448,395 @@ pub fn verify_tar_zst_archive_with_level(archive_path: impl AsRef<Path>, zstd_le
     let codec = zstd_archive::ZstdCodec::new(opts);
     verify_tar_zst_archive(&codec, archive_path)
 }
+
+/// Name of the embedded hash list inside OpenArc archives
+pub const HASHES_FILE_NAME: &str = "HASHES.sha256";
+
+/// Why an archived entry failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationFailureKind {
+    /// Entry content doesn't match the recorded digest
+    Mismatch { expected: String, actual: String },
+    /// Entry is listed in the hash file but absent from the archive
+    Missing,
+}
+
+/// One entry that failed embedded-hash verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationFailure {
+    pub rel_path: String,
+    pub kind: VerificationFailureKind,
+}
+
+fn normalize_tar_path(p: &str) -> String {
+    p.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+fn parse_hashes_text(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l.split_whitespace();
+            Some((parts.next()?.to_string(), normalize_tar_path(parts.next()?)))
+        })
+        .collect()
+}
+
+/// Check extracted files under `root` against the text of a hashes file
+///
+/// Only entries for which `include` returns true are checked. Returns how many
+/// files were checked along with every one that was missing or didn't match.
+pub fn verify_extracted_files(
+    root: impl AsRef<Path>,
+    hashes_text: &str,
+    include: impl Fn(&str) -> bool,
+) -> Result<(usize, Vec<VerificationFailure>)> {
+    let root = root.as_ref();
+    let algo = hashes_text
+        .lines()
+        .find_map(parse_algorithm_line)
+        .transpose()?
+        .unwrap_or(HashAlgorithm::Sha256);
+
+    let mut checked = 0usize;
+    let mut failures = Vec::new();
+    for (expected, rel) in parse_hashes_text(hashes_text) {
+        if !include(&rel) {
+            continue;
+        }
+        checked += 1;
+        let path = root.join(&rel);
+        if !path.is_file() {
+            failures.push(VerificationFailure {
+                rel_path: rel,
+                kind: VerificationFailureKind::Missing,
+            });
+            continue;
+        }
+        let actual = file_hex(&path, algo)?;
+        if actual != expected {
+            failures.push(VerificationFailure {
+                rel_path: rel,
+                kind: VerificationFailureKind::Mismatch { expected, actual },
+            });
+        }
+    }
+
+    Ok((checked, failures))
+}
+
+/// Outcome of a full read of an archive against its embedded hash list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Listed entries whose content matched, sorted by path
+    pub verified: Vec<String>,
+    /// Listed entries that were missing or mismatched, sorted by path
+    pub failures: Vec<VerificationFailure>,
+}
+
+impl VerifyReport {
+    /// Entries listed in the hash file but absent from the archive
+    pub fn missing(&self) -> impl Iterator<Item = &VerificationFailure> {
+        self.failures
+            .iter()
+            .filter(|f| f.kind == VerificationFailureKind::Missing)
+    }
+
+    /// Entries whose content doesn't match the recorded digest
+    pub fn mismatched(&self) -> impl Iterator<Item = &VerificationFailure> {
+        self.failures
+            .iter()
+            .filter(|f| matches!(f.kind, VerificationFailureKind::Mismatch { .. }))
+    }
+
+    /// Number of missing plus mismatched entries
+    pub fn problem_count(&self) -> usize {
+        self.failures.len()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Verify a `.tar.zst` archive against its embedded `HASHES.sha256` in one pass
+///
+/// Same as `verify_archive_full`, returning only the entries that failed. An
+/// empty result means every listed entry matched.
+pub fn verify_embedded_hashes(archive_path: impl AsRef<Path>) -> Result<Vec<VerificationFailure>> {
+    verify_archive_full(archive_path).map(|report| report.failures)
+}
+
+/// Read every entry of a `.tar.zst` archive and check it against the embedded
+/// `HASHES.sha256`
+///
+/// The whole stream is decompressed, so truncation or corruption anywhere in
+/// the archive surfaces as an error rather than going unnoticed. Each entry is
+/// hashed in memory; nothing is written to disk. Once the hash list has been
+/// seen, entries it doesn't mention are skipped without hashing; entries
+/// before it are hashed with every supported algorithm since the list names
+/// the one to use. Fails if the archive has no `HASHES.sha256`.
+pub fn verify_archive_full(archive_path: impl AsRef<Path>) -> Result<VerifyReport> {
+    let archive_path = archive_path.as_ref();
+    let file = zstd_archive::SplitReader::open(archive_path)
+        .with_context(|| format!("Failed to open {}", archive_path.display()))?;
+    let decoder = zstd_archive::archive_decoder(file)
+        .with_context(|| format!("Failed to create zstd decoder for {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(decoder);
+
+    const ALL_ALGORITHMS: [HashAlgorithm; 2] = [HashAlgorithm::Sha256, HashAlgorithm::Blake3];
+
+    let mut expected: Option<(HashAlgorithm, HashMap<String, String>)> = None;
+    // rel path -> digests, one per algorithm computed
+    let mut actual: HashMap<String, Vec<(HashAlgorithm, String)>> = HashMap::new();
+
+    for entry in archive.entries().context("Failed to read tar entries")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let rel = normalize_tar_path(&entry.path().context("Failed to read tar entry path")?.to_string_lossy());
+
+        if rel == HASHES_FILE_NAME {
+            let mut text = String::new();
+            entry
+                .read_to_string(&mut text)
+                .context("Failed to read embedded HASHES.sha256")?;
+            let algo = text
+                .lines()
+                .find_map(parse_algorithm_line)
+                .transpose()?
+                .unwrap_or(HashAlgorithm::Sha256);
+            let listed: HashMap<String, String> = parse_hashes_text(&text).into_iter().map(|(h, r)| (r, h)).collect();
+            // Entries seen before the list that it doesn't mention are irrelevant
+            actual.retain(|r, _| listed.contains_key(r));
+            expected = Some((algo, listed));
+            continue;
+        }
+
+        let algos: Vec<HashAlgorithm> = match expected {
+            Some((_, ref listed)) if !listed.contains_key(&rel) => continue,
+            Some((algo, _)) => vec![algo],
+            None => ALL_ALGORITHMS.to_vec(),
+        };
+        let mut hashers: Vec<Hasher> = algos.iter().map(|&a| Hasher::new(a)).collect();
+        let mut buf = vec![0u8; 1024 * 1024];
+        loop {
+            let n = entry.read(&mut buf).with_context(|| format!("Failed to hash {}", rel))?;
+            if n == 0 {
+                break;
+            }
+            for h in hashers.iter_mut() {
+                h.update(&buf[..n]);
+            }
+        }
+        let digests = algos.into_iter().zip(hashers.into_iter().map(Hasher::finalize_hex)).collect();
+        actual.insert(rel, digests);
+    }
+
+    let (algo, expected) = expected.ok_or_else(|| anyhow!("Archive has no embedded {}", HASHES_FILE_NAME))?;
+
+    let mut report = VerifyReport::default();
+    let failures = &mut report.failures;
+    for (rel, expected_hash) in expected {
+        let got = actual
+            .get(&rel)
+            .and_then(|digests| digests.iter().find(|(a, _)| *a == algo))
+            .map(|(_, d)| d);
+        match got {
+            Some(got) if *got == expected_hash => report.verified.push(rel),
+            Some(got) => failures.push(VerificationFailure {
+                rel_path: rel,
+                kind: VerificationFailureKind::Mismatch {
+                    expected: expected_hash,
+                    actual: got.clone(),
+                },
+            }),
+            None => failures.push(VerificationFailure {
+                rel_path: rel,
+                kind: VerificationFailureKind::Missing,
+            }),
+        }
+    }
+    report.verified.sort();
+    report.failures.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_checksum_format() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let archive = tmp.path().join("photos.tar.zst");
+        std::fs::write(&archive, b"abc").unwrap();
+
+        let sidecar = write_sidecar_checksum(&archive).unwrap();
+        assert_eq!(sidecar, tmp.path().join("photos.tar.zst.sha256"));
+        assert_eq!(
+            std::fs::read_to_string(&sidecar).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  photos.tar.zst\n"
+        );
+
+        // Split archives list each volume, as found next to the base name
+        let split = tmp.path().join("split.tar.zst");
+        std::fs::write(tmp.path().join("split.tar.zst.001"), b"abc").unwrap();
+        std::fs::write(tmp.path().join("split.tar.zst.002"), b"abc").unwrap();
+        let sidecar = write_sidecar_checksum(&split).unwrap();
+        assert_eq!(sidecar, tmp.path().join("split.tar.zst.sha256"));
+        assert_eq!(
+            std::fs::read_to_string(&sidecar).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  split.tar.zst.001\n\
+             ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  split.tar.zst.002\n"
+        );
+    }
+
+    #[test]
+    fn test_chunk_hashes_locate_damage() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let file = tmp.path().join("video.mp4");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&file, &data).unwrap();
+
+        let chunk_size = 4096;
+        let chunks: Vec<ChunkHash> = sha256_file_chunks_hex(&file, chunk_size)
+            .unwrap()
+            .into_iter()
+            .enumerate()
+            .map(|(i, hash)| ChunkHash {
+                rel_path: "video.mp4".to_string(),
+                chunk_size,
+                index: i as u64,
+                hash,
+            })
+            .collect();
+        assert_eq!(chunks.len(), 3);
+
+        let hashes_path = tmp.path().join("HASHES.sha256");
+        let whole = vec![(sha256_file_hex(&file).unwrap(), "video.mp4".to_string())];
+        write_hashes_file_with_chunks(&whole, &chunks, HashAlgorithm::Sha256, &hashes_path).unwrap();
+        assert_eq!(read_hashes_file(&hashes_path).unwrap(), whole);
+        assert_eq!(read_chunk_hashes_file(&hashes_path).unwrap(), chunks);
+
+        // Flip a byte in the last chunk
+        let mut corrupted = data.clone();
+        corrupted[9_000] ^= 0xFF;
+        std::fs::write(&file, &corrupted).unwrap();
+
+        let damage = verify_dir_chunks(tmp.path(), &hashes_path).unwrap();
+        assert_eq!(damage.len(), 1);
+        assert_eq!(damage[0].index, 2);
+        assert_eq!(damage[0].offset, 8192);
+        assert_eq!(damage[0].len, 10_000 - 8192);
+
+        let err = verify_dir_against_hashes(tmp.path(), &hashes_path).unwrap_err();
+        assert!(err.to_string().contains("chunk 2"));
+    }
+
+    #[test]
+    fn test_verify_embedded_hashes_streams_archive() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(src.join("media")).unwrap();
+        std::fs::write(src.join("media/a.bpg"), b"image data").unwrap();
+        std::fs::write(src.join("MANIFEST.txt"), b"manifest").unwrap();
+        let hashes = vec![
+            (sha256_bytes_hex(b"image data"), "media/a.bpg".to_string()),
+            (sha256_bytes_hex(b"manifest"), "MANIFEST.txt".to_string()),
+            (sha256_bytes_hex(b"gone"), "media/gone.bpg".to_string()),
+        ];
+        write_hashes_file(&hashes, src.join(HASHES_FILE_NAME)).unwrap();
+
+        let archive = tmp.path().join("test.tar.zst");
+        let codec = zstd_archive::ZstdCodec::new(zstd_archive::ZstdOptions::default());
+        codec.archive_dir_tar_zst(&src, &archive).unwrap();
+
+        let failures = verify_embedded_hashes(&archive).unwrap();
+        assert_eq!(
+            failures,
+            vec![VerificationFailure {
+                rel_path: "media/gone.bpg".to_string(),
+                kind: VerificationFailureKind::Missing,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_verify_archive_full_reports_every_entry() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("good.bin"), b"good").unwrap();
+        std::fs::write(src.join("bad.bin"), b"tampered").unwrap();
+        let hashes = vec![
+            (sha256_bytes_hex(b"good"), "good.bin".to_string()),
+            (sha256_bytes_hex(b"original"), "bad.bin".to_string()),
+            (sha256_bytes_hex(b"gone"), "gone.bin".to_string()),
+        ];
+        write_hashes_file(&hashes, src.join(HASHES_FILE_NAME)).unwrap();
+
+        let archive = tmp.path().join("test.tar.zst");
+        let codec = zstd_archive::ZstdCodec::new(zstd_archive::ZstdOptions::default());
+        codec.archive_dir_tar_zst(&src, &archive).unwrap();
+
+        let report = verify_archive_full(&archive).unwrap();
+        assert_eq!(report.verified, vec!["good.bin".to_string()]);
+        assert_eq!(report.problem_count(), 2);
+        assert!(!report.is_ok());
+        assert_eq!(report.missing().map(|f| f.rel_path.as_str()).collect::<Vec<_>>(), vec!["gone.bin"]);
+        assert_eq!(
+            report.mismatched().collect::<Vec<_>>(),
+            vec![&VerificationFailure {
+                rel_path: "bad.bin".to_string(),
+                kind: VerificationFailureKind::Mismatch {
+                    expected: sha256_bytes_hex(b"original"),
+                    actual: sha256_bytes_hex(b"tampered"),
+                },
+            }]
+        );
+
+        // A truncated archive fails outright instead of verifying what it can
+        let bytes = std::fs::read(&archive).unwrap();
+        std::fs::write(&archive, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(verify_archive_full(&archive).is_err());
+    }
+
+    #[test]
+    fn test_blake3_hashes_file_roundtrip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let src = tmp.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("a.bin"), b"blake3 data").unwrap();
+        let digest = file_hex(src.join("a.bin"), HashAlgorithm::Blake3).unwrap();
+        assert_eq!(digest, bytes_hex(b"blake3 data", HashAlgorithm::Blake3));
+        assert_ne!(digest, sha256_bytes_hex(b"blake3 data"));
+
+        let hashes_path = src.join(HASHES_FILE_NAME);
+        let hashes = vec![(digest, "a.bin".to_string())];
+        write_hashes_file_with_chunks(&hashes, &[], HashAlgorithm::Blake3, &hashes_path).unwrap();
+        assert_eq!(read_hashes_algorithm(&hashes_path).unwrap(), HashAlgorithm::Blake3);
+        assert_eq!(read_hashes_file(&hashes_path).unwrap(), hashes);
+        verify_dir_against_hashes(&src, &hashes_path).unwrap();
+
+        let archive = tmp.path().join("b3.tar.zst");
+        let codec = zstd_archive::ZstdCodec::new(zstd_archive::ZstdOptions::default());
+        codec.archive_dir_tar_zst(&src, &archive).unwrap();
+        assert!(verify_embedded_hashes(&archive).unwrap().is_empty());
+    }
+
+    #[test]
+    #[ignore] // Slow in debug builds; run with `cargo test --release -- --ignored --nocapture`
+    fn bench_hash_algorithm_throughput() {
+        let data: Vec<u8> = (0..100 * 1024 * 1024u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        for algo in [HashAlgorithm::Sha256, HashAlgorithm::Blake3] {
+            let start = std::time::Instant::now();
+            let digest = reader_hex(&mut data.as_slice(), algo).unwrap();
+            let secs = start.elapsed().as_secs_f64();
+            assert_eq!(digest, bytes_hex(&data, algo));
+            println!("{:>7}: {:8.1} MB/s", algo.name(), 100.0 / secs);
+        }
+    }
+}
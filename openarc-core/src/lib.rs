@@ -1,11 +1,21 @@
+pub mod archive_format;
 pub mod archive_tracker;
 pub mod backup_catalog;
+pub mod catalog_migrations;
 pub mod hash;
+pub mod location;
+pub mod method_rules;
 pub mod orchestrator;
 pub mod bpg_wrapper;
+pub mod transcode;
+
+pub use archive_format::{detect_archive_format, peek_archive, ArchiveFormat, ArchivePeek};
 
 // Re-export zstd-archive for FFI use
-pub use zstd_archive::{ZstdCodec, ZstdOptions};
+pub use zstd_archive::{Threads, ZstdCodec, ZstdOptions};
 
 // Re-export codecs for FFI use
 pub use codecs;
+
+// Re-export arcmax for FFI use
+pub use arcmax;
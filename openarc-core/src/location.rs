@@ -0,0 +1,200 @@
+//! GPS location summary for archived photos
+//!
+//! Coordinates come from the GPS EXIF tags of the original images. By default
+//! only a bounding box and counts are kept; per-file coordinates are stored
+//! only with `LocationMode::PerFile`.
+
+use exif::{In, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// How much location information to collect during archive creation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LocationMode {
+    #[default]
+    Off,
+    /// Bounding box and counts only
+    Summary,
+    /// Summary plus the coordinates of every geotagged file
+    PerFile,
+}
+
+impl LocationMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(LocationMode::Off),
+            "summary" => Some(LocationMode::Summary),
+            "per-file" | "per_file" => Some(LocationMode::PerFile),
+            _ => None,
+        }
+    }
+}
+
+/// Latitude/longitude in decimal degrees (south and west negative)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoBounds {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileLocation {
+    pub filename: String,
+    pub point: GeoPoint,
+}
+
+/// Where the photos in an archive were taken
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocationSummary {
+    pub with_location: usize,
+    pub without_location: usize,
+    pub bounds: Option<GeoBounds>,
+    /// Only filled with `LocationMode::PerFile`
+    #[serde(default)]
+    pub files: Vec<FileLocation>,
+}
+
+impl LocationSummary {
+    /// Account for one image; `point` is `None` when it has no GPS tags
+    pub fn add(&mut self, filename: &str, point: Option<GeoPoint>, mode: LocationMode) {
+        let Some(point) = point else {
+            self.without_location += 1;
+            return;
+        };
+
+        self.with_location += 1;
+        self.bounds = Some(match self.bounds {
+            None => GeoBounds {
+                min_lat: point.lat,
+                max_lat: point.lat,
+                min_lon: point.lon,
+                max_lon: point.lon,
+            },
+            Some(b) => GeoBounds {
+                min_lat: b.min_lat.min(point.lat),
+                max_lat: b.max_lat.max(point.lat),
+                min_lon: b.min_lon.min(point.lon),
+                max_lon: b.max_lon.max(point.lon),
+            },
+        });
+        if mode == LocationMode::PerFile {
+            self.files.push(FileLocation {
+                filename: filename.to_string(),
+                point,
+            });
+        }
+    }
+
+    /// Fold another archive's summary into this one
+    pub fn merge(&mut self, other: &LocationSummary) {
+        self.with_location += other.with_location;
+        self.without_location += other.without_location;
+        self.bounds = match (self.bounds, other.bounds) {
+            (Some(a), Some(b)) => Some(GeoBounds {
+                min_lat: a.min_lat.min(b.min_lat),
+                max_lat: a.max_lat.max(b.max_lat),
+                min_lon: a.min_lon.min(b.min_lon),
+                max_lon: a.max_lon.max(b.max_lon),
+            }),
+            (a, b) => a.or(b),
+        };
+        self.files.extend(other.files.iter().cloned());
+    }
+}
+
+/// Read the GPS position from an image's EXIF, if it has one
+pub fn read_gps(path: &Path) -> Option<GeoPoint> {
+    let file = File::open(path).ok()?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+
+    let coord = |value_tag: Tag, ref_tag: Tag| -> Option<f64> {
+        let value = exif.get_field(value_tag, In::PRIMARY)?;
+        let reference = match exif.get_field(ref_tag, In::PRIMARY).map(|f| &f.value) {
+            Some(Value::Ascii(parts)) => parts
+                .first()
+                .map(|p| String::from_utf8_lossy(p).to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        match value.value {
+            Value::Rational(ref dms) => dms_to_degrees(
+                &dms.iter().map(|r| r.to_f64()).collect::<Vec<_>>(),
+                &reference,
+            ),
+            _ => None,
+        }
+    };
+
+    let lat = coord(Tag::GPSLatitude, Tag::GPSLatitudeRef)?;
+    let lon = coord(Tag::GPSLongitude, Tag::GPSLongitudeRef)?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    Some(GeoPoint { lat, lon })
+}
+
+/// Degrees/minutes/seconds plus an N/S/E/W reference to signed decimal degrees
+fn dms_to_degrees(dms: &[f64], reference: &str) -> Option<f64> {
+    if dms.is_empty() || dms.iter().any(|v| !v.is_finite()) {
+        return None;
+    }
+    let degrees = dms[0] + dms.get(1).unwrap_or(&0.0) / 60.0 + dms.get(2).unwrap_or(&0.0) / 3600.0;
+    match reference.trim().to_ascii_uppercase().as_str() {
+        "S" | "W" => Some(-degrees),
+        _ => Some(degrees),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dms_and_summary() {
+        let lat = dms_to_degrees(&[48.0, 51.0, 29.52], "N").unwrap();
+        assert!((lat - 48.8582).abs() < 1e-4);
+        let lon = dms_to_degrees(&[73.0, 58.0, 0.0], "W").unwrap();
+        assert!((lon + 73.9667).abs() < 1e-4);
+        assert!(dms_to_degrees(&[], "N").is_none());
+
+        let mut summary = LocationSummary::default();
+        summary.add("paris.jpg", Some(GeoPoint { lat, lon: 2.2945 }), LocationMode::Summary);
+        summary.add("nyc.jpg", Some(GeoPoint { lat: 40.7, lon }), LocationMode::Summary);
+        summary.add("scan.png", None, LocationMode::Summary);
+
+        assert_eq!(summary.with_location, 2);
+        assert_eq!(summary.without_location, 1);
+        assert!(summary.files.is_empty());
+        let bounds = summary.bounds.unwrap();
+        assert_eq!(bounds.min_lat, 40.7);
+        assert_eq!(bounds.max_lat, lat);
+        assert_eq!(bounds.min_lon, lon);
+        assert_eq!(bounds.max_lon, 2.2945);
+
+        let mut per_file = LocationSummary::default();
+        per_file.add("paris.jpg", Some(GeoPoint { lat, lon: 2.2945 }), LocationMode::PerFile);
+        assert_eq!(per_file.files[0].filename, "paris.jpg");
+    }
+
+    #[test]
+    fn test_image_without_gps() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("plain.png");
+        image::RgbImage::new(2, 2).save(&path)?;
+        assert!(read_gps(&path).is_none());
+        assert!(read_gps(&dir.path().join("missing.jpg")).is_none());
+        Ok(())
+    }
+}
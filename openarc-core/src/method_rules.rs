@@ -0,0 +1,258 @@
+//! Per-file compression overrides from a JSON rules file
+//!
+//! A rules file maps globs to a partial set of encode settings:
+//!
+//! ```json
+//! { "rules": [
+//!     { "glob": "**/raw/**/*.mov", "store": true },
+//!     { "glob": "Screenshot*.png", "bpg_lossless": true, "bpg_chroma_format": 1 },
+//!     { "glob": "*.jpg", "bpg_quality": 30 }
+//! ] }
+//! ```
+//!
+//! Globs containing `/` are matched against the whole input path, others
+//! against the file name only. When several rules match a file, the one with
+//! the most literal (non-wildcard) characters wins; ties go to the rule listed
+//! first. Only the winning rule applies, fields it leaves out keep the global
+//! setting.
+
+use anyhow::{anyhow, bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::orchestrator::OrchestratorSettings;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    rules: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    glob: String,
+    bpg_quality: Option<i32>,
+    bpg_lossless: Option<bool>,
+    bpg_chroma_format: Option<i32>,
+    auto_chroma: Option<bool>,
+    video_preset: Option<i32>,
+    video_crf: Option<i32>,
+    #[serde(default)]
+    store: bool,
+}
+
+/// Settings a rule can override; `None` keeps the global value
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodOverrides {
+    pub bpg_quality: Option<i32>,
+    pub bpg_lossless: Option<bool>,
+    pub bpg_chroma_format: Option<i32>,
+    pub auto_chroma: Option<bool>,
+    pub video_preset: Option<i32>,
+    pub video_crf: Option<i32>,
+    /// Archive the original bytes without transcoding or recompressing
+    pub store: bool,
+}
+
+impl MethodOverrides {
+    fn is_empty(&self) -> bool {
+        *self == MethodOverrides::default()
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range("bpg_quality", self.bpg_quality, 0, 51)?;
+        check_range("bpg_chroma_format", self.bpg_chroma_format, 0, 2)?;
+        check_range("video_preset", self.video_preset, 0, 3)?;
+        check_range("video_crf", self.video_crf, 0, 51)?;
+        Ok(())
+    }
+
+    /// `base` with this rule's fields applied
+    pub fn apply(&self, base: &OrchestratorSettings) -> OrchestratorSettings {
+        let mut settings = base.clone();
+        if let Some(q) = self.bpg_quality {
+            settings.bpg_quality = q;
+        }
+        if let Some(lossless) = self.bpg_lossless {
+            settings.bpg_lossless = lossless;
+        }
+        if let Some(chroma) = self.bpg_chroma_format {
+            settings.bpg_chroma_format = chroma;
+            // An explicit chroma format beats the per-image guess
+            settings.auto_chroma = false;
+        }
+        if let Some(auto) = self.auto_chroma {
+            settings.auto_chroma = auto;
+        }
+        if let Some(preset) = self.video_preset {
+            settings.video_preset = preset;
+        }
+        if let Some(crf) = self.video_crf {
+            settings.video_crf = crf;
+        }
+        settings
+    }
+}
+
+fn check_range(name: &str, value: Option<i32>, min: i32, max: i32) -> Result<()> {
+    match value {
+        Some(v) if v < min || v > max => bail!("{} must be {}-{}, got {}", name, min, max, v),
+        _ => Ok(()),
+    }
+}
+
+/// One glob and the overrides it applies
+#[derive(Clone, Debug)]
+pub struct MethodRule {
+    pub glob: String,
+    pub overrides: MethodOverrides,
+    matcher: GlobMatcher,
+    whole_path: bool,
+    specificity: usize,
+}
+
+/// Validated rules, ready to match against input files
+#[derive(Clone, Debug, Default)]
+pub struct MethodRules {
+    rules: Vec<MethodRule>,
+}
+
+impl MethodRules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read rules file: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid rules file: {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: RulesFile = serde_json::from_str(text)?;
+        let mut rules = Vec::with_capacity(file.rules.len());
+        for (i, spec) in file.rules.into_iter().enumerate() {
+            let context = || format!("rule {} ({})", i + 1, spec.glob);
+            let overrides = MethodOverrides {
+                bpg_quality: spec.bpg_quality,
+                bpg_lossless: spec.bpg_lossless,
+                bpg_chroma_format: spec.bpg_chroma_format,
+                auto_chroma: spec.auto_chroma,
+                video_preset: spec.video_preset,
+                video_crf: spec.video_crf,
+                store: spec.store,
+            };
+            if overrides.is_empty() {
+                return Err(anyhow!("sets nothing")).with_context(context);
+            }
+            overrides.validate().with_context(context)?;
+            let matcher = GlobBuilder::new(&spec.glob)
+                .literal_separator(true)
+                .build()
+                .with_context(context)?
+                .compile_matcher();
+            rules.push(MethodRule {
+                whole_path: spec.glob.contains('/'),
+                specificity: literal_len(&spec.glob),
+                glob: spec.glob,
+                overrides,
+                matcher,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The most specific rule matching `path`, if any
+    pub fn rule_for(&self, path: &Path) -> Option<&MethodRule> {
+        let full = path.to_string_lossy().replace('\\', "/");
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        let mut best: Option<&MethodRule> = None;
+        for rule in &self.rules {
+            let subject = if rule.whole_path { full.as_str() } else { name.as_ref() };
+            if !rule.matcher.is_match(subject) {
+                continue;
+            }
+            match best {
+                Some(b) if b.specificity >= rule.specificity => {}
+                _ => best = Some(rule),
+            }
+        }
+        best
+    }
+}
+
+/// Characters of a glob that aren't wildcard syntax
+fn literal_len(glob: &str) -> usize {
+    let mut len = 0;
+    let mut in_class = false;
+    let mut escaped = false;
+    for c in glob.chars() {
+        if escaped {
+            len += 1;
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '*' | '?' | '{' | '}' | ',' => {}
+            _ if !in_class => len += 1,
+            _ => {}
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_specific_rule_wins() -> Result<()> {
+        let rules = MethodRules::parse(
+            r#"{ "rules": [
+                { "glob": "*.png", "bpg_quality": 30 },
+                { "glob": "Screenshot*.png", "bpg_lossless": true, "bpg_chroma_format": 1 },
+                { "glob": "**/raw/**", "store": true },
+                { "glob": "**/raw/**/*.png", "bpg_quality": 10 },
+                { "glob": "*.PNG", "bpg_quality": 40 },
+                { "glob": "?.png", "bpg_quality": 45 }
+            ] }"#,
+        )?;
+
+        let quality = |p: &str| rules.rule_for(Path::new(p)).and_then(|r| r.overrides.bpg_quality);
+        assert_eq!(quality("/photos/cat.png"), Some(30));
+        assert_eq!(quality("/photos/raw/2024/cat.png"), Some(10));
+        assert_eq!(quality("/photos/X.PNG"), Some(40));
+        // "*.png" and "?.png" both have 4 literal characters; the earlier rule wins
+        assert_eq!(quality("/photos/a.png"), Some(30));
+
+        let shot = rules.rule_for(Path::new("/desktop/Screenshot 1.png")).unwrap();
+        assert_eq!(shot.glob, "Screenshot*.png");
+        let shot = &shot.overrides;
+        assert_eq!(shot.bpg_lossless, Some(true));
+
+        let raw = rules.rule_for(Path::new("/photos/raw/clip.mov")).unwrap();
+        assert!(raw.overrides.store);
+        assert!(rules.rule_for(Path::new("/photos/clip.mov")).is_none());
+
+        let base = OrchestratorSettings { auto_chroma: true, ..OrchestratorSettings::default() };
+        let applied = shot.apply(&base);
+        assert!(applied.bpg_lossless);
+        assert_eq!((applied.bpg_chroma_format, applied.auto_chroma), (1, false));
+        assert_eq!(applied.bpg_quality, base.bpg_quality);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_rules_rejected() {
+        assert!(MethodRules::parse(r#"{ "rules": [ { "glob": "*.jpg" } ] }"#).is_err());
+        assert!(MethodRules::parse(r#"{ "rules": [ { "glob": "*.jpg", "bpg_quality": 60 } ] }"#).is_err());
+        assert!(MethodRules::parse(r#"{ "rules": [ { "glob": "[", "store": true } ] }"#).is_err());
+        assert!(MethodRules::parse(r#"{ "rules": [ { "glob": "*.jpg", "crf": 20 } ] }"#).is_err());
+    }
+}
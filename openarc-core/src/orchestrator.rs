@@ -1,25 +1,40 @@
 use anyhow::{anyhow, Context, Result};
+use arcmax::core::crypto::{create_decryptor, create_encryptor, CryptoError, PasswordEncoding};
+use arcmax::formats::freearc::reader::FreeArcReader;
+use arcmax::formats::freearc::utils::split_compressor_encryption;
 use arcmax::formats::freearc::writer::{ArchiveOptions, FreeArcWriter};
 use codecs::bpg::{BPGEncoderConfig, NativeBPGEncoder};
-use codecs::ffmpeg::{FfmpegEncodeOptions, FFmpegEncoder, VideoCodec, VideoSpeedPreset};
+use codecs::ffmpeg::{FfmpegEncodeOptions, FFmpegEncoder, VideoCodec, VideoSpeedPreset, DEFAULT_SPRITE_TILE_WIDTH};
 use codecs::video_analyzer::analyze_video_compression;
 #[cfg(feature = "heif")]
 use codecs::heic::{HeicCodec, HeicEncoderConfig, HeifCompressionFormat};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc, Condvar, Mutex as StdMutex};
 use std::thread;
 use std::time::Duration;
 use bytemuck::cast_vec;
+use globset::{Glob, GlobBuilder, GlobMatcher, GlobSetBuilder};
 use log::warn;
 use tempfile::TempDir;
-use zstd_archive::{ZstdCodec, ZstdOptions};
+use zstd_archive::{EntryMetadata, Threads, ZstdCodec, ZstdOptions};
 use image;
 use std::io::Read;
+use std::io::{Seek, SeekFrom};
+
+/// Default cap on simultaneous video encodes
+pub const DEFAULT_MAX_CONCURRENT_VIDEOS: usize = 2;
+/// Default cap on simultaneous decodes of images over 50 MB
+pub const DEFAULT_MAX_CONCURRENT_HEAVY_IMAGES: usize = 2;
+/// Default encoding pool size before it's scaled down under memory pressure
+pub const DEFAULT_ENCODING_THREADS: usize = 5;
+/// Default fraction of RAM in use above which encoding starts backing off
+pub const DEFAULT_MEMORY_BACKOFF_THRESHOLD: f64 = 0.85;
 
 /// Bounded limiter for heavy tasks (videos/very large images)
 struct HeavyLimiter {
@@ -90,7 +105,9 @@ impl<'a> Drop for HeavyGuard<'a> {
 }
 
 use crate::archive_tracker::{ArchiveTracker, ArchiveRecord, ArchiveFileMapping};
-use crate::backup_catalog::{normalize_path, BackupCatalog, BackupEntry};
+use crate::backup_catalog::{BackupCatalog, BackupEntry};
+use crate::location::{self, LocationMode, LocationSummary};
+use crate::method_rules::MethodRules;
 use crate::hash;
 
 /// Check current memory usage and return the percentage of memory used
@@ -143,23 +160,50 @@ fn detect_image_bit_depth(
     }
 }
 
+/// The format images will really be stored as: AVIF needs libheif, otherwise BPG is used
+fn effective_image_format(requested: ImageOutputFormat) -> ImageOutputFormat {
+    if requested == ImageOutputFormat::Avif && !HeicCodec::is_available() {
+        warn!("avif_unavailable, storing images as BPG (libheif missing or heif feature disabled)");
+        return ImageOutputFormat::Bpg;
+    }
+    requested
+}
+
+/// Encode `img` to an AVIF file at `out` with libheif's AV1 encoder
+fn encode_image_to_avif(img: &image::DynamicImage, out: &Path, settings: &OrchestratorSettings) -> Result<()> {
+    let codec = HeicCodec::new()?;
+    let config = HeicEncoderConfig {
+        quality: settings.avif_quality,
+        lossless: settings.bpg_lossless,
+        format: HeifCompressionFormat::AV1,
+    };
+    if img.color().has_alpha() {
+        let rgba = img.to_rgba8();
+        codec.encode_to_file(rgba.as_raw(), rgba.width(), rgba.height(), true, out, &config)
+    } else {
+        let rgb = img.to_rgb8();
+        codec.encode_to_file(rgb.as_raw(), rgb.width(), rgb.height(), false, out, &config)
+    }
+}
+
 /// Memory-constrained video encoding with additional safety checks
 fn encode_video_with_memory_constraints(
     input: &Path,
     output: &Path,
     opts: FfmpegEncodeOptions,
-    _settings: &OrchestratorSettings
+    settings: &OrchestratorSettings
 ) -> Result<()> {
     // Video encoding is memory-intensive, so we need to be extra careful
     let memory_usage = check_memory_usage();
+    let threshold = settings.memory_backoff_threshold();
 
     // If memory usage is very high, we should wait or potentially fail gracefully
-    if memory_usage > 0.95 {
+    if memory_usage > threshold + 0.10 {
         return Err(anyhow!("Insufficient memory to start video encoding ({}% used)", memory_usage * 100.0));
-    } else if memory_usage > 0.90 {
+    } else if memory_usage > threshold + 0.05 {
         // Wait a bit more before starting video encoding
         std::thread::sleep(std::time::Duration::from_millis(1000));
-    } else if memory_usage > 0.85 {
+    } else if memory_usage > threshold {
         std::thread::sleep(std::time::Duration::from_millis(500));
     }
 
@@ -174,16 +218,18 @@ fn encode_video_with_memory_constraints(
 }
 
 /// Determine optimal number of encoding threads based on memory usage
-fn get_optimal_thread_count(base_count: usize) -> usize {
+///
+/// The pool shrinks in steps around `backoff_threshold` (0.70/0.80/0.90 at the default 0.85).
+fn get_optimal_thread_count(base_count: usize, backoff_threshold: f64) -> usize {
     let memory_usage = check_memory_usage();
 
-    if memory_usage > 0.90 {
+    if memory_usage > backoff_threshold + 0.05 {
         // Severe memory pressure - reduce to minimum threads
         (base_count / 4).max(1)
-    } else if memory_usage > 0.80 {
+    } else if memory_usage > backoff_threshold - 0.05 {
         // Moderate memory pressure - reduce threads
         (base_count / 2).max(1)
-    } else if memory_usage > 0.70 {
+    } else if memory_usage > backoff_threshold - 0.15 {
         // Light memory pressure - slightly reduce threads
         ((base_count as f64 * 0.75) as usize).max(1)
     } else {
@@ -208,7 +254,7 @@ pub enum OriginalImageFormat {
     Tiff,
     /// BMP - decode BPG to PNG
     Bmp,
-    /// WebP - decode BPG to PNG (or WebP if supported)
+    /// WebP - decode BPG to PNG (or WebP with `ExtractionSettings::prefer_webp`)
     WebP,
 }
 
@@ -226,6 +272,12 @@ impl OriginalImageFormat {
         }
     }
 
+    /// Whether `ExtractionSettings::prefer_webp` applies: everything but JPEG,
+    /// which is restored as JPEG regardless
+    pub fn can_decode_to_webp(&self) -> bool {
+        !matches!(self, Self::Jpeg)
+    }
+
     /// Should this format be encoded via PNG intermediate for quality preservation?
     pub fn needs_png_intermediate(&self) -> bool {
         match self {
@@ -235,13 +287,66 @@ impl OriginalImageFormat {
     }
 }
 
+/// Codec images are stored with under `media/`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutputFormat {
+    /// BPG (HEVC intra)
+    #[default]
+    Bpg,
+    /// AVIF (AV1 in HEIF) through libheif; needs the `heif` feature
+    Avif,
+}
+
+impl ImageOutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bpg" => Some(ImageOutputFormat::Bpg),
+            "avif" => Some(ImageOutputFormat::Avif),
+            _ => None,
+        }
+    }
+
+    /// Extension of the stored file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Bpg => "bpg",
+            Self::Avif => "avif",
+        }
+    }
+}
+
 /// Metadata for a compressed image file
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImageMetadata {
     pub original_filename: String,
     pub original_format: OriginalImageFormat,
     pub original_extension: String,
+    /// Stored file name under `media/`; ends in `.avif` when `codec` is AVIF
     pub bpg_filename: String,
+    /// Codec of the stored file; absent (BPG) in older archives
+    #[serde(default)]
+    pub codec: ImageOutputFormat,
+    /// BPG chroma format the image was encoded with (0=4:2:0, 1=4:4:4, 2=RGB);
+    /// absent in older archives
+    #[serde(default)]
+    pub chroma_format: Option<i32>,
+}
+
+/// Metadata for an archived video
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    pub original_filename: String,
+    /// Archive-relative path of the (possibly transcoded) playback copy
+    pub proxy_path: String,
+    /// Archive-relative path of the untouched original, when kept
+    pub original_path: Option<String>,
+    /// Original filename of the still this clip belongs to (Live Photo pair)
+    #[serde(default)]
+    pub live_photo_image: Option<String>,
+    /// Copied in unchanged (already compressed, a method rule, or a failed encode)
+    #[serde(default)]
+    pub stored_as_is: bool,
 }
 
 /// Archive metadata containing format information for all files
@@ -249,10 +354,143 @@ pub struct ImageMetadata {
 pub struct ArchiveMetadata {
     pub version: u32,
     pub images: Vec<ImageMetadata>,
+    #[serde(default)]
+    pub videos: Vec<VideoMetadata>,
     pub created_at: u64,
+    /// Where the bytes went, per file class (absent in older archives)
+    #[serde(default)]
+    pub size_report: Option<SizeReport>,
+    /// Inputs left out as exact copies of another archived file
+    #[serde(default)]
+    pub duplicates: Vec<DuplicateMetadata>,
+    /// Where the photos were taken, when `location_mode` was enabled
+    #[serde(default)]
+    pub location_summary: Option<LocationSummary>,
+    /// Modification time (Unix seconds) of each input, keyed by archive-relative
+    /// path; restored on extraction (absent in older archives)
+    #[serde(default)]
+    pub mtimes: BTreeMap<String, u64>,
+    /// Links kept as links under `SymlinkPolicy::Preserve`
+    #[serde(default)]
+    pub symlinks: Vec<SymlinkMetadata>,
+    /// Inputs left out because an earlier archive in the catalog already holds their content
+    #[serde(default)]
+    pub references: Vec<ReferenceMetadata>,
+    /// Cipher method string (salt, IV and check code included) of an encrypted
+    /// `misc.arc`; the misc files are then only inside it, not under `misc/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub misc_encryption: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+/// An input whose content is stored in an earlier archive
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReferenceMetadata {
+    pub original_filename: String,
+    /// File name of the archive holding the content, as catalogued
+    pub archive: String,
+}
+
+/// A symbolic link recorded instead of the file it points to
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SymlinkMetadata {
+    /// Archive-relative path the link is recreated at (`misc/<name>`)
+    pub path: String,
+    /// Link target exactly as read from the source, relative or absolute
+    pub target: String,
+    /// Whether the target was a directory; Windows makes file and directory links differently
+    #[serde(default)]
+    pub target_is_dir: bool,
+}
+
+/// A deduplicated input and the archived file holding its content
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DuplicateMetadata {
+    pub original_filename: String,
+    /// Archive-relative path of the canonical copy
+    pub canonical_path: String,
+}
+
+/// Input and output totals for one `FileClass`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassSizeStats {
+    pub files: usize,
+    pub original_bytes: u64,
+    pub output_bytes: u64,
+}
+
+impl ClassSizeStats {
+    /// Output size as a fraction of the input (0.0 when nothing was processed)
+    pub fn ratio(&self) -> f64 {
+        if self.original_bytes == 0 {
+            0.0
+        } else {
+            self.output_bytes as f64 / self.original_bytes as f64
+        }
+    }
+
+    fn add(&mut self, original: u64, output: u64) {
+        self.files += 1;
+        self.original_bytes += original;
+        self.output_bytes += output;
+    }
+}
+
+/// Compression outcome grouped by file class
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeReport {
+    pub images: ClassSizeStats,
+    pub videos: ClassSizeStats,
+    pub misc: ClassSizeStats,
+}
+
+impl SizeReport {
+    pub fn from_processed(processed: &[ProcessedFile]) -> Self {
+        let mut report = Self::default();
+        for p in processed {
+            report.class_mut(p.class).add(p.original_size, p.output_size);
+        }
+        report
+    }
+
+    pub fn class(&self, class: FileClass) -> &ClassSizeStats {
+        match class {
+            FileClass::Image => &self.images,
+            FileClass::Video => &self.videos,
+            FileClass::Misc => &self.misc,
+        }
+    }
+
+    fn class_mut(&mut self, class: FileClass) -> &mut ClassSizeStats {
+        match class {
+            FileClass::Image => &mut self.images,
+            FileClass::Video => &mut self.videos,
+            FileClass::Misc => &mut self.misc,
+        }
+    }
+
+    /// Add another report's totals to this one
+    pub fn merge(&mut self, other: &SizeReport) {
+        for class in [FileClass::Image, FileClass::Video, FileClass::Misc] {
+            let (mine, theirs) = (self.class_mut(class), other.class(class));
+            mine.files += theirs.files;
+            mine.original_bytes += theirs.original_bytes;
+            mine.output_bytes += theirs.output_bytes;
+        }
+    }
+
+    /// Sum over all classes
+    pub fn total(&self) -> ClassSizeStats {
+        let mut total = ClassSizeStats::default();
+        for stats in [&self.images, &self.videos, &self.misc] {
+            total.files += stats.files;
+            total.original_bytes += stats.original_bytes;
+            total.output_bytes += stats.output_bytes;
+        }
+        total
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct ListedArchiveFile {
     pub filename: String,
     pub original_size: u64,
@@ -266,6 +504,12 @@ fn normalize_archive_rel_path(p: &str) -> String {
         .replace('\\', "/")
 }
 
+/// Whether a relative path taken from archive metadata stays below the
+/// directory it is joined to: plain names only, no `..`, root or prefix
+fn is_contained_rel_path(rel: &str) -> bool {
+    Path::new(rel).components().all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
 fn detect_file_type_from_name(name: &str) -> i32 {
     let lower = name.to_ascii_lowercase();
     let ext = std::path::Path::new(&lower)
@@ -274,7 +518,7 @@ fn detect_file_type_from_name(name: &str) -> i32 {
         .unwrap_or("");
 
     match ext {
-        "bpg" | "jpg" | "jpeg" | "png" | "bmp" | "tif" | "tiff" | "webp" | "heic" | "heif" | "ico" |
+        "bpg" | "avif" | "jpg" | "jpeg" | "png" | "bmp" | "tif" | "tiff" | "webp" | "heic" | "heif" | "ico" |
         "jp2" | "j2k" | "j2c" | "jpc" | "jpt" | "jph" | "jhc" |
         "dng" | "cr2" | "nef" | "arw" | "orf" | "rw2" | "raf" => 1,
         "mp4" | "mov" | "m4v" | "avi" | "mkv" | "wmv" | "webm" => 2,
@@ -321,9 +565,9 @@ fn parse_manifest_sizes(manifest_text: &str) -> HashMap<String, (u64, u64)> {
 }
 
 pub fn list_archive_contents(archive_path: &Path) -> Result<Vec<ListedArchiveFile>> {
-    let file = std::fs::File::open(archive_path)
+    let file = zstd_archive::SplitReader::open(archive_path)
         .with_context(|| format!("Failed to open archive: {}", archive_path.display()))?;
-    let decoder = zstd::stream::read::Decoder::new(file)
+    let decoder = zstd_archive::archive_decoder(file)
         .with_context(|| format!("Failed to create zstd decoder for {}", archive_path.display()))?;
     let mut archive = tar::Archive::new(decoder);
 
@@ -398,16 +642,137 @@ pub fn list_archive_contents(archive_path: &Path) -> Result<Vec<ListedArchiveFil
     Ok(out)
 }
 
+/// Disagreements between a backup catalog and the archive it describes
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct AuditReport {
+    /// Media and misc entries found in the archive
+    pub archive_entries: usize,
+    /// File mappings the catalog records for the archive
+    pub catalog_records: usize,
+    /// Archive-relative paths the catalog lists but the archive lacks
+    pub missing_in_archive: Vec<String>,
+    /// Catalogued files whose SHA-256 matches no archive entry
+    pub hash_mismatches: Vec<String>,
+    /// Archive entries the catalog has no record of
+    pub missing_in_catalog: Vec<String>,
+}
+
+impl AuditReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_in_archive.is_empty() && self.hash_mismatches.is_empty() && self.missing_in_catalog.is_empty()
+    }
+}
+
+/// Cross-check an archive against the catalog that recorded it
+///
+/// Every file mapping the catalog holds for the archive must exist as a tar
+/// entry, every catalogued SHA-256 for the archive must match the content of
+/// some entry, and every media/misc entry must have a mapping. Kept video
+/// originals, thumbnails and the archive's own metadata files are not
+/// catalogued and are ignored. The archive is looked up by its recorded path,
+/// falling back to its file name in case it was moved after cataloguing.
+/// Each entry is read and hashed once; nothing is written to disk.
+pub fn audit_archive(archive_path: &Path, catalog_path: &Path) -> Result<AuditReport> {
+    if !catalog_path.exists() {
+        return Err(anyhow!("Catalog not found: {}", catalog_path.display()));
+    }
+    let archive_name = archive_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string());
+
+    let mut catalog = BackupCatalog::new(catalog_path)?;
+    let catalogued_hashes: Vec<(String, String)> = catalog
+        .list_all()?
+        .into_iter()
+        .filter(|e| e.archive_id.is_some() && e.archive_id == archive_name)
+        .filter_map(|e| Some((e.path, e.sha256?.to_ascii_lowercase())))
+        .collect();
+
+    let tracker = ArchiveTracker::new(catalog.get_connection_mut())?;
+    let record = match tracker.get_archive_by_path(&archive_path.to_string_lossy())? {
+        Some(r) => Some(r),
+        None => tracker.get_all_archives()?.into_iter().find(|r| {
+            Path::new(&r.archive_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                == archive_name
+        }),
+    };
+    let mappings = match record.and_then(|r| r.id) {
+        Some(id) => tracker.get_archive_files(id)?,
+        None => Vec::new(),
+    };
+    let mapped: HashSet<String> = mappings
+        .iter()
+        .map(|m| normalize_archive_rel_path(&m.file_path))
+        .collect();
+
+    // rel path -> SHA-256 of the entry's content
+    let mut entries: HashMap<String, String> = HashMap::new();
+    let file = zstd_archive::SplitReader::open(archive_path)
+        .with_context(|| format!("Failed to open archive: {}", archive_path.display()))?;
+    let decoder = zstd_archive::archive_decoder(file)
+        .with_context(|| format!("Failed to create zstd decoder for {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries().context("Failed to read tar entries")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let rel = normalize_archive_rel_path(
+            &entry.path().context("Failed to read tar entry path")?.to_string_lossy(),
+        );
+        if !(rel.starts_with("media/") || rel.starts_with("misc/")) || rel.starts_with("media/originals/") {
+            continue;
+        }
+        let digest = hash::reader_hex(&mut entry, hash::HashAlgorithm::Sha256)
+            .with_context(|| format!("Failed to hash {}", rel))?;
+        entries.insert(rel, digest);
+    }
+
+    let entry_hashes: HashSet<&str> = entries.values().map(|h| h.as_str()).collect();
+    let mut report = AuditReport {
+        archive_entries: entries.len(),
+        catalog_records: mappings.len(),
+        missing_in_archive: mapped.iter().filter(|p| !entries.contains_key(*p)).cloned().collect(),
+        hash_mismatches: catalogued_hashes
+            .into_iter()
+            .filter(|(_, sha)| !entry_hashes.contains(sha.as_str()))
+            .map(|(path, _)| path)
+            .collect(),
+        missing_in_catalog: entries.keys().filter(|p| !mapped.contains(*p)).cloned().collect(),
+    };
+    report.missing_in_archive.sort();
+    report.hash_mismatches.sort();
+    report.missing_in_catalog.sort();
+    Ok(report)
+}
+
 pub fn extract_archive_entry(archive_path: &Path, entry_name: &str, output_path: &Path) -> Result<()> {
     let entry_name = normalize_archive_rel_path(entry_name);
 
-    let file = std::fs::File::open(archive_path)
+    let mut file = zstd_archive::SplitReader::open(archive_path)
         .with_context(|| format!("Failed to open archive: {}", archive_path.display()))?;
-    let decoder = zstd::stream::read::Decoder::new(file)
+
+    // Seekable archives skip over the data of earlier entries instead of decompressing it
+    if zstd_archive::is_seekable(&mut file)? {
+        let decoder = zstd_archive::seekable_archive_decoder(file)
+            .with_context(|| format!("Failed to read seek table of {}", archive_path.display()))?;
+        let mut archive = tar::Archive::new(decoder);
+        let entries = archive.entries_with_seek().context("Failed to read tar entries")?;
+        return extract_matching_entry(entries, &entry_name, output_path);
+    }
+
+    let decoder = zstd_archive::archive_decoder(file)
         .with_context(|| format!("Failed to create zstd decoder for {}", archive_path.display()))?;
     let mut archive = tar::Archive::new(decoder);
+    let entries = archive.entries().context("Failed to read tar entries")?;
+    extract_matching_entry(entries, &entry_name, output_path)
+}
 
-    for entry in archive.entries().context("Failed to read tar entries")? {
+/// Write the first file entry named `entry_name` to `output_path`
+fn extract_matching_entry<R: Read>(entries: tar::Entries<'_, R>, entry_name: &str, output_path: &Path) -> Result<()> {
+    for entry in entries {
         let mut entry = entry.context("Failed to read tar entry")?;
         if !entry.header().entry_type().is_file() {
             continue;
@@ -444,10 +809,143 @@ impl Default for ArchiveMetadata {
         Self {
             version: 1,
             images: Vec::new(),
+            videos: Vec::new(),
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
+            size_report: None,
+            duplicates: Vec::new(),
+            location_summary: None,
+            mtimes: BTreeMap::new(),
+            symlinks: Vec::new(),
+            references: Vec::new(),
+            misc_encryption: None,
+        }
+    }
+}
+
+/// Error returned when an operation stops because its cancel flag was set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Returns true if `err` came from a cancellation rather than a failure
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Error returned when archived or extracted content doesn't match its recorded hashes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationFailed(pub String);
+
+impl std::fmt::Display for VerificationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for VerificationFailed {}
+
+/// Returns true if `err` reports content failing hash verification, as opposed
+/// to the verification itself being unable to run
+pub fn is_verification_failure(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<VerificationFailed>().is_some()
+}
+
+fn check_cancelled(flag: &Option<Arc<AtomicBool>>) -> Result<()> {
+    match flag {
+        Some(flag) if flag.load(Ordering::Relaxed) => Err(Cancelled.into()),
+        _ => Ok(()),
+    }
+}
+
+/// What to do with files that can't be read (permissions, locks)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessErrorPolicy {
+    /// Leave the file out and report it in `OrchestratorResult::skipped_access_errors`
+    #[default]
+    Skip,
+    /// Abort archive creation
+    Fail,
+    /// Try again a few times (transient locks), then skip
+    Retry,
+}
+
+impl AccessErrorPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "skip" => Some(AccessErrorPolicy::Skip),
+            "fail" => Some(AccessErrorPolicy::Fail),
+            "retry" => Some(AccessErrorPolicy::Retry),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with inputs that would land on the same name ignoring case
+///
+/// Case-insensitive filesystems (default macOS and Windows) can't hold
+/// `IMG.jpg` and `img.jpg` side by side, so one would overwrite the other on
+/// extraction. Identical names from different folders clash the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseCollisionPolicy {
+    /// Store later files as `name_2.ext`, `name_3.ext`, ... and report them in
+    /// `OrchestratorResult::renamed_case_collisions`
+    #[default]
+    Rename,
+    /// Abort archive creation
+    Fail,
+    /// Keep the first file and report the rest in `OrchestratorResult::skipped_case_collisions`
+    Skip,
+}
+
+impl CaseCollisionPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "rename" => Some(CaseCollisionPolicy::Rename),
+            "fail" => Some(CaseCollisionPolicy::Fail),
+            "skip" => Some(CaseCollisionPolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with symbolic links found while walking input directories
+///
+/// Links given directly as inputs are always followed, except under `Preserve`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Leave links out
+    #[default]
+    Skip,
+    /// Archive what links point to, descending into linked directories; a link
+    /// back to one of its own ancestors is reported and skipped.
+    ///
+    /// Links may point anywhere the process can read, so a link planted in an
+    /// input tree (e.g. `notes -> ~/.ssh`) pulls that content into the archive.
+    /// Only use this on trees whose links you trust.
+    Follow,
+    /// Record each link and its target in the archive metadata and recreate it
+    /// on extraction, without archiving the target. Targets are stored verbatim,
+    /// so relative ones resolve against the link's extracted location.
+    Preserve,
+}
+
+impl SymlinkPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "skip" => Some(SymlinkPolicy::Skip),
+            "follow" => Some(SymlinkPolicy::Follow),
+            "preserve" => Some(SymlinkPolicy::Preserve),
+            _ => None,
         }
     }
 }
@@ -458,6 +956,9 @@ pub struct OrchestratorSettings {
     pub bpg_lossless: bool,
     pub bpg_bit_depth: i32,
     pub bpg_chroma_format: i32,
+    /// Pick the chroma format per image instead of using `bpg_chroma_format`:
+    /// 4:4:4 for screenshots, text and line art, 4:2:0 for photos
+    pub auto_chroma: bool,
     pub bpg_encoder_type: i32,
     pub bpg_compression_level: i32,
     pub video_preset: i32,
@@ -472,6 +973,95 @@ pub struct OrchestratorSettings {
     pub heic_quality: u8,
     /// Quality for JPEG output during extraction (1-100)
     pub jpeg_quality: u8,
+    /// Record per-chunk hashes of this size for files larger than one chunk
+    pub hash_chunk_size: Option<u64>,
+    /// Store a hover-scrub sprite sheet with this many frames per video under `thumbs/`
+    pub video_sprite_frames: Option<u32>,
+    /// Store the original video under `media/originals/` next to the transcode
+    pub keep_original_video: bool,
+    /// Rotate/flip image pixels per their EXIF orientation before BPG encoding
+    pub bake_orientation: bool,
+    /// Digest used for dedup and HASHES.sha256
+    pub hash_algo: hash::HashAlgorithm,
+    /// Re-read the finished archive and check it against its embedded hashes
+    pub verify_after_create: bool,
+    /// Write `<archive>.sha256` with the SHA-256 of the whole archive, checkable
+    /// with `sha256sum -c` independently of OpenArc
+    pub emit_sidecar_checksum: bool,
+    /// Keep Live Photo clips as-is next to their still instead of re-encoding them
+    pub preserve_live_photos: bool,
+    /// Handling of files that can't be opened or read
+    pub on_access_error: AccessErrorPolicy,
+    /// Handling of files whose names clash ignoring case
+    pub on_case_collision: CaseCollisionPolicy,
+    /// Process inputs in path order and pack entries by name, so work-item indices
+    /// (the `_N` in BPG names), metadata, manifest and tar order repeat run to run
+    pub sorted: bool,
+    /// Glob-matched per-file overrides of quality, chroma, video preset/CRF and
+    /// store-as-is (see `method_rules`)
+    pub method_rules: Option<MethodRules>,
+    /// Write `<name>.images`, `<name>.videos` and `<name>.misc` archives instead of one
+    pub split_by_class: bool,
+    /// GPS information gathered from image EXIF into metadata and catalog
+    pub location_mode: LocationMode,
+    /// Set to request a clean stop; `create_archive` then fails with `Cancelled`
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+    /// Stat every input before encoding and report problems in `OrchestratorResult::preflight`
+    pub preflight_check: bool,
+    /// Record catalog paths relative to this directory (e.g. the input root or `$HOME`)
+    pub catalog_path_base: Option<PathBuf>,
+    /// Catalog database to check and record in, shared between archives;
+    /// `None` uses `<archive>.catalog.sqlite` next to the output
+    pub catalog_path: Option<PathBuf>,
+    /// Video encodes allowed at once (>= 1); each ffmpeg HEVC encode of 4K
+    /// footage can take several GB, so raise this only with plenty of RAM
+    pub max_concurrent_videos: usize,
+    /// Images over 50 MB decoded at once (>= 1); RAW/TIFF frames expand to
+    /// hundreds of MB of pixels while encoding
+    pub max_concurrent_heavy_images: usize,
+    /// Encoding pool size before memory-pressure scaling; `None` uses
+    /// `DEFAULT_ENCODING_THREADS`
+    pub encoding_threads: Option<usize>,
+    /// Fraction of RAM in use (0-1) above which encoding pauses and the pool
+    /// shrinks; `None` uses `DEFAULT_MEMORY_BACKOFF_THRESHOLD`
+    pub memory_backoff_threshold: Option<f64>,
+    /// Store images as BPG or AVIF; AVIF falls back to BPG when libheif can't encode it
+    pub image_output_format: ImageOutputFormat,
+    /// Quality for AVIF images (1-100); `bpg_lossless` also makes AVIF lossless
+    pub avif_quality: u8,
+    /// Content checksum on the outer zstd frame, checked on extraction; the
+    /// per-file hashes in HASHES.sha256 are written either way
+    pub archive_checksum: bool,
+    /// Record the original files' permissions, owner and xattrs in the tar
+    /// headers of misc and stored-as-is entries (see `zstd_archive::entry_metadata`
+    /// for what each OS keeps); transcoded media always gets default headers
+    pub preserve_metadata: bool,
+    /// Only archive files matching one of these globs (all files when empty);
+    /// see `collect_files_filtered` for how they are matched
+    pub include_globs: Vec<String>,
+    /// Leave out files matching any of these globs, even if an include matches
+    pub exclude_globs: Vec<String>,
+    /// Leave out files smaller than this many bytes (e.g. app cache crumbs)
+    pub min_file_size: Option<u64>,
+    /// Leave out files larger than this many bytes
+    pub max_file_size: Option<u64>,
+    /// Handling of symbolic links inside input directories
+    pub symlinks: SymlinkPolicy,
+    /// Write the archive as volumes of at most this many bytes (`name.tar.zst.001`,
+    /// `.002`, ...), e.g. to fit FAT32's 4 GB file limit; extraction joins them
+    pub split_size: Option<u64>,
+    /// Write the archive as independent zstd frames plus a seek table, so
+    /// `extract_archive_entry` can pull one file without decompressing the rest
+    pub seekable: bool,
+    /// Stop after discovery, catalog filtering and dedup and report what would be
+    /// archived; nothing is encoded or written and no backups are catalogued
+    pub dry_run: bool,
+    /// Abort the whole archive on the first file that fails to process; by
+    /// default failures are reported in `OrchestratorResult::failed` instead
+    pub fail_fast: bool,
+    /// Cipher spec ("aes-256", "blowfish", ...) and password to encrypt misc
+    /// files with; they are then kept only in the encrypted `misc.arc`
+    pub encryption: Option<(String, String)>,
 }
 
 impl Default for OrchestratorSettings {
@@ -481,6 +1071,7 @@ impl Default for OrchestratorSettings {
             bpg_lossless: false,
             bpg_bit_depth: 8,
             bpg_chroma_format: 1,
+            auto_chroma: false,
             bpg_encoder_type: 0,
             bpg_compression_level: 8,
             video_preset: 0,
@@ -492,10 +1083,56 @@ impl Default for OrchestratorSettings {
             staging_dir: None,
             heic_quality: 90,
             jpeg_quality: 92,
+            hash_chunk_size: None,
+            video_sprite_frames: None,
+            keep_original_video: false,
+            bake_orientation: false,
+            hash_algo: hash::HashAlgorithm::Sha256,
+            verify_after_create: false,
+            emit_sidecar_checksum: false,
+            preserve_live_photos: true,
+            on_access_error: AccessErrorPolicy::Skip,
+            on_case_collision: CaseCollisionPolicy::Rename,
+            sorted: false,
+            method_rules: None,
+            split_by_class: false,
+            location_mode: LocationMode::Off,
+            cancel_flag: None,
+            preflight_check: false,
+            catalog_path_base: None,
+            catalog_path: None,
+            max_concurrent_videos: DEFAULT_MAX_CONCURRENT_VIDEOS,
+            max_concurrent_heavy_images: DEFAULT_MAX_CONCURRENT_HEAVY_IMAGES,
+            encoding_threads: None,
+            memory_backoff_threshold: None,
+            image_output_format: ImageOutputFormat::Bpg,
+            avif_quality: 80,
+            archive_checksum: true,
+            preserve_metadata: false,
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            min_file_size: None,
+            max_file_size: None,
+            symlinks: SymlinkPolicy::Skip,
+            split_size: None,
+            seekable: false,
+            dry_run: false,
+            fail_fast: false,
+            encryption: None,
         }
     }
 }
 
+impl OrchestratorSettings {
+    fn encoding_threads(&self) -> usize {
+        self.encoding_threads.unwrap_or(DEFAULT_ENCODING_THREADS)
+    }
+
+    fn memory_backoff_threshold(&self) -> f64 {
+        self.memory_backoff_threshold.unwrap_or(DEFAULT_MEMORY_BACKOFF_THRESHOLD)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FileClass {
     Image,
@@ -513,9 +1150,12 @@ pub struct ProcessedFile {
     pub output_path: PathBuf,
     pub original_size: u64,
     pub output_size: u64,
+    /// Digest of the archived output, using `OrchestratorSettings::hash_algo`
     pub sha256: Option<String>,
     pub skipped_processing: bool,
     pub original_format: Option<OriginalImageFormat>,
+    /// Untouched original kept next to a transcoded video (archive-relative path, staged path)
+    pub original_copy: Option<(String, PathBuf)>,
 }
 
 #[derive(Debug)]
@@ -524,6 +1164,43 @@ pub struct OrchestratorResult {
     pub processed: Vec<ProcessedFile>,
     pub skipped_by_catalog: Vec<PathBuf>,
     pub dedup_groups: usize,
+    /// Files left out because they couldn't be read, with the error
+    pub skipped_access_errors: Vec<(PathBuf, String)>,
+    /// Files stored under a new name to avoid a case-insensitive clash, with that name
+    pub renamed_case_collisions: Vec<(PathBuf, String)>,
+    /// Files left out because their name clashed ignoring case
+    pub skipped_case_collisions: Vec<PathBuf>,
+    /// Archives written (several with `split_by_class`)
+    pub archives: Vec<PathBuf>,
+    /// Problems found before encoding, when `preflight_check` was set
+    pub preflight: Option<PreflightReport>,
+    /// Files left out by `min_file_size`/`max_file_size`, with their size
+    pub skipped_by_filter: Vec<(PathBuf, u64)>,
+    /// Files whose content an earlier archive in the catalog already holds,
+    /// with that archive's name; catalogued as backed up there, not stored again
+    pub referenced_from_catalog: Vec<(PathBuf, String)>,
+    /// Files that couldn't be processed, with the error; everything else was archived
+    pub failed: Vec<(PathBuf, String)>,
+    /// With `dry_run`: files that would be encoded and their total size, a rough
+    /// upper bound for the archive size. Zero for real runs.
+    pub planned_files: usize,
+    pub planned_bytes: u64,
+}
+
+impl OrchestratorResult {
+    /// Original and output bytes per file class
+    pub fn size_report_by_class(&self) -> SizeReport {
+        SizeReport::from_processed(&self.processed)
+    }
+
+    /// Videos and misc files stored without re-encoding because they were
+    /// already compressed (efficient videos, Live Photo clips, zip/mp3/...)
+    pub fn avoided_recompression(&self) -> usize {
+        self.processed
+            .iter()
+            .filter(|p| p.skipped_processing && p.class != FileClass::Image)
+            .count()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -532,6 +1209,8 @@ struct WorkItem {
     input: PathBuf,
     class: FileClass,
     original_format: Option<OriginalImageFormat>,
+    /// File name to archive and restore under; differs from the input's after a case-collision rename
+    name: String,
 }
 
 #[derive(Clone, Debug)]
@@ -541,23 +1220,117 @@ struct WorkDone {
 }
 
 pub fn collect_files(input_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    collect_files_filtered(input_paths, &[], &[], SymlinkPolicy::Skip)
+}
+
+/// Like `collect_files`, keeping only files that match one of `include_globs`
+/// (or all, when empty) and none of `exclude_globs`
+///
+/// As with method rules, globs containing `/` are matched against the path
+/// relative to the input directory (`**/.thumbnails/**`), others against the
+/// file name only (`.DS_Store`, `*.tmp`). Matching is case-sensitive.
+/// Under `SymlinkPolicy::Preserve` the links themselves are returned among the files.
+pub fn collect_files_filtered(
+    input_paths: &[PathBuf],
+    include_globs: &[String],
+    exclude_globs: &[String],
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<PathBuf>> {
+    let include = PathGlobs::new(include_globs).context("Invalid include glob")?;
+    let exclude = PathGlobs::new(exclude_globs).context("Invalid exclude glob")?;
+    let wanted = |root: &Path, path: &Path| {
+        let rel = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        (include.is_empty() || include.is_match(&rel, &name)) && !exclude.is_match(&rel, &name)
+    };
+
+    let preserve = symlinks == SymlinkPolicy::Preserve;
+
     let mut files = Vec::new();
     for path in input_paths {
-        if path.is_file() {
-            files.push(path.clone());
+        if path.is_file() || (preserve && path.is_symlink()) {
+            if wanted(path.parent().unwrap_or(Path::new("")), path) {
+                files.push(path.clone());
+            }
         } else if path.is_dir() {
-            for entry in walkdir::WalkDir::new(path)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-            {
-                files.push(entry.path().to_path_buf());
+            let walker = walkdir::WalkDir::new(path).follow_links(symlinks == SymlinkPolicy::Follow);
+            for entry in walker.into_iter() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        if let Some(ancestor) = e.loop_ancestor() {
+                            warn!(
+                                "symlink_loop file={} ancestor={}",
+                                e.path().map(|p| p.display().to_string()).unwrap_or_default(),
+                                ancestor.display()
+                            );
+                        }
+                        continue;
+                    }
+                };
+                let keep = entry.file_type().is_file() || (preserve && entry.path_is_symlink());
+                if keep && wanted(path, entry.path()) {
+                    files.push(entry.path().to_path_buf());
+                }
             }
         }
     }
     Ok(files)
 }
 
+/// Split `files` into those within `[min, max]` bytes (both inclusive) and
+/// those outside, with their size; files that can't be stat'ed are kept for
+/// the access-error policy to deal with
+fn filter_by_size(files: Vec<PathBuf>, min: Option<u64>, max: Option<u64>) -> (Vec<PathBuf>, Vec<(PathBuf, u64)>) {
+    if min.is_none() && max.is_none() {
+        return (files, Vec::new());
+    }
+    let mut kept = Vec::with_capacity(files.len());
+    let mut skipped = Vec::new();
+    for file in files {
+        let Ok(md) = fs::metadata(&file) else {
+            kept.push(file);
+            continue;
+        };
+        let len = md.len();
+        if min.is_some_and(|m| len < m) || max.is_some_and(|m| len > m) {
+            skipped.push((file, len));
+        } else {
+            kept.push(file);
+        }
+    }
+    (kept, skipped)
+}
+
+/// Compiled include or exclude globs, each flagged whether it matches the whole
+/// relative path or just the file name
+struct PathGlobs(Vec<(bool, GlobMatcher)>);
+
+impl PathGlobs {
+    fn new(globs: &[String]) -> Result<Self> {
+        let mut matchers = Vec::with_capacity(globs.len());
+        for glob in globs {
+            let matcher = GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| glob.clone())?
+                .compile_matcher();
+            matchers.push((glob.contains('/'), matcher));
+        }
+        Ok(Self(matchers))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn is_match(&self, rel: &str, name: &str) -> bool {
+        self.0
+            .iter()
+            .any(|(whole_path, m)| m.is_match(if *whole_path { rel } else { name }))
+    }
+}
+
 /// Classify file and determine original format
 fn classify_file(path: &Path) -> (FileClass, Option<OriginalImageFormat>) {
     let ext = path
@@ -598,923 +1371,5476 @@ fn classify_file(path: &Path) -> (FileClass, Option<OriginalImageFormat>) {
     }
 }
 
-fn safe_file_name(path: &Path) -> String {
-    path.file_name()
-        .map(|n| n.to_string_lossy().into_owned())
-        .unwrap_or_else(|| "file".to_string())
+/// Extensions of misc files that are already compressed; another LZMA pass
+/// only costs time and can even grow them
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    // archives
+    "zip", "7z", "rar", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "zst", "lz", "lz4", "lzma", "cab",
+    "arc", "pea", "jar", "apk", "ipa",
+    // audio
+    "mp3", "aac", "m4a", "ogg", "oga", "opus", "flac", "wma", "amr",
+    // zip-based documents
+    "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub",
+    // compressed images we don't transcode
+    "gif", "avif", "jxl",
+];
+
+/// MIME types, as sniffed by `infer`, of the same kinds of files
+const COMPRESSED_MIME_TYPES: &[&str] = &[
+    "application/zip",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/gzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/zstd",
+    "application/x-lzip",
+    "application/vnd.ms-cab-compressed",
+    "application/epub+zip",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "audio/mpeg",
+    "audio/aac",
+    "audio/m4a",
+    "audio/ogg",
+    "audio/opus",
+    "audio/x-flac",
+    "audio/amr",
+    "image/gif",
+    "image/avif",
+    "image/jxl",
+];
+
+/// Whether a misc file is already compressed and should be stored as-is
+///
+/// The extension decides first; otherwise the content is sniffed so renamed
+/// files (`backup.bin` that is really a zip) are caught too.
+fn is_already_compressed(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if COMPRESSED_EXTENSIONS.contains(&ext.as_str()) {
+        return true;
+    }
+    match infer::get_from_path(path) {
+        Ok(Some(kind)) => COMPRESSED_MIME_TYPES.contains(&kind.mime_type()),
+        _ => false,
+    }
 }
 
-/// Convert non-JPEG image to PNG for quality preservation before BPG encoding
-fn convert_to_png_intermediate(input: &Path, output: &Path, format: OriginalImageFormat) -> Result<()> {
-    match format {
-        #[cfg(feature = "heif")]
-        OriginalImageFormat::Heic => {
-            // HEIC → PNG via libheif
-            if HeicCodec::is_available() {
-                let codec = HeicCodec::new()?;
-                codec.decode_to_png(input, output)?;
-            } else {
-                return Err(anyhow!("HEIC decoding not available - libheif not found"));
+/// Largest .mov still treated as the motion half of a Live Photo (they run ~3s)
+const LIVE_PHOTO_MAX_VIDEO_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Find Live Photo pairs: a JPEG/HEIC still and a short .mov sharing a stem
+/// in the same directory. Returns video path -> still path.
+///
+/// Matching is by name and size only; the Apple content identifier embedded
+/// in both files is not read.
+pub fn detect_live_photo_pairs(files: &[PathBuf]) -> HashMap<PathBuf, PathBuf> {
+    let key = |p: &Path| {
+        let stem = p.file_stem().map(|s| s.to_string_lossy().to_lowercase()).unwrap_or_default();
+        (p.parent().map(Path::to_path_buf), stem)
+    };
+    let ext_of = |p: &Path| {
+        p.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default()
+    };
+
+    let stills: HashMap<(Option<PathBuf>, String), &PathBuf> = files
+        .iter()
+        .filter(|p| matches!(ext_of(p).as_str(), "jpg" | "jpeg" | "heic" | "heif"))
+        .map(|p| (key(p), p))
+        .collect();
+
+    files
+        .iter()
+        .filter(|p| ext_of(p) == "mov")
+        .filter(|p| {
+            fs::metadata(p)
+                .map(|m| m.len() <= LIVE_PHOTO_MAX_VIDEO_BYTES)
+                .unwrap_or(false)
+        })
+        .filter_map(|video| stills.get(&key(video)).map(|still| (video.clone(), (*still).clone())))
+        .collect()
+}
+
+const ACCESS_RETRY_ATTEMPTS: u32 = 3;
+const ACCESS_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Open and read one byte; locked files on Windows often open but fail to read
+fn probe_readable(path: &Path) -> std::io::Result<()> {
+    let f = fs::File::open(path)?;
+    f.metadata()?;
+    std::io::copy(&mut f.take(1), &mut std::io::sink())?;
+    Ok(())
+}
+
+/// Split `files` into readable ones and those failing with an access error
+fn filter_accessible(
+    files: Vec<PathBuf>,
+    policy: AccessErrorPolicy,
+) -> Result<(Vec<PathBuf>, Vec<(PathBuf, String)>)> {
+    let mut readable = Vec::with_capacity(files.len());
+    let mut skipped = Vec::new();
+
+    for path in files {
+        let mut result = probe_readable(&path);
+        if policy == AccessErrorPolicy::Retry {
+            for _ in 0..ACCESS_RETRY_ATTEMPTS {
+                if result.is_ok() {
+                    break;
+                }
+                thread::sleep(ACCESS_RETRY_DELAY);
+                result = probe_readable(&path);
             }
         }
-        #[cfg(not(feature = "heif"))]
-        OriginalImageFormat::Heic => {
-            return Err(anyhow!("HEIC support not compiled - enable heif feature"));
-        }
-        OriginalImageFormat::Raw => {
-            // RAW → PNG via libraw (through codecs::raw)
-            // Fall back to image crate if libraw not available
-            let img = image::open(input)
-                .with_context(|| format!("Failed to open RAW image: {}", input.display()))?;
-            img.save(output)?;
-        }
-        OriginalImageFormat::Png | OriginalImageFormat::Tiff |
-        OriginalImageFormat::Bmp | OriginalImageFormat::WebP => {
-            // These formats can be opened by image crate and saved as PNG
-            let img = image::open(input)
-                .with_context(|| format!("Failed to open image: {}", input.display()))?;
-            img.save(output)?;
-        }
-        OriginalImageFormat::Jpeg => {
-            // JPEG should not go through this function
-            unreachable!("JPEG should not use PNG intermediate");
+
+        match result {
+            Ok(()) => readable.push(path),
+            Err(e) if policy == AccessErrorPolicy::Fail => {
+                return Err(anyhow!("Cannot read {}: {}", path.display(), e));
+            }
+            Err(e) => {
+                warn!("Skipping unreadable file {}: {}", path.display(), e);
+                skipped.push((path, e.to_string()));
+            }
         }
     }
-    Ok(())
+
+    Ok((readable, skipped))
 }
 
-pub fn create_archive(
-    input_paths: &[PathBuf],
-    output_archive: &Path,
-    settings: OrchestratorSettings,
-    progress: Option<Arc<ProgressFn>>,
-) -> Result<OrchestratorResult> {
-    let discovered = collect_files(input_paths)?;
-    if discovered.is_empty() {
-        return Ok(OrchestratorResult {
-            discovered_files: Vec::new(),
-            processed: Vec::new(),
-            skipped_by_catalog: Vec::new(),
-            dedup_groups: 0,
-        });
-    }
+/// Files larger than this are flagged by the preflight check
+pub const PREFLIGHT_OVERSIZED_BYTES: u64 = 64 * 1024 * 1024 * 1024;
 
-    let catalog_path = output_archive.with_extension("catalog.sqlite");
-    let mut catalog = if settings.enable_catalog {
-        Some(BackupCatalog::new(&catalog_path)?)
-    } else {
-        None
-    };
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreflightIssue {
+    /// Couldn't stat, open or read the file
+    Unreadable(String),
+    Empty,
+    /// Larger than `PREFLIGHT_OVERSIZED_BYTES`
+    Oversized(u64),
+}
 
-    let (skipped_by_catalog, to_process) = if let Some(ref cat) = catalog {
-        cat.filter_files_to_backup(discovered.clone())?
-    } else {
-        (Vec::new(), discovered.clone())
-    };
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreflightFinding {
+    pub path: PathBuf,
+    pub issue: PreflightIssue,
+}
 
-    let total = discovered.len();
-    if let Some(ref cb) = progress {
-        cb(0, total, "Preparing...");
-    }
+/// Result of checking the inputs before any encoding starts
+#[derive(Clone, Debug, Default)]
+pub struct PreflightReport {
+    pub files_checked: usize,
+    pub total_bytes: u64,
+    pub findings: Vec<PreflightFinding>,
+}
 
-    let mut dedup_canon: HashMap<String, PathBuf> = HashMap::new();
-    let mut duplicates_of: HashMap<PathBuf, PathBuf> = HashMap::new();
+impl PreflightReport {
+    pub fn unreadable_count(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| matches!(f.issue, PreflightIssue::Unreadable(_)))
+            .count()
+    }
+}
 
-    if settings.enable_dedup {
-        for p in &to_process {
-            let h = hash::sha256_file_hex(p)?;
-            if let Some(prev) = dedup_canon.get(&h) {
-                duplicates_of.insert(p.clone(), prev.clone());
-            } else {
-                dedup_canon.insert(h, p.clone());
+/// Stat and probe every file, collecting unreadable, empty and oversized ones
+pub fn preflight_check(files: &[PathBuf]) -> PreflightReport {
+    let checked: Vec<(u64, Option<PreflightFinding>)> = files
+        .par_iter()
+        .map(|path| {
+            let finding = |issue| Some(PreflightFinding { path: path.clone(), issue });
+            let len = match fs::metadata(path) {
+                Ok(meta) => meta.len(),
+                Err(e) => return (0, finding(PreflightIssue::Unreadable(e.to_string()))),
+            };
+            if len == 0 {
+                return (0, finding(PreflightIssue::Empty));
+            }
+            if let Err(e) = probe_readable(path) {
+                return (len, finding(PreflightIssue::Unreadable(e.to_string())));
             }
+            if len > PREFLIGHT_OVERSIZED_BYTES {
+                return (len, finding(PreflightIssue::Oversized(len)));
+            }
+            (len, None)
+        })
+        .collect();
+
+    PreflightReport {
+        files_checked: files.len(),
+        total_bytes: checked.iter().map(|(len, _)| len).sum(),
+        findings: checked.into_iter().filter_map(|(_, f)| f).collect(),
+    }
+}
+
+/// Discover the files under `input_paths` and run `preflight_check` on them
+pub fn preflight_inputs(input_paths: &[PathBuf]) -> Result<PreflightReport> {
+    Ok(preflight_check(&collect_files(input_paths)?))
+}
+
+/// Where a file lands on extraction, lowercased for comparison: decoded images
+/// and videos share `media/` under their stem, misc files keep their name in `misc/`
+fn landing_key(class: FileClass, original_format: Option<OriginalImageFormat>, name: &str) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let key = match class {
+        FileClass::Image => format!(
+            "media/{}.{}",
+            stem,
+            original_format.unwrap_or(OriginalImageFormat::Png).extraction_extension()
+        ),
+        FileClass::Video => format!("media/{}.mp4", stem),
+        FileClass::Misc => format!("misc/{}", name),
+    };
+    key.to_lowercase()
+}
+
+/// `name` with `_n` appended to its stem
+fn suffixed_name(name: &str, n: usize) -> String {
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    format!("{}_{}{}", stem, n, ext)
+}
+
+/// Apply `policy` to work items whose landing names clash ignoring case
+///
+/// Items are taken in discovery order, so the first keeps its name. The two
+/// halves of a Live Photo (`live_photo_pairs`, video -> still) are kept,
+/// renamed with the same suffix, or skipped together so their stems still
+/// match on extraction. Returns the renamed inputs with their new names and
+/// the inputs left out.
+fn resolve_case_collisions(
+    work: &mut Vec<WorkItem>,
+    policy: CaseCollisionPolicy,
+    live_photo_pairs: &HashMap<PathBuf, PathBuf>,
+) -> Result<(Vec<(PathBuf, String)>, Vec<PathBuf>)> {
+    let items: HashMap<PathBuf, (FileClass, Option<OriginalImageFormat>, String)> = work
+        .iter()
+        .map(|w| (w.input.clone(), (w.class, w.original_format, w.name.clone())))
+        .collect();
+    let mut partners: HashMap<&PathBuf, &PathBuf> = HashMap::new();
+    for (video, still) in live_photo_pairs {
+        if items.contains_key(video) && items.contains_key(still) {
+            partners.insert(video, still);
+            partners.insert(still, video);
         }
     }
 
-    let mut work: Vec<WorkItem> = Vec::new();
-    for (idx, p) in discovered.iter().enumerate() {
-        if skipped_by_catalog.contains(p) {
+    let mut taken: HashMap<String, PathBuf> = HashMap::new();
+    // Decided when the partner came first: the name to use, or None to skip
+    let mut decided: HashMap<PathBuf, Option<String>> = HashMap::new();
+    let mut renamed = Vec::new();
+    let mut skipped = Vec::new();
+    let mut kept = Vec::with_capacity(work.len());
+
+    for mut item in work.drain(..) {
+        if let Some(decision) = decided.remove(&item.input) {
+            match decision {
+                Some(name) => {
+                    if name != item.name {
+                        item.name = name;
+                        renamed.push((item.input.clone(), item.name.clone()));
+                    }
+                    kept.push(item);
+                }
+                None => skipped.push(item.input),
+            }
             continue;
         }
-        if settings.enable_dedup {
-            if let Some(canon) = duplicates_of.get(p) {
-                if canon != p {
+
+        // The item and, for a Live Photo, its partner, which must move in step
+        let mut members = vec![(item.input.clone(), item.class, item.original_format, item.name.clone())];
+        if let Some(partner) = partners.get(&item.input) {
+            let (class, format, name) = &items[*partner];
+            members.push(((*partner).clone(), *class, *format, name.clone()));
+        }
+        let keys_for = |n: Option<usize>| -> Vec<(String, String)> {
+            members
+                .iter()
+                .map(|(_, class, format, name)| {
+                    let name = n.map(|n| suffixed_name(name, n)).unwrap_or_else(|| name.clone());
+                    (landing_key(*class, *format, &name), name)
+                })
+                .collect()
+        };
+
+        let mut names = keys_for(None);
+        if let Some(first) = names.iter().find_map(|(key, _)| taken.get(key)) {
+            match policy {
+                CaseCollisionPolicy::Fail => {
+                    return Err(anyhow!(
+                        "{} and {} would be extracted to the same name on a case-insensitive filesystem",
+                        first.display(),
+                        item.input.display()
+                    ));
+                }
+                CaseCollisionPolicy::Skip => {
+                    for (input, ..) in members.iter().skip(1) {
+                        decided.insert(input.clone(), None);
+                    }
+                    skipped.push(item.input);
                     continue;
                 }
+                CaseCollisionPolicy::Rename => {
+                    let mut n = 2;
+                    names = loop {
+                        let names = keys_for(Some(n));
+                        if names.iter().all(|(key, _)| !taken.contains_key(key)) {
+                            break names;
+                        }
+                        n += 1;
+                    };
+                }
             }
         }
-        let (class, original_format) = classify_file(p);
-        work.push(WorkItem {
-            idx,
-            input: p.clone(),
-            class,
-            original_format,
-        });
-    }
 
-    let staging_root = settings
-        .staging_dir
-        .clone()
-        .unwrap_or_else(std::env::temp_dir);
-    let temp_dir = tempfile::Builder::new()
-        .prefix("openarc")
-        .tempdir_in(&staging_root)
-        .with_context(|| format!("Failed to create temp dir in {}", staging_root.display()))?;
-    let media_dir = temp_dir.path().join("media");
-    let misc_dir = temp_dir.path().join("misc");
-    fs::create_dir_all(&media_dir)?;
-    // Only create misc/ if there are actually misc files to archive.
-    // An empty misc/ directory can cause issues with tar on Windows.
-    let has_misc_files = work.iter().any(|w| w.class == FileClass::Misc);
-    if has_misc_files {
-        fs::create_dir_all(&misc_dir)?;
+        for ((input, ..), (key, name)) in members.iter().zip(&names) {
+            taken.insert(key.clone(), input.clone());
+            if *input != item.input {
+                decided.insert(input.clone(), Some(name.clone()));
+            }
+        }
+        let name = names.swap_remove(0).1;
+        if name != item.name {
+            item.name = name;
+            renamed.push((item.input.clone(), item.name.clone()));
+        }
+        kept.push(item);
     }
 
-    let processed_mutex = Arc::new(parking_lot::Mutex::new(Vec::<ProcessedFile>::new()));
-    let metadata_mutex = Arc::new(parking_lot::Mutex::new(ArchiveMetadata::default()));
-    let completed_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    *work = kept;
+    Ok((renamed, skipped))
+}
 
-    let (tx, rx) = flume::unbounded::<WorkDone>();
-    let progress_clone = progress.clone();
-    let work_total = work.len();
-    let progress_thread = std::thread::spawn(move || {
-        if let Some(cb) = progress_clone {
-            while let Ok(done) = rx.recv() {
-                cb(done.idx + 1, work_total, &done.file_name);
+fn safe_file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+/// Convert non-JPEG image to PNG for quality preservation before BPG encoding
+fn convert_to_png_intermediate(input: &Path, output: &Path, format: OriginalImageFormat) -> Result<()> {
+    match format {
+        #[cfg(feature = "heif")]
+        OriginalImageFormat::Heic => {
+            // HEIC → PNG via libheif
+            if HeicCodec::is_available() {
+                let codec = HeicCodec::new()?;
+                codec.decode_to_png(input, output)?;
+            } else {
+                return Err(anyhow!("HEIC decoding not available - libheif not found"));
             }
-        } else {
-            while rx.recv().is_ok() {}
         }
-    });
-
-    let settings_clone = settings.clone();
-    let heavy_limiter = Arc::new(HeavyLimiter::new(2));
-    let optimal_threads = get_optimal_thread_count(5); // Base thread count of 5
-    let encoding_pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(optimal_threads)
-        .build()
-        .context("Failed to create encoding thread pool")?;
-    encoding_pool.install(|| {
-    let heavy_limiter = heavy_limiter.clone();
-    work.par_iter().try_for_each(|item| -> Result<()> {
-        // Check memory usage before processing each item
-        let memory_usage = check_memory_usage();
-        if memory_usage > 0.85 { // 85% threshold
-            // Brief pause to allow garbage collection
-            std::thread::sleep(std::time::Duration::from_millis(100));
-        } else if memory_usage > 0.90 { // 90% threshold
-            // More significant pause
-            std::thread::sleep(std::time::Duration::from_millis(500));
+        #[cfg(not(feature = "heif"))]
+        OriginalImageFormat::Heic => {
+            return Err(anyhow!("HEIC support not compiled - enable heif feature"));
+        }
+        OriginalImageFormat::Raw => {
+            // RAW → PNG via libraw (through codecs::raw)
+            // Fall back to image crate if libraw not available
+            let img = image::open(input)
+                .with_context(|| format!("Failed to open RAW image: {}", input.display()))?;
+            img.save(output)?;
+        }
+        OriginalImageFormat::Png | OriginalImageFormat::Tiff |
+        OriginalImageFormat::Bmp | OriginalImageFormat::WebP => {
+            // These formats can be opened by image crate and saved as PNG
+            let img = image::open(input)
+                .with_context(|| format!("Failed to open image: {}", input.display()))?;
+            img.save(output)?;
+        }
+        OriginalImageFormat::Jpeg => {
+            // JPEG should not go through this function
+            unreachable!("JPEG should not use PNG intermediate");
         }
+    }
+    Ok(())
+}
 
-        let input = &item.input;
-        let file_name = safe_file_name(input);
-        let original_size = fs::metadata(input)?.len();
+/// Decode an image and apply its EXIF orientation to the pixels
+///
+/// BPG output carries no EXIF, so the encoded image is upright with no
+/// orientation tag left for downstream viewers to misinterpret.
+fn open_image_with_orientation(path: &Path) -> Result<image::DynamicImage> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .with_guessed_format()?
+        .into_decoder()
+        .with_context(|| format!("Failed to decode {}", path.display()))?;
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut img = image::DynamicImage::from_decoder(decoder)
+        .with_context(|| format!("Failed to decode {}", path.display()))?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
 
-        let (out_path, rel_path, skipped_processing, original_format) = match item.class {
-            FileClass::Image => {
-                let original_format = item.original_format.unwrap_or(OriginalImageFormat::Png);
-                let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
-                let original_ext = input.extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("unknown")
-                    .to_lowercase();
+/// BPG chroma format values (`BPGEncoderConfig::chroma_format`)
+pub const BPG_CHROMA_420: i32 = 0;
+pub const BPG_CHROMA_444: i32 = 1;
 
-                let out = media_dir.join(format!("{}_{}.bpg", stem, item.idx));
+/// Chroma format to encode `img` with: the fixed setting, or a per-image pick with `auto_chroma`
+fn chroma_format_for(img: &image::DynamicImage, settings: &OrchestratorSettings) -> i32 {
+    if !settings.auto_chroma {
+        return settings.bpg_chroma_format;
+    }
+    if is_graphic_content(img) {
+        BPG_CHROMA_444
+    } else {
+        BPG_CHROMA_420
+    }
+}
 
-                // Throttle massive images to avoid OOM alongside videos
-                let _heavy_guard = if original_size > 50_000_000 {
-                    Some(heavy_limiter.acquire())
-                } else {
-                    None
-                };
+/// Distinct colours at or below which an image is treated as a palette graphic
+const GRAPHIC_MAX_COLORS: usize = 256;
+/// Pixel pairs sampled at most when classifying an image
+const CHROMA_SAMPLE_TARGET: u64 = 250_000;
+
+/// Heuristic for screenshots, text and line art, where 4:2:0 subsampling
+/// smears colour across sharp edges
+///
+/// Looks at horizontally adjacent pixel pairs on a sparse grid. Graphic content
+/// either uses few colours, or is mostly flat (identical neighbours) with
+/// abrupt colour changes; photos have noise and smooth chroma gradients.
+fn is_graphic_content(img: &image::DynamicImage) -> bool {
+    let rgb = img.to_rgb8();
+    let (w, h) = rgb.dimensions();
+    if w < 2 || h == 0 {
+        return false;
+    }
+    let step = ((w as u64 * h as u64 / CHROMA_SAMPLE_TARGET) as f64).sqrt().max(1.0) as u32;
+
+    let mut colors = std::collections::HashSet::new();
+    let (mut pairs, mut flat, mut sharp) = (0u64, 0u64, 0u64);
+    for y in (0..h).step_by(step as usize) {
+        for x in (0..w - 1).step_by(step as usize) {
+            let a = rgb.get_pixel(x, y).0;
+            let b = rgb.get_pixel(x + 1, y).0;
+            if colors.len() <= GRAPHIC_MAX_COLORS {
+                colors.insert(a);
+            }
+            pairs += 1;
+            if a == b {
+                flat += 1;
+            } else if chroma_distance(a, b) > 48 {
+                sharp += 1;
+            }
+        }
+    }
 
-                // Load image into memory and convert to raw pixel data
-                let img_result = if original_format == OriginalImageFormat::Heic {
-                    #[cfg(feature = "heif")]
-                    {
-                        if HeicCodec::is_available() {
-                            let codec = HeicCodec::new()?;
-                            let temp_png = media_dir.join(format!("{}_heic_temp.png", stem));
-                            codec.decode_to_png(input, &temp_png)?;
-                            let img = image::open(&temp_png);
-                            let _ = fs::remove_file(&temp_png);
-                            img.map_err(|e| anyhow::anyhow!(e))
-                        } else {
-                            Err(anyhow!("HEIC decoding not available - libheif not found"))
-                        }
-                    }
-                    #[cfg(not(feature = "heif"))]
-                    {
-                        Err(anyhow!("HEIC support not compiled - enable heif feature"))
-                    }
-                } else {
-                    image::open(input).map_err(|e| anyhow::anyhow!(e))
-                };
+    if colors.len() <= GRAPHIC_MAX_COLORS {
+        return true;
+    }
+    let flat_ratio = flat as f64 / pairs as f64;
+    let sharp_ratio = sharp as f64 / pairs as f64;
+    flat_ratio > 0.5 && sharp_ratio > 0.005
+}
 
-                // If the image can't be decoded (corrupt/truncated), copy the original
-                // file as-is to preserve it in the archive without BPG encoding.
-                let img = match img_result {
-                    Ok(img) => img,
-                    Err(_) => {
-                        let copy_name = format!("{}_{}.{}", stem, item.idx, original_ext);
-                        let copy_out = media_dir.join(&copy_name);
-                        fs::copy(input, &copy_out)
-                            .with_context(|| format!("Failed to copy unreadable image: {}", input.display()))?;
-                        let rel_path = format!("media/{}", copy_name);
-                        return Ok({
-                            let output_size = fs::metadata(&copy_out)?.len();
-                            let sha = hash::sha256_file_hex(&copy_out).ok();
-                            {
-                                let mut guard = processed_mutex.lock();
-                                guard.push(ProcessedFile {
-                                    original_path: input.clone(),
-                                    class: item.class,
-                                    archived_rel_path: rel_path,
-                                    output_path: copy_out,
-                                    original_size,
-                                    output_size,
-                                    sha256: sha,
-                                    skipped_processing: true,
-                                    original_format: Some(original_format),
-                                });
-                            }
-                            let seq = completed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                            let _ = tx.send(WorkDone { idx: seq, file_name });
-                        });
-                    }
-                };
+/// Sum of the absolute Cb and Cr differences (BT.601, 0..=510)
+fn chroma_distance(a: [u8; 3], b: [u8; 3]) -> i32 {
+    let chroma = |p: [u8; 3]| {
+        let (r, g, b) = (p[0] as i32, p[1] as i32, p[2] as i32);
+        let cb = (-43 * r - 85 * g + 128 * b) >> 8;
+        let cr = (128 * r - 107 * g - 21 * b) >> 8;
+        (cb, cr)
+    };
+    let (cb_a, cr_a) = chroma(a);
+    let (cb_b, cr_b) = chroma(b);
+    (cb_a - cb_b).abs() + (cr_a - cr_b).abs()
+}
 
-                // Convert to RGB8 or RGBA8 for BPG encoding
-                let target_bit_depth = detect_image_bit_depth(&img, original_format, settings_clone.bpg_bit_depth);
-                let wants_high_depth = target_bit_depth > 8;
+/// Encode a decoded image to BPG bytes using the archive settings
+fn encode_image_to_bpg_bytes(
+    img: &image::DynamicImage,
+    original_format: OriginalImageFormat,
+    chroma_format: i32,
+    settings: &OrchestratorSettings,
+) -> Result<Vec<u8>> {
+    // Convert to RGB8 or RGBA8 for BPG encoding
+    let target_bit_depth = detect_image_bit_depth(img, original_format, settings.bpg_bit_depth);
+    let wants_high_depth = target_bit_depth > 8;
+
+    let (width, height, pixel_data, format, bytes_per_sample) = if wants_high_depth {
+        match img {
+            image::DynamicImage::ImageRgb16(rgb) => {
+                let (w, h) = rgb.dimensions();
+                let data = cast_vec(rgb.clone().into_raw());
+                (w, h, data, codecs::bpg::BPGImageFormat::RGB24, 2u32)
+            }
+            image::DynamicImage::ImageRgba16(rgba) => {
+                let (w, h) = rgba.dimensions();
+                let data = cast_vec(rgba.clone().into_raw());
+                (w, h, data, codecs::bpg::BPGImageFormat::RGBA32, 2u32)
+            }
+            _ => {
+                let rgb = img.to_rgb16();
+                let (w, h) = rgb.dimensions();
+                let data = cast_vec(rgb.into_raw());
+                (w, h, data, codecs::bpg::BPGImageFormat::RGB24, 2u32)
+            }
+        }
+    } else {
+        match img {
+            image::DynamicImage::ImageRgb8(rgb) => {
+                let (w, h) = rgb.dimensions();
+                (w, h, rgb.clone().into_raw(), codecs::bpg::BPGImageFormat::RGB24, 1u32)
+            }
+            image::DynamicImage::ImageRgba8(rgba) => {
+                let (w, h) = rgba.dimensions();
+                (w, h, rgba.clone().into_raw(), codecs::bpg::BPGImageFormat::RGBA32, 1u32)
+            }
+            _ => {
+                let rgb = img.to_rgb8();
+                let (w, h) = rgb.dimensions();
+                (w, h, rgb.into_raw(), codecs::bpg::BPGImageFormat::RGB24, 1u32)
+            }
+        }
+    };
 
-                let (width, height, pixel_data, format, bytes_per_sample) = if wants_high_depth {
-                    match &img {
-                        image::DynamicImage::ImageRgb16(rgb) => {
-                            let (w, h) = rgb.dimensions();
-                            let data = cast_vec(rgb.clone().into_raw());
-                            (w, h, data, codecs::bpg::BPGImageFormat::RGB24, 2u32)
-                        }
-                        image::DynamicImage::ImageRgba16(rgba) => {
-                            let (w, h) = rgba.dimensions();
-                            let data = cast_vec(rgba.clone().into_raw());
-                            (w, h, data, codecs::bpg::BPGImageFormat::RGBA32, 2u32)
-                        }
-                        _ => {
-                            let rgb = img.to_rgb16();
-                            let (w, h) = rgb.dimensions();
-                            let data = cast_vec(rgb.into_raw());
-                            (w, h, data, codecs::bpg::BPGImageFormat::RGB24, 2u32)
-                        }
-                    }
-                } else {
-                    match &img {
-                        image::DynamicImage::ImageRgb8(rgb) => {
-                            let (w, h) = rgb.dimensions();
-                            (w, h, rgb.clone().into_raw(), codecs::bpg::BPGImageFormat::RGB24, 1u32)
-                        }
-                        image::DynamicImage::ImageRgba8(rgba) => {
-                            let (w, h) = rgba.dimensions();
-                            (w, h, rgba.clone().into_raw(), codecs::bpg::BPGImageFormat::RGBA32, 1u32)
-                        }
-                        _ => {
-                            let rgb = img.to_rgb8();
-                            let (w, h) = rgb.dimensions();
-                            (w, h, rgb.into_raw(), codecs::bpg::BPGImageFormat::RGB24, 1u32)
-                        }
-                    }
-                };
+    // Encode to BPG in-memory
+    let mut enc = NativeBPGEncoder::new().context("Failed to create BPG encoder")?;
+    let mut cfg: BPGEncoderConfig = NativeBPGEncoder::default_config();
+    cfg.quality = settings.bpg_quality;
+    cfg.lossless = if settings.bpg_lossless { 1 } else { 0 };
 
-                // Encode to BPG in-memory
-                let mut enc = NativeBPGEncoder::new().context("Failed to create BPG encoder")?;
-                let mut cfg: BPGEncoderConfig = NativeBPGEncoder::default_config();
-                cfg.quality = settings_clone.bpg_quality;
-                cfg.lossless = if settings_clone.bpg_lossless { 1 } else { 0 };
-
-                // Auto-detect optimal bit depth based on source image
-                cfg.bit_depth = target_bit_depth;
-
-                cfg.chroma_format = settings_clone.bpg_chroma_format;
-                cfg.encoder_type = settings_clone.bpg_encoder_type;
-                cfg.compress_level = settings_clone.bpg_compression_level;
-                enc.set_config(&cfg).context("Failed to apply BPG config")?;
-
-                // Use in-memory encoding
-                let channels = if format as i32 == codecs::bpg::BPGImageFormat::RGB24 as i32 { 3 } else { 4 };
-                let stride = width * channels * bytes_per_sample;
-                let bpg_data = enc.encode_from_memory(
-                    &pixel_data,
-                    width,
-                    height,
-                    stride,
-                    format,
-                ).with_context(|| format!("Failed to encode {} to BPG", input.display()))?;
-
-                // Write BPG data to output file
-                fs::write(&out, &bpg_data)
-                    .with_context(|| format!("Failed to write BPG file: {}", out.display()))?;
+    // Auto-detect optimal bit depth based on source image
+    cfg.bit_depth = target_bit_depth;
 
-                // Record metadata for extraction
-                {
-                    let mut meta = metadata_mutex.lock();
-                    meta.images.push(ImageMetadata {
-                        original_filename: file_name.clone(),
-                        original_format,
-                        original_extension: original_ext,
-                        bpg_filename: format!("{}_{}.bpg", stem, item.idx),
-                    });
-                }
+    cfg.chroma_format = chroma_format;
+    cfg.encoder_type = settings.bpg_encoder_type;
+    cfg.compress_level = settings.bpg_compression_level;
+    enc.set_config(&cfg).context("Failed to apply BPG config")?;
 
-                // Explicitly drop large data structures to free memory immediately
-                drop(pixel_data);
-                drop(bpg_data);
+    // Use in-memory encoding
+    let channels = if format as i32 == codecs::bpg::BPGImageFormat::RGB24 as i32 { 3 } else { 4 };
+    let stride = width * channels * bytes_per_sample;
+    enc.encode_from_memory(&pixel_data, width, height, stride, format)
+}
 
-                // Periodic cleanup check - yield to allow other threads to run
-                if item.idx % 10 == 0 {  // Every 10th item
-                    std::thread::yield_now();
-                }
+/// Projected output for one file class
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassEstimate {
+    pub class: String,
+    pub files: usize,
+    pub input_bytes: u64,
+    pub sampled_files: usize,
+    /// Output/input size ratio measured on the sample
+    pub ratio: f64,
+    pub ratio_low: f64,
+    pub ratio_high: f64,
+    pub estimated_secs: f64,
+}
+
+/// Quick projection of archive size and duration from a sampled trial run
+#[derive(Debug, Clone, Serialize)]
+pub struct Estimate {
+    pub total_files: usize,
+    pub total_input_bytes: u64,
+    pub sampled_files: usize,
+    pub estimated_output_bytes: u64,
+    /// ~95% confidence bounds on the output size
+    pub output_bytes_low: u64,
+    pub output_bytes_high: u64,
+    pub estimated_duration_secs: f64,
+    pub classes: Vec<ClassEstimate>,
+}
+
+/// Files sampled per class by `estimate_archive`
+const ESTIMATE_SAMPLES_PER_CLASS: usize = 8;
+
+/// Bytes of each misc file fed to the trial LZMA run
+const ESTIMATE_MISC_SAMPLE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Pick up to `count` distinct indices out of `len` with a small xorshift PRNG
+fn sample_indices(len: usize, count: usize, seed: u64) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..len).collect();
+    let mut state = seed | 1;
+    let take = count.min(len);
+    for i in 0..take {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let j = i + (state % (len - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(take);
+    indices
+}
+
+/// Mean ratio and ~95% confidence interval from per-sample ratios
+fn ratio_bounds(ratios: &[f64]) -> (f64, f64, f64) {
+    if ratios.is_empty() {
+        return (1.0, 1.0, 1.0);
+    }
+    let n = ratios.len() as f64;
+    let mean = ratios.iter().sum::<f64>() / n;
+    if ratios.len() < 2 {
+        // A single sample says little about spread; assume it could be off by half
+        return (mean, mean * 0.5, (mean * 1.5).max(mean));
+    }
+    let var = ratios.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let margin = 1.96 * var.sqrt() / n.sqrt();
+    (mean, (mean - margin).max(0.0), mean + margin)
+}
+
+/// Trial-process one file, returning (output bytes, input bytes measured, seconds)
+fn estimate_sample(path: &Path, class: FileClass, settings: &OrchestratorSettings) -> Result<(u64, u64, f64)> {
+    let size = fs::metadata(path)?.len();
+    let start = std::time::Instant::now();
+
+    match class {
+        FileClass::Image => {
+            let (_, original_format) = classify_file(path);
+            let original_format = original_format.unwrap_or(OriginalImageFormat::Png);
+            // Undecodable images are stored as-is
+            let out = match image::open(path) {
+                Ok(img) => encode_image_to_bpg_bytes(&img, original_format, chroma_format_for(&img, settings), settings)
+                    .map(|b| b.len() as u64)
+                    .unwrap_or(size),
+                Err(_) => size,
+            };
+            Ok((out, size, start.elapsed().as_secs_f64()))
+        }
+        FileClass::Video => {
+            // Encoding a whole video is far too slow for an estimate; project from
+            // the analyzer instead and assume roughly realtime encoding speed.
+            let analysis = safe_analyze_video(path);
+            let keep_as_is = settings.skip_already_compressed_videos
+                && analysis.as_ref().map(|a| a.is_efficiently_compressed).unwrap_or(false);
+            if keep_as_is {
+                return Ok((size, size, start.elapsed().as_secs_f64()));
+            }
+            let reduction = analysis.as_ref().map(|a| a.estimated_reduction_percent()).unwrap_or(50.0);
+            let out = (size as f64 * (1.0 - reduction / 100.0)) as u64;
+            let speed_factor = match settings.video_preset {
+                2 => 0.5,
+                3 => 2.0,
+                _ => 1.0,
+            };
+            let secs = analysis.map(|a| a.duration_secs * speed_factor).unwrap_or(0.0);
+            Ok((out, size, secs))
+        }
+        FileClass::Misc => {
+            let mut head = Vec::new();
+            fs::File::open(path)?
+                .take(ESTIMATE_MISC_SAMPLE_BYTES as u64)
+                .read_to_end(&mut head)?;
+            if head.is_empty() {
+                return Ok((0, 0, 0.0));
+            }
+            let packed = arcmax::codecs::lzma2::compress_lzma_default(&head)?;
+            Ok((packed.len() as u64, head.len() as u64, start.elapsed().as_secs_f64()))
+        }
+    }
+}
+
+/// Estimate output size and run time without building the archive
+///
+/// Samples a few files per class, runs the real image/misc encoders on them and
+/// extrapolates by bytes. Video size comes from the analyzer rather than a trial
+/// encode, so video-heavy sets carry wider error.
+pub fn estimate_archive(input_paths: &[PathBuf], settings: &OrchestratorSettings) -> Result<Estimate> {
+    let mut files =
+        collect_files_filtered(input_paths, &settings.include_globs, &settings.exclude_globs, settings.symlinks)?;
+    files.retain(|f| !(settings.symlinks == SymlinkPolicy::Preserve && f.is_symlink()));
+    let (files, _) = filter_by_size(files, settings.min_file_size, settings.max_file_size);
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x9E37_79B9_7F4A_7C15);
+
+    let mut by_class: Vec<(FileClass, Vec<(PathBuf, u64)>)> =
+        vec![(FileClass::Image, Vec::new()), (FileClass::Video, Vec::new()), (FileClass::Misc, Vec::new())];
+    for f in files {
+        let size = fs::metadata(&f).map(|m| m.len()).unwrap_or(0);
+        let (class, _) = classify_file(&f);
+        if let Some((_, list)) = by_class.iter_mut().find(|(c, _)| *c == class) {
+            list.push((f, size));
+        }
+    }
+
+    let threads = get_optimal_thread_count(settings.encoding_threads(), settings.memory_backoff_threshold()) as f64;
+    let mut estimate = Estimate {
+        total_files: 0,
+        total_input_bytes: 0,
+        sampled_files: 0,
+        estimated_output_bytes: 0,
+        output_bytes_low: 0,
+        output_bytes_high: 0,
+        estimated_duration_secs: 0.0,
+        classes: Vec::new(),
+    };
+
+    for (class, list) in by_class {
+        if list.is_empty() {
+            continue;
+        }
+        let input_bytes: u64 = list.iter().map(|(_, s)| *s).sum();
+
+        let mut ratios = Vec::new();
+        let mut sample_in = 0u64;
+        let mut sample_secs = 0.0;
+        for i in sample_indices(list.len(), ESTIMATE_SAMPLES_PER_CLASS, seed ^ class as u64) {
+            let (path, size) = &list[i];
+            match estimate_sample(path, class, settings) {
+                Ok((out, measured, secs)) if measured > 0 => {
+                    ratios.push(out as f64 / measured as f64);
+                    // Scale timing of partial reads up to the whole file
+                    sample_in += size;
+                    sample_secs += secs * (*size as f64 / measured as f64);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Estimate sample failed for {}: {}", path.display(), e),
+            }
+        }
+
+        let (ratio, ratio_low, ratio_high) = ratio_bounds(&ratios);
+        let parallelism = if class == FileClass::Video { 2.0 } else { threads };
+        let estimated_secs = if sample_in > 0 {
+            (sample_secs / sample_in as f64) * input_bytes as f64 / parallelism
+        } else {
+            0.0
+        };
+
+        estimate.total_files += list.len();
+        estimate.total_input_bytes += input_bytes;
+        estimate.sampled_files += ratios.len();
+        estimate.estimated_output_bytes += (input_bytes as f64 * ratio) as u64;
+        estimate.output_bytes_low += (input_bytes as f64 * ratio_low) as u64;
+        estimate.output_bytes_high += (input_bytes as f64 * ratio_high) as u64;
+        estimate.estimated_duration_secs += estimated_secs;
+        estimate.classes.push(ClassEstimate {
+            class: format!("{:?}", class),
+            files: list.len(),
+            input_bytes,
+            sampled_files: ratios.len(),
+            ratio,
+            ratio_low,
+            ratio_high,
+            estimated_secs,
+        });
+    }
+
+    Ok(estimate)
+}
+
+pub fn create_archive(
+    input_paths: &[PathBuf],
+    output_archive: &Path,
+    settings: OrchestratorSettings,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<OrchestratorResult> {
+    create_archive_inner(input_paths, output_archive, settings, progress, None)
+}
+
+/// Stage of archive creation, reported by `ArchiveEvent::PhaseChanged`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchivePhase {
+    /// Walking the inputs and running the preflight check
+    Scanning,
+    Encoding,
+    /// Writing the tar.zst
+    Packing,
+    Verifying,
+    Cataloguing,
+}
+
+/// Typed progress for `create_archive_events`
+///
+/// With `split_by_class` the phases repeat once per archive written.
+#[derive(Debug)]
+pub enum ArchiveEvent {
+    PhaseChanged(ArchivePhase),
+    FileStarted { path: PathBuf },
+    FileDone { path: PathBuf, size: u64, out_size: u64 },
+    /// The file was left out; the run carries on unless `fail_fast` is set
+    FileFailed { path: PathBuf, error: String },
+    /// The run failed; no `Completed` follows
+    Error(String),
+    Completed(OrchestratorResult),
+}
+
+type EventSender = flume::Sender<ArchiveEvent>;
+
+fn emit(events: Option<&EventSender>, event: ArchiveEvent) {
+    if let Some(tx) = events {
+        // A dropped receiver only means nobody is listening any more
+        let _ = tx.send(event);
+    }
+}
+
+/// Run `create_archive` on a background thread and stream its progress
+///
+/// The returned receiver works both blocking (`recv`, iteration) and from
+/// async code (`recv_async`, `into_stream`). The stream always ends with
+/// exactly one `Completed` or `Error`. The callback-based `create_archive`
+/// remains the entry point for the C FFI.
+pub fn create_archive_events(
+    input_paths: Vec<PathBuf>,
+    output_archive: PathBuf,
+    settings: OrchestratorSettings,
+) -> flume::Receiver<ArchiveEvent> {
+    let (tx, rx) = flume::unbounded();
+    thread::spawn(move || {
+        let event = match create_archive_inner(&input_paths, &output_archive, settings, None, Some(&tx)) {
+            Ok(result) => ArchiveEvent::Completed(result),
+            Err(e) => ArchiveEvent::Error(format!("{:#}", e)),
+        };
+        let _ = tx.send(event);
+    });
+    rx
+}
+
+fn create_archive_inner(
+    input_paths: &[PathBuf],
+    output_archive: &Path,
+    settings: OrchestratorSettings,
+    progress: Option<Arc<ProgressFn>>,
+    events: Option<&EventSender>,
+) -> Result<OrchestratorResult> {
+    if settings.max_concurrent_videos == 0 || settings.max_concurrent_heavy_images == 0 {
+        return Err(anyhow!(
+            "max_concurrent_videos and max_concurrent_heavy_images must be at least 1"
+        ));
+    }
+    if !(1..=100).contains(&settings.avif_quality) {
+        return Err(anyhow!("avif_quality must be 1-100, got {}", settings.avif_quality));
+    }
+    if settings.encoding_threads == Some(0) {
+        return Err(anyhow!("encoding_threads must be at least 1"));
+    }
+    if let Some(t) = settings.memory_backoff_threshold {
+        if !(t > 0.0 && t <= 1.0) {
+            return Err(anyhow!("memory_backoff_threshold must be in (0, 1], got {}", t));
+        }
+    }
+    if settings.split_size == Some(0) {
+        return Err(anyhow!("split_size must be at least 1 byte"));
+    }
+    // Catch a bad cipher name now rather than after every file is encoded
+    if let Some((spec, password)) = &settings.encryption {
+        create_encryptor(spec, password, PasswordEncoding::Utf8).context("Invalid encryption settings")?;
+    }
+    if let (Some(min), Some(max)) = (settings.min_file_size, settings.max_file_size) {
+        if min > max {
+            return Err(anyhow!("min_file_size ({}) is larger than max_file_size ({})", min, max));
+        }
+    }
+
+    emit(events, ArchiveEvent::PhaseChanged(ArchivePhase::Scanning));
+    let files =
+        collect_files_filtered(input_paths, &settings.include_globs, &settings.exclude_globs, settings.symlinks)?;
+    // Preserved links are recorded, not read; keep them away from hashing and encoding
+    let (mut symlinks, files): (Vec<PathBuf>, Vec<PathBuf>) = if settings.symlinks == SymlinkPolicy::Preserve {
+        files.into_iter().partition(|f| f.is_symlink())
+    } else {
+        (Vec::new(), files)
+    };
+    let (mut files, skipped_by_filter) = filter_by_size(files, settings.min_file_size, settings.max_file_size);
+    if settings.sorted {
+        files.sort();
+        symlinks.sort();
+    }
+    let preflight = if settings.preflight_check {
+        let report = preflight_check(&files);
+        if settings.on_access_error == AccessErrorPolicy::Fail && report.unreadable_count() > 0 {
+            return Err(anyhow!(
+                "Preflight found {} unreadable files, aborting before encoding",
+                report.unreadable_count()
+            ));
+        }
+        Some(report)
+    } else {
+        None
+    };
+    let (discovered, skipped_access_errors) = filter_accessible(files, settings.on_access_error)?;
+    if settings.encryption.is_some() && !discovered.iter().any(|p| classify_file(p).0 == FileClass::Misc) {
+        warn!("encryption_unused, no misc files to encrypt; media is never encrypted so the archive is not either");
+    }
+
+    if !settings.split_by_class {
+        let mut result = create_single_archive(
+            discovered,
+            skipped_access_errors,
+            symlinks,
+            output_archive,
+            settings,
+            progress,
+            events,
+        )?;
+        result.preflight = preflight;
+        result.skipped_by_filter = skipped_by_filter;
+        return Ok(result);
+    }
+
+    let mut combined = OrchestratorResult {
+        discovered_files: Vec::new(),
+        processed: Vec::new(),
+        skipped_by_catalog: Vec::new(),
+        dedup_groups: 0,
+        skipped_access_errors,
+        renamed_case_collisions: Vec::new(),
+        skipped_case_collisions: Vec::new(),
+        archives: Vec::new(),
+        preflight,
+        skipped_by_filter,
+        referenced_from_catalog: Vec::new(),
+        failed: Vec::new(),
+        planned_files: 0,
+        planned_bytes: 0,
+    };
+    for (label, files) in split_files_by_class(discovered, settings.preserve_live_photos) {
+        let links = if label == "misc" { std::mem::take(&mut symlinks) } else { Vec::new() };
+        if files.is_empty() && links.is_empty() {
+            continue;
+        }
+        let result = create_single_archive(
+            files,
+            Vec::new(),
+            links,
+            &split_output_path(output_archive, label),
+            settings.clone(),
+            progress.clone(),
+            events,
+        )?;
+        combined.discovered_files.extend(result.discovered_files);
+        combined.processed.extend(result.processed);
+        combined.skipped_by_catalog.extend(result.skipped_by_catalog);
+        combined.dedup_groups += result.dedup_groups;
+        combined.renamed_case_collisions.extend(result.renamed_case_collisions);
+        combined.skipped_case_collisions.extend(result.skipped_case_collisions);
+        combined.referenced_from_catalog.extend(result.referenced_from_catalog);
+        combined.failed.extend(result.failed);
+        combined.planned_files += result.planned_files;
+        combined.planned_bytes += result.planned_bytes;
+        combined.archives.extend(result.archives);
+    }
+    Ok(combined)
+}
+
+/// Group files into the per-class archives used by `split_by_class`
+///
+/// Live Photo clips stay with their stills so the pair can still be matched.
+fn split_files_by_class(files: Vec<PathBuf>, keep_live_photos: bool) -> [(&'static str, Vec<PathBuf>); 3] {
+    let live_clips = if keep_live_photos {
+        detect_live_photo_pairs(&files)
+    } else {
+        HashMap::new()
+    };
+
+    let (mut images, mut videos, mut misc) = (Vec::new(), Vec::new(), Vec::new());
+    for file in files {
+        match classify_file(&file).0 {
+            FileClass::Video if live_clips.contains_key(&file) => images.push(file),
+            FileClass::Image => images.push(file),
+            FileClass::Video => videos.push(file),
+            FileClass::Misc => misc.push(file),
+        }
+    }
+    [("images", images), ("videos", videos), ("misc", misc)]
+}
+
+/// `photos.tar.zst` -> `photos.images.tar.zst`
+fn split_output_path(output_archive: &Path, label: &str) -> PathBuf {
+    let name = output_archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "archive.tar.zst".to_string());
+    let (stem, ext) = match name.strip_suffix(".tar.zst") {
+        Some(stem) => (stem.to_string(), "tar.zst".to_string()),
+        None => match name.rsplit_once('.') {
+            Some((stem, ext)) => (stem.to_string(), ext.to_string()),
+            None => (name.clone(), "tar.zst".to_string()),
+        },
+    };
+    output_archive.with_file_name(format!("{}.{}.{}", stem, label, ext))
+}
+
+/// Build one archive from already discovered, readable files and the links
+/// recorded under `SymlinkPolicy::Preserve`
+fn create_single_archive(
+    discovered: Vec<PathBuf>,
+    skipped_access_errors: Vec<(PathBuf, String)>,
+    symlinks: Vec<PathBuf>,
+    output_archive: &Path,
+    settings: OrchestratorSettings,
+    progress: Option<Arc<ProgressFn>>,
+    events: Option<&EventSender>,
+) -> Result<OrchestratorResult> {
+    if discovered.is_empty() && symlinks.is_empty() {
+        return Ok(OrchestratorResult {
+            discovered_files: Vec::new(),
+            processed: Vec::new(),
+            skipped_by_catalog: Vec::new(),
+            dedup_groups: 0,
+            skipped_access_errors,
+            renamed_case_collisions: Vec::new(),
+            skipped_case_collisions: Vec::new(),
+            archives: Vec::new(),
+            preflight: None,
+            skipped_by_filter: Vec::new(),
+            referenced_from_catalog: Vec::new(),
+            failed: Vec::new(),
+            planned_files: 0,
+            planned_bytes: 0,
+        });
+    }
+
+    let catalog_path = settings
+        .catalog_path
+        .clone()
+        .unwrap_or_else(|| output_archive.with_extension("catalog.sqlite"));
+    // A dry run reads an existing catalog but never creates, migrates or writes one
+    let catalog = if !settings.enable_catalog {
+        None
+    } else if !settings.dry_run {
+        Some(BackupCatalog::new(&catalog_path)?.with_path_base(settings.catalog_path_base.clone()))
+    } else if catalog_path.exists() {
+        Some(
+            BackupCatalog::open_read_only(&catalog_path)?
+                .with_path_base(settings.catalog_path_base.clone()),
+        )
+    } else {
+        None
+    };
+
+    let (skipped_by_catalog, to_process) = if let Some(ref cat) = catalog {
+        cat.filter_files_to_backup(discovered.clone())?
+    } else {
+        (Vec::new(), discovered.clone())
+    };
+
+    let total = discovered.len();
+    if let Some(ref cb) = progress {
+        cb(0, total, "Preparing...");
+    }
+
+    let mut dedup_canon: HashMap<String, PathBuf> = HashMap::new();
+    let mut duplicates_of: HashMap<PathBuf, PathBuf> = HashMap::new();
+    // Original content hashes, recorded in the catalog for later runs to match against
+    let mut source_hashes: HashMap<PathBuf, String> = HashMap::new();
+    let mut referenced_from_catalog: Vec<(PathBuf, String)> = Vec::new();
+
+    if settings.enable_dedup {
+        // The catalog only holds SHA-256 digests
+        let prior = catalog
+            .as_ref()
+            .filter(|_| settings.hash_algo == hash::HashAlgorithm::Sha256);
+        for p in &to_process {
+            let h = hash::file_hex(p, settings.hash_algo)?;
+            source_hashes.insert(p.clone(), h.clone());
+            if let Some(cat) = prior {
+                if let Some(archive) = cat.contains_hash(&h)? {
+                    referenced_from_catalog.push((p.clone(), archive));
+                    continue;
+                }
+            }
+            if let Some(prev) = dedup_canon.get(&h) {
+                duplicates_of.insert(p.clone(), prev.clone());
+            } else {
+                dedup_canon.insert(h, p.clone());
+            }
+        }
+    }
+
+    let live_photo_pairs = if settings.preserve_live_photos {
+        detect_live_photo_pairs(&discovered)
+    } else {
+        HashMap::new()
+    };
+
+    let referenced: HashSet<&PathBuf> = referenced_from_catalog.iter().map(|(p, _)| p).collect();
+    let mut work: Vec<WorkItem> = Vec::new();
+    for (idx, p) in discovered.iter().enumerate() {
+        if skipped_by_catalog.contains(p) || referenced.contains(p) {
+            continue;
+        }
+        if settings.enable_dedup {
+            if let Some(canon) = duplicates_of.get(p) {
+                if canon != p {
+                    continue;
+                }
+            }
+        }
+        let (class, original_format) = classify_file(p);
+        work.push(WorkItem {
+            idx,
+            input: p.clone(),
+            class,
+            original_format,
+            name: safe_file_name(p),
+        });
+    }
+    let (renamed_case_collisions, skipped_case_collisions) =
+        resolve_case_collisions(&mut work, settings.on_case_collision, &live_photo_pairs)?;
+
+    if settings.dry_run {
+        let planned_bytes = work
+            .iter()
+            .map(|w| fs::metadata(&w.input).map(|m| m.len()).unwrap_or(0))
+            .sum();
+        return Ok(OrchestratorResult {
+            discovered_files: discovered,
+            processed: Vec::new(),
+            skipped_by_catalog,
+            dedup_groups: if settings.enable_dedup { dedup_canon.len() } else { 0 },
+            skipped_access_errors,
+            renamed_case_collisions,
+            skipped_case_collisions,
+            archives: Vec::new(),
+            preflight: None,
+            skipped_by_filter: Vec::new(),
+            referenced_from_catalog,
+            failed: Vec::new(),
+            planned_files: work.len(),
+            planned_bytes,
+        });
+    }
+    // Live Photo clips point at their still by name, which may have just changed
+    let work_names: HashMap<PathBuf, String> = work.iter().map(|w| (w.input.clone(), w.name.clone())).collect();
+
+    let staging_root = settings
+        .staging_dir
+        .clone()
+        .unwrap_or_else(std::env::temp_dir);
+    let temp_dir = tempfile::Builder::new()
+        .prefix("openarc")
+        .tempdir_in(&staging_root)
+        .with_context(|| format!("Failed to create temp dir in {}", staging_root.display()))?;
+    let media_dir = temp_dir.path().join("media");
+    let misc_dir = temp_dir.path().join("misc");
+    let thumbs_dir = temp_dir.path().join("thumbs");
+    fs::create_dir_all(&media_dir)?;
+    // Only create misc/ if there are actually misc files to archive.
+    // An empty misc/ directory can cause issues with tar on Windows.
+    let has_misc_files = work.iter().any(|w| w.class == FileClass::Misc);
+    if has_misc_files {
+        fs::create_dir_all(&misc_dir)?;
+    }
+
+    let processed_mutex = Arc::new(parking_lot::Mutex::new(Vec::<ProcessedFile>::new()));
+    let metadata_mutex = Arc::new(parking_lot::Mutex::new(ArchiveMetadata::default()));
+    let completed_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+    emit(events, ArchiveEvent::PhaseChanged(ArchivePhase::Encoding));
+    let (tx, rx) = flume::unbounded::<WorkDone>();
+    let progress_clone = progress.clone();
+    let work_total = work.len();
+    let progress_thread = std::thread::spawn(move || {
+        if let Some(cb) = progress_clone {
+            while let Ok(done) = rx.recv() {
+                cb(done.idx + 1, work_total, &done.file_name);
+            }
+        } else {
+            while rx.recv().is_ok() {}
+        }
+    });
+
+    let settings_clone = settings.clone();
+    let video_limiter = Arc::new(HeavyLimiter::new(settings.max_concurrent_videos));
+    let image_limiter = Arc::new(HeavyLimiter::new(settings.max_concurrent_heavy_images));
+    let optimal_threads = get_optimal_thread_count(settings.encoding_threads(), settings.memory_backoff_threshold());
+    let image_format = effective_image_format(settings.image_output_format);
+    let encoding_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(optimal_threads)
+        .build()
+        .context("Failed to create encoding thread pool")?;
+    let mut failed: Vec<(PathBuf, String)> = encoding_pool.install(|| -> Result<Vec<(PathBuf, String)>> {
+    let video_limiter = video_limiter.clone();
+    let image_limiter = image_limiter.clone();
+    let process_item = |item: &WorkItem| -> Result<()> {
+        // Stop picking up work once cancelled; TempDir drop removes the intermediates
+        check_cancelled(&settings.cancel_flag)?;
+        emit(events, ArchiveEvent::FileStarted { path: item.input.clone() });
+
+        // Check memory usage before processing each item
+        let memory_usage = check_memory_usage();
+        let threshold = settings.memory_backoff_threshold();
+        if memory_usage > threshold + 0.05 {
+            // More significant pause
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        } else if memory_usage > threshold {
+            // Brief pause to allow garbage collection
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+
+        let input = &item.input;
+        let file_name = item.name.clone();
+        let original_size = fs::metadata(input)?.len();
+
+        // The most specific matching rule overrides the encode settings for this file only
+        let rule = settings.method_rules.as_ref().and_then(|r| r.rule_for(input));
+        let rule_settings;
+        let settings_clone = match rule {
+            Some(rule) => {
+                rule_settings = rule.overrides.apply(&settings_clone);
+                &rule_settings
+            }
+            None => &settings_clone,
+        };
+        let store_as_is = rule.is_some_and(|r| r.overrides.store);
+
+        let mut original_copy: Option<(String, PathBuf)> = None;
+        let (out_path, rel_path, skipped_processing, original_format) = match item.class {
+            FileClass::Image => {
+                let original_format = item.original_format.unwrap_or(OriginalImageFormat::Png);
+                let stem = Path::new(&item.name).file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+                let original_ext = input.extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("unknown")
+                    .to_lowercase();
+
+                let out = media_dir.join(format!("{}_{}.{}", stem, item.idx, image_format.extension()));
+
+                // Throttle massive images to avoid OOM alongside videos
+                let _heavy_guard = if original_size > 50_000_000 {
+                    Some(image_limiter.acquire())
+                } else {
+                    None
+                };
+
+                // Load image into memory and convert to raw pixel data
+                let img_result = if store_as_is {
+                    Err(anyhow!("Stored as-is by method rule"))
+                } else if original_format == OriginalImageFormat::Heic {
+                    #[cfg(feature = "heif")]
+                    {
+                        if HeicCodec::is_available() {
+                            let codec = HeicCodec::new()?;
+                            let temp_png = media_dir.join(format!("{}_heic_temp.png", stem));
+                            codec.decode_to_png(input, &temp_png)?;
+                            let img = image::open(&temp_png);
+                            let _ = fs::remove_file(&temp_png);
+                            img.map_err(|e| anyhow::anyhow!(e))
+                        } else {
+                            Err(anyhow!("HEIC decoding not available - libheif not found"))
+                        }
+                    }
+                    #[cfg(not(feature = "heif"))]
+                    {
+                        Err(anyhow!("HEIC support not compiled - enable heif feature"))
+                    }
+                } else if settings_clone.bake_orientation {
+                    open_image_with_orientation(input)
+                } else {
+                    image::open(input).map_err(|e| anyhow::anyhow!(e))
+                };
+
+                // If the image can't be decoded (corrupt/truncated) or a rule says to store
+                // it, copy the original file as-is to preserve it without BPG encoding.
+                let img = match img_result {
+                    Ok(img) => img,
+                    Err(_) => {
+                        let copy_name = format!("{}_{}.{}", stem, item.idx, original_ext);
+                        let copy_out = media_dir.join(&copy_name);
+                        fs::copy(input, &copy_out)
+                            .with_context(|| format!("Failed to copy image as-is: {}", input.display()))?;
+                        let rel_path = format!("media/{}", copy_name);
+                        return Ok({
+                            let output_size = fs::metadata(&copy_out)?.len();
+                            let sha = hash::file_hex(&copy_out, settings_clone.hash_algo).ok();
+                            emit(events, ArchiveEvent::FileDone {
+                                path: input.clone(),
+                                size: original_size,
+                                out_size: output_size,
+                            });
+                            {
+                                let mut guard = processed_mutex.lock();
+                                guard.push(ProcessedFile {
+                                    original_path: input.clone(),
+                                    class: item.class,
+                                    archived_rel_path: rel_path,
+                                    output_path: copy_out,
+                                    original_size,
+                                    output_size,
+                                    sha256: sha,
+                                    skipped_processing: true,
+                                    original_format: Some(original_format),
+                                    original_copy: None,
+                                });
+                            }
+                            let seq = completed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            let _ = tx.send(WorkDone { idx: seq, file_name });
+                        });
+                    }
+                };
+
+                let chroma_format = match image_format {
+                    ImageOutputFormat::Bpg => {
+                        // Encode to BPG in-memory
+                        let chroma_format = chroma_format_for(&img, settings_clone);
+                        let bpg_data = encode_image_to_bpg_bytes(&img, original_format, chroma_format, settings_clone)
+                            .with_context(|| format!("Failed to encode {} to BPG", input.display()))?;
+
+                        // Write BPG data to output file
+                        fs::write(&out, &bpg_data)
+                            .with_context(|| format!("Failed to write BPG file: {}", out.display()))?;
+                        Some(chroma_format)
+                    }
+                    ImageOutputFormat::Avif => {
+                        if let Err(e) = encode_image_to_avif(&img, &out, settings_clone) {
+                            // Don't leave a partial file behind to be packed
+                            let _ = fs::remove_file(&out);
+                            return Err(e.context(format!("Failed to encode {} to AVIF", input.display())));
+                        }
+                        None
+                    }
+                };
+
+                // Record metadata for extraction
+                {
+                    let mut meta = metadata_mutex.lock();
+                    meta.images.push(ImageMetadata {
+                        original_filename: file_name.clone(),
+                        original_format,
+                        original_extension: original_ext,
+                        bpg_filename: format!("{}_{}.{}", stem, item.idx, image_format.extension()),
+                        codec: image_format,
+                        chroma_format,
+                    });
+                }
+
+                // Explicitly drop large data structures to free memory immediately
+                drop(img);
+
+                // Periodic cleanup check - yield to allow other threads to run
+                if item.idx % 10 == 0 {  // Every 10th item
+                    std::thread::yield_now();
+                }
+
+                let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
+                (out, rel_path, false, Some(original_format))
+            }
+            FileClass::Video => {
+                // Live Photo clips are stored untouched under their original name so
+                // they sit next to the decoded still with a matching stem
+                let should_skip = if store_as_is || live_photo_pairs.contains_key(input) {
+                    true
+                } else if settings_clone.skip_already_compressed_videos {
+                    safe_analyze_video(input)
+                        .map(|a| a.is_efficiently_compressed)
+                        .unwrap_or(false)
+                } else {
+                    false
+                };
+
+                if should_skip {
+                    let out = media_dir.join(&item.name);
+                    fs::copy(input, &out)?;
+                    let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
+                    (out, rel_path, true, None)
+                } else {
+                    // Limit concurrent heavy video encodes to prevent memory spikes
+                    let _heavy_guard = video_limiter.acquire();
+
+                    let (codec, preset) = match settings_clone.video_preset {
+                        1 => (VideoCodec::H265, VideoSpeedPreset::Medium),
+                        2 => (VideoCodec::H264, VideoSpeedPreset::Fast),
+                        3 => (VideoCodec::H265, VideoSpeedPreset::Slow),
+                        _ => (VideoCodec::H264, VideoSpeedPreset::Medium),
+                    };
+
+                    let out = media_dir.join(format!(
+                        "{}.mp4",
+                        Path::new(&item.name).file_stem().and_then(|s| s.to_str()).unwrap_or("video")
+                    ));
+
+                    let opts = FfmpegEncodeOptions {
+                        codec,
+                        speed: preset,
+                        crf: Some(settings_clone.video_crf as u8),
+                        copy_audio: true,
+                    };
+
+                    // Use memory-constrained video encoding
+                    if let Err(e) = encode_video_with_memory_constraints(input, &out, opts, settings_clone) {
+                        // Like an undecodable image, keep the original instead of losing the file
+                        let _ = fs::remove_file(&out);
+                        let copy = media_dir.join(&item.name);
+                        fs::copy(input, &copy).with_context(|| {
+                            format!("Failed to encode ({:#}) or copy video as-is: {}", e, input.display())
+                        })?;
+                        let rel_path = format!("media/{}", copy.file_name().unwrap().to_string_lossy());
+                        (copy, rel_path, true, None)
+                    } else {
+                        if settings_clone.keep_original_video {
+                            let originals_dir = media_dir.join("originals");
+                            fs::create_dir_all(&originals_dir)?;
+                            // The work name is unique per archive, so same-named clips keep one original each
+                            let kept = originals_dir.join(&item.name);
+                            fs::copy(input, &kept)
+                                .with_context(|| format!("Failed to keep original video: {}", input.display()))?;
+                            let kept_rel = format!("media/originals/{}", kept.file_name().unwrap().to_string_lossy());
+                            original_copy = Some((kept_rel, kept));
+                        }
+
+                        let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
+                        (out, rel_path, false, None)
+                    }
+                }
+            }
+            FileClass::Misc => {
+                let out = misc_dir.join(&item.name);
+                if let Err(e) = fs::copy(input, &out) {
+                    let _ = fs::remove_file(&out);
+                    return Err(anyhow!(e).context(format!("Failed to copy {}", input.display())));
+                }
+                let rel_path = format!("misc/{}", out.file_name().unwrap().to_string_lossy());
+                // Already-compressed files are stored in misc.arc instead of recompressed
+                (out, rel_path, store_as_is || is_already_compressed(input), None)
+            }
+        };
+
+        if item.class == FileClass::Video {
+            metadata_mutex.lock().videos.push(VideoMetadata {
+                original_filename: file_name.clone(),
+                proxy_path: rel_path.clone(),
+                original_path: original_copy.as_ref().map(|(rel, _)| rel.clone()),
+                live_photo_image: live_photo_pairs
+                    .get(input)
+                    .map(|still| work_names.get(still).cloned().unwrap_or_else(|| safe_file_name(still))),
+                stored_as_is: skipped_processing,
+            });
+
+            if let Some(frames) = settings_clone.video_sprite_frames {
+                // Best effort: a missing sprite never fails the archive
+                let stem = out_path.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+                let sprite = thumbs_dir.join(format!("{}.jpg", stem));
+                let sheet = fs::create_dir_all(&thumbs_dir).map_err(anyhow::Error::from).and_then(|_| {
+                    FFmpegEncoder::with_options(FfmpegEncodeOptions::default()).generate_sprite_sheet(
+                        input,
+                        &sprite,
+                        frames,
+                        DEFAULT_SPRITE_TILE_WIDTH,
+                    )
+                });
+                if let Err(e) = sheet {
+                    warn!("Sprite sheet failed for {}: {:#}", input.display(), e);
+                }
+            }
+        }
+
+        let output_size = fs::metadata(&out_path)?.len();
+        let sha = hash::file_hex(&out_path, settings_clone.hash_algo).ok();
+        emit(events, ArchiveEvent::FileDone {
+            path: input.clone(),
+            size: original_size,
+            out_size: output_size,
+        });
+
+        {
+            let mut guard = processed_mutex.lock();
+            guard.push(ProcessedFile {
+                original_path: input.clone(),
+                class: item.class,
+                archived_rel_path: rel_path,
+                output_path: out_path,
+                original_size,
+                output_size,
+                sha256: sha,
+                skipped_processing,
+                original_format,
+                original_copy,
+            });
+        }
+
+        let seq = completed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let _ = tx.send(WorkDone { idx: seq, file_name });
+        Ok(())
+    };
+    if settings.fail_fast {
+        work.par_iter().try_for_each(&process_item)?;
+        return Ok(Vec::new());
+    }
+    // One unreadable or unencodable file shouldn't cost the rest of the backup
+    Ok(work
+        .par_iter()
+        .filter_map(|item| {
+            let error = format!("{:#}", process_item(item).err()?);
+            emit(events, ArchiveEvent::FileFailed { path: item.input.clone(), error: error.clone() });
+            let seq = completed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let _ = tx.send(WorkDone { idx: seq, file_name: item.name.clone() });
+            Some((item.input.clone(), error))
+        })
+        .collect())
+    })?;
+    // Cancelled items fail like any other; don't mistake that for a finished run
+    check_cancelled(&settings.cancel_flag)?;
+
+    drop(tx);
+    let _ = progress_thread.join();
+
+    let mut processed = Arc::try_unwrap(processed_mutex)
+        .map_err(|_| anyhow!("Failed to unwrap processed results"))?
+        .into_inner();
+
+    let mut metadata = Arc::try_unwrap(metadata_mutex)
+        .map_err(|_| anyhow!("Failed to unwrap metadata"))?
+        .into_inner();
+    if settings.sorted {
+        // Workers finish in any order; put everything derived from them back in input order
+        processed.sort_by(|a, b| a.original_path.cmp(&b.original_path));
+        metadata.images.sort_by(|a, b| a.bpg_filename.cmp(&b.bpg_filename));
+        metadata.videos.sort_by(|a, b| a.proxy_path.cmp(&b.proxy_path));
+    }
+    // Duplicates of a file that failed have nothing to point at
+    let failed_paths: HashSet<&PathBuf> = failed.iter().map(|(p, _)| p).collect();
+    let mut failed_duplicates: Vec<(PathBuf, String)> = duplicates_of
+        .iter()
+        .filter(|(_, canon)| failed_paths.contains(canon))
+        .map(|(dup, canon)| (dup.clone(), format!("duplicate of {}, which failed", canon.display())))
+        .collect();
+    failed_duplicates.sort();
+    failed.extend(failed_duplicates);
+
+    metadata.size_report = Some(SizeReport::from_processed(&processed));
+    metadata.duplicates = duplicate_metadata(&duplicates_of, &processed);
+    metadata.references = referenced_from_catalog
+        .iter()
+        .map(|(p, archive)| ReferenceMetadata {
+            original_filename: safe_file_name(p),
+            archive: archive.clone(),
+        })
+        .collect();
+    metadata.mtimes = original_mtimes(&processed);
+    metadata.symlinks = symlink_metadata(&symlinks);
+    if settings.location_mode != LocationMode::Off {
+        let mut summary = LocationSummary::default();
+        for p in processed.iter().filter(|p| p.class == FileClass::Image) {
+            let name = p.original_path.file_name().unwrap_or_default().to_string_lossy();
+            summary.add(&name, location::read_gps(&p.original_path), settings.location_mode);
+        }
+        metadata.location_summary = Some(summary);
+    }
+
+    let misc_arc_path = temp_dir.path().join("misc.arc");
+    metadata.misc_encryption =
+        create_misc_arc(&processed, &misc_arc_path, settings.compression_level, settings.encryption.as_ref())?;
+    if metadata.misc_encryption.is_some() {
+        // Plaintext copies next to the encrypted misc.arc would defeat it
+        for p in processed.iter().filter(|p| p.class == FileClass::Misc) {
+            fs::remove_file(&p.output_path)
+                .with_context(|| format!("Failed to remove {}", p.output_path.display()))?;
+        }
+    }
+
+    // Write metadata JSON
+    let metadata_path = temp_dir.path().join("OPENARC_METADATA.json");
+    let metadata_json = serde_json::to_string_pretty(&metadata)?;
+    fs::write(&metadata_path, &metadata_json)?;
+
+    let manifest_path = temp_dir.path().join("MANIFEST.txt");
+    write_manifest(&processed, &skipped_by_catalog, &manifest_path)?;
+
+    let hashes_path = temp_dir.path().join("HASHES.sha256");
+    write_hashes(
+        &processed,
+        &hashes_path,
+        &misc_arc_path,
+        &manifest_path,
+        settings.hash_chunk_size,
+        settings.hash_algo,
+        metadata.misc_encryption.is_some(),
+    )?;
+
+    // Last point to stop before anything is written next to the inputs
+    check_cancelled(&settings.cancel_flag)?;
+
+    emit(events, ArchiveEvent::PhaseChanged(ArchivePhase::Packing));
+    let mut zstd_opts = make_zstd_with_checksum(3, settings.archive_checksum).options().clone();
+    zstd_opts.split_size = settings.split_size;
+    if settings.seekable {
+        zstd_opts.seekable_frame_size = Some(zstd_archive::DEFAULT_SEEKABLE_FRAME_SIZE);
+    }
+    let zstd = ZstdCodec::new(zstd_opts);
+    if settings.preserve_metadata {
+        zstd.archive_dir_tar_zst_with_metadata(temp_dir.path(), output_archive, &original_metadata(&processed))
+    } else if settings.sorted {
+        // The metadata-aware packer walks directories in name order
+        zstd.archive_dir_tar_zst_with_metadata(temp_dir.path(), output_archive, &HashMap::new())
+    } else {
+        zstd.archive_dir_tar_zst(temp_dir.path(), output_archive)
+    }
+    .with_context(|| format!("Failed to create zstd archive at {}", output_archive.display()))?;
+
+    // Check before cataloguing so a bad archive never marks files as backed up
+    if settings.verify_after_create {
+        emit(events, ArchiveEvent::PhaseChanged(ArchivePhase::Verifying));
+        let failures = hash::verify_embedded_hashes(output_archive)
+            .with_context(|| format!("Failed to verify {}", output_archive.display()))?;
+        if !failures.is_empty() {
+            let names: Vec<&str> = failures.iter().map(|f| f.rel_path.as_str()).collect();
+            return Err(VerificationFailed(format!(
+                "Verification of {} failed for {} entries: {}",
+                output_archive.display(),
+                failures.len(),
+                names.join(", ")
+            ))
+            .into());
+        }
+    }
+
+    if settings.emit_sidecar_checksum {
+        hash::write_sidecar_checksum(output_archive)?;
+    }
+
+    // Record archive information in the database. Backups, the archive row and
+    // its file mappings commit together so a crash can't leave them half-written.
+    if let Some(mut cat) = catalog {
+        emit(events, ArchiveEvent::PhaseChanged(ArchivePhase::Cataloguing));
+        // Sums the volumes of a split archive
+        let archive_size = zstd_archive::SplitReader::open(output_archive)
+            .with_context(|| format!("Failed to get metadata for archive: {}", output_archive.display()))?
+            .len();
+
+        let archive_record = ArchiveRecord {
+            id: None,
+            archive_path: output_archive.to_string_lossy().to_string(),
+            archive_size,
+            creation_date: 0, // Will be set by the database
+            original_location: output_archive.parent()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|| ".".to_string()),
+            destination_location: None, // Will be set later when moved
+            description: Some(format!("Archive with {} files", processed.len())),
+            file_count: processed.len() as u32,
+        };
+
+        cat.in_transaction(|cat| {
+            record_catalog_entries(cat, &processed, output_archive, settings.hash_algo)?;
+            record_catalog_references(cat, &referenced_from_catalog)?;
+            let hashes: Vec<(String, String)> = processed
+                .iter()
+                .map(|p| &p.original_path)
+                .chain(referenced_from_catalog.iter().map(|(p, _)| p))
+                .filter_map(|p| Some((cat.catalog_key(p), source_hashes.get(p)?.clone())))
+                .collect();
+            // The column only holds SHA-256 digests, which is all `contains_hash` looks up
+            if settings.hash_algo == hash::HashAlgorithm::Sha256 {
+                cat.record_source_hashes(&hashes)?;
+            }
+
+            // Archive tracker shares the catalog connection and its transaction
+            let mut tracker = ArchiveTracker::new(cat.get_connection_mut())
+                .context("Could not create archive tracker")?;
+            let archive_id = tracker
+                .record_archive(archive_record)
+                .context("Failed to record archive in tracker")?;
+
+            let file_mappings: Vec<ArchiveFileMapping> = processed.iter().map(|p| {
+                ArchiveFileMapping {
+                    id: None,
+                    archive_id,
+                    file_path: p.archived_rel_path.clone(),
+                    original_path: p.original_path.to_string_lossy().to_string(),
+                    file_size: p.original_size,
+                    archived_at: 0, // Will be set by the database
+                }
+            }).collect();
+            tracker
+                .record_archive_files(archive_id, file_mappings)
+                .context("Failed to record archive files")?;
+
+            if let Some(ref summary) = metadata.location_summary {
+                tracker.set_location_summary(archive_id, summary)?;
+            }
+            Ok(())
+        })?;
+
+        if let Err(e) = cat.close() {
+            warn!("{:#}", e);
+        }
+    }
+
+    let dedup_groups = if settings.enable_dedup { dedup_canon.len() } else { 0 };
+
+    Ok(OrchestratorResult {
+        discovered_files: discovered,
+        processed,
+        skipped_by_catalog,
+        dedup_groups,
+        skipped_access_errors,
+        renamed_case_collisions,
+        skipped_case_collisions,
+        archives: if settings.split_size.is_some() {
+            zstd_archive::split::volume_paths(output_archive)
+        } else {
+            vec![output_archive.to_path_buf()]
+        },
+        preflight: None,
+        skipped_by_filter: Vec::new(),
+        referenced_from_catalog,
+        failed,
+        planned_files: 0,
+        planned_bytes: 0,
+    })
+}
+
+/// Timing of `watch_and_archive`
+#[derive(Clone, Copy, Debug)]
+pub struct WatchOptions {
+    /// Quiet time after the last change before a batch is archived
+    pub debounce: Duration,
+    /// Longest a change waits while a burst keeps going
+    pub max_delay: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Catalog shared by every archive a watch writes into its output directory
+pub const WATCH_CATALOG_NAME: &str = "watch.catalog.sqlite";
+
+/// A running `watch_and_archive`; dropping it stops the watcher like `stop`
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    archives: Arc<parking_lot::Mutex<Vec<PathBuf>>>,
+    /// Taken by `stop`, so `Drop` only joins a worker nobody waited for
+    thread: Option<thread::JoinHandle<Result<()>>>,
+}
+
+impl WatchHandle {
+    /// Archives written so far
+    pub fn archives(&self) -> Vec<PathBuf> {
+        self.archives.lock().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Stop watching and wait for the worker; an archive in progress is cancelled
+    /// and its files are picked up again by the next watch's initial sweep
+    pub fn stop(mut self) -> Result<Vec<PathBuf>> {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.join().map_err(|_| anyhow!("Watch thread panicked"))??;
+        }
+        Ok(self.archives.lock().clone())
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Continuously back up `inputs` into rolling archives in `output_dir`
+///
+/// Existing files not yet in the watch catalog are archived first; after that
+/// new and modified files are collected, coalesced and archived once changes
+/// settle for `WatchOptions::debounce`. Every archive is checked against and
+/// recorded in `<output_dir>/watch.catalog.sqlite`, so unchanged files are never
+/// archived twice, also across restarts.
+pub fn watch_and_archive(inputs: &[PathBuf], output_dir: &Path, settings: OrchestratorSettings) -> Result<WatchHandle> {
+    watch_and_archive_with_options(inputs, output_dir, settings, WatchOptions::default())
+}
+
+pub fn watch_and_archive_with_options(
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    settings: OrchestratorSettings,
+    options: WatchOptions,
+) -> Result<WatchHandle> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output dir: {}", output_dir.display()))?;
+    let output_dir = output_dir.canonicalize()?;
+    // Events carry the watched path as prefix; canonical roots make the output-dir filter reliable
+    let inputs = inputs
+        .iter()
+        .map(|p| p.canonicalize().with_context(|| format!("Failed to resolve {}", p.display())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let (tx, rx) = flume::unbounded::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let _ = tx.send(res);
+    })
+    .context("Failed to create file watcher")?;
+    for input in &inputs {
+        let mode = if input.is_dir() { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        watcher
+            .watch(input, mode)
+            .with_context(|| format!("Failed to watch {}", input.display()))?;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let archives = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let settings = OrchestratorSettings {
+        enable_catalog: true,
+        catalog_path: Some(output_dir.join(WATCH_CATALOG_NAME)),
+        cancel_flag: Some(stop.clone()),
+        ..settings
+    };
+    let (thread_stop, thread_archives) = (stop.clone(), archives.clone());
+
+    let thread = thread::spawn(move || -> Result<()> {
+        // Keep the watcher alive for as long as the loop runs
+        let _watcher = watcher;
+        // The initial sweep goes through the same catalog check as later changes
+        let mut pending: HashSet<PathBuf> = inputs.into_iter().collect();
+        let mut last_change = std::time::Instant::now();
+        let mut first_change = Some(last_change);
+        // After a failed batch: wait before retrying, doubling up to `max_delay`
+        let mut retry_delay = Duration::ZERO;
+        let mut retry_after: Option<std::time::Instant> = None;
+
+        while !thread_stop.load(Ordering::SeqCst) {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        // Our own archives and catalog live in the output dir
+                        let changed: Vec<PathBuf> =
+                            event.paths.into_iter().filter(|p| !p.starts_with(&output_dir)).collect();
+                        if !changed.is_empty() {
+                            pending.extend(changed);
+                            last_change = std::time::Instant::now();
+                            first_change.get_or_insert(last_change);
+                        }
+                    }
+                }
+                Ok(Err(e)) => warn!("Watch error: {}", e),
+                Err(flume::RecvTimeoutError::Timeout) => {}
+                Err(flume::RecvTimeoutError::Disconnected) => break,
+            }
+
+            let due = match first_change {
+                Some(first) => last_change.elapsed() >= options.debounce || first.elapsed() >= options.max_delay,
+                None => false,
+            };
+            if !due || retry_after.is_some_and(|t| std::time::Instant::now() < t) {
+                continue;
+            }
+            first_change = None;
+            let batch: Vec<PathBuf> = pending.drain().collect();
+            match archive_watch_batch(&batch, &output_dir, &settings) {
+                Ok(archive) => {
+                    thread_archives.lock().extend(archive);
+                    retry_delay = Duration::ZERO;
+                    retry_after = None;
+                }
+                Err(e) if is_cancelled(&e) => break,
+                // Keep watching and put the batch back, so the files don't wait for their next change
+                Err(e) => {
+                    retry_delay = (retry_delay * 2).max(options.debounce).min(options.max_delay);
+                    warn!("Watch archive failed, retrying in {:?}: {:#}", retry_delay, e);
+                    pending.extend(batch);
+                    retry_after = Some(std::time::Instant::now() + retry_delay);
+                    first_change = Some(std::time::Instant::now());
+                }
+            }
+        }
+        Ok(())
+    });
+
+    Ok(WatchHandle { stop, archives, thread: Some(thread) })
+}
+
+/// Archive the new or changed files among `paths`; `None` if nothing changed
+fn archive_watch_batch(paths: &[PathBuf], output_dir: &Path, settings: &OrchestratorSettings) -> Result<Option<PathBuf>> {
+    let mut files = collect_files_filtered(paths, &settings.include_globs, &settings.exclude_globs, settings.symlinks)?;
+    files.retain(|f| !f.starts_with(output_dir));
+    files.sort();
+    files.dedup();
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let catalog_path = settings.catalog_path.clone().unwrap_or_else(|| output_dir.join(WATCH_CATALOG_NAME));
+    let changed = BackupCatalog::new(&catalog_path)?
+        .with_path_base(settings.catalog_path_base.clone())
+        .filter_files_to_backup(files)?
+        .1;
+    if changed.is_empty() {
+        return Ok(None);
+    }
+
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut archive = output_dir.join(format!("watch_{}.tar.zst", stamp));
+    let mut n = 2;
+    while archive.exists() {
+        archive = output_dir.join(format!("watch_{}_{}.tar.zst", stamp, n));
+        n += 1;
+    }
+    create_archive(&changed, &archive, settings.clone(), None)?;
+    Ok(Some(archive))
+}
+
+/// Outcome of re-encoding an existing archive
+#[derive(Debug, Clone)]
+pub struct TranscodeResult {
+    pub images_reencoded: usize,
+    pub videos_reencoded: usize,
+    pub input_archive_size: u64,
+    pub output_archive_size: u64,
+}
+
+impl TranscodeResult {
+    /// Bytes saved (negative if the new archive is larger)
+    pub fn size_delta(&self) -> i64 {
+        self.input_archive_size as i64 - self.output_archive_size as i64
+    }
+}
+
+/// Decode an archived BPG back to pixels for re-encoding
+fn decode_bpg_to_image(bpg_path: &Path) -> Result<image::DynamicImage> {
+    let (data, width, height, _format) = codecs::bpg::decode_file(&bpg_path.to_string_lossy())
+        .with_context(|| format!("Failed to decode {}", bpg_path.display()))?;
+    let rgba = image::RgbaImage::from_raw(width, height, data)
+        .ok_or_else(|| anyhow!("Decoded BPG buffer has unexpected size: {}", bpg_path.display()))?;
+
+    // Drop the alpha plane unless it carries information
+    if rgba.pixels().all(|p| p.0[3] == 255) {
+        Ok(image::DynamicImage::ImageRgb8(image::DynamicImage::ImageRgba8(rgba).to_rgb8()))
+    } else {
+        Ok(image::DynamicImage::ImageRgba8(rgba))
+    }
+}
+
+/// Replace the output size of manifest lines whose archived path is in `new_sizes`
+fn rewrite_manifest_sizes(manifest_text: &str, new_sizes: &HashMap<String, u64>) -> String {
+    let mut out = String::with_capacity(manifest_text.len());
+    for line in manifest_text.lines() {
+        let rewritten = (|| {
+            let arrow_idx = line.find(" -> ")?;
+            let after_arrow = &line[(arrow_idx + 4)..];
+            let open_paren = after_arrow.find(" (")?;
+            let rel = normalize_archive_rel_path(after_arrow[..open_paren].trim());
+            let new_size = new_sizes.get(&rel)?;
+
+            let sizes_start = arrow_idx + 4 + open_paren + 2;
+            let close_paren = line[sizes_start..].find(')')? + sizes_start;
+            let orig = line[sizes_start..close_paren].split("->").next()?.trim();
+            Some(format!(
+                "{}{} -> {}{}",
+                &line[..sizes_start],
+                orig,
+                new_size,
+                &line[close_paren..]
+            ))
+        })();
+        out.push_str(rewritten.as_deref().unwrap_or(line));
+        out.push('\n');
+    }
+    out
+}
+
+/// Recompute every whole-file and chunk hash listed in `HASHES.sha256`
+fn refresh_hashes_file(root: &Path) -> Result<()> {
+    let hashes_path = root.join("HASHES.sha256");
+    if !hashes_path.exists() {
+        return Ok(());
+    }
+
+    // Keep whichever algorithm the archive was created with
+    let algo = hash::read_hashes_algorithm(&hashes_path)?;
+    let mut hashes = Vec::new();
+    for (_, rel) in hash::read_hashes_file(&hashes_path)? {
+        let path = root.join(&rel);
+        if path.exists() {
+            hashes.push((hash::file_hex(&path, algo)?, rel));
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let old_chunks = hash::read_chunk_hashes_file(&hashes_path)?;
+    let mut seen: Vec<(String, u64)> = Vec::new();
+    for c in old_chunks {
+        if seen.iter().any(|(r, s)| *r == c.rel_path && *s == c.chunk_size) {
+            continue;
+        }
+        seen.push((c.rel_path.clone(), c.chunk_size));
+        let path = root.join(&c.rel_path);
+        if !path.exists() {
+            continue;
+        }
+        let digests = hash::file_chunks_hex(&path, c.chunk_size, algo)?;
+        chunks.extend(digests.into_iter().enumerate().map(|(i, h)| hash::ChunkHash {
+            rel_path: c.rel_path.clone(),
+            chunk_size: c.chunk_size,
+            index: i as u64,
+            hash: h,
+        }));
+    }
+
+    hash::write_hashes_file_with_chunks(&hashes, &chunks, algo, &hashes_path)
+}
+
+/// Re-encode the images and videos of an existing archive at new settings
+///
+/// Works from the archive alone: BPG images are decoded and re-encoded using
+/// the original format recorded in the metadata, videos are re-encoded with
+/// the new preset/CRF, and everything else (misc.arc, metadata) is carried over.
+/// Manifest sizes and hashes are updated before repacking.
+pub fn transcode_archive(
+    input_archive: &Path,
+    output_archive: &Path,
+    settings: OrchestratorSettings,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<TranscodeResult> {
+    if !zstd_archive::split::archive_exists(input_archive) {
+        return Err(anyhow!("Archive not found: {}", input_archive.display()));
+    }
+    // Sums the volumes of a split archive
+    let input_archive_size = zstd_archive::SplitReader::open(input_archive)
+        .with_context(|| format!("Failed to open archive: {}", input_archive.display()))?
+        .len();
+
+    let staging_root = settings.staging_dir.clone().unwrap_or_else(std::env::temp_dir);
+    let temp_dir = tempfile::Builder::new()
+        .prefix("openarc-transcode")
+        .tempdir_in(&staging_root)
+        .with_context(|| format!("Failed to create temp dir in {}", staging_root.display()))?;
+    let root = temp_dir.path();
+
+    make_zstd(3)
+        .extract_tar_zst(input_archive, root)
+        .with_context(|| format!("Failed to extract archive: {}", input_archive.display()))?;
+
+    let metadata_path = root.join("OPENARC_METADATA.json");
+    let mut metadata: ArchiveMetadata = if metadata_path.exists() {
+        serde_json::from_str(&fs::read_to_string(&metadata_path)?)
+            .context("Failed to parse archive metadata")?
+    } else {
+        ArchiveMetadata::default()
+    };
+
+    let media_dir = root.join("media");
+    let image_names: std::collections::HashSet<&str> =
+        metadata.images.iter().map(|m| m.bpg_filename.as_str()).collect();
+    // Live Photo clips and videos copied in unchanged keep their original bytes;
+    // re-encoding them would put MP4 data under the original extension
+    let untouched_videos: std::collections::HashSet<&str> = metadata
+        .videos
+        .iter()
+        .filter(|v| v.live_photo_image.is_some() || v.stored_as_is)
+        .filter_map(|v| v.proxy_path.strip_prefix("media/"))
+        .collect();
+    let mut videos = Vec::new();
+    if media_dir.exists() {
+        for entry in fs::read_dir(&media_dir)? {
+            let path = entry?.path();
+            let name = safe_file_name(&path);
+            // Encoded proxies are always .mp4, so anything else was stored as-is,
+            // including in archives written before `stored_as_is` was recorded
+            let is_proxy = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("mp4"));
+            if !image_names.contains(name.as_str())
+                && !untouched_videos.contains(name.as_str())
+                && is_proxy
+                && classify_file(&path).0 == FileClass::Video
+            {
+                videos.push(path);
+            }
+        }
+    }
+    videos.sort();
+
+    let total = metadata.images.len() + videos.len();
+    let done = std::sync::atomic::AtomicUsize::new(0);
+    let new_sizes = parking_lot::Mutex::new(HashMap::<String, u64>::new());
+    let new_chroma = parking_lot::Mutex::new(HashMap::<String, i32>::new());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(get_optimal_thread_count(settings.encoding_threads(), settings.memory_backoff_threshold()))
+        .build()
+        .context("Failed to create encoding thread pool")?;
+    let images_reencoded = pool.install(|| {
+        metadata
+            .images
+            .par_iter()
+            .map(|img_meta| -> Result<usize> {
+                let bpg_path = media_dir.join(&img_meta.bpg_filename);
+                // Only BPG images are re-encoded; AVIF ones are carried over
+                if img_meta.codec != ImageOutputFormat::Bpg || !bpg_path.exists() {
+                    return Ok(0);
+                }
+                // The decoder only yields 8-bit RGBA, so 10/12-bit images (from
+                // 16-bit sources) are carried over rather than losing precision
+                let color = codecs::bpg::read_color_info(&bpg_path.to_string_lossy())
+                    .with_context(|| format!("Failed to read {}", img_meta.bpg_filename))?;
+                if color.bit_depth > 8 {
+                    return Ok(0);
+                }
+                let img = decode_bpg_to_image(&bpg_path)?;
+                let chroma_format = chroma_format_for(&img, &settings);
+                let bpg_data = encode_image_to_bpg_bytes(&img, img_meta.original_format, chroma_format, &settings)
+                    .with_context(|| format!("Failed to re-encode {}", img_meta.bpg_filename))?;
+                fs::write(&bpg_path, &bpg_data)?;
+                new_chroma.lock().insert(img_meta.bpg_filename.clone(), chroma_format);
+                new_sizes
+                    .lock()
+                    .insert(format!("media/{}", img_meta.bpg_filename), bpg_data.len() as u64);
+
+                let n = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                if let Some(ref cb) = progress {
+                    cb(n, total, &img_meta.original_filename);
+                }
+                Ok(1)
+            })
+            .sum::<Result<usize>>()
+    })?;
+
+    let (codec, preset) = match settings.video_preset {
+        1 => (VideoCodec::H265, VideoSpeedPreset::Medium),
+        2 => (VideoCodec::H264, VideoSpeedPreset::Fast),
+        3 => (VideoCodec::H265, VideoSpeedPreset::Slow),
+        _ => (VideoCodec::H264, VideoSpeedPreset::Medium),
+    };
+    let mut videos_reencoded = 0usize;
+    for video in &videos {
+        let name = safe_file_name(video);
+        let encoded = video.with_extension("transcode.mp4");
+        let opts = FfmpegEncodeOptions {
+            codec,
+            speed: preset,
+            crf: Some(settings.video_crf as u8),
+            copy_audio: true,
+        };
+        encode_video_with_memory_constraints(video, &encoded, opts, &settings)
+            .with_context(|| format!("Failed to re-encode video {}", name))?;
+
+        // Keep the archived name so manifest and hashes still line up
+        fs::rename(&encoded, video)?;
+        new_sizes
+            .lock()
+            .insert(format!("media/{}", name), fs::metadata(video)?.len());
+        videos_reencoded += 1;
+
+        let n = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        if let Some(ref cb) = progress {
+            cb(n, total, &name);
+        }
+    }
+
+    // Keep the recorded chroma in step with the re-encoded images
+    let new_chroma = new_chroma.into_inner();
+    if !new_chroma.is_empty() {
+        for img_meta in &mut metadata.images {
+            if let Some(&chroma) = new_chroma.get(&img_meta.bpg_filename) {
+                img_meta.chroma_format = Some(chroma);
+            }
+        }
+        fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)?;
+    }
+
+    let manifest_path = root.join("MANIFEST.txt");
+    if manifest_path.exists() {
+        let text = fs::read_to_string(&manifest_path)?;
+        fs::write(&manifest_path, rewrite_manifest_sizes(&text, &new_sizes.lock()))?;
+    }
+    refresh_hashes_file(root)?;
+
+    make_zstd(3)
+        .archive_dir_tar_zst(root, output_archive)
+        .with_context(|| format!("Failed to create zstd archive at {}", output_archive.display()))?;
+
+    Ok(TranscodeResult {
+        images_reencoded,
+        videos_reencoded,
+        input_archive_size,
+        output_archive_size: fs::metadata(output_archive)?.len(),
+    })
+}
+
+/// Files every archive carries that are rebuilt rather than copied when merging
+const MERGE_REBUILT_FILES: [&str; 3] = ["MANIFEST.txt", "HASHES.sha256", "OPENARC_METADATA.json"];
+
+/// Change the password, and optionally the cipher, of an encrypted FreeARC archive
+///
+/// One pass over the archive: every block is decrypted with `old_password` and
+/// re-encrypted with `new_password` under `new_spec` ("aes-256", "blowfish", ...)
+/// without decompressing anything. The re-keyed copy replaces `archive` only
+/// once it is complete and opens with the new password.
+pub fn rekey_archive(archive: &Path, old_password: &str, new_password: &str, new_spec: &str) -> Result<()> {
+    let tmp = archive.with_extension("rekey.tmp");
+    let rekeyed = (|| -> Result<()> {
+        let source = fs::File::open(archive)
+            .with_context(|| format!("Failed to open {}", archive.display()))?;
+        let out = fs::File::create(&tmp)
+            .with_context(|| format!("Failed to create {}", tmp.display()))?;
+        let options = ArchiveOptions {
+            compression: "lzma".to_string(),
+            compression_level: 0,
+            encryption: Some(new_spec.to_string()),
+            password: Some(new_password.to_string()),
+        };
+        let writer = FreeArcWriter::rekey(
+            std::io::BufReader::new(source),
+            Some(old_password),
+            std::io::BufWriter::new(out),
+            options,
+        )?;
+        writer.into_inner().map_err(|e| anyhow!(e.into_error()))?.sync_all()?;
+
+        FreeArcReader::new(fs::File::open(&tmp)?, Some(new_password.to_string()))
+            .context("Re-keyed archive does not open with the new password")?;
+        Ok(())
+    })();
+
+    if let Err(e) = rekeyed {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, archive)
+        .with_context(|| format!("Failed to replace {}", archive.display()))
+}
+
+/// Consolidate several OpenArc archives into one
+///
+/// Each input is unpacked in turn into a staging directory and its payload moved
+/// into the merged tree. Content already present (same digest in the embedded
+/// HASHES.sha256) is stored once and recorded as a duplicate so extraction can
+/// recreate it. A name already taken by different content is namespaced with
+/// the source archive's name (`media/trip_IMG_0001_3.bpg`). Metadata, manifests,
+/// hashes and any catalogs next to the inputs are merged for the output.
+pub fn merge_archives(inputs: &[PathBuf], output: &Path) -> Result<()> {
+    if inputs.is_empty() {
+        return Err(anyhow!("No archives to merge"));
+    }
+
+    let staging = tempfile::Builder::new()
+        .prefix("openarc-merge")
+        .tempdir()
+        .context("Failed to create merge staging directory")?;
+    let merged_root = staging.path().join("merged");
+    fs::create_dir_all(&merged_root)?;
+
+    let mut algo = None;
+    let mut metadata = ArchiveMetadata::default();
+    let mut manifest = String::from("OpenArc Archive Manifest (merged)\n=================================\n");
+    let mut hashes: Vec<(String, String)> = Vec::new();
+    let mut by_hash: HashMap<String, String> = HashMap::new();
+    let mut renames_per_input: Vec<HashMap<String, String>> = Vec::new();
+
+    for (n, input) in inputs.iter().enumerate() {
+        let src = staging.path().join(format!("src{}", n));
+        make_zstd(3)
+            .extract_tar_zst(input, &src)
+            .with_context(|| format!("Failed to extract archive: {}", input.display()))?;
+
+        let metadata_path = src.join("OPENARC_METADATA.json");
+        let src_meta: Option<ArchiveMetadata> = if metadata_path.exists() {
+            let meta = serde_json::from_str(&fs::read_to_string(&metadata_path)?)
+                .with_context(|| format!("Failed to parse metadata of {}", input.display()))?;
+            Some(meta)
+        } else {
+            None
+        };
+        // Its misc.arc would be renamed away from where extraction looks for it
+        if let Some(method) = src_meta.as_ref().and_then(|m| m.misc_encryption.as_deref()) {
+            return Err(anyhow!(
+                "Cannot merge {}: its misc files are encrypted ({}); extract it first",
+                input.display(),
+                method
+            ));
+        }
+
+        let hashes_path = src.join("HASHES.sha256");
+        let (src_algo, src_hashes) = if hashes_path.exists() {
+            let src_algo = hash::read_hashes_algorithm(&hashes_path)?;
+            let map: HashMap<String, String> = hash::read_hashes_file(&hashes_path)?
+                .into_iter()
+                .map(|(h, rel)| (normalize_archive_rel_path(&rel), h))
+                .collect();
+            (src_algo, map)
+        } else {
+            (hash::HashAlgorithm::Sha256, HashMap::new())
+        };
+        // The first archive decides the algorithm of the merged hash list
+        let algo = *algo.get_or_insert(src_algo);
+        let label = archive_label(input);
+
+        // Old archive-relative path -> path in the merged archive
+        let mut renames: HashMap<String, String> = HashMap::new();
+        let mut files: Vec<PathBuf> = walkdir::WalkDir::new(&src)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .map(|e| e.into_path())
+            .collect();
+        files.sort();
+
+        for path in files {
+            let rel = normalize_archive_rel_path(&path.strip_prefix(&src)?.to_string_lossy());
+            if MERGE_REBUILT_FILES.contains(&rel.as_str()) {
+                continue;
+            }
+
+            let digest = match src_hashes.get(&rel) {
+                Some(h) if src_algo == algo => h.clone(),
+                _ => hash::file_hex(&path, algo)?,
+            };
+            if let Some(existing) = by_hash.get(&digest) {
+                renames.insert(rel, existing.clone());
+                continue;
+            }
+
+            let mut target = rel.clone();
+            let mut attempt = 1;
+            while merged_root.join(&target).exists() {
+                target = namespaced_rel_path(&rel, &label, attempt);
+                attempt += 1;
+            }
+            let dest = merged_root.join(&target);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&path, &dest)
+                .with_context(|| format!("Failed to stage {} from {}", rel, input.display()))?;
+
+            by_hash.insert(digest.clone(), target.clone());
+            hashes.push((digest, target.clone()));
+            renames.insert(rel, target);
+        }
+
+        if let Some(src_meta) = src_meta {
+            merge_archive_metadata(&mut metadata, src_meta, &renames);
+        }
+
+        let manifest_path = src.join("MANIFEST.txt");
+        if manifest_path.exists() {
+            manifest.push_str(&format!("\n# From {}\n", input.display()));
+            for line in fs::read_to_string(&manifest_path)?.lines() {
+                manifest.push_str(&rename_manifest_line(line, &renames));
+                manifest.push('\n');
+            }
+        }
+
+        fs::remove_dir_all(&src).ok();
+        renames_per_input.push(renames);
+    }
+
+    let algo = algo.unwrap_or_default();
+    let manifest_path = merged_root.join("MANIFEST.txt");
+    fs::write(&manifest_path, manifest)?;
+    hashes.push((hash::file_hex(&manifest_path, algo)?, "MANIFEST.txt".to_string()));
+    hashes.sort_by(|a, b| a.1.cmp(&b.1));
+    hash::write_hashes_file_with_chunks(&hashes, &[], algo, merged_root.join("HASHES.sha256"))?;
+    fs::write(
+        merged_root.join("OPENARC_METADATA.json"),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    make_zstd(3)
+        .archive_dir_tar_zst(&merged_root, output)
+        .with_context(|| format!("Failed to create zstd archive at {}", output.display()))?;
+
+    merge_catalogs(inputs, output, &renames_per_input)
+}
+
+/// Archive name without `.tar.zst`/extension, safe to use in file names
+fn archive_label(archive: &Path) -> String {
+    let name = archive
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = name
+        .strip_suffix(".tar.zst")
+        .or_else(|| name.rsplit_once('.').map(|(s, _)| s))
+        .unwrap_or(&name);
+    stem.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// `media/IMG_1.bpg` -> `media/trip_IMG_1.bpg` (with a counter after the first attempt)
+fn namespaced_rel_path(rel: &str, label: &str, attempt: usize) -> String {
+    let (dir, name) = match rel.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), rel),
+    };
+    if attempt <= 1 {
+        format!("{}{}_{}", dir, label, name)
+    } else {
+        format!("{}{}{}_{}", dir, label, attempt, name)
+    }
+}
+
+/// Point a `<original> -> <rel> (...)` manifest line at the file's merged path
+fn rename_manifest_line(line: &str, renames: &HashMap<String, String>) -> String {
+    let Some(arrow) = line.find(" -> ") else {
+        return line.to_string();
+    };
+    let after = &line[arrow + 4..];
+    let Some(paren) = after.find(" (") else {
+        return line.to_string();
+    };
+    let rel = normalize_archive_rel_path(after[..paren].trim());
+    match renames.get(&rel) {
+        Some(new_rel) if *new_rel != rel => format!("{} -> {}{}", &line[..arrow], new_rel, &after[paren..]),
+        _ => line.to_string(),
+    }
+}
+
+/// Fold one source archive's metadata into the merged metadata
+fn merge_archive_metadata(
+    merged: &mut ArchiveMetadata,
+    source: ArchiveMetadata,
+    renames: &HashMap<String, String>,
+) {
+    let renamed = |rel: &str| renames.get(rel).cloned().unwrap_or_else(|| rel.to_string());
+    let mut stored: std::collections::HashSet<String> = merged
+        .images
+        .iter()
+        .map(|i| format!("media/{}", i.bpg_filename))
+        .chain(merged.videos.iter().map(|v| v.proxy_path.clone()))
+        .collect();
+
+    for mut image in source.images {
+        let rel = renamed(&format!("media/{}", image.bpg_filename));
+        if !stored.insert(rel.clone()) {
+            // Same content already archived under another entry
+            merged.duplicates.push(DuplicateMetadata {
+                original_filename: image.original_filename,
+                canonical_path: rel,
+            });
+            continue;
+        }
+        image.bpg_filename = rel.strip_prefix("media/").unwrap_or(&rel).to_string();
+        merged.images.push(image);
+    }
+    for mut video in source.videos {
+        let rel = renamed(&video.proxy_path);
+        if !stored.insert(rel.clone()) {
+            merged.duplicates.push(DuplicateMetadata {
+                original_filename: video.original_filename,
+                canonical_path: rel,
+            });
+            continue;
+        }
+        video.proxy_path = rel;
+        video.original_path = video.original_path.map(|p| renamed(&p));
+        merged.videos.push(video);
+    }
+    for mut dup in source.duplicates {
+        dup.canonical_path = renamed(&dup.canonical_path);
+        merged.duplicates.push(dup);
+    }
+
+    if let Some(report) = source.size_report {
+        merged.size_report.get_or_insert_with(SizeReport::default).merge(&report);
+    }
+    if let Some(summary) = source.location_summary {
+        merged.location_summary.get_or_insert_with(LocationSummary::default).merge(&summary);
+    }
+    for (rel, secs) in source.mtimes {
+        merged.mtimes.entry(renamed(&rel)).or_insert(secs);
+    }
+    merged.symlinks.extend(source.symlinks);
+    merged.references.extend(source.references);
+}
+
+/// Carry catalog rows of the inputs over to the merged archive's catalog
+fn merge_catalogs(inputs: &[PathBuf], output: &Path, renames: &[HashMap<String, String>]) -> Result<()> {
+    let sources: Vec<(usize, PathBuf)> = inputs
+        .iter()
+        .enumerate()
+        .map(|(n, input)| (n, input.with_extension("catalog.sqlite")))
+        .filter(|(_, db)| db.exists())
+        .collect();
+    if sources.is_empty() {
+        return Ok(());
+    }
+
+    let output_name = output.file_name().map(|n| n.to_string_lossy().to_string());
+    let mut backups = Vec::new();
+    let mut mappings = Vec::new();
+    for (n, db) in sources {
+        let input_name = inputs[n].file_name().map(|f| f.to_string_lossy().to_string());
+        let mut source = BackupCatalog::new(&db)?;
+        backups.extend(
+            source
+                .list_all()?
+                .into_iter()
+                .filter(|e| e.archive_id == input_name)
+                .map(|e| BackupEntry {
+                    archive_id: output_name.clone(),
+                    ..e
+                }),
+        );
+
+        let tracker = ArchiveTracker::new(source.get_connection_mut())?;
+        if let Some(id) = tracker
+            .get_archive_by_path(&inputs[n].to_string_lossy())?
+            .and_then(|r| r.id)
+        {
+            for mut file in tracker.get_archive_files(id)? {
+                if let Some(new_rel) = renames[n].get(&file.file_path) {
+                    file.file_path = new_rel.clone();
+                }
+                mappings.push(file);
+            }
+        }
+    }
+
+    let mut catalog = BackupCatalog::new(output.with_extension("catalog.sqlite"))?;
+    let record = ArchiveRecord {
+        id: None,
+        archive_path: output.to_string_lossy().to_string(),
+        archive_size: fs::metadata(output)?.len(),
+        creation_date: 0, // Will be set by the database
+        original_location: output
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string()),
+        destination_location: None,
+        description: Some(format!("Merged from {} archives", inputs.len())),
+        file_count: mappings.len() as u32,
+    };
+    catalog.in_transaction(|cat| {
+        cat.record_backups(backups)?;
+        let mut tracker = ArchiveTracker::new(cat.get_connection_mut())?;
+        let archive_id = tracker.record_archive(record)?;
+        let mappings = mappings
+            .into_iter()
+            .map(|m| ArchiveFileMapping { id: None, archive_id, ..m })
+            .collect();
+        tracker.record_archive_files(archive_id, mappings)
+    })?;
+    catalog.close()
+}
+
+/// Pack the misc files into `output_arc`, encrypted when `encryption` (spec,
+/// password) is given
+///
+/// Returns the cipher method string of an encrypted archive, which extraction
+/// needs to check the password before reading any block.
+fn create_misc_arc(
+    processed: &[ProcessedFile],
+    output_arc: &Path,
+    compression_level: i32,
+    encryption: Option<&(String, String)>,
+) -> Result<Option<String>> {
+    let misc: Vec<&ProcessedFile> = processed.iter().filter(|p| p.class == FileClass::Misc).collect();
+    if misc.is_empty() {
+        return Ok(None);
+    }
+
+    let f = std::fs::File::create(output_arc)
+        .with_context(|| format!("Failed to create {}", output_arc.display()))?;
+    let mut writer = std::io::BufWriter::new(f);
+
+    let mut arc = FreeArcWriter::new(
+        &mut writer,
+        ArchiveOptions {
+            compression: "lzma".to_string(),
+            compression_level,
+            encryption: encryption.map(|(spec, _)| spec.clone()),
+            password: encryption.map(|(_, password)| password.clone()),
+        },
+    )?;
+
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+
+    for item in misc {
+        let data = std::fs::read(&item.output_path)?;
+        let mut name = item
+            .output_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("file")
+            .to_string();
+
+        let c = name_counts.entry(name.clone()).or_insert(0);
+        if *c > 0 {
+            name = format!("{}_{}", *c, name);
+        }
+        *c += 1;
+
+        if item.skipped_processing {
+            arc.add_file_stored(&name, &data)?;
+        } else {
+            arc.add_file(&name, &data)?;
+        }
+    }
+
+    let inner = arc.finish()?;
+    inner.flush()?;
+
+    let Some((_, password)) = encryption else {
+        return Ok(None);
+    };
+    // Each block carries its own salt; any one of them identifies the cipher and password
+    let reader = FreeArcReader::new(fs::File::open(output_arc)?, Some(password.clone()))
+        .with_context(|| format!("Encrypted {} does not open", output_arc.display()))?;
+    let method = reader
+        .directory
+        .data_blocks
+        .first()
+        .map(|b| split_compressor_encryption(&b.compressor).1)
+        .ok_or_else(|| anyhow!("{} has no data blocks", output_arc.display()))?;
+    Ok(Some(method))
+}
+
+fn write_manifest(processed: &[ProcessedFile], skipped: &[PathBuf], manifest_path: &Path) -> Result<()> {
+    let mut f = std::fs::File::create(manifest_path)?;
+
+    writeln!(f, "OpenArc Archive Manifest")?;
+    writeln!(f, "========================")?;
+    writeln!(f)?;
+
+    writeln!(f, "Processed files: {}", processed.len())?;
+    writeln!(f, "Skipped by catalog: {}", skipped.len())?;
+    writeln!(f)?;
+
+    for p in processed {
+        let format_info = p.original_format
+            .map(|f| format!(" [orig: {:?}]", f))
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{} -> {} ({} -> {}){}{}",
+            p.original_path.display(),
+            p.archived_rel_path,
+            p.original_size,
+            p.output_size,
+            if p.skipped_processing { " [skipped_processing]" } else { "" },
+            format_info
+        )?;
+        if let Some((ref rel, _)) = p.original_copy {
+            writeln!(
+                f,
+                "{} -> {} ({} -> {}) [original_copy]",
+                p.original_path.display(),
+                rel,
+                p.original_size,
+                p.original_size
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_hashes(
+    processed: &[ProcessedFile],
+    hashes_path: &Path,
+    misc_arc_path: &Path,
+    manifest_path: &Path,
+    chunk_size: Option<u64>,
+    algo: hash::HashAlgorithm,
+    misc_encrypted: bool,
+) -> Result<()> {
+    let mut hashes: Vec<(String, String)> = Vec::new();
+    let mut chunks: Vec<hash::ChunkHash> = Vec::new();
+
+    // Encrypted misc files aren't in the tar; the misc.arc digest covers them
+    for p in processed.iter().filter(|p| !(misc_encrypted && p.class == FileClass::Misc)) {
+        if let Some(ref h) = p.sha256 {
+            hashes.push((h.clone(), p.archived_rel_path.clone()));
+            if let Some((ref rel, ref path)) = p.original_copy {
+                hashes.push((hash::file_hex(path, algo)?, rel.clone()));
+            }
+
+            // Only large files benefit from locating damage within them
+            if let Some(size) = chunk_size.filter(|&c| c > 0 && p.output_size > c) {
+                let digests = hash::file_chunks_hex(&p.output_path, size, algo)?;
+                chunks.extend(digests.into_iter().enumerate().map(|(i, h)| hash::ChunkHash {
+                    rel_path: p.archived_rel_path.clone(),
+                    chunk_size: size,
+                    index: i as u64,
+                    hash: h,
+                }));
+            }
+        }
+    }
+
+    if misc_arc_path.exists() {
+        let h = hash::file_hex(misc_arc_path, algo)?;
+        hashes.push((h, "misc.arc".to_string()));
+    }
+
+    if manifest_path.exists() {
+        let h = hash::file_hex(manifest_path, algo)?;
+        hashes.push((h, "MANIFEST.txt".to_string()));
+    }
+
+    hash::write_hashes_file_with_chunks(&hashes, &chunks, algo, hashes_path)?;
+    Ok(())
+}
+
+fn record_catalog_entries(
+    catalog: &mut BackupCatalog,
+    processed: &[ProcessedFile],
+    output_archive: &Path,
+    algo: hash::HashAlgorithm,
+) -> Result<()> {
+    let mut entries = Vec::new();
+    let archive_id = output_archive
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_string());
+
+    for p in processed {
+        let md = fs::metadata(&p.original_path)?;
+        let mtime_secs = mtime_secs(&md)?;
+
+        entries.push(BackupEntry {
+            path: catalog.catalog_key(&p.original_path),
+            size: md.len(),
+            mtime_secs,
+            // The catalog column only holds SHA-256 digests
+            sha256: p.sha256.clone().filter(|_| algo == hash::HashAlgorithm::Sha256),
+            backed_up_at: 0,
+            archive_id: archive_id.clone(),
+        });
+    }
+
+    catalog.record_backups(entries)
+}
+
+/// Catalog files left out as already archived under the archive holding their content
+fn record_catalog_references(catalog: &mut BackupCatalog, referenced: &[(PathBuf, String)]) -> Result<()> {
+    let mut entries = Vec::with_capacity(referenced.len());
+    for (path, archive) in referenced {
+        let md = fs::metadata(path)?;
+        entries.push(BackupEntry {
+            path: catalog.catalog_key(path),
+            size: md.len(),
+            mtime_secs: mtime_secs(&md)?,
+            // `sha256` describes archived bytes, and none were archived for this path
+            sha256: None,
+            backed_up_at: 0,
+            archive_id: Some(archive.clone()),
+        });
+    }
+    catalog.record_backups(entries)
+}
+
+/// Metadata of the original files behind misc and stored-as-is entries, keyed by
+/// archive-relative path; unreadable originals keep the staged copy's metadata
+fn original_metadata(processed: &[ProcessedFile]) -> HashMap<PathBuf, EntryMetadata> {
+    let mut out = HashMap::new();
+    for p in processed {
+        if p.class == FileClass::Misc || p.skipped_processing {
+            if let Ok(meta) = EntryMetadata::read(&p.original_path) {
+                out.insert(PathBuf::from(&p.archived_rel_path), meta);
+            }
+        }
+        if let Some((ref rel, _)) = p.original_copy {
+            if let Ok(meta) = EntryMetadata::read(&p.original_path) {
+                out.insert(PathBuf::from(rel), meta);
+            }
+        }
+    }
+    out
+}
+
+/// Modification time of each original, keyed by the archive-relative path it
+/// was stored under (including kept video originals)
+fn original_mtimes(processed: &[ProcessedFile]) -> BTreeMap<String, u64> {
+    let mut out = BTreeMap::new();
+    for p in processed {
+        let Some(secs) = fs::metadata(&p.original_path).ok().and_then(|md| mtime_secs(&md).ok()) else {
+            continue;
+        };
+        out.insert(p.archived_rel_path.clone(), secs);
+        if let Some((ref rel, _)) = p.original_copy {
+            out.insert(rel.clone(), secs);
+        }
+    }
+    out
+}
+
+/// Link targets of the links kept under `SymlinkPolicy::Preserve`, stored next to misc files
+fn symlink_metadata(links: &[PathBuf]) -> Vec<SymlinkMetadata> {
+    let mut out = Vec::with_capacity(links.len());
+    for link in links {
+        match fs::read_link(link) {
+            Ok(target) => out.push(SymlinkMetadata {
+                path: format!("misc/{}", safe_file_name(link)),
+                target: target.to_string_lossy().into_owned(),
+                target_is_dir: link.is_dir(),
+            }),
+            Err(e) => warn!("read_link_failed file={} error={}", link.display(), e),
+        }
+    }
+    out
+}
+
+fn mtime_secs(md: &fs::Metadata) -> Result<u64> {
+    Ok(md
+        .modified()?
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0))
+}
+
+fn make_zstd(level: i32) -> ZstdCodec {
+    make_zstd_with_checksum(level, true)
+}
+
+fn make_zstd_with_checksum(level: i32, include_checksum: bool) -> ZstdCodec {
+    let mut opts = ZstdOptions::default();
+    opts.level = level;
+    opts.include_checksum = include_checksum;
+    // The outer archive can be tens of GB; use every core for packing it
+    opts.threads = Threads::Auto;
+    ZstdCodec::new(opts)
+}
+
+/// Update the destination location of an archive in the tracking database
+pub fn update_archive_destination(
+    catalog_db_path: &Path,
+    archive_path: &str,
+    destination_path: &str,
+) -> Result<()> {
+    let mut catalog = BackupCatalog::new(catalog_db_path)?;
+    if let Ok(mut tracker) = ArchiveTracker::new(catalog.get_connection_mut()) {
+        tracker.update_archive_destination(archive_path, destination_path)?;
+    }
+    Ok(())
+}
+
+/// Update the description of an archive in the tracking database
+pub fn update_archive_description(
+    catalog_db_path: &Path,
+    archive_path: &str,
+    description: &str,
+) -> Result<()> {
+    let mut catalog = BackupCatalog::new(catalog_db_path)?;
+    if let Ok(mut tracker) = ArchiveTracker::new(catalog.get_connection_mut()) {
+        tracker.update_archive_description(archive_path, description)?;
+    }
+    Ok(())
+}
+
+/// Write every catalog entry to `output_path` as JSON (see `BackupCatalog::export_json`)
+///
+/// The catalog is opened read-only; a missing one is an error rather than an
+/// empty export.
+pub fn export_catalog_json(catalog_db_path: &Path, output_path: &Path) -> Result<()> {
+    if !catalog_db_path.is_file() {
+        return Err(anyhow!("Catalog not found: {}", catalog_db_path.display()));
+    }
+    let catalog = BackupCatalog::open_read_only(catalog_db_path)?;
+    let file = fs::File::create(output_path)
+        .with_context(|| format!("Failed to create {}", output_path.display()))?;
+    catalog
+        .export_json(file)
+        .with_context(|| format!("Failed to export catalog to {}", output_path.display()))
+}
+
+/// Forget an archive (and which files it holds) in the catalog database
+pub fn delete_archive_record(catalog_db_path: &Path, archive_id: i64) -> Result<()> {
+    let mut catalog = BackupCatalog::new(catalog_db_path)?;
+    ArchiveTracker::new(catalog.get_connection_mut())?.delete_archive(archive_id)
+}
+
+/// Map each deduplicated input to the archive path of its canonical copy
+fn duplicate_metadata(
+    duplicates_of: &HashMap<PathBuf, PathBuf>,
+    processed: &[ProcessedFile],
+) -> Vec<DuplicateMetadata> {
+    let archived: HashMap<&Path, &str> = processed
+        .iter()
+        .map(|p| (p.original_path.as_path(), p.archived_rel_path.as_str()))
+        .collect();
+
+    let mut duplicates: Vec<DuplicateMetadata> = duplicates_of
+        .iter()
+        .filter_map(|(dup, canon)| {
+            let canonical_path = archived.get(canon.as_path())?;
+            Some(DuplicateMetadata {
+                original_filename: dup.file_name()?.to_string_lossy().to_string(),
+                canonical_path: canonical_path.to_string(),
+            })
+        })
+        .collect();
+    duplicates.sort_by(|a, b| a.original_filename.cmp(&b.original_filename));
+    duplicates
+}
+
+/// Result of archive extraction
+#[derive(Debug)]
+pub struct ExtractionResult {
+    pub files_extracted: usize,
+    pub total_size: u64,
+    pub decoded_files: usize,
+    /// Files checked against `HASHES.sha256` (0 unless `ExtractionSettings::verify` is set)
+    pub verified_files: usize,
+}
+
+/// Settings for extraction with decoding
+#[derive(Clone, Debug)]
+pub struct ExtractionSettings {
+    /// Decode BPG files back to original formats
+    pub decode_images: bool,
+    /// Quality for HEIC re-encoding (1-100)
+    pub heic_quality: u8,
+    /// Quality for JPEG output (1-100)
+    pub jpeg_quality: u8,
+    /// How deduplicated files are recreated next to their canonical copy
+    pub dedup_as: DedupMode,
+    /// Only decode images whose original file name matches this glob
+    pub decode_filter: Option<Glob>,
+    /// Only decode images that were originally one of these formats
+    pub decode_formats: Option<Vec<OriginalImageFormat>>,
+    /// Restore permissions, xattrs and (when privileged) owners recorded in the archive
+    pub restore_metadata: bool,
+    /// Checked between entries and between decoded images; once set, extraction
+    /// stops with a `Cancelled` error and leaves what was already written
+    pub cancel_flag: Option<Arc<AtomicBool>>,
+    /// Check extracted files against `HASHES.sha256` before decoding touches them,
+    /// failing with the list of mismatches
+    pub verify: bool,
+    /// Password for archives created with `OrchestratorSettings::encryption`
+    pub password: Option<String>,
+    /// Restore non-JPEG images as WebP instead of PNG/HEIC
+    pub prefer_webp: bool,
+    /// Quality for lossy WebP output (1-100)
+    pub webp_quality: u8,
+    /// Write lossless WebP instead; `webp_quality` is ignored
+    pub webp_lossless: bool,
+}
+
+impl ExtractionSettings {
+    /// Whether `image` passes the decode filters; `matcher` is the compiled `decode_filter`
+    fn should_decode(&self, image: &ImageMetadata, matcher: Option<&GlobMatcher>) -> bool {
+        if let Some(ref formats) = self.decode_formats {
+            if !formats.contains(&image.original_format) {
+                return false;
+            }
+        }
+        match matcher {
+            Some(m) => m.is_match(&image.original_filename),
+            None => true,
+        }
+    }
+}
+
+impl Default for ExtractionSettings {
+    fn default() -> Self {
+        Self {
+            decode_images: true,
+            heic_quality: 90,
+            jpeg_quality: 92,
+            dedup_as: DedupMode::Links,
+            decode_filter: None,
+            decode_formats: None,
+            restore_metadata: false,
+            cancel_flag: None,
+            verify: false,
+            password: None,
+            prefer_webp: false,
+            webp_quality: 85,
+            webp_lossless: false,
+        }
+    }
+}
+
+/// Representation of deduplicated files on extraction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DedupMode {
+    /// Hard links to the canonical file, falling back to copies where unsupported
+    Links,
+    /// Independent copies
+    Copies,
+}
+
+/// Recreate `target` from `source`; returns true if it ended up a hard link
+/// Create a symbolic link at `link` pointing to `target`
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path, _target_is_dir: bool) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Create a symbolic link at `link` pointing to `target`; needs Developer
+/// Mode or the symlink privilege
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path, target_is_dir: bool) -> std::io::Result<()> {
+    if target_is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(_target: &Path, _link: &Path, _target_is_dir: bool) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "symbolic links not supported"))
+}
+
+fn place_duplicate(source: &Path, target: &Path, mode: DedupMode) -> Result<bool> {
+    place_duplicate_with(source, target, mode, |s, t| fs::hard_link(s, t))
+}
+
+fn place_duplicate_with(
+    source: &Path,
+    target: &Path,
+    mode: DedupMode,
+    link: impl Fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<bool> {
+    if mode == DedupMode::Links {
+        match link(source, target) {
+            Ok(()) => return Ok(true),
+            Err(e) => warn!(
+                "hard_link_failed file={} error={}, copying instead",
+                target.display(),
+                e
+            ),
+        }
+    }
+    fs::copy(source, target)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), target.display()))?;
+    Ok(false)
+}
+
+/// Extract a .tar.zst archive to a directory with progress reporting.
+pub fn extract_archive(
+    archive_path: &Path,
+    output_dir: &Path,
+    compression_level: i32,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<ExtractionResult> {
+    let settings = ExtractionSettings::default();
+    extract_archive_with_decoding(archive_path, output_dir, compression_level, settings, progress)
+}
+
+/// Extract archive and decode images back to original formats
+pub fn extract_archive_with_decoding(
+    archive_path: &Path,
+    output_dir: &Path,
+    compression_level: i32,
+    settings: ExtractionSettings,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<ExtractionResult> {
+    if !zstd_archive::split::archive_exists(archive_path) {
+        return Err(anyhow!("Archive not found: {}", archive_path.display()));
+    }
+    check_cancelled(&settings.cancel_flag)?;
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+
+    if let Some(ref cb) = progress {
+        cb(0, 1, "Extracting archive...");
+    }
+
+    // Extract the archive, reporting compressed bytes read so large archives don't look stuck
+    let mut opts = make_zstd(compression_level).options().clone();
+    opts.preserve_metadata = settings.restore_metadata;
+    let zstd = ZstdCodec::new(opts);
+    zstd.extract_tar_zst_with_progress(archive_path, output_dir, |read, total| {
+        if let Some(ref cb) = progress {
+            cb(read as usize, total as usize, "Extracting archive...");
+        }
+    })
+    .with_context(|| format!("Failed to extract archive: {}", archive_path.display()))?;
+    check_cancelled(&settings.cancel_flag)?;
+
+    // Misc files of an encrypted archive only exist inside misc.arc
+    let misc_encryption = fs::read_to_string(output_dir.join("OPENARC_METADATA.json"))
+        .ok()
+        .and_then(|json| serde_json::from_str::<ArchiveMetadata>(&json).ok())
+        .and_then(|meta| meta.misc_encryption);
+    if let Some(method) = misc_encryption {
+        let arc = fs::File::open(output_dir.join("misc.arc")).context("Encrypted archive has no misc.arc")?;
+        unpack_encrypted_misc(arc, &method, settings.password.as_deref(), output_dir, &|_: &str| true)?;
+    }
+
+    // Decoding deletes the BPG files, so verify while they're as archived
+    let verified_files = if settings.verify {
+        let hashes_path = output_dir.join(hash::HASHES_FILE_NAME);
+        let hashes_text = fs::read_to_string(&hashes_path)
+            .with_context(|| format!("Archive has no {} to verify against", hash::HASHES_FILE_NAME))?;
+        verify_extracted(output_dir, &hashes_text, &|_: &str| true, &progress)?
+    } else {
+        0
+    };
+
+    let mut result = finish_extraction(output_dir, &settings, &progress)?;
+    result.verified_files = verified_files;
+    Ok(result)
+}
+
+/// Extract only the entries under `subtree_prefix` (e.g. `media/2023`)
+///
+/// The archive is streamed once and only matching entries are written, so
+/// pulling one folder out of a large archive needs no room for the rest. The
+/// prefix matches whole path components: `media/2023` selects `media/2023/...`
+/// but not `media/20230/...`. Images in the subtree are decoded and duplicates
+/// recreated as with `extract_archive_with_decoding`.
+pub fn extract_subtree(
+    archive_path: &Path,
+    subtree_prefix: &str,
+    output_dir: &Path,
+    settings: ExtractionSettings,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<ExtractionResult> {
+    let prefix = normalize_subtree_prefix(subtree_prefix);
+    let (matched, result) =
+        extract_selected(archive_path, output_dir, &settings, &progress, |rel| in_subtree(rel, &prefix))?;
+    if matched == 0 {
+        return Err(anyhow!("No entries under {} in {}", subtree_prefix, archive_path.display()));
+    }
+    Ok(result)
+}
+
+/// Extract only the entries whose archive path matches any of `patterns`
+///
+/// Patterns are globs over the normalized archive path (`media/clip_3.mp4`,
+/// `misc/notes.txt`); `*` also crosses `/`, so `*.mp4` or `media/*` select
+/// across folders. The archive is streamed once however many patterns are
+/// given. Matched images are decoded and duplicates recreated as with
+/// `extract_archive_with_decoding`. Returns how many entries were written.
+pub fn extract_matching(
+    archive_path: &Path,
+    output_dir: &Path,
+    patterns: &[String],
+    settings: ExtractionSettings,
+    progress: Option<Arc<ProgressFn>>,
+) -> Result<usize> {
+    if patterns.is_empty() {
+        return Err(anyhow!("No extraction patterns given"));
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let normalized = normalize_archive_rel_path(pattern.trim());
+        builder.add(Glob::new(&normalized).with_context(|| format!("Invalid pattern: {}", pattern))?);
+    }
+    let set = builder.build().context("Failed to compile extraction patterns")?;
+
+    let (matched, _) = extract_selected(archive_path, output_dir, &settings, &progress, |rel| set.is_match(rel))?;
+    Ok(matched)
+}
+
+/// Stream `archive_path` once, writing entries whose normalized path passes
+/// `select`, then decode and place duplicates for what was written
+///
+/// Returns the number of entries written alongside the extraction result.
+fn extract_selected(
+    archive_path: &Path,
+    output_dir: &Path,
+    settings: &ExtractionSettings,
+    progress: &Option<Arc<ProgressFn>>,
+    select: impl Fn(&str) -> bool,
+) -> Result<(usize, ExtractionResult)> {
+    let file = zstd_archive::SplitReader::open(archive_path)
+        .with_context(|| format!("Failed to open archive: {}", archive_path.display()))?;
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+
+    let decoder = zstd_archive::archive_decoder(file)
+        .with_context(|| format!("Failed to create zstd decoder for {}", archive_path.display()))?;
+    let mut archive = tar::Archive::new(decoder);
+    archive.set_preserve_permissions(settings.restore_metadata);
+    archive.set_unpack_xattrs(settings.restore_metadata);
+    if let Some(ref cb) = progress {
+        cb(0, 1, "Extracting archive...");
+    }
+
+    let mut metadata: Option<ArchiveMetadata> = None;
+    let mut hashes_text: Option<String> = None;
+    let mut misc_arc: Option<fs::File> = None;
+    let mut matched = 0usize;
+    for entry in archive.entries().context("Failed to read tar entries")? {
+        check_cancelled(&settings.cancel_flag)?;
+        let mut entry = entry?;
+        let rel = normalize_archive_rel_path(&entry.path()?.to_string_lossy());
+        if rel == "OPENARC_METADATA.json" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            metadata = serde_json::from_str(&content).ok();
+        } else if rel == hash::HASHES_FILE_NAME && settings.verify {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            if select(&rel) {
+                fs::write(output_dir.join(&rel), &content)?;
+                matched += 1;
+            }
+            hashes_text = Some(content);
+        } else if rel == "misc.arc" && !matches!(metadata, Some(ref m) if m.misc_encryption.is_none()) {
+            // Kept until the metadata says whether it holds encrypted misc files,
+            // spooled to disk when it isn't being extracted itself
+            let mut spool = if select(&rel) {
+                matched += 1;
+                fs::File::options()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(output_dir.join(&rel))?
+            } else {
+                tempfile::tempfile().context("Failed to spool misc.arc")?
+            };
+            std::io::copy(&mut entry, &mut spool)?;
+            misc_arc = Some(spool);
+        } else if select(&rel) {
+            // Entries that would land outside output_dir fail rather than being skipped
+            let target = zstd_archive::unpack_in_checked(&mut entry, output_dir)
+                .with_context(|| format!("Failed to extract {}", rel))?;
+            if settings.restore_metadata {
+                zstd_archive::entry_metadata::restore_owner(&target, entry.header());
+            }
+            if !entry.header().entry_type().is_dir() {
+                matched += 1;
+            }
+        }
+    }
+    let misc_encryption = metadata.as_ref().and_then(|m| m.misc_encryption.as_deref());
+    if let (Some(method), Some(arc)) = (misc_encryption, misc_arc) {
+        matched += unpack_encrypted_misc(arc, method, settings.password.as_deref(), output_dir, &select)?;
+    }
+    if matched == 0 {
+        return Ok((0, empty_extraction_result()));
+    }
+
+    // Hand the decoder only what describes the extracted files
+    if let Some(mut meta) = metadata {
+        meta.images.retain(|i| select(&format!("media/{}", i.bpg_filename)));
+        meta.videos.retain(|v| select(&v.proxy_path));
+        meta.duplicates.retain(|d| select(&d.canonical_path));
+        meta.mtimes.retain(|rel, _| select(rel));
+        meta.symlinks.retain(|l| select(&l.path));
+        fs::write(output_dir.join("OPENARC_METADATA.json"), serde_json::to_string_pretty(&meta)?)?;
+    }
+
+    let verified_files = if settings.verify {
+        let hashes_text = hashes_text
+            .ok_or_else(|| anyhow!("Archive has no {} to verify against", hash::HASHES_FILE_NAME))?;
+        verify_extracted(output_dir, &hashes_text, &select, progress)?
+    } else {
+        0
+    };
+
+    let mut result = finish_extraction(output_dir, settings, progress)?;
+    result.verified_files = verified_files;
+    Ok((matched, result))
+}
+
+/// Write the misc files held in an encrypted `misc.arc` to `output_dir/misc`
+///
+/// `method` is the cipher method string from the archive metadata; the
+/// password is checked against it before any block is read, so a wrong one
+/// fails with `CryptoError::InvalidPassword`. Only files whose `misc/<name>`
+/// path passes `select` are written; returns how many. Each data block is
+/// decrypted into a temporary file and the files are copied out of it, so
+/// at most one compressed block is held in memory.
+fn unpack_encrypted_misc<R: Read + Seek>(
+    arc: R,
+    method: &str,
+    password: Option<&str>,
+    output_dir: &Path,
+    select: &dyn Fn(&str) -> bool,
+) -> Result<usize> {
+    let password = password.ok_or(CryptoError::PasswordRequired)?;
+    create_decryptor(method, password, None, None)?;
+    let reader = FreeArcReader::new(arc, Some(password.to_string())).context("Failed to open misc.arc")?;
+
+    let misc_dir = output_dir.join("misc");
+    let mut written = 0usize;
+    for block_idx in 0..reader.directory.data_blocks.len() {
+        // Names come from the archive; keep them inside misc/
+        let wanted: Vec<_> = reader
+            .directory
+            .files
+            .iter()
+            .filter(|f| !f.is_dir && f.data_block_index == Some(block_idx))
+            .map(|f| (f, safe_file_name(Path::new(&f.name))))
+            .filter(|(_, name)| select(&format!("misc/{}", name)))
+            .collect();
+        if wanted.is_empty() {
+            continue;
+        }
+
+        let mut block = tempfile::tempfile().context("Failed to create misc.arc spool file")?;
+        reader
+            .extract_block_to(block_idx, &mut block)
+            .with_context(|| format!("Failed to read block {} of misc.arc", block_idx))?;
+        fs::create_dir_all(&misc_dir)?;
+        for (file, name) in wanted {
+            block.seek(SeekFrom::Start(file.offset_in_block))?;
+            let mut out = fs::File::create(misc_dir.join(&name))?;
+            let copied = std::io::copy(&mut (&block).take(file.size), &mut out)?;
+            if copied != file.size {
+                return Err(anyhow!("misc/{} is truncated in misc.arc", name));
+            }
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+fn empty_extraction_result() -> ExtractionResult {
+    ExtractionResult {
+        files_extracted: 0,
+        total_size: 0,
+        decoded_files: 0,
+        verified_files: 0,
+    }
+}
+
+/// Check the files passing `select` listed in `hashes_text` against what was
+/// extracted to `output_dir`; returns how many were verified
+fn verify_extracted(
+    output_dir: &Path,
+    hashes_text: &str,
+    select: &dyn Fn(&str) -> bool,
+    progress: &Option<Arc<ProgressFn>>,
+) -> Result<usize> {
+    if let Some(ref cb) = progress {
+        cb(0, 1, "Verifying hashes...");
+    }
+    let (verified, failures) = hash::verify_extracted_files(output_dir, hashes_text, select)?;
+    if !failures.is_empty() {
+        let listed: Vec<String> = failures
+            .iter()
+            .map(|f| match f.kind {
+                hash::VerificationFailureKind::Mismatch { ref expected, ref actual } => {
+                    format!("{} (expected {}, got {})", f.rel_path, expected, actual)
+                }
+                hash::VerificationFailureKind::Missing => format!("{} (missing)", f.rel_path),
+            })
+            .collect();
+        return Err(VerificationFailed(format!(
+            "Hash verification failed for {} of {} files: {}",
+            failures.len(),
+            verified,
+            listed.join(", ")
+        ))
+        .into());
+    }
+    Ok(verified)
+}
+
+/// `media/2023`, `./media/2023/` and `media\2023` all become `media/2023/`; empty selects everything
+fn normalize_subtree_prefix(prefix: &str) -> String {
+    let p = normalize_archive_rel_path(prefix.trim());
+    let p = p.trim_end_matches('/');
+    if p.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", p)
+    }
+}
+
+/// Whether archive path `rel` is the subtree root itself or below it
+fn in_subtree(rel: &str, prefix: &str) -> bool {
+    prefix.is_empty() || rel.starts_with(prefix) || format!("{}/", rel.trim_end_matches('/')) == prefix
+}
+
+/// Decode images, recreate duplicates and count what ended up in `output_dir`
+/// once the archive entries have been written there
+fn finish_extraction(
+    output_dir: &Path,
+    settings: &ExtractionSettings,
+    progress: &Option<Arc<ProgressFn>>,
+) -> Result<ExtractionResult> {
+    let mut decoded_count = 0usize;
+
+    // Load metadata if available
+    let metadata_path = output_dir.join("OPENARC_METADATA.json");
+    let metadata: Option<ArchiveMetadata> = if metadata_path.exists() {
+        let content = fs::read_to_string(&metadata_path)?;
+        serde_json::from_str(&content).ok()
+    } else {
+        None
+    };
+
+    // Where each archived file ended up after decoding, for placing duplicates
+    let mut decoded_paths: HashMap<String, PathBuf> = HashMap::new();
+
+    // Decode images if settings allow and metadata exists
+    // Images filtered out stay as BPG; keep the metadata so they can be decoded later
+    let mut left_encoded = 0usize;
+    if settings.decode_images {
+        let matcher = settings.decode_filter.as_ref().map(|g| g.compile_matcher());
+        if let Some(ref meta) = metadata {
+            let total_images = meta.images.len();
+
+            for (idx, img_meta) in meta.images.iter().enumerate() {
+                check_cancelled(&settings.cancel_flag)?;
+                if let Some(ref cb) = progress {
+                    cb(idx, total_images, &img_meta.bpg_filename);
+                }
+
+                let bpg_path = output_dir.join("media").join(&img_meta.bpg_filename);
+                if !bpg_path.exists() {
+                    continue;
+                }
+                if !settings.should_decode(img_meta, matcher.as_ref()) {
+                    left_encoded += 1;
+                    continue;
+                }
+
+                let result = match img_meta.codec {
+                    ImageOutputFormat::Bpg => decode_bpg_to_original(
+                        &bpg_path,
+                        img_meta.original_format,
+                        &img_meta.original_filename,
+                        settings,
+                    ),
+                    ImageOutputFormat::Avif => decode_avif_to_original(
+                        &bpg_path,
+                        img_meta.original_format,
+                        &img_meta.original_filename,
+                        settings,
+                    ),
+                };
+
+                match result {
+                    Ok(output_path) => {
+                        // Remove the BPG file after successful decode
+                        let _ = fs::remove_file(&bpg_path);
+                        decoded_count += 1;
+
+                        // Rename to original filename if different, keeping the extension
+                        // actually produced (WebP, or PNG when HEIC encoding is unavailable)
+                        let extension = output_path
+                            .extension()
+                            .and_then(|e| e.to_str())
+                            .unwrap_or_else(|| img_meta.original_format.extraction_extension());
+                        let target_name = format!(
+                            "{}.{}",
+                            Path::new(&img_meta.original_filename)
+                                .file_stem()
+                                .and_then(|s| s.to_str())
+                                .unwrap_or("image"),
+                            extension
+                        );
+                        let target_path = output_path.parent().unwrap().join(&target_name);
+                        if output_path != target_path && fs::rename(&output_path, &target_path).is_ok() {
+                            decoded_paths.insert(format!("media/{}", img_meta.bpg_filename), target_path);
+                        } else {
+                            decoded_paths.insert(format!("media/{}", img_meta.bpg_filename), output_path);
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            "decode_failed file={} error={}",
+                            img_meta.bpg_filename,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        // Clean up metadata file
+        if left_encoded == 0 {
+            let _ = fs::remove_file(&metadata_path);
+        }
+    }
+
+    if let Some(ref meta) = metadata {
+        for dup in &meta.duplicates {
+            let source = match decoded_paths.get(&dup.canonical_path) {
+                Some(path) => path.clone(),
+                None => {
+                    let rel = normalize_archive_rel_path(&dup.canonical_path);
+                    if !is_contained_rel_path(&rel) {
+                        warn!("duplicate_outside_output file={}", dup.canonical_path);
+                        continue;
+                    }
+                    output_dir.join(rel)
+                }
+            };
+            // Only regular files; a link could hand out content from outside output_dir
+            if !source.symlink_metadata().map(|m| m.is_file()).unwrap_or(false) {
+                continue;
+            }
+
+            // Keep the duplicate's name but the canonical file's (possibly decoded) extension
+            let stem = Path::new(&dup.original_filename)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| dup.original_filename.clone());
+            let target_name = match source.extension() {
+                Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+                None => stem,
+            };
+            let target = source.with_file_name(target_name);
+            if target.symlink_metadata().is_ok() {
+                warn!("duplicate_exists file={}", target.display());
+                continue;
+            }
+            place_duplicate(&source, &target, settings.dedup_as)?;
+        }
+
+        // Last, so no archive entry is ever written through a recreated link
+        for link in &meta.symlinks {
+            let rel = normalize_archive_rel_path(&link.path);
+            if !is_contained_rel_path(&rel) {
+                warn!("symlink_outside_output file={}", link.path);
+                continue;
+            }
+            let path = output_dir.join(rel);
+            if path.symlink_metadata().is_ok() {
+                warn!("symlink_exists file={}", path.display());
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Err(e) = create_symlink(Path::new(&link.target), &path, link.target_is_dir) {
+                warn!("symlink_failed file={} error={}", path.display(), e);
+            }
+        }
 
-                let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
-                (out, rel_path, false, Some(original_format))
+        // Decoding and renaming leave images stamped "now"; put the originals' times back
+        for (rel, &secs) in &meta.mtimes {
+            let path = match decoded_paths.get(rel) {
+                Some(path) => path.clone(),
+                None => {
+                    let normalized = normalize_archive_rel_path(rel);
+                    if !is_contained_rel_path(&normalized) {
+                        warn!("mtime_outside_output file={}", rel);
+                        continue;
+                    }
+                    output_dir.join(normalized)
+                }
+            };
+            // Not through links: those were just recreated and may point anywhere
+            if !path.symlink_metadata().map(|m| m.is_file()).unwrap_or(false) {
+                continue;
             }
-            FileClass::Video => {
-                let should_skip = if settings_clone.skip_already_compressed_videos {
-                    safe_analyze_video(input)
-                        .map(|a| a.is_efficiently_compressed)
-                        .unwrap_or(false)
-                } else {
-                    false
-                };
+            if let Err(e) = filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(secs as i64, 0)) {
+                warn!("mtime_restore_failed file={} error={}", path.display(), e);
+            }
+        }
+    }
 
-                if should_skip {
-                    let out = media_dir.join(input.file_name().unwrap());
-                    fs::copy(input, &out)?;
-                    let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
-                    (out, rel_path, true, None)
-                } else {
-                    // Limit concurrent heavy video encodes to prevent memory spikes
-                    let _heavy_guard = heavy_limiter.acquire();
+    // Calculate final stats
+    let mut files_extracted = 0usize;
+    let mut total_size = 0u64;
 
-                    let (codec, preset) = match settings_clone.video_preset {
-                        1 => (VideoCodec::H265, VideoSpeedPreset::Medium),
-                        2 => (VideoCodec::H264, VideoSpeedPreset::Fast),
-                        3 => (VideoCodec::H265, VideoSpeedPreset::Slow),
-                        _ => (VideoCodec::H264, VideoSpeedPreset::Medium),
-                    };
+    for entry in walkdir::WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        files_extracted += 1;
+        if let Ok(meta) = entry.metadata() {
+            total_size += meta.len();
+        }
+    }
 
-                    let out = media_dir.join(format!(
-                        "{}.mp4",
-                        input.file_stem().and_then(|s| s.to_str()).unwrap_or("video")
-                    ));
+    if let Some(ref cb) = progress {
+        cb(1, 1, "Extraction complete");
+    }
 
-                    let opts = FfmpegEncodeOptions {
-                        codec,
-                        speed: preset,
-                        crf: Some(settings_clone.video_crf as u8),
-                        copy_audio: true,
-                    };
+    Ok(ExtractionResult {
+        files_extracted,
+        total_size,
+        decoded_files: decoded_count,
+        verified_files: 0,
+    })
+}
 
-                    // Use memory-constrained video encoding
-                    encode_video_with_memory_constraints(input, &out, opts, &settings_clone)?;
+/// Decode a BPG file back to its original format
+fn decode_bpg_to_original(
+    bpg_path: &Path,
+    original_format: OriginalImageFormat,
+    _original_filename: &str,
+    settings: &ExtractionSettings,
+) -> Result<PathBuf> {
+    let stem = bpg_path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let parent = bpg_path.parent().unwrap_or(Path::new("."));
 
-                    let rel_path = format!("media/{}", out.file_name().unwrap().to_string_lossy());
-                    (out, rel_path, false, None)
-                }
-            }
-            FileClass::Misc => {
-                let out = misc_dir.join(input.file_name().unwrap());
-                fs::copy(input, &out)?;
-                let rel_path = format!("misc/{}", out.file_name().unwrap().to_string_lossy());
-                (out, rel_path, false, None)
+    if settings.prefer_webp && original_format.can_decode_to_webp() {
+        // BPG → RGBA → WebP
+        let output_path = parent.join(format!("{}.webp", stem));
+        let rgba = match codecs::bpg::decode_file(&bpg_path.to_string_lossy()) {
+            Ok((data, width, height, _format)) => image::RgbaImage::from_raw(width, height, data)
+                .ok_or_else(|| anyhow!("Failed to create image buffer"))?,
+            Err(_) => {
+                let temp_png = output_path.with_extension("temp.png");
+                decode_bpg_to_png(bpg_path, &temp_png)?;
+                let img = image::open(&temp_png)?;
+                let _ = fs::remove_file(&temp_png);
+                img.to_rgba8()
             }
         };
+        write_webp(&rgba, &output_path, settings.webp_quality, settings.webp_lossless)?;
+        return Ok(output_path);
+    }
 
-        let output_size = fs::metadata(&out_path)?.len();
-        let sha = hash::sha256_file_hex(&out_path).ok();
+    match original_format {
+        OriginalImageFormat::Jpeg => {
+            // BPG → JPEG directly
+            let output_path = parent.join(format!("{}.jpg", stem));
+            decode_bpg_to_jpeg(bpg_path, &output_path, settings.jpeg_quality)?;
+            Ok(output_path)
+        }
+        #[cfg(feature = "heif")]
+        OriginalImageFormat::Heic => {
+            // BPG → PNG → HEIC
+            let temp_png = parent.join(format!("{}_temp.png", stem));
+            let output_path = parent.join(format!("{}.heic", stem));
 
-        {
-            let mut guard = processed_mutex.lock();
-            guard.push(ProcessedFile {
-                original_path: input.clone(),
-                class: item.class,
-                archived_rel_path: rel_path,
-                output_path: out_path,
-                original_size,
-                output_size,
-                sha256: sha,
-                skipped_processing,
-                original_format,
-            });
+            decode_bpg_to_png(bpg_path, &temp_png)?;
+
+            if HeicCodec::is_available() {
+                let codec = HeicCodec::new()?;
+                let config = HeicEncoderConfig {
+                    quality: settings.heic_quality,
+                    lossless: false,
+                    format: HeifCompressionFormat::HEVC,
+                };
+                codec.png_to_heic(&temp_png, &output_path, &config)?;
+                let _ = fs::remove_file(&temp_png);
+                Ok(output_path)
+            } else {
+                // Fallback to PNG if HEIC encoding not available
+                let png_output = parent.join(format!("{}.png", stem));
+                fs::rename(&temp_png, &png_output)?;
+                Ok(png_output)
+            }
+        }
+        #[cfg(not(feature = "heif"))]
+        OriginalImageFormat::Heic => {
+            // Fallback to PNG when HEIC support is not compiled
+            let output_path = parent.join(format!("{}.png", stem));
+            decode_bpg_to_png(bpg_path, &output_path)?;
+            Ok(output_path)
+        }
+        OriginalImageFormat::Raw | OriginalImageFormat::Png |
+        OriginalImageFormat::Tiff | OriginalImageFormat::Bmp | OriginalImageFormat::WebP => {
+            // BPG → PNG (RAW cannot be recreated, others convert to PNG for compatibility)
+            let output_path = parent.join(format!("{}.png", stem));
+            decode_bpg_to_png(bpg_path, &output_path)?;
+            Ok(output_path)
         }
+    }
+}
 
-        let seq = completed_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        let _ = tx.send(WorkDone { idx: seq, file_name });
-        Ok(())
-    })
-    })?;
+/// Decode an AVIF file back to its original format
+fn decode_avif_to_original(
+    avif_path: &Path,
+    original_format: OriginalImageFormat,
+    _original_filename: &str,
+    settings: &ExtractionSettings,
+) -> Result<PathBuf> {
+    if !HeicCodec::is_available() {
+        return Err(anyhow!("AVIF decoding not available - enable the heif feature and install libheif"));
+    }
+    let codec = HeicCodec::new()?;
+    let stem = avif_path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let parent = avif_path.parent().unwrap_or(Path::new("."));
+
+    if settings.prefer_webp && original_format.can_decode_to_webp() {
+        // AVIF → PNG → WebP
+        let temp_png = parent.join(format!("{}_temp.png", stem));
+        let output_path = parent.join(format!("{}.webp", stem));
+        codec.decode_to_png(avif_path, &temp_png)?;
+        let img = image::open(&temp_png)?;
+        let _ = fs::remove_file(&temp_png);
+        write_webp(&img.to_rgba8(), &output_path, settings.webp_quality, settings.webp_lossless)?;
+        return Ok(output_path);
+    }
 
-    drop(tx);
-    let _ = progress_thread.join();
+    match original_format {
+        OriginalImageFormat::Jpeg => {
+            let output_path = parent.join(format!("{}.jpg", stem));
+            codec.decode_to_jpeg(avif_path, &output_path, settings.jpeg_quality)?;
+            Ok(output_path)
+        }
+        OriginalImageFormat::Heic => {
+            // AVIF → PNG → HEIC
+            let temp_png = parent.join(format!("{}_temp.png", stem));
+            let output_path = parent.join(format!("{}.heic", stem));
+            codec.decode_to_png(avif_path, &temp_png)?;
+            let config = HeicEncoderConfig {
+                quality: settings.heic_quality,
+                lossless: false,
+                format: HeifCompressionFormat::HEVC,
+            };
+            codec.png_to_heic(&temp_png, &output_path, &config)?;
+            let _ = fs::remove_file(&temp_png);
+            Ok(output_path)
+        }
+        OriginalImageFormat::Raw | OriginalImageFormat::Png |
+        OriginalImageFormat::Tiff | OriginalImageFormat::Bmp | OriginalImageFormat::WebP => {
+            let output_path = parent.join(format!("{}.png", stem));
+            codec.decode_to_png(avif_path, &output_path)?;
+            Ok(output_path)
+        }
+    }
+}
 
-    let processed = Arc::try_unwrap(processed_mutex)
-        .map_err(|_| anyhow!("Failed to unwrap processed results"))?
-        .into_inner();
+/// Decode BPG to PNG
+fn decode_bpg_to_png(bpg_path: &Path, output_path: &Path) -> Result<()> {
+    // Try native decoder first
+    match codecs::bpg::decode_file(&bpg_path.to_string_lossy()) {
+        Ok((data, width, height, _format)) => {
+            image::save_buffer(output_path, &data, width, height, image::ColorType::Rgba8)?;
+            Ok(())
+        }
+        Err(_) => {
+            // Fall back to JS decoder
+            if codecs::bpg_js::is_bpg_js_available() {
+                codecs::bpg_js::bpg_js_to_png(bpg_path, output_path)
+            } else {
+                Err(anyhow!("No BPG decoder available"))
+            }
+        }
+    }
+}
 
-    let metadata = Arc::try_unwrap(metadata_mutex)
-        .map_err(|_| anyhow!("Failed to unwrap metadata"))?
-        .into_inner();
+/// Decode BPG to JPEG
+///
+/// The decoder hands back RGB in the source primaries, so any ICC profile
+/// stored in the BPG (e.g. Display P3) is carried into the JPEG; without one
+/// the output is left untagged, i.e. sRGB.
+fn decode_bpg_to_jpeg(bpg_path: &Path, output_path: &Path, quality: u8) -> Result<()> {
+    let icc_profile = match codecs::bpg::read_color_info(&bpg_path.to_string_lossy()) {
+        Ok(info) => info.icc_profile,
+        Err(e) => {
+            warn!("Could not read color info from {}: {}", bpg_path.display(), e);
+            None
+        }
+    };
 
-    // Write metadata JSON
-    let metadata_path = temp_dir.path().join("OPENARC_METADATA.json");
-    let metadata_json = serde_json::to_string_pretty(&metadata)?;
-    fs::write(&metadata_path, &metadata_json)?;
+    // Try native decoder first
+    match codecs::bpg::decode_file(&bpg_path.to_string_lossy()) {
+        Ok((data, width, height, _format)) => {
+            // Convert RGBA to RGB
+            let rgb_data: Vec<u8> = data.chunks(4)
+                .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+                .collect();
 
-    let misc_arc_path = temp_dir.path().join("misc.arc");
-    create_misc_arc(&processed, &misc_arc_path, settings.compression_level)?;
+            let img = image::RgbImage::from_raw(width, height, rgb_data)
+                .ok_or_else(|| anyhow!("Failed to create image buffer"))?;
 
-    let manifest_path = temp_dir.path().join("MANIFEST.txt");
-    write_manifest(&processed, &skipped_by_catalog, &manifest_path)?;
+            write_jpeg(&img, output_path, quality, icc_profile)
+        }
+        Err(_) => {
+            // Fall back to PNG then convert
+            let temp_png = output_path.with_extension("temp.png");
+            if codecs::bpg_js::is_bpg_js_available() {
+                codecs::bpg_js::bpg_js_to_png(bpg_path, &temp_png)?;
+                let img = image::open(&temp_png)?;
+                let rgb = img.to_rgb8();
+                let _ = fs::remove_file(&temp_png);
+                write_jpeg(&rgb, output_path, quality, icc_profile)
+            } else {
+                Err(anyhow!("No BPG decoder available"))
+            }
+        }
+    }
+}
+
+/// Encode RGB pixels as JPEG, embedding `icc_profile` when given
+fn write_jpeg(
+    img: &image::RgbImage,
+    output_path: &Path,
+    quality: u8,
+    icc_profile: Option<Vec<u8>>,
+) -> Result<()> {
+    use image::ImageEncoder;
+
+    let mut file = std::io::BufWriter::new(fs::File::create(output_path)?);
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality);
+    if let Some(icc) = icc_profile {
+        encoder
+            .set_icc_profile(icc)
+            .map_err(|e| anyhow!("Failed to embed ICC profile: {}", e))?;
+    }
+    encoder.write_image(img.as_raw(), img.width(), img.height(), image::ExtendedColorType::Rgb8)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Encode RGBA pixels as WebP, lossy at `quality` (1-100) or lossless
+///
+/// The `image` crate only writes lossless WebP, so lossy output goes through libwebp.
+fn write_webp(img: &image::RgbaImage, output_path: &Path, quality: u8, lossless: bool) -> Result<()> {
+    use image::ImageEncoder;
+
+    if lossless {
+        let mut file = std::io::BufWriter::new(fs::File::create(output_path)?);
+        image::codecs::webp::WebPEncoder::new_lossless(&mut file).write_image(
+            img.as_raw(),
+            img.width(),
+            img.height(),
+            image::ExtendedColorType::Rgba8,
+        )?;
+        file.flush()?;
+    } else {
+        let encoded = webp::Encoder::from_rgba(img.as_raw(), img.width(), img.height()).encode(quality as f32);
+        fs::write(output_path, &*encoded)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_webp_lossless_and_lossy() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let img = image::RgbaImage::from_fn(64, 64, |x, y| {
+            image::Rgba([(x * 4) as u8, (y * 4) as u8, ((x ^ y) * 4) as u8, 255])
+        });
+
+        let lossless = dir.path().join("lossless.webp");
+        write_webp(&img, &lossless, 50, true)?;
+        let bytes = fs::read(&lossless)?;
+        assert_eq!(&bytes[8..16], b"WEBPVP8L");
+        assert_eq!(image::open(&lossless)?.to_rgba8(), img);
+
+        let lossy = dir.path().join("lossy.webp");
+        write_webp(&img, &lossy, 50, false)?;
+        let bytes = fs::read(&lossy)?;
+        assert_eq!(&bytes[8..16], b"WEBPVP8 ");
+        let decoded = image::open(&lossy)?.to_rgba8();
+        assert_eq!(decoded.dimensions(), (64, 64));
+        assert_ne!(decoded, img);
+
+        // Quality trades size for fidelity
+        let high = dir.path().join("high.webp");
+        write_webp(&img, &high, 95, false)?;
+        assert!(fs::metadata(&high)?.len() > fs::metadata(&lossy)?.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_jpeg_embeds_icc() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let img = image::RgbImage::from_pixel(8, 8, image::Rgb([200, 40, 40]));
+        let icc = b"openarc-test-icc-profile".to_vec();
+
+        let tagged = dir.path().join("tagged.jpg");
+        write_jpeg(&img, &tagged, 90, Some(icc.clone()))?;
+        let bytes = fs::read(&tagged)?;
+        assert!(bytes.windows(12).any(|w| w == b"ICC_PROFILE\0"));
+        assert!(bytes.windows(icc.len()).any(|w| w == icc.as_slice()));
+
+        let untagged = dir.path().join("untagged.jpg");
+        write_jpeg(&img, &untagged, 90, None)?;
+        assert!(!fs::read(&untagged)?.windows(11).any(|w| w == b"ICC_PROFILE"));
+        assert_eq!(image::open(&untagged)?.width(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_indices_distinct() {
+        let picked = sample_indices(20, 8, 12345);
+        assert_eq!(picked.len(), 8);
+        let mut sorted = picked.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 8);
+        assert!(picked.iter().all(|&i| i < 20));
+
+        assert_eq!(sample_indices(3, 8, 1).len(), 3);
+    }
+
+    #[test]
+    fn test_rewrite_manifest_sizes() {
+        let manifest = "OpenArc Archive Manifest\n\
+                        /photos/a.jpg -> media/a_0.bpg (5000 -> 1200) [orig: Jpeg]\n\
+                        /photos/b.txt -> misc/b.txt (10 -> 10)\n";
+        let mut sizes = HashMap::new();
+        sizes.insert("media/a_0.bpg".to_string(), 800u64);
+
+        let out = rewrite_manifest_sizes(manifest, &sizes);
+        assert!(out.contains("/photos/a.jpg -> media/a_0.bpg (5000 -> 800) [orig: Jpeg]"));
+        assert!(out.contains("/photos/b.txt -> misc/b.txt (10 -> 10)"));
+        assert_eq!(parse_manifest_sizes(&out).get("media/a_0.bpg"), Some(&(5000, 800)));
+    }
+
+    #[test]
+    fn test_ratio_bounds() {
+        let (mean, low, high) = ratio_bounds(&[0.2, 0.3, 0.25, 0.35]);
+        assert!((mean - 0.275).abs() < 1e-9);
+        assert!(low < mean && mean < high);
 
-    let hashes_path = temp_dir.path().join("HASHES.sha256");
-    write_hashes(&processed, &hashes_path, &misc_arc_path, &manifest_path)?;
+        assert_eq!(ratio_bounds(&[]), (1.0, 1.0, 1.0));
+    }
 
-    let zstd = make_zstd(3);
-    zstd.archive_dir_tar_zst(temp_dir.path(), output_archive)
-        .with_context(|| format!("Failed to create zstd archive at {}", output_archive.display()))?;
+    #[test]
+    fn test_manifest_lists_kept_original_video() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let processed = vec![ProcessedFile {
+            original_path: PathBuf::from("/phone/clip.mov"),
+            class: FileClass::Video,
+            archived_rel_path: "media/clip.mp4".to_string(),
+            output_path: dir.path().join("clip.mp4"),
+            original_size: 9000,
+            output_size: 2000,
+            sha256: None,
+            skipped_processing: false,
+            original_format: None,
+            original_copy: Some(("media/originals/clip.mov".to_string(), dir.path().join("clip.mov"))),
+        }];
+
+        let manifest_path = dir.path().join("MANIFEST.txt");
+        write_manifest(&processed, &[], &manifest_path)?;
+        let sizes = parse_manifest_sizes(&fs::read_to_string(&manifest_path)?);
+        assert_eq!(sizes.get("media/clip.mp4"), Some(&(9000, 2000)));
+        assert_eq!(sizes.get("media/originals/clip.mov"), Some(&(9000, 9000)));
+        Ok(())
+    }
 
-    // Record archive information in the database
-    if let Some(ref mut cat) = catalog {
-        record_catalog_entries(cat, &processed, output_archive)?;
+    #[test]
+    fn test_kept_originals_of_same_named_videos_stay_apart() -> Result<()> {
+        // Originals are copied to media/originals/<work name>; same-named clips
+        // from different folders must not share one
+        let mut work: Vec<WorkItem> = ["/phone/a/clip.mov", "/phone/b/clip.mov", "/phone/c/CLIP.MP4"]
+            .iter()
+            .enumerate()
+            .map(|(idx, p)| WorkItem {
+                idx,
+                input: PathBuf::from(p),
+                class: FileClass::Video,
+                original_format: None,
+                name: safe_file_name(Path::new(p)),
+            })
+            .collect();
+        resolve_case_collisions(&mut work, CaseCollisionPolicy::Rename, &HashMap::new())?;
+        let mut originals: Vec<String> = work
+            .iter()
+            .map(|w| format!("media/originals/{}", w.name).to_lowercase())
+            .collect();
+        originals.sort();
+        originals.dedup();
+        assert_eq!(originals.len(), 3);
+        Ok(())
+    }
 
-        // Also record archive tracking information
-        let archive_metadata = std::fs::metadata(output_archive)
-            .with_context(|| format!("Failed to get metadata for archive: {}", output_archive.display()))?;
+    #[test]
+    fn test_bake_orientation_rotates_exif_jpeg() -> Result<()> {
+        // 32x16: left half red, right half blue
+        let img = image::RgbImage::from_fn(32, 16, |x, _| {
+            if x < 16 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+        });
+        let mut jpeg = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 95).encode_image(&img)?;
+
+        // Insert an APP1 EXIF segment with Orientation = 6 (rotate 90 CW) after SOI
+        let mut exif = vec![0xFF, 0xE1, 0x00, 0x22];
+        exif.extend_from_slice(b"Exif\0\0MM\0\x2A\0\0\0\x08");
+        exif.extend_from_slice(&[0x00, 0x01, 0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00]);
+        exif.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        let mut rotated = jpeg[..2].to_vec();
+        rotated.extend_from_slice(&exif);
+        rotated.extend_from_slice(&jpeg[2..]);
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("phone.jpg");
+        fs::write(&path, &rotated)?;
+
+        let upright = open_image_with_orientation(&path)?.to_rgb8();
+        assert_eq!(upright.dimensions(), (16, 32));
+        // The left (red) edge is now at the top
+        assert!(upright.get_pixel(8, 4)[0] > 200 && upright.get_pixel(8, 4)[2] < 60);
+        assert!(upright.get_pixel(8, 28)[2] > 200 && upright.get_pixel(8, 28)[0] < 60);
+
+        // Without baking, pixels keep the stored layout
+        assert_eq!(image::open(&path)?.to_rgb8().dimensions(), (32, 16));
+        Ok(())
+    }
 
-        let archive_record = ArchiveRecord {
-            id: None,
-            archive_path: output_archive.to_string_lossy().to_string(),
-            archive_size: archive_metadata.len(),
-            creation_date: 0, // Will be set by the database
-            original_location: output_archive.parent()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| ".".to_string()),
-            destination_location: None, // Will be set later when moved
-            description: Some(format!("Archive with {} files", processed.len())),
-            file_count: processed.len() as u32,
-        };
+    #[test]
+    fn test_detect_live_photo_pairs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let still = dir.path().join("IMG_0001.HEIC");
+        let clip = dir.path().join("IMG_0001.MOV");
+        let lone_clip = dir.path().join("IMG_0002.MOV");
+        let other_dir = dir.path().join("other");
+        fs::create_dir_all(&other_dir)?;
+        let elsewhere = other_dir.join("IMG_0002.jpg");
+        for p in [&still, &clip, &lone_clip, &elsewhere] {
+            fs::write(p, b"x")?;
+        }
 
-        // Create archive tracker using the same connection as the backup catalog
-        if let Ok(mut tracker) = ArchiveTracker::new(cat.get_connection_mut()) {
-            if let Ok(archive_id) = tracker.record_archive(archive_record) {
-                // Record the files in this archive
-                let file_mappings: Vec<ArchiveFileMapping> = processed.iter().map(|p| {
-                    ArchiveFileMapping {
-                        id: None,
-                        archive_id,
-                        file_path: p.archived_rel_path.clone(),
-                        original_path: p.original_path.to_string_lossy().to_string(),
-                        file_size: p.original_size,
-                        archived_at: 0, // Will be set by the database
-                    }
-                }).collect();
+        let pairs = detect_live_photo_pairs(&[still.clone(), clip.clone(), lone_clip, elsewhere]);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs.get(&clip), Some(&still));
+        Ok(())
+    }
 
-                if let Err(e) = tracker.record_archive_files(archive_id, file_mappings) {
-                    eprintln!("Warning: Failed to record archive files: {}", e);
-                }
+    #[test]
+    fn test_case_collisions_rename_live_photo_halves_together() -> Result<()> {
+        let item = |idx: usize, path: &str| {
+            let input = PathBuf::from(path);
+            let (class, original_format) = if path.ends_with(".mov") {
+                (FileClass::Video, None)
             } else {
-                eprintln!("Warning: Failed to record archive in tracker");
-            }
-        } else {
-            eprintln!("Warning: Could not create archive tracker");
-        }
+                (FileClass::Image, Some(OriginalImageFormat::Jpeg))
+            };
+            WorkItem { idx, input: input.clone(), class, original_format, name: safe_file_name(&input) }
+        };
+        let clip = PathBuf::from("/b/img_0001.mov");
+        let still = PathBuf::from("/b/img_0001.jpg");
+        let pairs = HashMap::from([(clip.clone(), still.clone())]);
+        // The clip comes first and is free on its own; only its still clashes
+        let inputs = || vec![item(0, "/a/IMG_0001.JPG"), item(1, "/b/img_0001.mov"), item(2, "/b/img_0001.jpg")];
+
+        let mut work = inputs();
+        let (renamed, skipped) = resolve_case_collisions(&mut work, CaseCollisionPolicy::Rename, &pairs)?;
+        assert!(skipped.is_empty());
+        let names: Vec<&str> = work.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["IMG_0001.JPG", "img_0001_2.mov", "img_0001_2.jpg"]);
+        assert_eq!(renamed.len(), 2);
+
+        let mut work = inputs();
+        let (renamed, skipped) = resolve_case_collisions(&mut work, CaseCollisionPolicy::Skip, &pairs)?;
+        assert!(renamed.is_empty());
+        assert_eq!(skipped, vec![clip, still]);
+        assert_eq!(work.len(), 1);
+        Ok(())
     }
 
-    let dedup_groups = if settings.enable_dedup { dedup_canon.len() } else { 0 };
+    #[test]
+    fn test_filter_accessible_policies() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let present = dir.path().join("ok.txt");
+        fs::write(&present, b"data")?;
+        let missing = dir.path().join("gone.txt");
 
-    Ok(OrchestratorResult {
-        discovered_files: discovered,
-        processed,
-        skipped_by_catalog,
-        dedup_groups,
-    })
-}
+        let (readable, skipped) =
+            filter_accessible(vec![present.clone(), missing.clone()], AccessErrorPolicy::Skip)?;
+        assert_eq!(readable, vec![present.clone()]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, missing);
 
-fn create_misc_arc(processed: &[ProcessedFile], output_arc: &Path, compression_level: i32) -> Result<()> {
-    let misc: Vec<&ProcessedFile> = processed.iter().filter(|p| p.class == FileClass::Misc).collect();
-    if misc.is_empty() {
-        return Ok(());
+        assert!(filter_accessible(vec![present, missing], AccessErrorPolicy::Fail).is_err());
+        Ok(())
     }
 
-    let f = std::fs::File::create(output_arc)
-        .with_context(|| format!("Failed to create {}", output_arc.display()))?;
-    let mut writer = std::io::BufWriter::new(f);
+    #[test]
+    fn test_size_report_groups_by_class() {
+        let file = |class, original_size, output_size| ProcessedFile {
+            original_path: PathBuf::from("/in/file"),
+            class,
+            archived_rel_path: "media/file".to_string(),
+            output_path: PathBuf::from("/out/file"),
+            original_size,
+            output_size,
+            sha256: None,
+            skipped_processing: false,
+            original_format: None,
+            original_copy: None,
+        };
+        let processed = vec![
+            file(FileClass::Image, 1000, 250),
+            file(FileClass::Image, 3000, 750),
+            file(FileClass::Video, 8000, 4000),
+            file(FileClass::Misc, 200, 190),
+        ];
+
+        let report = SizeReport::from_processed(&processed);
+        assert_eq!(report.class(FileClass::Image).files, 2);
+        assert_eq!(report.images.original_bytes, 4000);
+        assert_eq!(report.images.output_bytes, 1000);
+        assert!((report.videos.ratio() - 0.5).abs() < f64::EPSILON);
+        assert_eq!(report.misc.output_bytes, 190);
+        assert_eq!(report.total().original_bytes, 12200);
+        assert_eq!(ClassSizeStats::default().ratio(), 0.0);
+
+        // Older metadata without the report still parses
+        let json = r#"{"version":1,"images":[],"created_at":0}"#;
+        let meta: ArchiveMetadata = serde_json::from_str(json).unwrap();
+        assert!(meta.size_report.is_none());
+    }
 
-    let mut arc = FreeArcWriter::new(
-        &mut writer,
-        ArchiveOptions {
-            compression: "lzma".to_string(),
-            compression_level,
-            encryption: None,
-            password: None,
-        },
-    )?;
+    #[test]
+    fn test_place_duplicate_links_and_copies() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("canon.jpg");
+        fs::write(&source, b"same bytes")?;
 
-    let mut name_counts: HashMap<String, usize> = HashMap::new();
+        let linked = dir.path().join("linked.jpg");
+        assert!(place_duplicate(&source, &linked, DedupMode::Links)?);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert_eq!(fs::metadata(&source)?.nlink(), 2);
+        }
 
-    for item in misc {
-        let data = std::fs::read(&item.output_path)?;
-        let mut name = item
-            .output_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("file")
-            .to_string();
+        let copied = dir.path().join("copied.jpg");
+        assert!(!place_duplicate(&source, &copied, DedupMode::Copies)?);
+        assert_eq!(fs::read(&copied)?, b"same bytes");
 
-        let c = name_counts.entry(name.clone()).or_insert(0);
-        if *c > 0 {
-            name = format!("{}_{}", *c, name);
+        // Filesystems without hard links fall back to a copy
+        let fallback = dir.path().join("fallback.jpg");
+        let unsupported = |_: &Path, _: &Path| -> std::io::Result<()> {
+            Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "no links"))
+        };
+        assert!(!place_duplicate_with(&source, &fallback, DedupMode::Links, unsupported)?);
+        assert_eq!(fs::read(&fallback)?, b"same bytes");
+
+        // Canonical paths from the metadata can't reach outside the output directory
+        let secret = dir.path().join("secret.txt");
+        fs::write(&secret, b"secret")?;
+        let out = dir.path().join("out");
+        fs::create_dir_all(out.join("misc"))?;
+        let mut meta = ArchiveMetadata::default();
+        for canonical in ["../secret.txt", "misc/../../secret.txt", "/etc/hostname"] {
+            meta.duplicates.push(DuplicateMetadata {
+                original_filename: "leak.txt".to_string(),
+                canonical_path: canonical.to_string(),
+            });
         }
-        *c += 1;
-
-        arc.add_file(&name, &data)?;
+        // Nor through a link the archive itself unpacked
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&secret, out.join("misc/link.txt"))?;
+            meta.duplicates.push(DuplicateMetadata {
+                original_filename: "via_link.txt".to_string(),
+                canonical_path: "misc/link.txt".to_string(),
+            });
+        }
+        fs::write(out.join("OPENARC_METADATA.json"), serde_json::to_string(&meta)?)?;
+        let settings = ExtractionSettings { decode_images: false, ..ExtractionSettings::default() };
+        finish_extraction(&out, &settings, &None)?;
+        assert!(!dir.path().join("leak.txt").exists());
+        assert!(!out.join("leak.txt").exists());
+        assert!(!out.join("misc/via_link.txt").exists());
+
+        let mut duplicates_of = HashMap::new();
+        duplicates_of.insert(PathBuf::from("/in/copy.jpg"), PathBuf::from("/in/canon.jpg"));
+        let processed = vec![ProcessedFile {
+            original_path: PathBuf::from("/in/canon.jpg"),
+            class: FileClass::Image,
+            archived_rel_path: "media/canon.bpg".to_string(),
+            output_path: source.clone(),
+            original_size: 10,
+            output_size: 10,
+            sha256: None,
+            skipped_processing: false,
+            original_format: None,
+            original_copy: None,
+        }];
+        let dups = duplicate_metadata(&duplicates_of, &processed);
+        assert_eq!(dups.len(), 1);
+        assert_eq!(dups[0].original_filename, "copy.jpg");
+        assert_eq!(dups[0].canonical_path, "media/canon.bpg");
+        Ok(())
     }
 
-    let inner = arc.finish()?;
-    inner.flush()?;
+    #[test]
+    fn test_decode_filters() -> Result<()> {
+        let image = |name: &str, format| ImageMetadata {
+            original_filename: name.to_string(),
+            original_format: format,
+            original_extension: String::new(),
+            bpg_filename: format!("{}.bpg", name),
+            codec: ImageOutputFormat::Bpg,
+            chroma_format: None,
+        };
+        let jpeg = image("IMG_0001.jpg", OriginalImageFormat::Jpeg);
+        let raw = image("DSC_0002.nef", OriginalImageFormat::Raw);
 
-    Ok(())
-}
+        let all = ExtractionSettings::default();
+        assert!(all.should_decode(&jpeg, None));
+        assert!(all.should_decode(&raw, None));
 
-fn write_manifest(processed: &[ProcessedFile], skipped: &[PathBuf], manifest_path: &Path) -> Result<()> {
-    let mut f = std::fs::File::create(manifest_path)?;
+        let jpeg_only = ExtractionSettings {
+            decode_formats: Some(vec![OriginalImageFormat::Jpeg]),
+            ..ExtractionSettings::default()
+        };
+        assert!(jpeg_only.should_decode(&jpeg, None));
+        assert!(!jpeg_only.should_decode(&raw, None));
 
-    writeln!(f, "OpenArc Archive Manifest")?;
-    writeln!(f, "========================")?;
-    writeln!(f)?;
+        let by_name = ExtractionSettings {
+            decode_filter: Some(Glob::new("DSC_*")?),
+            ..ExtractionSettings::default()
+        };
+        let matcher = by_name.decode_filter.as_ref().map(|g| g.compile_matcher());
+        assert!(!by_name.should_decode(&jpeg, matcher.as_ref()));
+        assert!(by_name.should_decode(&raw, matcher.as_ref()));
+
+        // Both filters must pass
+        let both = ExtractionSettings {
+            decode_formats: Some(vec![OriginalImageFormat::Jpeg]),
+            ..by_name
+        };
+        assert!(!both.should_decode(&raw, matcher.as_ref()));
+        Ok(())
+    }
 
-    writeln!(f, "Processed files: {}", processed.len())?;
-    writeln!(f, "Skipped by catalog: {}", skipped.len())?;
-    writeln!(f)?;
+    #[test]
+    fn test_split_by_class_routing() -> Result<()> {
+        assert_eq!(
+            split_output_path(Path::new("/out/trip.tar.zst"), "videos"),
+            PathBuf::from("/out/trip.videos.tar.zst")
+        );
+        assert_eq!(
+            split_output_path(Path::new("/out/trip.oarc"), "images"),
+            PathBuf::from("/out/trip.images.oarc")
+        );
+
+        let dir = tempfile::tempdir()?;
+        let still = dir.path().join("IMG_0001.HEIC");
+        let live_clip = dir.path().join("IMG_0001.MOV");
+        let movie = dir.path().join("holiday.mp4");
+        let notes = dir.path().join("notes.txt");
+        for p in [&still, &live_clip, &movie, &notes] {
+            fs::write(p, b"x")?;
+        }
 
-    for p in processed {
-        let format_info = p.original_format
-            .map(|f| format!(" [orig: {:?}]", f))
-            .unwrap_or_default();
-        writeln!(
-            f,
-            "{} -> {} ({} -> {}){}{}",
-            p.original_path.display(),
-            p.archived_rel_path,
-            p.original_size,
-            p.output_size,
-            if p.skipped_processing { " [skipped_processing]" } else { "" },
-            format_info
-        )?;
-    }
+        let groups = split_files_by_class(vec![still.clone(), live_clip.clone(), movie.clone(), notes.clone()], true);
+        assert_eq!(groups[0], ("images", vec![still.clone(), live_clip.clone()]));
+        assert_eq!(groups[1], ("videos", vec![movie.clone()]));
+        assert_eq!(groups[2], ("misc", vec![notes.clone()]));
 
-    Ok(())
-}
+        // Without pairing the clip is just another video
+        let groups = split_files_by_class(vec![still, live_clip.clone(), movie], false);
+        assert_eq!(groups[1].1.len(), 2);
+        assert!(groups[1].1.contains(&live_clip));
+        Ok(())
+    }
 
-fn write_hashes(processed: &[ProcessedFile], hashes_path: &Path, misc_arc_path: &Path, manifest_path: &Path) -> Result<()> {
-    let mut hashes: Vec<(String, String)> = Vec::new();
+    #[test]
+    fn test_already_compressed_misc_is_stored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), b"plain text plain text plain text")?;
+        fs::write(src.join("song.mp3"), b"ID3 not really audio")?;
+        // Renamed zip: caught by sniffing the content
+        fs::write(src.join("backup.bin"), b"PK\x03\x04\x14\x00\x00\x00\x08\x00rest of a zip")?;
+
+        assert!(!is_already_compressed(&src.join("notes.txt")));
+        assert!(is_already_compressed(&src.join("song.mp3")));
+        assert!(is_already_compressed(&src.join("backup.bin")));
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&[src], &output, settings, None)?;
+        assert_eq!(result.processed.len(), 3);
+        assert_eq!(result.avoided_recompression(), 2);
+        let stored: Vec<bool> = ["notes.txt", "song.mp3", "backup.bin"]
+            .iter()
+            .map(|name| {
+                result
+                    .processed
+                    .iter()
+                    .find(|p| p.original_path.ends_with(name))
+                    .map(|p| p.skipped_processing)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(stored, [false, true, true]);
+        Ok(())
+    }
 
-    for p in processed {
-        if let Some(ref h) = p.sha256 {
-            hashes.push((h.clone(), p.archived_rel_path.clone()));
+    #[test]
+    fn test_create_archive_events() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("a.txt"), b"alpha alpha alpha")?;
+        fs::write(src.join("b.txt"), b"beta")?;
+        let output = dir.path().join("out.tar.zst");
+
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let events: Vec<ArchiveEvent> = create_archive_events(vec![src.clone()], output.clone(), settings)
+            .iter()
+            .collect();
+
+        assert!(matches!(events.first(), Some(ArchiveEvent::PhaseChanged(ArchivePhase::Scanning))));
+        let started = events.iter().filter(|e| matches!(e, ArchiveEvent::FileStarted { .. })).count();
+        assert_eq!(started, 2);
+        let done_b = events.iter().find_map(|e| match e {
+            ArchiveEvent::FileDone { path, size, .. } if path.ends_with("b.txt") => Some(*size),
+            _ => None,
+        });
+        assert_eq!(done_b, Some(4));
+        assert!(events.iter().any(|e| matches!(e, ArchiveEvent::PhaseChanged(ArchivePhase::Packing))));
+        match events.last() {
+            Some(ArchiveEvent::Completed(result)) => assert_eq!(result.processed.len(), 2),
+            other => panic!("expected Completed, got {:?}", other),
         }
-    }
+        assert!(output.exists());
 
-    if misc_arc_path.exists() {
-        let h = hash::sha256_file_hex(misc_arc_path)?;
-        hashes.push((h, "misc.arc".to_string()));
+        // Failures end the stream with a single Error
+        let bad = OrchestratorSettings {
+            max_concurrent_videos: 0,
+            ..OrchestratorSettings::default()
+        };
+        let events: Vec<ArchiveEvent> = create_archive_events(vec![src], output, bad).iter().collect();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], ArchiveEvent::Error(msg) if msg.contains("at least 1")));
+        Ok(())
     }
 
-    if manifest_path.exists() {
-        let h = hash::sha256_file_hex(manifest_path)?;
-        hashes.push((h, "MANIFEST.txt".to_string()));
+    #[test]
+    fn test_audit_archive_reports_mismatches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("a.txt"), b"alpha alpha alpha")?;
+        fs::write(src.join("b.txt"), b"beta")?;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src.clone()], &output, settings, None)?;
+        let catalog_path = output.with_extension("catalog.sqlite");
+
+        let clean = audit_archive(&output, &catalog_path)?;
+        assert!(clean.is_consistent(), "{:?}", clean);
+        assert_eq!((clean.archive_entries, clean.catalog_records), (2, 2));
+
+        // Forget b.txt's mapping, claim a file the archive never got and
+        // record a hash for a.txt that doesn't match what was archived
+        let mut catalog = BackupCatalog::new(&catalog_path)?;
+        let conn = catalog.get_connection_mut();
+        conn.execute("DELETE FROM archive_files WHERE file_path = 'misc/b.txt'", [])?;
+        conn.execute(
+            "INSERT INTO archive_files (archive_id, file_path, original_path, file_size, archived_at)
+             SELECT archive_id, 'misc/ghost.txt', '/nowhere/ghost.txt', 1, 0 FROM archive_files LIMIT 1",
+            [],
+        )?;
+        let a_key = catalog.catalog_key(&src.join("a.txt"));
+        catalog.record_backup(BackupEntry {
+            path: a_key.clone(),
+            size: 17,
+            mtime_secs: 0,
+            sha256: Some("00".repeat(32)),
+            backed_up_at: 0,
+            archive_id: Some("out.tar.zst".to_string()),
+        })?;
+        catalog.close()?;
+
+        let report = audit_archive(&output, &catalog_path)?;
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing_in_archive, vec!["misc/ghost.txt".to_string()]);
+        assert_eq!(report.missing_in_catalog, vec!["misc/b.txt".to_string()]);
+        assert_eq!(report.hash_mismatches, vec![a_key]);
+        Ok(())
     }
 
-    hash::write_hashes_file(&hashes, hashes_path)?;
-    Ok(())
-}
+    #[test]
+    fn test_case_collisions_keep_every_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub"))?;
+        fs::write(src.join("Notes.txt"), b"upper")?;
+        fs::write(src.join("sub").join("notes.txt"), b"lower")?;
+        fs::write(src.join("sub").join("NOTES.TXT"), b"shouting")?;
+        let output = dir.path().join("out.tar.zst");
+
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&[src.clone()], &output, settings.clone(), None)?;
+        assert_eq!(result.processed.len(), 3);
+        assert_eq!(result.renamed_case_collisions.len(), 2);
+        let mut names: Vec<String> = result
+            .processed
+            .iter()
+            .map(|p| p.archived_rel_path.to_lowercase())
+            .collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 3, "archived names still clash: {:?}", names);
+
+        let skip = OrchestratorSettings {
+            on_case_collision: CaseCollisionPolicy::Skip,
+            ..settings.clone()
+        };
+        let result = create_archive(&[src.clone()], &output, skip, None)?;
+        assert_eq!((result.processed.len(), result.skipped_case_collisions.len()), (1, 2));
 
-fn record_catalog_entries(catalog: &mut BackupCatalog, processed: &[ProcessedFile], output_archive: &Path) -> Result<()> {
-    let mut entries = Vec::new();
-    let archive_id = output_archive
-        .file_name()
-        .and_then(|n| n.to_str())
-        .map(|s| s.to_string());
+        let fail = OrchestratorSettings {
+            on_case_collision: CaseCollisionPolicy::Fail,
+            ..settings
+        };
+        let err = create_archive(&[src], &output, fail, None).unwrap_err();
+        assert!(err.to_string().contains("case-insensitive"));
+        Ok(())
+    }
 
-    for p in processed {
-        let md = fs::metadata(&p.original_path)?;
-        let mtime_secs = md
-            .modified()?
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+    #[test]
+    fn test_sorted_archives_have_stable_entry_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        for sub in ["b", "a", "c"] {
+            fs::create_dir_all(src.join(sub))?;
+            for i in 0..5 {
+                fs::write(src.join(sub).join(format!("{}{}.txt", sub, i)), format!("{} {}", sub, i))?;
+            }
+        }
 
-        entries.push(BackupEntry {
-            path: normalize_path(&p.original_path),
-            size: md.len(),
-            mtime_secs,
-            sha256: p.sha256.clone(),
-            backed_up_at: 0,
-            archive_id: archive_id.clone(),
-        });
+        let mut runs = Vec::new();
+        for run in 0..2 {
+            let output = dir.path().join(format!("run{}.tar.zst", run));
+            let settings = OrchestratorSettings {
+                staging_dir: Some(dir.path().to_path_buf()),
+                enable_catalog: false,
+                sorted: true,
+                ..OrchestratorSettings::default()
+            };
+            let result = create_archive(&[src.clone()], &output, settings, None)?;
+            let inputs: Vec<&PathBuf> = result.processed.iter().map(|p| &p.original_path).collect();
+            let mut expected = inputs.clone();
+            expected.sort();
+            assert_eq!(inputs, expected);
+
+            let mut tar = tar::Archive::new(zstd_archive::archive_decoder(fs::File::open(&output)?)?);
+            let mut names = Vec::new();
+            for entry in tar.entries()? {
+                names.push(entry?.path()?.to_string_lossy().trim_start_matches("./").to_string());
+            }
+            runs.push(names);
+        }
+
+        assert_eq!(runs[0], runs[1]);
+        let misc: Vec<&String> = runs[0].iter().filter(|n| n.starts_with("misc/") && n.ends_with(".txt")).collect();
+        let mut expected = misc.clone();
+        expected.sort();
+        assert_eq!(misc.len(), 15);
+        assert_eq!(misc, expected);
+        Ok(())
     }
 
-    catalog.record_backups(entries)
-}
+    #[test]
+    fn test_watch_archives_existing_then_new_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("old.txt"), b"already here")?;
+        let out = dir.path().join("out");
+
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let options = WatchOptions {
+            debounce: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        };
+        let handle = watch_and_archive_with_options(&[src.clone()], &out, settings, options)?;
+        let wait_for = |count: usize| {
+            let start = std::time::Instant::now();
+            while handle.archives().len() < count && start.elapsed() < Duration::from_secs(20) {
+                thread::sleep(Duration::from_millis(50));
+            }
+            handle.archives().len()
+        };
 
-fn make_zstd(level: i32) -> ZstdCodec {
-    let mut opts = ZstdOptions::default();
-    opts.level = level;
-    ZstdCodec::new(opts)
-}
+        assert_eq!(wait_for(1), 1);
+        // A burst of changes lands in a single archive
+        for i in 0..3 {
+            fs::write(src.join(format!("new{}.txt", i)), format!("new {}", i))?;
+        }
+        assert_eq!(wait_for(2), 2);
+        let archives = handle.stop()?;
+        assert_eq!(archives.len(), 2);
+
+        let entries = BackupCatalog::new(out.join(WATCH_CATALOG_NAME))?.list_all()?;
+        assert_eq!(entries.len(), 4);
+        let in_archive = |archive: &Path| {
+            let name = archive.file_name().unwrap().to_string_lossy().to_string();
+            entries.iter().filter(|e| e.archive_id.as_deref() == Some(name.as_str())).count()
+        };
+        assert_eq!((in_archive(&archives[0]), in_archive(&archives[1])), (1, 3));
+        Ok(())
+    }
 
-/// Update the destination location of an archive in the tracking database
-pub fn update_archive_destination(
-    catalog_db_path: &Path,
-    archive_path: &str,
-    destination_path: &str,
-) -> Result<()> {
-    let mut catalog = BackupCatalog::new(catalog_db_path)?;
-    if let Ok(mut tracker) = ArchiveTracker::new(catalog.get_connection_mut()) {
-        tracker.update_archive_destination(archive_path, destination_path)?;
+    #[test]
+    fn test_watch_retries_failed_batch() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("old.txt"), b"already here")?;
+        let out = dir.path().join("out");
+        // A directory where the catalog should be makes every batch fail
+        let blocker = out.join(WATCH_CATALOG_NAME);
+        fs::create_dir_all(&blocker)?;
+
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let options = WatchOptions {
+            debounce: Duration::from_millis(100),
+            max_delay: Duration::from_millis(400),
+        };
+        let handle = watch_and_archive_with_options(&[src.clone()], &out, settings, options)?;
+        thread::sleep(Duration::from_millis(500));
+        assert!(handle.archives().is_empty());
+        assert!(handle.is_running());
+
+        // No new change is made; the failed batch is retried on its own
+        fs::remove_dir(&blocker)?;
+        let start = std::time::Instant::now();
+        while handle.archives().is_empty() && start.elapsed() < Duration::from_secs(20) {
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(handle.archives().len(), 1);
+
+        // Dropping the handle stops the worker instead of leaving it running
+        drop(handle);
+        fs::write(src.join("late.txt"), b"after drop")?;
+        thread::sleep(Duration::from_millis(500));
+        let archives = fs::read_dir(&out)?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tar.zst"))
+            .count();
+        assert_eq!(archives, 1);
+        Ok(())
     }
-    Ok(())
-}
 
-/// Result of archive extraction
-#[derive(Debug)]
-pub struct ExtractionResult {
-    pub files_extracted: usize,
-    pub total_size: u64,
-    pub decoded_files: usize,
-}
+    #[test]
+    fn test_rekey_archive_swaps_password() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let arc_path = dir.path().join("secret.arc");
+        {
+            let mut f = std::io::BufWriter::new(fs::File::create(&arc_path)?);
+            let mut arc = FreeArcWriter::new(
+                &mut f,
+                ArchiveOptions {
+                    compression: "lzma".to_string(),
+                    compression_level: 0,
+                    encryption: Some("aes-256".to_string()),
+                    password: Some("old secret".to_string()),
+                },
+            )?;
+            arc.add_file("a.txt", b"first file")?;
+            arc.add_file_stored("b.bin", &[7u8; 4096])?;
+            arc.finish()?.flush()?;
+        }
 
-/// Settings for extraction with decoding
-#[derive(Clone, Debug)]
-pub struct ExtractionSettings {
-    /// Decode BPG files back to original formats
-    pub decode_images: bool,
-    /// Quality for HEIC re-encoding (1-100)
-    pub heic_quality: u8,
-    /// Quality for JPEG output (1-100)
-    pub jpeg_quality: u8,
-}
+        rekey_archive(&arc_path, "old secret", "new secret", "blowfish")?;
 
-impl Default for ExtractionSettings {
-    fn default() -> Self {
-        Self {
-            decode_images: true,
-            heic_quality: 90,
-            jpeg_quality: 92,
+        let open = |pwd: &str| -> Result<FreeArcReader<fs::File>> {
+            FreeArcReader::new(fs::File::open(&arc_path)?, Some(pwd.to_string()))
+        };
+        let reader = open("new secret")?;
+        assert_eq!(reader.extract_file(0)?, b"first file");
+        assert_eq!(reader.extract_file(1)?, vec![7u8; 4096]);
+        assert!(reader.directory.data_blocks.iter().all(|b| b.compressor.contains("+blowfish-448")));
+        assert!(open("old secret").is_err());
+
+        // A failed re-key leaves the archive as it was
+        assert!(rekey_archive(&arc_path, "old secret", "other", "aes-256").is_err());
+        assert!(open("new secret").is_ok());
+        assert!(!arc_path.with_extension("rekey.tmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_files_include_exclude() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("photos");
+        for rel in ["a.jpg", "b.tmp", ".DS_Store", "trip/c.jpg", "trip/day1/d.JPG", "trip/.thumbnails/e.jpg"] {
+            let path = root.join(rel);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, rel)?;
         }
+        let names = |files: Vec<PathBuf>| {
+            let mut rels: Vec<String> = files
+                .iter()
+                .map(|f| f.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+                .collect();
+            rels.sort();
+            rels
+        };
+        let globs = |g: &[&str]| g.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let inputs = vec![root.clone()];
+
+        // Name-only globs apply at any depth; path globs reach into nested folders
+        let files = collect_files_filtered(
+            &inputs,
+            &[],
+            &globs(&["*.tmp", ".DS_Store", "**/.thumbnails/**"]),
+            SymlinkPolicy::Skip,
+        )?;
+        assert_eq!(names(files), ["a.jpg", "trip/c.jpg", "trip/day1/d.JPG"]);
+
+        // Case-sensitive, and an exclude beats a matching include
+        let files = collect_files_filtered(&inputs, &globs(&["*.jpg"]), &globs(&["trip/*"]), SymlinkPolicy::Skip)?;
+        assert_eq!(names(files), ["a.jpg", "trip/.thumbnails/e.jpg"]);
+
+        let files = collect_files_filtered(&inputs, &globs(&["trip/**/*.JPG"]), &[], SymlinkPolicy::Skip)?;
+        assert_eq!(names(files), ["trip/day1/d.JPG"]);
+
+        assert!(collect_files_filtered(&inputs, &globs(&["[unclosed"]), &[], SymlinkPolicy::Skip).is_err());
+        Ok(())
     }
-}
 
-/// Extract a .tar.zst archive to a directory with progress reporting.
-pub fn extract_archive(
-    archive_path: &Path,
-    output_dir: &Path,
-    compression_level: i32,
-    progress: Option<Arc<ProgressFn>>,
-) -> Result<ExtractionResult> {
-    let settings = ExtractionSettings::default();
-    extract_archive_with_decoding(archive_path, output_dir, compression_level, settings, progress)
-}
+    #[test]
+    fn test_second_run_references_content_from_earlier_archive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let first_src = dir.path().join("laptop");
+        let second_src = dir.path().join("phone");
+        for src in [&first_src, &second_src] {
+            fs::create_dir_all(src)?;
+            fs::write(src.join("notes.txt"), "same notes")?;
+            fs::write(src.join("todo.txt"), "same todo")?;
+        }
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            catalog_path: Some(dir.path().join("shared.catalog.sqlite")),
+            ..OrchestratorSettings::default()
+        };
 
-/// Extract archive and decode images back to original formats
-pub fn extract_archive_with_decoding(
-    archive_path: &Path,
-    output_dir: &Path,
-    compression_level: i32,
-    settings: ExtractionSettings,
-    progress: Option<Arc<ProgressFn>>,
-) -> Result<ExtractionResult> {
-    if !archive_path.exists() {
-        return Err(anyhow!("Archive not found: {}", archive_path.display()));
+        let first = create_archive(&[first_src], &dir.path().join("first.tar.zst"), settings.clone(), None)?;
+        assert_eq!(first.processed.len(), 2);
+        assert!(first.referenced_from_catalog.is_empty());
+
+        // Different paths, so only the content hash can match
+        let second = create_archive(&[second_src.clone()], &dir.path().join("second.tar.zst"), settings, None)?;
+        assert!(second.processed.is_empty());
+        assert!(second.skipped_by_catalog.is_empty());
+        let mut referenced = second.referenced_from_catalog.clone();
+        referenced.sort();
+        assert_eq!(
+            referenced,
+            [
+                (second_src.join("notes.txt"), "first.tar.zst".to_string()),
+                (second_src.join("todo.txt"), "first.tar.zst".to_string()),
+            ]
+        );
+
+        // The references are catalogued, so a third run skips them by path
+        let catalog = BackupCatalog::new(dir.path().join("shared.catalog.sqlite"))?;
+        assert_eq!(catalog.should_skip_file(second_src.join("notes.txt"))?, Some(true));
+
+        // Once the archive is gone its content no longer counts as stored
+        let notes = hash::sha256_bytes_hex(b"same notes");
+        assert_eq!(catalog.contains_hash(&notes)?.as_deref(), Some("first.tar.zst"));
+        fs::remove_file(dir.path().join("first.tar.zst"))?;
+        assert_eq!(catalog.contains_hash(&notes)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unencodable_video_is_stored_as_is() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("clip.mov"), b"not really a movie")?;
+        fs::write(src.join("notes.txt"), "notes")?;
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        assert!(!settings.fail_fast);
+
+        let result = create_archive(&[src.clone()], &dir.path().join("out.tar.zst"), settings, None)?;
+        assert!(result.failed.is_empty());
+        assert_eq!(result.processed.len(), 2);
+        let clip = result
+            .processed
+            .iter()
+            .find(|p| p.original_path == src.join("clip.mov"))
+            .expect("clip archived");
+        assert!(clip.skipped_processing);
+        assert_eq!(clip.archived_rel_path, "media/clip.mov");
+        assert_eq!(clip.output_size, 18);
+        Ok(())
     }
 
-    fs::create_dir_all(output_dir)
-        .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+    #[test]
+    fn test_failed_input_is_reported_and_others_archived() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        let inputs: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"].iter().map(|n| src.join(n)).collect();
+        for p in &inputs {
+            fs::write(p, format!("contents of {}", p.display()))?;
+        }
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
 
-    if let Some(ref cb) = progress {
-        cb(0, 1, "Extracting archive...");
+        // Each send waits for the receiver, so when the first FileStarted arrives no
+        // other file has been opened yet; removing one then makes it fail for real
+        let (tx, rx) = flume::bounded::<ArchiveEvent>(0);
+        let watched = inputs.clone();
+        let listener = thread::spawn(move || {
+            let mut victim = None;
+            for event in rx.iter() {
+                if let (ArchiveEvent::FileStarted { path }, None) = (&event, &victim) {
+                    let v = watched.iter().find(|p| *p != path).unwrap().clone();
+                    fs::remove_file(&v).unwrap();
+                    victim = Some(v);
+                }
+            }
+            victim.unwrap()
+        });
+        let result = create_archive_inner(&inputs, &output, settings, None, Some(&tx));
+        drop(tx);
+        let victim = listener.join().unwrap();
+        let result = result?;
+
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, victim);
+        let mut archived: Vec<&PathBuf> = result.processed.iter().map(|p| &p.original_path).collect();
+        archived.sort();
+        let expected: Vec<&PathBuf> = inputs.iter().filter(|p| **p != victim).collect();
+        assert_eq!(archived, expected);
+        assert!(output.exists());
+        Ok(())
     }
 
-    // Extract the archive
-    let zstd = make_zstd(compression_level);
-    zstd.extract_tar_zst(archive_path, output_dir)
-        .with_context(|| format!("Failed to extract archive: {}", archive_path.display()))?;
+    #[test]
+    fn test_split_archive_extracts_identically() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        // Incompressible content so the archive spans several volumes
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut noise = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect()
+        };
+        let files = [("a.bin", noise(9000)), ("b.bin", noise(7000)), ("c.txt", b"small".to_vec())];
+        for (name, data) in &files {
+            fs::write(src.join(name), data)?;
+        }
 
-    let mut decoded_count = 0usize;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            split_size: Some(4096),
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&[src], &output, settings, None)?;
+        assert!(!output.exists());
+        assert!(result.archives.len() >= 3, "{:?}", result.archives);
+        assert_eq!(result.archives[0], dir.path().join("out.tar.zst.001"));
+        for volume in &result.archives[..result.archives.len() - 1] {
+            assert_eq!(fs::metadata(volume)?.len(), 4096);
+        }
 
-    // Load metadata if available
-    let metadata_path = output_dir.join("OPENARC_METADATA.json");
-    let metadata: Option<ArchiveMetadata> = if metadata_path.exists() {
-        let content = fs::read_to_string(&metadata_path)?;
-        serde_json::from_str(&content).ok()
-    } else {
-        None
-    };
+        let extracted = dir.path().join("extracted");
+        let verify = ExtractionSettings {
+            verify: true,
+            ..ExtractionSettings::default()
+        };
+        extract_archive_with_decoding(&output, &extracted, 3, verify, None)?;
+        for (name, data) in &files {
+            assert_eq!(&fs::read(extracted.join("misc").join(name))?, data);
+        }
 
-    // Decode images if settings allow and metadata exists
-    if settings.decode_images {
-        if let Some(meta) = metadata {
-            let total_images = meta.images.len();
+        // Transcoding reads the volumes too
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let transcoded = transcode_archive(&output, &dir.path().join("re.tar.zst"), settings, None)?;
+        let total: u64 = result.archives.iter().map(|v| fs::metadata(v).map(|m| m.len())).sum::<std::io::Result<u64>>()?;
+        assert_eq!(transcoded.input_archive_size, total);
+        Ok(())
+    }
 
-            for (idx, img_meta) in meta.images.iter().enumerate() {
-                if let Some(ref cb) = progress {
-                    cb(idx, total_images, &img_meta.bpg_filename);
-                }
+    #[test]
+    fn test_dry_run_writes_nothing() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("a.txt"), "same content")?;
+        fs::write(src.join("b.txt"), "same content")?;
+        fs::write(src.join("c.txt"), "different")?;
+
+        let output = dir.path().join("out.tar.zst");
+        let catalog_path = dir.path().join("out.catalog.sqlite");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            catalog_path: Some(catalog_path.clone()),
+            dry_run: true,
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&[src], &output, settings, None)?;
+
+        assert_eq!(result.discovered_files.len(), 3);
+        assert!(result.processed.is_empty());
+        assert!(result.archives.is_empty());
+        assert_eq!(result.dedup_groups, 2);
+        assert_eq!(result.planned_files, 2);
+        assert_eq!(result.planned_bytes, ("same content".len() + "different".len()) as u64);
+        assert!(!output.exists());
+        assert!(!catalog_path.exists());
+        // The staging area holds nothing either
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
 
-                let bpg_path = output_dir.join("media").join(&img_meta.bpg_filename);
-                if !bpg_path.exists() {
-                    continue;
-                }
+    #[test]
+    fn test_unknown_cipher_fails_before_encoding() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "private notes")?;
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            encryption: Some(("rot13".to_string(), "hunter2".to_string())),
+            ..OrchestratorSettings::default()
+        };
+        let err = create_archive(&[src], &output, settings, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown encryption method"));
+        assert!(!output.exists());
+        // Nothing was staged either
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
 
-                let result = decode_bpg_to_original(
-                    &bpg_path,
-                    img_meta.original_format,
-                    &img_meta.original_filename,
-                    &settings,
-                );
+    #[test]
+    fn test_encrypted_misc_round_trip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "private notes")?;
+        fs::write(src.join("todo.txt"), "private todo")?;
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            verify_after_create: true,
+            encryption: Some(("aes-256".to_string(), "hunter2".to_string())),
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
+        // No plaintext copy next to misc.arc
+        assert!(extract_archive_entry(&output, "misc/notes.txt", &dir.path().join("x")).is_err());
+
+        let with_password = |password: Option<&str>| ExtractionSettings {
+            verify: true,
+            password: password.map(str::to_string),
+            ..ExtractionSettings::default()
+        };
+        let err = extract_archive_with_decoding(&output, &dir.path().join("none"), 3, with_password(None), None)
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::PasswordRequired)));
+        let err = extract_archive_with_decoding(&output, &dir.path().join("wrong"), 3, with_password(Some("nope")), None)
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidPassword)));
+
+        let extracted = dir.path().join("extracted");
+        extract_archive_with_decoding(&output, &extracted, 3, with_password(Some("hunter2")), None)?;
+        assert_eq!(fs::read_to_string(extracted.join("misc/notes.txt"))?, "private notes");
+        assert_eq!(fs::read_to_string(extracted.join("misc/todo.txt"))?, "private todo");
+
+        let selected = dir.path().join("selected");
+        let patterns = vec!["misc/todo.txt".to_string()];
+        assert_eq!(extract_matching(&output, &selected, &patterns, with_password(Some("hunter2")), None)?, 1);
+        assert_eq!(fs::read_to_string(selected.join("misc/todo.txt"))?, "private todo");
+        assert!(!selected.join("misc/notes.txt").exists());
+
+        // Merging would rename misc.arc and lose its cipher, so it's refused
+        let merged = dir.path().join("merged.tar.zst");
+        let err = merge_archives(&[output.clone()], &merged).unwrap_err();
+        assert!(err.to_string().contains("misc files are encrypted"), "{:#}", err);
+        assert!(!merged.exists());
+        Ok(())
+    }
 
-                match result {
-                    Ok(output_path) => {
-                        // Remove the BPG file after successful decode
-                        let _ = fs::remove_file(&bpg_path);
-                        decoded_count += 1;
+    #[test]
+    fn test_seekable_archive_entry_extraction() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "seekable notes")?;
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            seekable: true,
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
+        assert!(zstd_archive::is_seekable(&mut fs::File::open(&output)?)?);
+
+        let manifest = dir.path().join("MANIFEST.txt");
+        extract_archive_entry(&output, "MANIFEST.txt", &manifest)?;
+        assert!(fs::read_to_string(&manifest)?.contains("notes.txt"));
+        assert!(extract_archive_entry(&output, "missing.txt", &dir.path().join("x")).is_err());
+
+        // Full extraction reads seekable archives like any other
+        let extracted = dir.path().join("extracted");
+        extract_archive_with_decoding(&output, &extracted, 3, ExtractionSettings::default(), None)?;
+        assert_eq!(fs::read_to_string(extracted.join("misc").join("notes.txt"))?, "seekable notes");
+        Ok(())
+    }
 
-                        // Rename to original filename if different
-                        let target_name = format!(
-                            "{}.{}",
-                            Path::new(&img_meta.original_filename)
-                                .file_stem()
-                                .and_then(|s| s.to_str())
-                                .unwrap_or("image"),
-                            img_meta.original_format.extraction_extension()
-                        );
-                        let target_path = output_path.parent().unwrap().join(&target_name);
-                        if output_path != target_path {
-                            let _ = fs::rename(&output_path, &target_path);
-                        }
-                    }
-                    Err(e) => {
-                        warn!(
-                            "decode_failed file={} error={}",
-                            img_meta.bpg_filename,
-                            e
-                        );
-                    }
-                }
-            }
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub"))?;
+        fs::write(src.join("real.txt"), "real")?;
+        std::os::unix::fs::symlink("real.txt", src.join("link.txt"))?;
+        std::os::unix::fs::symlink("..", src.join("sub/loop"))?;
+        let inputs = vec![src.clone()];
+        let names = |policy| -> Result<Vec<String>> {
+            let mut names: Vec<String> = collect_files_filtered(&inputs, &[], &[], policy)?
+                .iter()
+                .map(|f| f.strip_prefix(&src).unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            Ok(names)
+        };
+
+        assert_eq!(names(SymlinkPolicy::Skip)?, ["real.txt"]);
+        // The loop back to `src` is detected rather than walked forever
+        assert_eq!(names(SymlinkPolicy::Follow)?, ["link.txt", "real.txt"]);
+        assert_eq!(names(SymlinkPolicy::Preserve)?, ["link.txt", "real.txt", "sub/loop"]);
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            symlinks: SymlinkPolicy::Preserve,
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&inputs, &output, settings, None)?;
+        assert_eq!(result.processed.len(), 1);
+
+        let extracted = dir.path().join("extracted");
+        extract_archive_with_decoding(&output, &extracted, 3, ExtractionSettings::default(), None)?;
+        let link = extracted.join("misc/link.txt");
+        assert!(link.symlink_metadata()?.file_type().is_symlink());
+        assert_eq!(fs::read_link(&link)?, PathBuf::from("real.txt"));
+        assert_eq!(fs::read_to_string(&link)?, "real");
+        assert_eq!(fs::read_link(extracted.join("misc/loop"))?, PathBuf::from(".."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_filter_bounds_are_inclusive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        for (name, len) in [("tiny.txt", 9), ("min.txt", 10), ("mid.txt", 15), ("max.txt", 20), ("huge.txt", 21)] {
+            fs::write(src.join(name), vec![b'x'; len])?;
         }
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            sorted: true,
+            min_file_size: Some(10),
+            max_file_size: Some(20),
+            ..OrchestratorSettings::default()
+        };
+        let result = create_archive(&[src.clone()], &dir.path().join("out.tar.zst"), settings.clone(), None)?;
 
-        // Clean up metadata file
-        let _ = fs::remove_file(&metadata_path);
+        let kept: Vec<_> = result.discovered_files.iter().map(|p| p.file_name().unwrap().to_owned()).collect();
+        assert_eq!(kept, ["max.txt", "mid.txt", "min.txt"]);
+        let mut skipped = result.skipped_by_filter.clone();
+        skipped.sort();
+        assert_eq!(skipped, [(src.join("huge.txt"), 21), (src.join("tiny.txt"), 9)]);
+
+        let inverted = OrchestratorSettings {
+            min_file_size: Some(21),
+            ..settings
+        };
+        assert!(create_archive(&[src], &dir.path().join("bad.tar.zst"), inverted, None).is_err());
+        Ok(())
     }
 
-    // Calculate final stats
-    let mut files_extracted = 0usize;
-    let mut total_size = 0u64;
+    #[test]
+    fn test_subtree_prefix_matches_whole_components() {
+        let prefix = normalize_subtree_prefix("./media/2023/");
+        assert_eq!(prefix, "media/2023/");
+        assert_eq!(normalize_subtree_prefix("media\\2023"), prefix);
+        assert!(in_subtree("media/2023/a.bpg", &prefix));
+        assert!(in_subtree("media/2023/", &prefix));
+        assert!(in_subtree("media/2023", &prefix));
+        assert!(!in_subtree("media/20230/a.bpg", &prefix));
+        assert!(!in_subtree("media/2023.bpg", &prefix));
+        assert!(in_subtree("MANIFEST.txt", &normalize_subtree_prefix("/")));
+    }
 
-    for entry in walkdir::WalkDir::new(output_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        files_extracted += 1;
-        if let Ok(meta) = entry.metadata() {
-            total_size += meta.len();
-        }
+    #[test]
+    fn test_extract_subtree_writes_only_matching_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "notes")?;
+        fs::write(src.join("todo.txt"), "todo")?;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
+
+        let extracted = dir.path().join("extracted");
+        extract_subtree(&output, "misc/", &extracted, ExtractionSettings::default(), None)?;
+        assert_eq!(fs::read_to_string(extracted.join("misc/notes.txt"))?, "notes");
+        assert_eq!(fs::read_to_string(extracted.join("misc/todo.txt"))?, "todo");
+        assert!(!extracted.join("MANIFEST.txt").exists());
+        assert!(!extracted.join("HASHES.sha256").exists());
+
+        // "mis" is not a path component of "misc/..."
+        let partial = dir.path().join("partial");
+        assert!(extract_subtree(&output, "mis", &partial, ExtractionSettings::default(), None).is_err());
+        Ok(())
     }
 
-    if let Some(ref cb) = progress {
-        cb(1, 1, "Extraction complete");
+    #[test]
+    fn test_extract_matching_streams_once_for_all_patterns() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "notes")?;
+        fs::write(src.join("todo.txt"), "todo")?;
+        fs::write(src.join("data.csv"), "a,b")?;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
+
+        let extracted = dir.path().join("extracted");
+        let patterns = vec!["*.csv".to_string(), "./misc/notes.*".to_string()];
+        let matched = extract_matching(&output, &extracted, &patterns, ExtractionSettings::default(), None)?;
+        assert_eq!(matched, 2);
+        assert_eq!(fs::read_to_string(extracted.join("misc/data.csv"))?, "a,b");
+        assert_eq!(fs::read_to_string(extracted.join("misc/notes.txt"))?, "notes");
+        assert!(!extracted.join("misc/todo.txt").exists());
+        assert!(!extracted.join("MANIFEST.txt").exists());
+
+        let none = dir.path().join("none");
+        let nothing = vec!["*.mp4".to_string()];
+        assert_eq!(extract_matching(&output, &none, &nothing, ExtractionSettings::default(), None)?, 0);
+        assert!(extract_matching(&output, &none, &["[".to_string()], ExtractionSettings::default(), None).is_err());
+        Ok(())
     }
 
-    Ok(ExtractionResult {
-        files_extracted,
-        total_size,
-        decoded_files: decoded_count,
-    })
-}
+    #[test]
+    fn test_extract_verify_reports_mismatches() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), "notes")?;
+        fs::write(src.join("todo.txt"), "todo")?;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
 
-/// Decode a BPG file back to its original format
-fn decode_bpg_to_original(
-    bpg_path: &Path,
-    original_format: OriginalImageFormat,
-    _original_filename: &str,
-    settings: &ExtractionSettings,
-) -> Result<PathBuf> {
-    let stem = bpg_path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
-    let parent = bpg_path.parent().unwrap_or(Path::new("."));
+        let verify = ExtractionSettings {
+            verify: true,
+            ..ExtractionSettings::default()
+        };
+        let extracted = dir.path().join("extracted");
+        let result = extract_archive_with_decoding(&output, &extracted, 3, verify.clone(), None)?;
+        assert!(result.verified_files >= 3);
 
-    match original_format {
-        OriginalImageFormat::Jpeg => {
-            // BPG → JPEG directly
-            let output_path = parent.join(format!("{}.jpg", stem));
-            decode_bpg_to_jpeg(bpg_path, &output_path, settings.jpeg_quality)?;
-            Ok(output_path)
-        }
-        #[cfg(feature = "heif")]
-        OriginalImageFormat::Heic => {
-            // BPG → PNG → HEIC
-            let temp_png = parent.join(format!("{}_temp.png", stem));
-            let output_path = parent.join(format!("{}.heic", stem));
+        // Repack with one file altered after HASHES.sha256 was written
+        fs::write(extracted.join("misc/notes.txt"), "tampered")?;
+        let tampered = dir.path().join("tampered.tar.zst");
+        ZstdCodec::new(ZstdOptions::default()).archive_dir_tar_zst(&extracted, &tampered)?;
 
-            decode_bpg_to_png(bpg_path, &temp_png)?;
+        let err = extract_archive_with_decoding(&tampered, &dir.path().join("bad"), 3, verify, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("misc/notes.txt"), "{}", err);
+        assert!(!err.contains("misc/todo.txt"), "{}", err);
+        Ok(())
+    }
 
-            if HeicCodec::is_available() {
-                let codec = HeicCodec::new()?;
-                let config = HeicEncoderConfig {
-                    quality: settings.heic_quality,
-                    lossless: false,
-                    format: HeifCompressionFormat::HEVC,
-                };
-                codec.png_to_heic(&temp_png, &output_path, &config)?;
-                let _ = fs::remove_file(&temp_png);
-                Ok(output_path)
-            } else {
-                // Fallback to PNG if HEIC encoding not available
-                let png_output = parent.join(format!("{}.png", stem));
-                fs::rename(&temp_png, &png_output)?;
-                Ok(png_output)
-            }
+    #[test]
+    fn test_extract_restores_original_mtime() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        let notes = src.join("notes.txt");
+        fs::write(&notes, "notes")?;
+        let original = std::time::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        fs::File::options().write(true).open(&notes)?.set_modified(original)?;
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            enable_catalog: false,
+            ..OrchestratorSettings::default()
+        };
+        create_archive(&[src], &output, settings, None)?;
+
+        let extracted = dir.path().join("extracted");
+        extract_archive_with_decoding(&output, &extracted, 3, ExtractionSettings::default(), None)?;
+        let restored = fs::metadata(extracted.join("misc/notes.txt"))?.modified()?;
+        assert_eq!(restored, original);
+
+        // Times are only set on regular files below the output directory
+        let outside = dir.path().join("outside.txt");
+        fs::write(&outside, "outside")?;
+        let before = fs::metadata(&outside)?.modified()?;
+        let out = dir.path().join("crafted");
+        fs::create_dir_all(&out)?;
+        let mut meta = ArchiveMetadata::default();
+        meta.mtimes.insert("../outside.txt".to_string(), 1_000_000_000);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&outside, out.join("link.txt"))?;
+            meta.mtimes.insert("link.txt".to_string(), 1_000_000_000);
         }
-        #[cfg(not(feature = "heif"))]
-        OriginalImageFormat::Heic => {
-            // Fallback to PNG when HEIC support is not compiled
-            let output_path = parent.join(format!("{}.png", stem));
-            decode_bpg_to_png(bpg_path, &output_path)?;
-            Ok(output_path)
+        fs::write(out.join("OPENARC_METADATA.json"), serde_json::to_string(&meta)?)?;
+        finish_extraction(&out, &ExtractionSettings::default(), &None)?;
+        assert_eq!(fs::metadata(&outside)?.modified()?, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_create_writes_nothing() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("notes.txt"), b"hello")?;
+        let output = dir.path().join("out.tar.zst");
+
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            cancel_flag: Some(Arc::new(AtomicBool::new(true))),
+            ..OrchestratorSettings::default()
+        };
+        let err = create_archive(&[src], &output, settings, None).unwrap_err();
+        assert!(is_cancelled(&err));
+        assert!(!output.exists());
+
+        // The staging directory was cleaned up
+        let leftovers = fs::read_dir(dir.path())?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("openarc"))
+            .count();
+        assert_eq!(leftovers, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_concurrency_rejected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("out.tar.zst");
+        for (videos, images) in [(0, 2), (2, 0)] {
+            let settings = OrchestratorSettings {
+                max_concurrent_videos: videos,
+                max_concurrent_heavy_images: images,
+                ..OrchestratorSettings::default()
+            };
+            let err = create_archive(&[dir.path().to_path_buf()], &output, settings, None)
+                .unwrap_err();
+            assert!(err.to_string().contains("at least 1"));
         }
-        OriginalImageFormat::Raw | OriginalImageFormat::Png |
-        OriginalImageFormat::Tiff | OriginalImageFormat::Bmp | OriginalImageFormat::WebP => {
-            // BPG → PNG (RAW cannot be recreated, others convert to PNG for compatibility)
-            let output_path = parent.join(format!("{}.png", stem));
-            decode_bpg_to_png(bpg_path, &output_path)?;
-            Ok(output_path)
+        assert!(!output.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_codec_recorded_and_defaults_to_bpg() -> Result<()> {
+        // Archives written before the codec field was added are all BPG
+        let old = r#"{"original_filename":"a.jpg","original_format":"Jpeg","original_extension":"jpg","bpg_filename":"a_0.bpg"}"#;
+        let meta: ImageMetadata = serde_json::from_str(old)?;
+        assert_eq!(meta.codec, ImageOutputFormat::Bpg);
+
+        let avif = ImageMetadata { codec: ImageOutputFormat::Avif, ..meta };
+        let json = serde_json::to_string(&avif)?;
+        assert!(json.contains(r#""codec":"avif""#));
+        assert_eq!(serde_json::from_str::<ImageMetadata>(&json)?.codec, ImageOutputFormat::Avif);
+
+        assert_eq!(ImageOutputFormat::from_name("AVIF"), Some(ImageOutputFormat::Avif));
+        assert_eq!(ImageOutputFormat::from_name("webp"), None);
+        if !HeicCodec::is_available() {
+            assert_eq!(effective_image_format(ImageOutputFormat::Avif), ImageOutputFormat::Bpg);
         }
+        assert_eq!(effective_image_format(ImageOutputFormat::Bpg), ImageOutputFormat::Bpg);
+        Ok(())
     }
-}
 
-/// Decode BPG to PNG
-fn decode_bpg_to_png(bpg_path: &Path, output_path: &Path) -> Result<()> {
-    // Try native decoder first
-    match codecs::bpg::decode_file(&bpg_path.to_string_lossy()) {
-        Ok((data, width, height, _format)) => {
-            image::save_buffer(output_path, &data, width, height, image::ColorType::Rgba8)?;
-            Ok(())
+    #[test]
+    fn test_encoding_limits_validated_and_defaulted() -> Result<()> {
+        let defaults = OrchestratorSettings::default();
+        assert_eq!(defaults.encoding_threads(), DEFAULT_ENCODING_THREADS);
+        assert_eq!(defaults.memory_backoff_threshold(), DEFAULT_MEMORY_BACKOFF_THRESHOLD);
+
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("out.tar.zst");
+        let bad = [
+            OrchestratorSettings { encoding_threads: Some(0), ..OrchestratorSettings::default() },
+            OrchestratorSettings { memory_backoff_threshold: Some(0.0), ..OrchestratorSettings::default() },
+            OrchestratorSettings { memory_backoff_threshold: Some(1.5), ..OrchestratorSettings::default() },
+        ];
+        for settings in bad {
+            assert!(create_archive(&[dir.path().to_path_buf()], &output, settings, None).is_err());
         }
-        Err(_) => {
-            // Fall back to JS decoder
-            if codecs::bpg_js::is_bpg_js_available() {
-                codecs::bpg_js::bpg_js_to_png(bpg_path, output_path)
-            } else {
-                Err(anyhow!("No BPG decoder available"))
+        assert!(!output.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_preflight_flags_problems() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let good = dir.path().join("good.jpg");
+        let empty = dir.path().join("empty.jpg");
+        let missing = dir.path().join("missing.jpg");
+        fs::write(&good, b"data")?;
+        fs::write(&empty, b"")?;
+
+        let report = preflight_check(&[good, empty.clone(), missing.clone()]);
+        assert_eq!(report.files_checked, 3);
+        assert_eq!(report.total_bytes, 4);
+        assert_eq!(report.findings.len(), 2);
+        assert!(report.findings.contains(&PreflightFinding { path: empty, issue: PreflightIssue::Empty }));
+        assert!(report
+            .findings
+            .iter()
+            .any(|f| f.path == missing && matches!(f.issue, PreflightIssue::Unreadable(_))));
+        assert_eq!(report.unreadable_count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_archives_dedups_and_namespaces() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let build = |name: &str, files: &[(&str, &[u8])], images: &[(&str, &str)]| -> Result<PathBuf> {
+            let root = dir.path().join(format!("{}_tree", name));
+            fs::create_dir_all(root.join("media"))?;
+            let mut hashes = Vec::new();
+            for (rel, data) in files {
+                fs::write(root.join(rel), data)?;
+                hashes.push((hash::sha256_bytes_hex(data), rel.to_string()));
             }
-        }
+            hash::write_hashes_file(&hashes, root.join("HASHES.sha256"))?;
+            let mut meta = ArchiveMetadata::default();
+            for (original, bpg) in images {
+                meta.images.push(ImageMetadata {
+                    original_filename: original.to_string(),
+                    original_format: OriginalImageFormat::Jpeg,
+                    original_extension: "jpg".to_string(),
+                    bpg_filename: bpg.to_string(),
+                    codec: ImageOutputFormat::Bpg,
+                    chroma_format: None,
+                });
+            }
+            fs::write(root.join("OPENARC_METADATA.json"), serde_json::to_string(&meta)?)?;
+            let archive = dir.path().join(format!("{}.tar.zst", name));
+            make_zstd(3).archive_dir_tar_zst(&root, &archive)?;
+            Ok(archive)
+        };
+
+        let a = build(
+            "spring",
+            &[("media/IMG_1_0.bpg", &b"first"[..]), ("media/IMG_2_1.bpg", &b"shared"[..])],
+            &[("IMG_1.jpg", "IMG_1_0.bpg"), ("IMG_2.jpg", "IMG_2_1.bpg")],
+        )?;
+        let b = build(
+            "summer",
+            &[("media/IMG_1_0.bpg", &b"different"[..]), ("media/COPY_1.bpg", &b"shared"[..])],
+            &[("IMG_1.jpg", "IMG_1_0.bpg"), ("COPY.jpg", "COPY_1.bpg")],
+        )?;
+
+        let merged = dir.path().join("all.tar.zst");
+        merge_archives(&[a, b], &merged)?;
+
+        let out = dir.path().join("out");
+        make_zstd(3).extract_tar_zst(&merged, &out)?;
+        assert_eq!(fs::read(out.join("media/IMG_1_0.bpg"))?, &b"first"[..]);
+        assert_eq!(fs::read(out.join("media/summer_IMG_1_0.bpg"))?, &b"different"[..]);
+        assert!(!out.join("media/COPY_1.bpg").exists());
+
+        let meta: ArchiveMetadata = serde_json::from_str(&fs::read_to_string(out.join("OPENARC_METADATA.json"))?)?;
+        assert_eq!(meta.images.len(), 3);
+        assert!(meta.images.iter().any(|i| i.bpg_filename == "summer_IMG_1_0.bpg"));
+        assert_eq!(meta.duplicates.len(), 1);
+        assert_eq!(meta.duplicates[0].original_filename, "COPY.jpg");
+        assert_eq!(meta.duplicates[0].canonical_path, "media/IMG_2_1.bpg");
+
+        // The rebuilt hash list matches the merged contents
+        hash::verify_dir_against_hashes(&out, out.join("HASHES.sha256"))?;
+        Ok(())
     }
-}
 
-/// Decode BPG to JPEG
-fn decode_bpg_to_jpeg(bpg_path: &Path, output_path: &Path, quality: u8) -> Result<()> {
-    // Try native decoder first
-    match codecs::bpg::decode_file(&bpg_path.to_string_lossy()) {
-        Ok((data, width, height, _format)) => {
-            // Convert RGBA to RGB
-            let rgb_data: Vec<u8> = data.chunks(4)
-                .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
-                .collect();
+    #[test]
+    fn test_transcode_leaves_untouched_videos_alone() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("media"))?;
+        fs::write(root.join("media/IMG_1.MOV"), b"live photo clip")?;
+        fs::write(root.join("media/clip.mp4"), b"already compressed")?;
+        let mut meta = ArchiveMetadata::default();
+        meta.videos.push(VideoMetadata {
+            original_filename: "IMG_1.MOV".to_string(),
+            proxy_path: "media/IMG_1.MOV".to_string(),
+            original_path: None,
+            live_photo_image: Some("IMG_1.HEIC".to_string()),
+            stored_as_is: true,
+        });
+        meta.videos.push(VideoMetadata {
+            original_filename: "clip.mp4".to_string(),
+            proxy_path: "media/clip.mp4".to_string(),
+            original_path: None,
+            live_photo_image: None,
+            stored_as_is: true,
+        });
+        fs::write(root.join("OPENARC_METADATA.json"), serde_json::to_string(&meta)?)?;
+        let archive = dir.path().join("in.tar.zst");
+        make_zstd(3).archive_dir_tar_zst(&root, &archive)?;
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let result = transcode_archive(&archive, &output, settings, None)?;
+        assert_eq!(result.videos_reencoded, 0);
 
-            let img = image::RgbImage::from_raw(width, height, rgb_data)
-                .ok_or_else(|| anyhow!("Failed to create image buffer"))?;
+        let out = dir.path().join("out");
+        make_zstd(3).extract_tar_zst(&output, &out)?;
+        assert_eq!(fs::read(out.join("media/IMG_1.MOV"))?, &b"live photo clip"[..]);
+        assert_eq!(fs::read(out.join("media/clip.mp4"))?, &b"already compressed"[..]);
+        Ok(())
+    }
 
-            let mut file = fs::File::create(output_path)?;
-            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality);
-            img.write_with_encoder(encoder)?;
-            Ok(())
-        }
-        Err(_) => {
-            // Fall back to PNG then convert
-            let temp_png = output_path.with_extension("temp.png");
-            if codecs::bpg_js::is_bpg_js_available() {
-                codecs::bpg_js::bpg_js_to_png(bpg_path, &temp_png)?;
-                let img = image::open(&temp_png)?;
-                let rgb = img.to_rgb8();
-                let mut file = fs::File::create(output_path)?;
-                let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut file, quality);
-                rgb.write_with_encoder(encoder)?;
-                let _ = fs::remove_file(&temp_png);
-                Ok(())
+    #[test]
+    fn test_transcode_keeps_high_bit_depth_bpg() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("media"))?;
+        // Header only: 4:2:0, 10 bits, 4x4, followed by a stand-in payload
+        let bpg = [&b"BPG\xFB"[..], &[0x22, 0x00, 4, 4, 3][..], &b"hvc"[..]].concat();
+        fs::write(root.join("media/IMG_1_0.bpg"), &bpg)?;
+        let mut meta = ArchiveMetadata::default();
+        meta.images.push(ImageMetadata {
+            original_filename: "IMG_1.png".to_string(),
+            original_format: OriginalImageFormat::Png,
+            original_extension: "png".to_string(),
+            bpg_filename: "IMG_1_0.bpg".to_string(),
+            codec: ImageOutputFormat::Bpg,
+            chroma_format: None,
+        });
+        fs::write(root.join("OPENARC_METADATA.json"), serde_json::to_string(&meta)?)?;
+        let archive = dir.path().join("in.tar.zst");
+        make_zstd(3).archive_dir_tar_zst(&root, &archive)?;
+
+        let output = dir.path().join("out.tar.zst");
+        let settings = OrchestratorSettings {
+            staging_dir: Some(dir.path().to_path_buf()),
+            ..OrchestratorSettings::default()
+        };
+        let result = transcode_archive(&archive, &output, settings, None)?;
+        assert_eq!(result.images_reencoded, 0);
+
+        let out = dir.path().join("out");
+        make_zstd(3).extract_tar_zst(&output, &out)?;
+        assert_eq!(fs::read(out.join("media/IMG_1_0.bpg"))?, bpg);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_catalog_json_requires_existing_catalog() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let missing = dir.path().join("typo.sqlite");
+        let output = dir.path().join("export.json");
+        assert!(export_catalog_json(&missing, &output).is_err());
+        assert!(!missing.exists());
+        assert!(!output.exists());
+
+        let db_path = dir.path().join("catalog.sqlite");
+        BackupCatalog::new(&db_path)?.close()?;
+        let before = fs::read(&db_path)?;
+        export_catalog_json(&db_path, &output)?;
+        assert_eq!(fs::read_to_string(&output)?.trim(), "[]");
+        assert_eq!(fs::read(&db_path)?, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_helpers() {
+        assert_eq!(archive_label(Path::new("/a/trip 2023.tar.zst")), "trip_2023");
+        assert_eq!(namespaced_rel_path("media/x.bpg", "trip", 1), "media/trip_x.bpg");
+        assert_eq!(namespaced_rel_path("misc.arc", "trip", 2), "trip2_misc.arc");
+
+        let mut renames = HashMap::new();
+        renames.insert("media/x.bpg".to_string(), "media/trip_x.bpg".to_string());
+        assert_eq!(
+            rename_manifest_line("/p/x.jpg -> media/x.bpg (10 -> 5)", &renames),
+            "/p/x.jpg -> media/trip_x.bpg (10 -> 5)"
+        );
+        assert_eq!(rename_manifest_line("Processed files: 1", &renames), "Processed files: 1");
+    }
+
+    #[test]
+    fn test_auto_chroma_classification() {
+        // Screenshot-like: white background with saturated coloured bars and many colours in a gradient strip
+        let screenshot = image::RgbImage::from_fn(400, 300, |x, y| {
+            if y < 20 {
+                image::Rgb([(x % 256) as u8, (y * 10) as u8, 200])
+            } else if (x / 10) % 4 == 0 && y % 30 < 12 {
+                image::Rgb([220, 20, 20])
             } else {
-                Err(anyhow!("No BPG decoder available"))
+                image::Rgb([255, 255, 255])
             }
-        }
+        });
+        assert!(is_graphic_content(&image::DynamicImage::ImageRgb8(screenshot)));
+
+        // Photo-like: smooth gradient with per-pixel noise
+        let mut seed = 12345u32;
+        let photo = image::RgbImage::from_fn(400, 300, |x, y| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let noise = ((seed >> 16) % 9) as i32 - 4;
+            let v = |base: u32| (base as i32 + noise).clamp(0, 255) as u8;
+            image::Rgb([v(x * 255 / 400), v(y * 255 / 300), v(128)])
+        });
+        let photo = image::DynamicImage::ImageRgb8(photo);
+        assert!(!is_graphic_content(&photo));
+
+        let fixed = OrchestratorSettings::default();
+        assert_eq!(chroma_format_for(&photo, &fixed), fixed.bpg_chroma_format);
+        let auto = OrchestratorSettings {
+            auto_chroma: true,
+            ..OrchestratorSettings::default()
+        };
+        assert_eq!(chroma_format_for(&photo, &auto), BPG_CHROMA_420);
     }
 }
@@ -0,0 +1,165 @@
+// Standalone image transcoding for FFI use
+use anyhow::{anyhow, Context, Result};
+use bpg_viewer::universal_decode::UniversalDecodedImage;
+use codecs::bpg::{BPGImageFormat, NativeBPGEncoder};
+#[cfg(feature = "heif")]
+use codecs::heic::{HeicCodec, HeicEncoderConfig, HeifCompressionFormat};
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::bpg_wrapper::BpgConfig;
+
+/// Formats `transcode_image` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscodeTarget {
+    Bpg,
+    Avif,
+    Heic,
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl TranscodeTarget {
+    /// Parse a format name or extension ("jpg", ".AVIF", ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().trim_start_matches('.').to_ascii_lowercase().as_str() {
+            "bpg" => Some(Self::Bpg),
+            "avif" => Some(Self::Avif),
+            "heic" | "heif" => Some(Self::Heic),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::WebP),
+            _ => None,
+        }
+    }
+}
+
+/// Decode any image the viewer understands and re-encode it as `target`
+///
+/// `quality` (1-100) applies to JPEG, HEIC and AVIF; BPG is encoded with `bpg`,
+/// and PNG and WebP are always lossless. Returns the size of the written file.
+pub fn transcode_image(
+    input: &Path,
+    output: &Path,
+    target: TranscodeTarget,
+    quality: u8,
+    bpg: &BpgConfig,
+) -> Result<u64> {
+    let decoded = UniversalDecodedImage::decode_file(input)
+        .with_context(|| format!("Failed to decode {}", input.display()))?;
+    let (width, height) = (decoded.width, decoded.height);
+    let quality = quality.clamp(1, 100);
+
+    match target {
+        TranscodeTarget::Bpg => {
+            let mut encoder = NativeBPGEncoder::new().context("Failed to create BPG encoder")?;
+            encoder.set_config(&bpg.to_encoder_config()).context("Failed to set BPG config")?;
+            let data = encoder
+                .encode_from_memory(&decoded.data, width, height, width * 4, BPGImageFormat::BGRA32)
+                .context("Failed to encode BPG")?;
+            fs::write(output, data)?;
+        }
+        TranscodeTarget::Jpeg => {
+            let rgb: Vec<u8> = decoded.data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0]]).collect();
+            let file = fs::File::create(output)
+                .with_context(|| format!("Failed to create {}", output.display()))?;
+            image::codecs::jpeg::JpegEncoder::new_with_quality(BufWriter::new(file), quality)
+                .encode(&rgb, width, height, image::ExtendedColorType::Rgb8)?;
+        }
+        TranscodeTarget::Png | TranscodeTarget::WebP => {
+            let format = if target == TranscodeTarget::Png {
+                image::ImageFormat::Png
+            } else {
+                image::ImageFormat::WebP
+            };
+            image::save_buffer_with_format(
+                output,
+                &bgra_to_rgba(&decoded.data),
+                width,
+                height,
+                image::ColorType::Rgba8,
+                format,
+            )?;
+        }
+        TranscodeTarget::Heic | TranscodeTarget::Avif => {
+            encode_heif(&decoded, output, target, quality)?;
+        }
+    }
+
+    Ok(fs::metadata(output)?.len())
+}
+
+fn bgra_to_rgba(bgra: &[u8]) -> Vec<u8> {
+    bgra.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect()
+}
+
+#[cfg(feature = "heif")]
+fn encode_heif(decoded: &UniversalDecodedImage, output: &Path, target: TranscodeTarget, quality: u8) -> Result<()> {
+    if !HeicCodec::is_available() {
+        return Err(anyhow!("HEIC/AVIF encoding not available - libheif not found"));
+    }
+    let config = HeicEncoderConfig {
+        quality,
+        lossless: false,
+        format: if target == TranscodeTarget::Avif {
+            HeifCompressionFormat::AV1
+        } else {
+            HeifCompressionFormat::HEVC
+        },
+    };
+    // Only keep the alpha plane when the image actually uses it
+    let has_alpha = decoded.data.chunks_exact(4).any(|p| p[3] != 255);
+    let data = if has_alpha {
+        bgra_to_rgba(&decoded.data)
+    } else {
+        decoded.data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0]]).collect()
+    };
+    HeicCodec::new()?.encode_to_file(&data, decoded.width, decoded.height, has_alpha, output, &config)
+}
+
+#[cfg(not(feature = "heif"))]
+fn encode_heif(_decoded: &UniversalDecodedImage, _output: &Path, _target: TranscodeTarget, _quality: u8) -> Result<()> {
+    Err(anyhow!("HEIC/AVIF support not compiled - enable heif feature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_from_name() {
+        assert_eq!(TranscodeTarget::from_name("JPG"), Some(TranscodeTarget::Jpeg));
+        assert_eq!(TranscodeTarget::from_name(".webp"), Some(TranscodeTarget::WebP));
+        assert_eq!(TranscodeTarget::from_name("heif"), Some(TranscodeTarget::Heic));
+        assert_eq!(TranscodeTarget::from_name("gif"), None);
+    }
+
+    #[test]
+    fn test_png_to_jpeg_and_back() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let png = dir.path().join("in.png");
+        let img = image::RgbImage::from_fn(32, 24, |x, y| image::Rgb([x as u8 * 8, y as u8 * 10, 128]));
+        img.save(&png)?;
+        let bpg = BpgConfig {
+            quality: 25,
+            lossless: false,
+            bit_depth: 8,
+            chroma_format: 1,
+            encoder_type: 0,
+            compression_level: 8,
+        };
+
+        let jpeg = dir.path().join("out.jpg");
+        let size = transcode_image(&png, &jpeg, TranscodeTarget::Jpeg, 85, &bpg)?;
+        assert_eq!(size, fs::metadata(&jpeg)?.len());
+        let decoded = image::open(&jpeg)?;
+        assert_eq!((decoded.width(), decoded.height()), (32, 24));
+
+        let back = dir.path().join("back.png");
+        transcode_image(&jpeg, &back, TranscodeTarget::Png, 0, &bpg)?;
+        assert_eq!(image::open(&back)?.width(), 32);
+        Ok(())
+    }
+}
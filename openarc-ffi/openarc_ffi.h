@@ -3,6 +3,59 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Returned by CreateArchive and ExtractArchive when their cancel token was triggered
+ */
+#define OPENARC_CANCELLED -2
+
+/**
+ * Failure codes returned by CreateArchive, ExtractArchive, ExtractArcArchive and VerifyArchive
+ *
+ * Success is 0 or a non-negative count. The detailed message of any failure
+ * is still available from GetOpenArcError.
+ */
+typedef enum OpenArcResult {
+  Ok = 0,
+  /**
+   * A required pointer argument was null
+   */
+  NullArgument = -1,
+  /**
+   * The cancel token was triggered
+   */
+  Cancelled = -2,
+  /**
+   * A file couldn't be read or written (missing, permissions, disk full, ...)
+   */
+  IoError = -3,
+  /**
+   * Archive or media data is corrupt or in an unexpected format
+   */
+  DecodeError = -4,
+  /**
+   * Content doesn't match the hashes recorded in the archive
+   */
+  VerificationFailed = -5,
+  /**
+   * A string argument wasn't valid UTF-8
+   */
+  InvalidArgument = -6,
+  /**
+   * Any other failure
+   */
+  Failed = -7,
+  /**
+   * The archive is encrypted and the password was wrong or not given
+   */
+  InvalidPassword = -8,
+} OpenArcResult;
+
+/**
+ * Opaque handle a caller can trigger to stop a running CreateArchive, ExtractArchive
+ * or phone backup
+ */
+typedef struct OpenArcCancelToken OpenArcCancelToken;
+
 typedef struct Option_ProgressCallback Option_ProgressCallback;
 
 /**
@@ -22,6 +75,10 @@ typedef struct CompressionSettings {
   bool enable_catalog;
   bool enable_dedup;
   bool skip_already_compressed_videos;
+  /**
+   * Simultaneous video encodes; each can use several GB (<= 0 selects the default of 2)
+   */
+  int max_concurrent_videos;
 } CompressionSettings;
 
 /**
@@ -66,19 +123,82 @@ typedef struct ArchiveRecordInfo {
   uint32_t file_count;
 } ArchiveRecordInfo;
 
+/**
+ * Password callback: write a NUL-terminated password for `archive_path` into
+ * `buffer` (capacity `buffer_len` bytes) and return 1, or return 0 to cancel.
+ */
+typedef int (*PasswordCallback)(const char *archive_path, char *buffer, int buffer_len);
+
 int ExtractArchiveEntry(const char *archive_path, const char *entry_name, const char *output_path);
 
+/**
+ * Create a cancel token; release it with OpenArcCancelTokenFree
+ */
+struct OpenArcCancelToken *OpenArcCancelTokenCreate(void);
+
+/**
+ * Ask the operations using `token` to stop; safe to call from any thread
+ */
+void OpenArcCancelTokenCancel(const struct OpenArcCancelToken *token);
+
+/**
+ * Free a token from OpenArcCancelTokenCreate. Operations already running keep
+ * their own reference, so this may be called before they return.
+ */
+void OpenArcCancelTokenFree(struct OpenArcCancelToken *token);
+
+/**
+ * Create an archive from `input_files`
+ *
+ * `cancel_token` may be null. When it is triggered the run stops, staging
+ * files are removed, no archive is written and OPENARC_CANCELLED is returned.
+ */
 int CreateArchive(const char *output_path,
                   const char *const *input_files,
                   int file_count,
                   const struct CompressionSettings *settings,
-                  struct Option_ProgressCallback callback);
+                  struct Option_ProgressCallback callback,
+                  const struct OpenArcCancelToken *cancel_token);
 
 int VerifyArchive(const char *archive_path);
 
+/**
+ * Read every entry of an archive and check it against its embedded hash list
+ *
+ * Returns the number of missing or mismatched entries (0 when the archive is
+ * intact), or -1 if the archive couldn't be read to the end. When problems are
+ * found the last error names the first of them.
+ */
+int VerifyArchiveFull(const char *archive_path);
+
+/**
+ * Extract an archive, decoding images with the default settings
+ *
+ * `cancel_token` may be null. When it is triggered extraction stops between
+ * entries, keeps what was already written and returns OPENARC_CANCELLED.
+ */
 int ExtractArchive(const char *archive_path,
                    const char *output_dir,
-                   struct Option_ProgressCallback callback);
+                   struct Option_ProgressCallback callback,
+                   const struct OpenArcCancelToken *cancel_token);
+
+/**
+ * Register the callback asked for passwords of encrypted archives, or clear it
+ * with null. The callback may be invoked from a worker thread.
+ */
+void SetPasswordCallback(PasswordCallback callback);
+
+/**
+ * Forget the passwords remembered for this session
+ */
+void ClearPasswordCache(void);
+
+/**
+ * Extract a FreeARC (.arc) or PeaZip (.pea) archive, asking the registered
+ * password callback when the archive is encrypted. Returns the entry count,
+ * or an OpenArcResult code such as InvalidPassword.
+ */
+int ExtractArcArchive(const char *archive_path, const char *output_dir);
 
 /**
  * Extract archive with optional decoding of BPG/HEIC files
@@ -88,24 +208,74 @@ int ExtractArchiveWithDecoding(const char *archive_path,
                                const struct ExtractionSettings *settings,
                                struct Option_ProgressCallback callback);
 
+/**
+ * Last error of a failed call made from the calling thread, or null
+ *
+ * Errors are kept per thread. The pointer stays valid until the next FFI call
+ * that fails on the same thread; copy the string before making one.
+ */
 const char *GetOpenArcError(void);
 
 void FreeCString(char *ptr);
 
 char *PhoneGetStatusJson(const char *phone_root);
 
+/**
+ * Back up the phone's pending files in archives of up to 500 files each
+ *
+ * `cancel_token` may be null. When it is triggered the backup stops at the
+ * next file; completed archives stay recorded in the device DB, the next run
+ * picks up the remaining files, and the number archived so far is returned.
+ */
 int PhoneArchivePendingFiles(const char *phone_root,
                              const char *output_path,
                              const struct CompressionSettings *settings,
-                             struct Option_ProgressCallback callback);
+                             struct Option_ProgressCallback callback,
+                             const struct OpenArcCancelToken *cancel_token);
 
+/**
+ * Back up the phone's pending files into size-bounded archives in `output_dir`,
+ * named after the date range they cover (see phone_dated_archive_path). The
+ * device DB is saved after each archive, so re-running after an interruption or
+ * a cancel continues with whatever is still pending. `chunk_bytes` of 0 puts
+ * everything in one archive. `cancel_token` may be null and behaves as in
+ * PhoneArchivePendingFiles. Returns the number of files archived.
+ */
+int PhoneArchivePendingFilesChunked(const char *phone_root,
+                                    const char *output_dir,
+                                    uint64_t chunk_bytes,
+                                    const struct CompressionSettings *settings,
+                                    struct Option_ProgressCallback callback,
+                                    const struct OpenArcCancelToken *cancel_token);
+
+/**
+ * Free the array and filenames returned by ListArchive
+ *
+ * `count` must be the `file_count` ListArchive reported, and each array must
+ * be freed exactly once.
+ */
+void FreeArchiveFileInfoArray(struct ArchiveFileInfo *files, int count);
+
+/**
+ * Older name for FreeArchiveFileInfoArray
+ */
 void FreeArchiveFileList(struct ArchiveFileInfo *files, int count);
 
 /**
  * List archive contents
+ *
+ * On success `*files` points to `*file_count` entries (null when the archive
+ * is empty). The array and its filename strings are owned by the caller and
+ * must be released exactly once with FreeArchiveFileInfoArray.
  */
 int ListArchive(const char *archive_path, int *file_count, struct ArchiveFileInfo **files);
 
+/**
+ * Quick JSON summary of an archive (format, file count, sizes, creation date,
+ * encryption) read from its headers and metadata only; free with FreeCString
+ */
+char *PeekArchive(const char *archive_path);
+
 /**
  * Update archive destination location
  */
@@ -113,6 +283,25 @@ int UpdateArchiveDestination(const char *catalog_db_path,
                              const char *archive_path,
                              const char *destination_path);
 
+/**
+ * Update archive description
+ */
+int UpdateArchiveDescription(const char *catalog_db_path,
+                             const char *archive_path,
+                             const char *description);
+
+/**
+ * Remove an archive record and its file mappings from the database, e.g. after
+ * the archive file was deleted; `archive_id` is `ArchiveRecordInfo::id`
+ */
+int DeleteArchiveRecord(const char *catalog_db_path, int64_t archive_id);
+
+/**
+ * Write every catalog entry to `output_path` as a JSON array, for auditing
+ * backup history outside the app
+ */
+int ExportCatalogJson(const char *catalog_db_path, const char *output_path);
+
 /**
  * Get all archives from the database
  */
@@ -121,10 +310,24 @@ int GetAllArchives(const char *catalog_db_path,
                    struct ArchiveRecordInfo **archives);
 
 /**
- * Free the memory allocated by GetAllArchives
+ * Get archives created in [start, end) (Unix seconds), newest first
+ */
+int GetArchivesInRange(const char *catalog_db_path,
+                       uint64_t start,
+                       uint64_t end,
+                       int *archive_count,
+                       struct ArchiveRecordInfo **archives);
+
+/**
+ * Free the memory allocated by GetAllArchives or GetArchivesInRange
  */
 void FreeArchivesArray(struct ArchiveRecordInfo *archives, int count);
 
+/**
+ * JSON savings summary for a catalog (original vs archived bytes); free with FreeCString
+ */
+char *GetCatalogSavingsJson(const char *catalog_db_path);
+
 /**
  * Encode a single image file to BPG
  */
@@ -132,9 +335,29 @@ int EncodeBpgFile(const char *input_path,
                   const char *output_path,
                   const struct CompressionSettings *settings);
 
+/**
+ * Convert a single image to "bpg", "avif", "heic", "jpeg", "png" or "webp";
+ * returns the output size in bytes, or -1 on error
+ */
+int64_t TranscodeImage(const char *input_path,
+                       const char *output_path,
+                       const char *target_format,
+                       int quality,
+                       const struct CompressionSettings *settings);
+
 /**
  * Encode a single video file with FFmpeg
  */
 int EncodeVideoFile(const char *input_path,
                     const char *output_path,
                     const struct CompressionSettings *settings);
+
+/**
+ * Generate a sprite sheet of `frames` evenly spaced frames from a video.
+ * tile_width of 0 uses the default (160px). Returns 1 if written, 0 if the
+ * video has fewer frames than requested (nothing written), -1 on error.
+ */
+int GenerateVideoSpriteSheet(const char *input_path,
+                             const char *output_path,
+                             int frames,
+                             int tile_width);
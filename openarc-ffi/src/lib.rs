@@ -1,8 +1,11 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::ptr;
 use std::slice;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,9 +14,14 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use openarc_core::orchestrator::{self, OrchestratorSettings};
+use openarc_core::arcmax::core::crypto::CryptoError;
+use openarc_core::arcmax::core::password::{CachingPasswordProvider, CallbackPasswordProvider};
 
-// Global error message storage (mutable)
-static LAST_ERROR: Mutex<Option<CString>> = Mutex::new(None);
+thread_local! {
+    // Each calling thread sees only the errors of its own calls, so concurrent
+    // calls from a multithreaded GUI can't clobber each other's message
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -93,7 +101,7 @@ pub struct CompressionSettings {
     pub bpg_quality: c_int,           // 0-51, lower = better quality (default: 25)
     pub bpg_lossless: bool,           // Enable lossless BPG compression
     pub bpg_bit_depth: c_int,         // 8-12 bit depth
-    pub bpg_chroma_format: c_int,     // 0=420, 1=444, 2=RGB
+    pub bpg_chroma_format: c_int,     // 0=420, 1=444, 2=RGB, -1=auto (per image)
     pub bpg_encoder_type: c_int,      // 0=default, 1=slow
     pub bpg_compression_level: c_int, // 1-9
     pub video_codec: c_int,           // 0=H264, 1=H265
@@ -103,6 +111,7 @@ pub struct CompressionSettings {
     pub enable_catalog: bool,         // Enable incremental backup tracking (default: true)
     pub enable_dedup: bool,           // Enable file deduplication (default: true)
     pub skip_already_compressed_videos: bool, // Skip re-encoding efficient videos (default: true)
+    pub max_concurrent_videos: c_int, // Simultaneous video encodes, ~several GB each (<= 0: default 2)
 }
 
 #[repr(C)]
@@ -116,19 +125,16 @@ pub struct ProgressInfo {
 
 pub type ProgressCallback = unsafe extern "C" fn(progress: ProgressInfo);
 
+/// Record the error for the calling thread. Must run on the thread that made
+/// the FFI call, i.e. after joining any worker thread.
 fn set_last_error(error: String) {
-    if let Ok(mut guard) = LAST_ERROR.lock() {
-        *guard = CString::new(error).ok();
-    }
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(error).ok());
 }
 
+/// The calling thread's last error, or null. The pointer stays valid until the
+/// next FFI call that fails on the same thread.
 fn get_last_error_ptr() -> *const c_char {
-    if let Ok(guard) = LAST_ERROR.lock() {
-        if let Some(ref msg) = *guard {
-            return msg.as_ptr();
-        }
-    }
-    ptr::null()
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |msg| msg.as_ptr()))
 }
 
 fn detect_file_type_ffi(file_path: &str) -> c_int {
@@ -145,6 +151,104 @@ fn detect_file_type_ffi(file_path: &str) -> c_int {
     }
 }
 
+/// Failure codes returned by CreateArchive, ExtractArchive, ExtractArcArchive and VerifyArchive
+///
+/// Success is 0 or a non-negative count. The detailed message of any failure
+/// is still available from GetOpenArcError.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenArcResult {
+    Ok = 0,
+    /// A required pointer argument was null
+    NullArgument = -1,
+    /// The cancel token was triggered
+    Cancelled = -2,
+    /// A file couldn't be read or written (missing, permissions, disk full, ...)
+    IoError = -3,
+    /// Archive or media data is corrupt or in an unexpected format
+    DecodeError = -4,
+    /// Content doesn't match the hashes recorded in the archive
+    VerificationFailed = -5,
+    /// A string argument wasn't valid UTF-8
+    InvalidArgument = -6,
+    /// Any other failure
+    Failed = -7,
+    /// The archive is encrypted and the password was wrong or not given
+    InvalidPassword = -8,
+}
+
+/// Returned by CreateArchive and ExtractArchive when their cancel token was triggered
+pub const OPENARC_CANCELLED: c_int = OpenArcResult::Cancelled as c_int;
+
+/// Classify a failed operation for its FFI return code
+fn error_code(err: &anyhow::Error) -> OpenArcResult {
+    if orchestrator::is_cancelled(err) {
+        return OpenArcResult::Cancelled;
+    }
+    if orchestrator::is_verification_failure(err) {
+        return OpenArcResult::VerificationFailed;
+    }
+    let password_error = err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<CryptoError>(),
+            Some(CryptoError::InvalidPassword | CryptoError::PasswordRequired)
+        )
+    });
+    if password_error {
+        return OpenArcResult::InvalidPassword;
+    }
+    // Context layers wrap the underlying error; the first I/O error decides
+    for cause in err.chain() {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            return match io.kind() {
+                std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => OpenArcResult::DecodeError,
+                _ => OpenArcResult::IoError,
+            };
+        }
+    }
+    OpenArcResult::Failed
+}
+
+/// Opaque handle a caller can trigger to stop a running CreateArchive, ExtractArchive
+/// or phone backup
+pub struct OpenArcCancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl OpenArcCancelToken {
+    /// The token's flag, or `None` for a null token
+    unsafe fn flag_of(token: *const OpenArcCancelToken) -> Option<Arc<AtomicBool>> {
+        token.as_ref().map(|t| t.flag.clone())
+    }
+}
+
+/// Create a cancel token; release it with OpenArcCancelTokenFree
+#[export_name = "OpenArcCancelTokenCreate"]
+pub extern "C" fn OpenArcCancelTokenCreate() -> *mut OpenArcCancelToken {
+    Box::into_raw(Box::new(OpenArcCancelToken { flag: Arc::new(AtomicBool::new(false)) }))
+}
+
+/// Ask the operations using `token` to stop; safe to call from any thread
+#[export_name = "OpenArcCancelTokenCancel"]
+pub unsafe extern "C" fn OpenArcCancelTokenCancel(token: *const OpenArcCancelToken) {
+    if let Some(token) = token.as_ref() {
+        token.flag.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Free a token from OpenArcCancelTokenCreate. Operations already running keep
+/// their own reference, so this may be called before they return.
+#[export_name = "OpenArcCancelTokenFree"]
+pub unsafe extern "C" fn OpenArcCancelTokenFree(token: *mut OpenArcCancelToken) {
+    if !token.is_null() {
+        drop(Box::from_raw(token));
+    }
+}
+
+/// Create an archive from `input_files`
+///
+/// `cancel_token` may be null. When it is triggered the run stops, staging
+/// files are removed, no archive is written and OPENARC_CANCELLED is returned.
 #[export_name = "CreateArchive"]
 pub unsafe extern "C" fn CreateArchive(
     output_path: *const c_char,
@@ -152,17 +256,18 @@ pub unsafe extern "C" fn CreateArchive(
     file_count: c_int,
     settings: *const CompressionSettings,
     callback: Option<ProgressCallback>,
+    cancel_token: *const OpenArcCancelToken,
 ) -> c_int {
     if output_path.is_null() || input_files.is_null() || settings.is_null() {
         set_last_error("Null pointer passed to CreateArchive".to_string());
-        return -1;
+        return OpenArcResult::NullArgument as c_int;
     }
 
     let output_path = match CStr::from_ptr(output_path).to_str() {
         Ok(s) => s,
         Err(_) => {
             set_last_error("Invalid output path string".to_string());
-            return -1;
+            return OpenArcResult::InvalidArgument as c_int;
         }
     };
 
@@ -172,14 +277,14 @@ pub unsafe extern "C" fn CreateArchive(
     for &ptr in input_slice {
         if ptr.is_null() {
             set_last_error("Null file path in input array".to_string());
-            return -1;
+            return OpenArcResult::NullArgument as c_int;
         }
         
         let path = match CStr::from_ptr(ptr).to_str() {
             Ok(s) => s,
             Err(_) => {
                 set_last_error("Invalid file path string in input array".to_string());
-                return -1;
+                return OpenArcResult::InvalidArgument as c_int;
             }
         };
         
@@ -187,6 +292,7 @@ pub unsafe extern "C" fn CreateArchive(
     }
 
     let compression_settings = *settings;
+    let cancel_flag = OpenArcCancelToken::flag_of(cancel_token);
     
     // Run in a blocking thread to avoid blocking the main thread
     match thread::spawn(move || -> Result<c_int> {
@@ -228,7 +334,8 @@ pub unsafe extern "C" fn CreateArchive(
             bpg_quality: compression_settings.bpg_quality,
             bpg_lossless: compression_settings.bpg_lossless,
             bpg_bit_depth: compression_settings.bpg_bit_depth,
-            bpg_chroma_format: compression_settings.bpg_chroma_format,
+            bpg_chroma_format: compression_settings.bpg_chroma_format.max(0),
+            auto_chroma: compression_settings.bpg_chroma_format < 0,
             bpg_encoder_type: compression_settings.bpg_encoder_type,
             bpg_compression_level: compression_settings.bpg_compression_level,
             video_preset,
@@ -240,6 +347,46 @@ pub unsafe extern "C" fn CreateArchive(
             staging_dir: None,
             heic_quality: 90,  // Default HEIC quality for extraction
             jpeg_quality: 92,  // Default JPEG quality for extraction
+            hash_chunk_size: None,
+            video_sprite_frames: None,
+            keep_original_video: false,
+            bake_orientation: false,
+            hash_algo: openarc_core::hash::HashAlgorithm::Sha256,
+            verify_after_create: false,
+            emit_sidecar_checksum: false,
+            preserve_live_photos: true,
+            on_access_error: orchestrator::AccessErrorPolicy::Skip,
+            on_case_collision: orchestrator::CaseCollisionPolicy::Rename,
+            sorted: false,
+            method_rules: None,
+            split_by_class: false,
+            location_mode: openarc_core::location::LocationMode::Off,
+            cancel_flag,
+            preflight_check: false,
+            catalog_path_base: None,
+            catalog_path: None,
+            max_concurrent_videos: if compression_settings.max_concurrent_videos > 0 {
+                compression_settings.max_concurrent_videos as usize
+            } else {
+                orchestrator::DEFAULT_MAX_CONCURRENT_VIDEOS
+            },
+            max_concurrent_heavy_images: orchestrator::DEFAULT_MAX_CONCURRENT_HEAVY_IMAGES,
+            encoding_threads: None,
+            memory_backoff_threshold: None,
+            image_output_format: orchestrator::ImageOutputFormat::Bpg,
+            avif_quality: 80,
+            archive_checksum: true,
+            preserve_metadata: false,
+            include_globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            min_file_size: None,
+            max_file_size: None,
+            symlinks: orchestrator::SymlinkPolicy::Skip,
+            split_size: None,
+            seekable: false,
+            dry_run: false,
+            fail_fast: false,
+            encryption: None,
         };
 
         let _res = orchestrator::create_archive(
@@ -253,14 +400,18 @@ pub unsafe extern "C" fn CreateArchive(
     }).join() {
         Ok(result) => match result {
             Ok(count) => count,
+            Err(e) if orchestrator::is_cancelled(&e) => {
+                set_last_error("Archive creation cancelled".to_string());
+                OPENARC_CANCELLED
+            }
             Err(e) => {
                 set_last_error(format!("Failed to create archive: {}", e));
-                -1
+                error_code(&e) as c_int
             }
         },
         Err(_) => {
             set_last_error("Thread panicked during archive creation".to_string());
-            -1
+            OpenArcResult::Failed as c_int
         }
     }
 }
@@ -271,19 +422,30 @@ pub unsafe extern "C" fn VerifyArchive(
 ) -> c_int {
     if archive_path.is_null() {
         set_last_error("Null pointer passed to verify_archive".to_string());
-        return -1;
+        return OpenArcResult::NullArgument as c_int;
     }
 
     let archive_path = match CStr::from_ptr(archive_path).to_str() {
         Ok(s) => std::path::Path::new(s).to_path_buf(),
         Err(_) => {
             set_last_error("Invalid archive path string".to_string());
-            return -1;
+            return OpenArcResult::InvalidArgument as c_int;
         }
     };
 
     match thread::spawn(move || -> Result<c_int> {
-        openarc_core::hash::verify_tar_zst_archive_with_level(&archive_path, 3)?;
+        // Stream the archive against its embedded hash list; nothing touches disk
+        let failures = openarc_core::hash::verify_embedded_hashes(&archive_path)?;
+        if let Some(first) = failures.first() {
+            return Err(orchestrator::VerificationFailed(format!(
+                "{} entr{} failed verification (first: {} {:?})",
+                failures.len(),
+                if failures.len() == 1 { "y" } else { "ies" },
+                first.rel_path,
+                first.kind
+            ))
+            .into());
+        }
         Ok(0)
     })
     .join()
@@ -292,9 +454,53 @@ pub unsafe extern "C" fn VerifyArchive(
             Ok(code) => code,
             Err(e) => {
                 set_last_error(format!("Failed to verify archive: {}", e));
-                -1
+                error_code(&e) as c_int
             }
         },
+        Err(_) => {
+            set_last_error("Thread panicked during archive verification".to_string());
+            OpenArcResult::Failed as c_int
+        }
+    }
+}
+
+/// Read every entry of an archive and check it against its embedded hash list
+///
+/// Returns the number of missing or mismatched entries (0 when the archive is
+/// intact), or -1 if the archive couldn't be read to the end. When problems are
+/// found the last error names the first of them.
+#[export_name = "VerifyArchiveFull"]
+pub unsafe extern "C" fn VerifyArchiveFull(archive_path: *const c_char) -> c_int {
+    if archive_path.is_null() {
+        set_last_error("Null pointer passed to verify_archive_full".to_string());
+        return -1;
+    }
+
+    let archive_path = match CStr::from_ptr(archive_path).to_str() {
+        Ok(s) => std::path::Path::new(s).to_path_buf(),
+        Err(_) => {
+            set_last_error("Invalid archive path string".to_string());
+            return -1;
+        }
+    };
+
+    match thread::spawn(move || openarc_core::hash::verify_archive_full(&archive_path)).join() {
+        Ok(Ok(report)) => {
+            if let Some(first) = report.failures.first() {
+                set_last_error(format!(
+                    "{} of {} entries failed verification (first: {} {:?})",
+                    report.problem_count(),
+                    report.problem_count() + report.verified.len(),
+                    first.rel_path,
+                    first.kind
+                ));
+            }
+            report.problem_count().min(c_int::MAX as usize) as c_int
+        }
+        Ok(Err(e)) => {
+            set_last_error(format!("Failed to verify archive: {}", e));
+            -1
+        }
         Err(_) => {
             set_last_error("Thread panicked during archive verification".to_string());
             -1
@@ -302,22 +508,27 @@ pub unsafe extern "C" fn VerifyArchive(
     }
 }
 
+/// Extract an archive, decoding images with the default settings
+///
+/// `cancel_token` may be null. When it is triggered extraction stops between
+/// entries, keeps what was already written and returns OPENARC_CANCELLED.
 #[export_name = "ExtractArchive"]
 pub unsafe extern "C" fn ExtractArchive(
     archive_path: *const c_char,
     output_dir: *const c_char,
     callback: Option<ProgressCallback>,
+    cancel_token: *const OpenArcCancelToken,
 ) -> c_int {
     if archive_path.is_null() || output_dir.is_null() {
         set_last_error("Null pointer passed to extract_archive".to_string());
-        return -1;
+        return OpenArcResult::NullArgument as c_int;
     }
 
     let archive_path = match CStr::from_ptr(archive_path).to_str() {
         Ok(s) => s,
         Err(_) => {
             set_last_error("Invalid archive path string".to_string());
-            return -1;
+            return OpenArcResult::InvalidArgument as c_int;
         }
     };
 
@@ -325,10 +536,12 @@ pub unsafe extern "C" fn ExtractArchive(
         Ok(s) => s,
         Err(_) => {
             set_last_error("Invalid output directory string".to_string());
-            return -1;
+            return OpenArcResult::InvalidArgument as c_int;
         }
     };
 
+    let cancel_flag = OpenArcCancelToken::flag_of(cancel_token);
+
     // Extract using openarc-core orchestrator
     match thread::spawn(move || -> Result<c_int> {
         let progress_fn: Option<Arc<orchestrator::ProgressFn>> = callback.map(|cb| {
@@ -353,10 +566,15 @@ pub unsafe extern "C" fn ExtractArchive(
             }) as Arc<orchestrator::ProgressFn>
         });
 
-        let result = orchestrator::extract_archive(
+        let settings = orchestrator::ExtractionSettings {
+            cancel_flag,
+            ..orchestrator::ExtractionSettings::default()
+        };
+        let result = orchestrator::extract_archive_with_decoding(
             std::path::Path::new(archive_path),
             std::path::Path::new(output_dir),
             3, // Default compression level for decompression
+            settings,
             progress_fn,
         )?;
 
@@ -364,14 +582,123 @@ pub unsafe extern "C" fn ExtractArchive(
     }).join() {
         Ok(result) => match result {
             Ok(count) => count,
+            Err(e) if orchestrator::is_cancelled(&e) => {
+                set_last_error("Archive extraction cancelled".to_string());
+                OPENARC_CANCELLED
+            }
             Err(e) => {
                 set_last_error(format!("Failed to extract archive: {}", e));
-                -1
+                error_code(&e) as c_int
             }
         },
         Err(_) => {
             set_last_error("Thread panicked during archive extraction".to_string());
-            -1
+            OpenArcResult::Failed as c_int
+        }
+    }
+}
+
+/// Password callback: write a NUL-terminated password for `archive_path` into
+/// `buffer` (capacity `buffer_len` bytes) and return 1, or return 0 to cancel.
+pub type PasswordCallback =
+    unsafe extern "C" fn(archive_path: *const c_char, buffer: *mut c_char, buffer_len: c_int) -> c_int;
+
+const PASSWORD_BUFFER_LEN: usize = 1024;
+
+static PASSWORD_CALLBACK: Mutex<Option<PasswordCallback>> = Mutex::new(None);
+static PASSWORD_PROVIDER: OnceLock<CachingPasswordProvider<CallbackPasswordProvider>> = OnceLock::new();
+
+fn request_password_from_callback(archive: &Path) -> Option<String> {
+    let cb = (*PASSWORD_CALLBACK.lock().ok()?)?;
+    let archive_c = CString::new(archive.to_string_lossy().as_bytes()).ok()?;
+    let mut buffer = vec![0u8; PASSWORD_BUFFER_LEN];
+
+    let ok = unsafe { cb(archive_c.as_ptr(), buffer.as_mut_ptr() as *mut c_char, buffer.len() as c_int) };
+    let password = if ok != 0 {
+        CStr::from_bytes_until_nul(&buffer).ok().and_then(|s| s.to_str().ok()).map(|s| s.to_string())
+    } else {
+        None
+    };
+
+    // Don't leave the password lying around in the scratch buffer
+    buffer.iter_mut().for_each(|b| *b = 0);
+    password
+}
+
+/// Session-wide provider; passwords accepted once are remembered until cleared
+fn password_provider() -> &'static CachingPasswordProvider<CallbackPasswordProvider> {
+    PASSWORD_PROVIDER.get_or_init(|| {
+        CachingPasswordProvider::new(CallbackPasswordProvider::new(request_password_from_callback))
+    })
+}
+
+/// Register the callback asked for passwords of encrypted archives, or clear it
+/// with null. The callback may be invoked from a worker thread.
+#[export_name = "SetPasswordCallback"]
+pub unsafe extern "C" fn SetPasswordCallback(callback: Option<PasswordCallback>) {
+    if let Ok(mut guard) = PASSWORD_CALLBACK.lock() {
+        *guard = callback;
+    }
+}
+
+/// Forget the passwords remembered for this session
+#[export_name = "ClearPasswordCache"]
+pub unsafe extern "C" fn ClearPasswordCache() {
+    password_provider().clear();
+}
+
+/// Extract a FreeARC (.arc) or PeaZip (.pea) archive, asking the registered
+/// password callback when the archive is encrypted. Returns the entry count,
+/// or an OpenArcResult code such as InvalidPassword.
+#[export_name = "ExtractArcArchive"]
+pub unsafe extern "C" fn ExtractArcArchive(
+    archive_path: *const c_char,
+    output_dir: *const c_char,
+) -> c_int {
+    if archive_path.is_null() || output_dir.is_null() {
+        set_last_error("Null pointer passed to extract_arc_archive".to_string());
+        return OpenArcResult::NullArgument as c_int;
+    }
+
+    let archive_path = match CStr::from_ptr(archive_path).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid archive path string".to_string());
+            return OpenArcResult::InvalidArgument as c_int;
+        }
+    };
+
+    let output_dir = match CStr::from_ptr(output_dir).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid output directory string".to_string());
+            return OpenArcResult::InvalidArgument as c_int;
+        }
+    };
+
+    match thread::spawn(move || -> Result<c_int> {
+        let mut reader = openarc_core::arcmax::core::format::detect_format_with_provider(
+            &archive_path,
+            password_provider(),
+            None,
+        )?;
+        fs::create_dir_all(&output_dir)?;
+        let count = reader.list()?.len();
+        reader.extract_all(&output_dir)?;
+        Ok(count as c_int)
+    })
+    .join()
+    {
+        Ok(result) => match result {
+            Ok(count) => count,
+            Err(e) => {
+                set_last_error(format!("Failed to extract archive: {}", e));
+                error_code(&e) as c_int
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during archive extraction".to_string());
+            OpenArcResult::Failed as c_int
         }
     }
 }
@@ -460,6 +787,16 @@ pub unsafe extern "C" fn ExtractArchiveWithDecoding(
             decode_images: ext_settings.decode_images,
             heic_quality: ext_settings.heic_quality as u8,
             jpeg_quality: ext_settings.jpeg_quality as u8,
+            dedup_as: orchestrator::DedupMode::Links,
+            decode_filter: None,
+            decode_formats: None,
+            restore_metadata: false,
+            cancel_flag: None,
+            verify: false,
+            password: None,
+            prefer_webp: false,
+            webp_quality: 85,
+            webp_lossless: false,
         };
 
         let result = orchestrator::extract_archive_with_decoding(
@@ -499,6 +836,10 @@ pub unsafe extern "C" fn DetectFileType(file_path: *const c_char) -> c_int {
     detect_file_type_ffi(path)
 }
 
+/// Last error of a failed call made from the calling thread, or null
+///
+/// Errors are kept per thread. The pointer stays valid until the next FFI call
+/// that fails on the same thread; copy the string before making one.
 #[export_name = "GetOpenArcError"]
 pub unsafe extern "C" fn GetOpenArcError() -> *const c_char {
     get_last_error_ptr()
@@ -722,12 +1063,267 @@ pub unsafe extern "C" fn PhoneGetStatusJson(phone_root: *const c_char) -> *mut c
     }
 }
 
+/// Files per archive when backing up a phone; the device DB is saved after each
+const PHONE_BACKUP_CHUNK_FILES: usize = 500;
+
+/// Archive path for the given chunk: `backup.tar.zst`, `backup_part2.tar.zst`, ...
+fn phone_chunk_archive_path(output_path: &Path, chunk_idx: usize) -> PathBuf {
+    if chunk_idx == 0 {
+        return output_path.to_path_buf();
+    }
+    let name = output_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "backup".to_string());
+    let (stem, ext) = match name.find('.') {
+        Some(i) => (&name[..i], &name[i..]),
+        None => (name.as_str(), ""),
+    };
+    output_path.with_file_name(format!("{}_part{}{}", stem, chunk_idx + 1, ext))
+}
+
+fn merge_into_phone_db(db: &mut PhoneDb, root: &Path, processed: &[orchestrator::ProcessedFile]) {
+    for pf in processed {
+        let p = &pf.original_path;
+        let meta = match fs::metadata(p) {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        let rel = normalize_rel_path(root, p);
+        db.files.retain(|x| x.path != rel);
+        db.files.push(PhoneDbEntry {
+            path: rel,
+            size: meta.len(),
+            mtime_secs: file_mtime_secs(&meta),
+        });
+    }
+    db.last_backup_at = now_secs();
+}
+
+/// Split pending files into runs of at most `chunk_bytes` (0 = no limit), oldest first,
+/// so each archive covers a contiguous date range. A file larger than the limit gets
+/// a chunk of its own. Each chunk comes with the mtimes of its oldest and newest file.
+fn phone_size_chunks(files: &[PathBuf], chunk_bytes: u64) -> Vec<(Vec<PathBuf>, u64, u64)> {
+    let mut sized: Vec<(PathBuf, u64, u64)> = files
+        .iter()
+        .map(|f| {
+            let (size, mtime) = fs::metadata(f)
+                .map(|m| (m.len(), file_mtime_secs(&m)))
+                .unwrap_or((0, 0));
+            (f.clone(), size, mtime)
+        })
+        .collect();
+    sized.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+    let mut chunks: Vec<(Vec<PathBuf>, u64, u64)> = Vec::new();
+    let mut current_bytes = 0u64;
+    for (path, size, mtime) in sized {
+        let fits = chunk_bytes == 0 || current_bytes.saturating_add(size) <= chunk_bytes;
+        match chunks.last_mut() {
+            Some((chunk, _, newest)) if fits => {
+                chunk.push(path);
+                *newest = mtime;
+                current_bytes += size;
+            }
+            _ => {
+                chunks.push((vec![path], mtime, mtime));
+                current_bytes = size;
+            }
+        }
+    }
+    chunks
+}
+
+/// `YYYY-MM-DD` (UTC) for a Unix timestamp.
+fn utc_date_string(secs: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar)
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Dated archive name for a chunk, e.g. `phone_2024-03-01_2024-05-12.tar.zst`.
+/// Names already on disk or in `taken` get a `_2`, `_3`, ... suffix.
+fn phone_dated_archive_path(output_dir: &Path, oldest: u64, newest: u64, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let (from, to) = (utc_date_string(oldest), utc_date_string(newest));
+    let stem = if from == to {
+        format!("phone_{}", from)
+    } else {
+        format!("phone_{}_{}", from, to)
+    };
+
+    let mut candidate = output_dir.join(format!("{}.tar.zst", stem));
+    let mut n = 2;
+    while candidate.exists() || taken.contains(&candidate) {
+        candidate = output_dir.join(format!("{}_{}.tar.zst", stem, n));
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// Orchestrator settings shared by the phone backup entry points (no catalog, unreadable files skipped).
+fn phone_orchestrator_settings(compression_settings: &CompressionSettings) -> OrchestratorSettings {
+    let video_preset = match (compression_settings.video_codec, compression_settings.video_speed) {
+        (0, 1) => 0,
+        (1, 1) => 1,
+        (0, 0) => 2,
+        (1, 2) => 3,
+        (0, _) => 2,
+        (1, _) => 1,
+        _ => 0,
+    };
+
+    OrchestratorSettings {
+        bpg_quality: compression_settings.bpg_quality,
+        bpg_lossless: compression_settings.bpg_lossless,
+        bpg_bit_depth: compression_settings.bpg_bit_depth,
+        bpg_chroma_format: compression_settings.bpg_chroma_format.max(0),
+        auto_chroma: compression_settings.bpg_chroma_format < 0,
+        bpg_encoder_type: compression_settings.bpg_encoder_type,
+        bpg_compression_level: compression_settings.bpg_compression_level,
+        video_preset,
+        video_crf: compression_settings.video_crf,
+        compression_level: compression_settings.compression_level,
+        enable_catalog: false,
+        enable_dedup: compression_settings.enable_dedup,
+        skip_already_compressed_videos: compression_settings.skip_already_compressed_videos,
+        staging_dir: None,
+        heic_quality: 90,
+        jpeg_quality: 92,
+        hash_chunk_size: None,
+        video_sprite_frames: None,
+        keep_original_video: false,
+        bake_orientation: false,
+        hash_algo: openarc_core::hash::HashAlgorithm::Sha256,
+        verify_after_create: false,
+        emit_sidecar_checksum: false,
+        preserve_live_photos: true,
+        on_access_error: orchestrator::AccessErrorPolicy::Skip,
+        on_case_collision: orchestrator::CaseCollisionPolicy::Rename,
+        sorted: false,
+        method_rules: None,
+        split_by_class: false,
+        location_mode: openarc_core::location::LocationMode::Off,
+        cancel_flag: None,
+        preflight_check: false,
+        catalog_path_base: None,
+        catalog_path: None,
+        max_concurrent_videos: if compression_settings.max_concurrent_videos > 0 {
+            compression_settings.max_concurrent_videos as usize
+        } else {
+            orchestrator::DEFAULT_MAX_CONCURRENT_VIDEOS
+        },
+        max_concurrent_heavy_images: orchestrator::DEFAULT_MAX_CONCURRENT_HEAVY_IMAGES,
+        encoding_threads: None,
+        memory_backoff_threshold: None,
+        image_output_format: orchestrator::ImageOutputFormat::Bpg,
+        avif_quality: 80,
+        archive_checksum: true,
+        preserve_metadata: false,
+        include_globs: Vec::new(),
+        exclude_globs: Vec::new(),
+        min_file_size: None,
+        max_file_size: None,
+        symlinks: orchestrator::SymlinkPolicy::Skip,
+        split_size: None,
+        seekable: false,
+        dry_run: false,
+        fail_fast: false,
+        encryption: None,
+    }
+}
+
+/// Archive each `(files, output)` chunk in turn and save the device DB after every
+/// one, so an interrupted or cancelled backup keeps everything already written.
+/// `orch_settings.cancel_flag` is checked between files; a cancelled chunk
+/// writes nothing and its files stay pending. Returns the number of files
+/// archived and a warning if the device DB couldn't be saved, for the caller
+/// to report on its own thread.
+fn archive_phone_chunks(
+    root: &Path,
+    db: &mut PhoneDb,
+    chunks: Vec<(Vec<PathBuf>, PathBuf)>,
+    orch_settings: &OrchestratorSettings,
+    callback: Option<ProgressCallback>,
+) -> Result<(usize, Option<String>)> {
+    let cancelled = || orch_settings.cancel_flag.as_ref().is_some_and(|f| f.load(Ordering::SeqCst));
+    let grand_total: usize = chunks.iter().map(|(files, _)| files.len()).sum();
+    let mut archived_count = 0usize;
+    let mut offset = 0usize;
+    let mut warning = None;
+
+    for (chunk, chunk_output) in &chunks {
+        if cancelled() {
+            break;
+        }
+
+        let chunk_offset = offset;
+        let progress_fn: Option<Arc<orchestrator::ProgressFn>> = callback.map(|cb| {
+            Arc::new(move |cur: usize, _total: usize, name: &str| {
+                let file_name_c = match CString::new(name) {
+                    Ok(s) => s.into_raw(),
+                    Err(_) => ptr::null(),
+                };
+
+                let overall = chunk_offset + cur;
+                let progress = ProgressInfo {
+                    current_file: overall as c_int,
+                    total_files: grand_total as c_int,
+                    progress_percent: if grand_total > 0 { (overall as f64 / grand_total as f64) * 100.0 } else { 0.0 },
+                    current_file_name: file_name_c,
+                };
+
+                unsafe { cb(progress) };
+
+                if !file_name_c.is_null() {
+                    unsafe { let _ = CString::from_raw(file_name_c as *mut c_char); }
+                }
+            }) as Arc<orchestrator::ProgressFn>
+        });
+
+        let res = match orchestrator::create_archive(
+            chunk,
+            chunk_output,
+            orch_settings.clone(),
+            progress_fn,
+        ) {
+            Ok(res) => res,
+            Err(e) if orchestrator::is_cancelled(&e) => break,
+            Err(e) => return Err(e),
+        };
+
+        merge_into_phone_db(db, root, &res.processed);
+        if let Err(e) = save_phone_db(root, db) {
+            warning = Some(e);
+        }
+        // Failed inputs stay pending in the device DB and aren't counted
+        archived_count += res.processed.len();
+        offset += chunk.len();
+    }
+
+    Ok((archived_count, warning))
+}
+
+/// Back up the phone's pending files in archives of up to 500 files each
+///
+/// `cancel_token` may be null. When it is triggered the backup stops at the
+/// next file; completed archives stay recorded in the device DB, the next run
+/// picks up the remaining files, and the number archived so far is returned.
 #[export_name = "PhoneArchivePendingFiles"]
 pub unsafe extern "C" fn PhoneArchivePendingFiles(
     phone_root: *const c_char,
     output_path: *const c_char,
     settings: *const CompressionSettings,
     callback: Option<ProgressCallback>,
+    cancel_token: *const OpenArcCancelToken,
 ) -> c_int {
     if phone_root.is_null() || output_path.is_null() || settings.is_null() {
         set_last_error("Null pointer passed to PhoneArchivePendingFiles".to_string());
@@ -751,8 +1347,9 @@ pub unsafe extern "C" fn PhoneArchivePendingFiles(
     };
 
     let compression_settings = *settings;
+    let cancel_flag = OpenArcCancelToken::flag_of(cancel_token);
 
-    match thread::spawn(move || -> Result<c_int> {
+    match thread::spawn(move || -> Result<(c_int, Option<String>)> {
         let root = PathBuf::from(&phone_root);
 
         let (status, pending, mut db) = compute_phone_status(&root)
@@ -762,100 +1359,31 @@ pub unsafe extern "C" fn PhoneArchivePendingFiles(
             if status.first_time {
                 let _ = save_phone_db(&root, &db);
             }
-            return Ok(0);
+            return Ok((0, None));
         }
 
-        let progress_fn: Option<Arc<orchestrator::ProgressFn>> = callback.map(|cb| {
-            Arc::new(move |cur: usize, total: usize, name: &str| {
-                let file_name_c = match CString::new(name) {
-                    Ok(s) => s.into_raw(),
-                    Err(_) => ptr::null(),
-                };
-
-                let progress = ProgressInfo {
-                    current_file: cur as c_int,
-                    total_files: total as c_int,
-                    progress_percent: if total > 0 { (cur as f64 / total as f64) * 100.0 } else { 0.0 },
-                    current_file_name: file_name_c,
-                };
-
-                unsafe { cb(progress) };
-
-                if !file_name_c.is_null() {
-                    unsafe { let _ = CString::from_raw(file_name_c as *mut c_char); }
-                }
-            }) as Arc<orchestrator::ProgressFn>
-        });
-
-        let video_preset = match (compression_settings.video_codec, compression_settings.video_speed) {
-            (0, 1) => 0,
-            (1, 1) => 1,
-            (0, 0) => 2,
-            (1, 2) => 3,
-            (0, _) => 2,
-            (1, _) => 1,
-            _ => 0,
-        };
-
         let orch_settings = OrchestratorSettings {
-            bpg_quality: compression_settings.bpg_quality,
-            bpg_lossless: compression_settings.bpg_lossless,
-            bpg_bit_depth: compression_settings.bpg_bit_depth,
-            bpg_chroma_format: compression_settings.bpg_chroma_format,
-            bpg_encoder_type: compression_settings.bpg_encoder_type,
-            bpg_compression_level: compression_settings.bpg_compression_level,
-            video_preset,
-            video_crf: compression_settings.video_crf,
-            compression_level: compression_settings.compression_level,
-            enable_catalog: false,
-            enable_dedup: compression_settings.enable_dedup,
-            skip_already_compressed_videos: compression_settings.skip_already_compressed_videos,
-            staging_dir: None,
-            heic_quality: 90,
-            jpeg_quality: 92,
+            cancel_flag,
+            ..phone_orchestrator_settings(&compression_settings)
         };
-
-        let res = orchestrator::create_archive(
-            &pending,
-            Path::new(&output_path),
-            orch_settings,
-            progress_fn,
-        )?;
-
-        let mut new_files: Vec<PhoneDbEntry> = Vec::new();
-        for pf in &res.processed {
-            let p = &pf.original_path;
-            let meta = match fs::metadata(p) {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-            let rel = normalize_rel_path(&root, p);
-            new_files.push(PhoneDbEntry {
-                path: rel,
-                size: meta.len(),
-                mtime_secs: file_mtime_secs(&meta),
-            });
-        }
-
-        let mut merged: Vec<PhoneDbEntry> = Vec::new();
-        merged.extend(db.files.into_iter());
-        for e in new_files {
-            merged.retain(|x| x.path != e.path);
-            merged.push(e);
-        }
-        db.files = merged;
-        db.last_backup_at = now_secs();
-
-        if let Err(e) = save_phone_db(&root, &db) {
-            set_last_error(e);
-        }
-
-        Ok(res.processed.len() as c_int)
+        let chunks = pending
+            .chunks(PHONE_BACKUP_CHUNK_FILES)
+            .enumerate()
+            .map(|(idx, chunk)| (chunk.to_vec(), phone_chunk_archive_path(Path::new(&output_path), idx)))
+            .collect();
+        let (archived_count, warning) = archive_phone_chunks(&root, &mut db, chunks, &orch_settings, callback)?;
+
+        Ok((archived_count as c_int, warning))
     })
     .join()
     {
         Ok(result) => match result {
-            Ok(count) => count,
+            Ok((count, warning)) => {
+                if let Some(warning) = warning {
+                    set_last_error(warning);
+                }
+                count
+            }
             Err(e) => {
                 set_last_error(format!("Failed to archive phone files: {}", e));
                 -1
@@ -868,32 +1396,142 @@ pub unsafe extern "C" fn PhoneArchivePendingFiles(
     }
 }
 
-/// Archive file information for listing
-#[repr(C)]
-#[derive(Debug)]
-pub struct ArchiveFileInfo {
-    pub filename: *const c_char,
-    pub original_size: u64,
-    pub compressed_size: u64,
-    pub file_type: c_int, // 0=unknown, 1=image, 2=video, 3=document
-}
-
-#[export_name = "FreeArchiveFileList"]
-pub unsafe extern "C" fn FreeArchiveFileList(files: *mut ArchiveFileInfo, count: c_int) {
-    if files.is_null() || count <= 0 {
-        return;
+/// Back up the phone's pending files into size-bounded archives in `output_dir`,
+/// named after the date range they cover (see phone_dated_archive_path). The
+/// device DB is saved after each archive, so re-running after an interruption or
+/// a cancel continues with whatever is still pending. `chunk_bytes` of 0 puts
+/// everything in one archive. `cancel_token` may be null and behaves as in
+/// PhoneArchivePendingFiles. Returns the number of files archived.
+#[export_name = "PhoneArchivePendingFilesChunked"]
+pub unsafe extern "C" fn PhoneArchivePendingFilesChunked(
+    phone_root: *const c_char,
+    output_dir: *const c_char,
+    chunk_bytes: u64,
+    settings: *const CompressionSettings,
+    callback: Option<ProgressCallback>,
+    cancel_token: *const OpenArcCancelToken,
+) -> c_int {
+    if phone_root.is_null() || output_dir.is_null() || settings.is_null() {
+        set_last_error("Null pointer passed to PhoneArchivePendingFilesChunked".to_string());
+        return -1;
     }
 
-    let count = count as usize;
-    let vec = Vec::from_raw_parts(files, count, count);
-    for item in vec {
-        if !item.filename.is_null() {
-            let _ = CString::from_raw(item.filename as *mut c_char);
+    let phone_root = match CStr::from_ptr(phone_root).to_str() {
+        Ok(s) => s.to_string(),
+        Err(_) => {
+            set_last_error("Invalid phone root string".to_string());
+            return -1;
+        }
+    };
+
+    let output_dir = match CStr::from_ptr(output_dir).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid output directory string".to_string());
+            return -1;
+        }
+    };
+
+    let compression_settings = *settings;
+    let cancel_flag = OpenArcCancelToken::flag_of(cancel_token);
+
+    match thread::spawn(move || -> Result<(c_int, Option<String>)> {
+        let root = PathBuf::from(&phone_root);
+
+        let (status, pending, mut db) = compute_phone_status(&root)
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+        if pending.is_empty() {
+            if status.first_time {
+                let _ = save_phone_db(&root, &db);
+            }
+            return Ok((0, None));
+        }
+
+        fs::create_dir_all(&output_dir)?;
+
+        let orch_settings = OrchestratorSettings {
+            cancel_flag,
+            ..phone_orchestrator_settings(&compression_settings)
+        };
+        let mut taken = HashSet::new();
+        let chunks = phone_size_chunks(&pending, chunk_bytes)
+            .into_iter()
+            .map(|(files, oldest, newest)| {
+                let output = phone_dated_archive_path(&output_dir, oldest, newest, &mut taken);
+                (files, output)
+            })
+            .collect();
+        let (archived_count, warning) = archive_phone_chunks(&root, &mut db, chunks, &orch_settings, callback)?;
+
+        Ok((archived_count as c_int, warning))
+    })
+    .join()
+    {
+        Ok(result) => match result {
+            Ok((count, warning)) => {
+                if let Some(warning) = warning {
+                    set_last_error(warning);
+                }
+                count
+            }
+            Err(e) => {
+                set_last_error(format!("Failed to archive phone files: {}", e));
+                -1
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during phone archiving".to_string());
+            -1
         }
     }
 }
 
+/// Archive file information for listing
+#[repr(C)]
+#[derive(Debug)]
+pub struct ArchiveFileInfo {
+    pub filename: *const c_char,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub file_type: c_int, // 0=unknown, 1=image, 2=video, 3=document
+}
+
+/// Free the array and filenames returned by ListArchive
+///
+/// `count` must be the `file_count` ListArchive reported, and each array must
+/// be freed exactly once.
+#[export_name = "FreeArchiveFileInfoArray"]
+pub unsafe extern "C" fn FreeArchiveFileInfoArray(files: *mut ArchiveFileInfo, count: c_int) {
+    if files.is_null() || count <= 0 {
+        return;
+    }
+
+    let slice = std::slice::from_raw_parts_mut(files, count as usize);
+
+    // Free individual C strings
+    for file in slice.iter_mut() {
+        if !file.filename.is_null() {
+            let _ = CString::from_raw(file.filename as *mut c_char);
+            file.filename = ptr::null();
+        }
+    }
+
+    // Free the array itself
+    let _ = Box::from_raw(slice as *mut [ArchiveFileInfo]);
+}
+
+/// Older name for FreeArchiveFileInfoArray
+#[export_name = "FreeArchiveFileList"]
+pub unsafe extern "C" fn FreeArchiveFileList(files: *mut ArchiveFileInfo, count: c_int) {
+    FreeArchiveFileInfoArray(files, count)
+}
+
 /// List archive contents
+///
+/// On success `*files` points to `*file_count` entries (null when the archive
+/// is empty). The array and its filename strings are owned by the caller and
+/// must be released exactly once with FreeArchiveFileInfoArray.
 #[export_name = "ListArchive"]
 pub unsafe extern "C" fn ListArchive(
     archive_path: *const c_char,
@@ -927,15 +1565,11 @@ pub unsafe extern "C" fn ListArchive(
         }
     };
 
-    if listed.is_empty() {
-        *file_count = 0;
-        *files = ptr::null_mut();
-        return 0;
-    }
-
     let mut out: Vec<ArchiveFileInfo> = Vec::with_capacity(listed.len());
     for f in listed {
-        let cstr = CString::new(f.filename).unwrap_or_else(|_| CString::new("invalid").unwrap());
+        // Interior NULs can't cross the C boundary; keep the rest of the name
+        let name = f.filename.replace('\0', "");
+        let cstr = CString::new(name).unwrap_or_default();
         out.push(ArchiveFileInfo {
             filename: cstr.into_raw(),
             original_size: f.original_size,
@@ -944,15 +1578,54 @@ pub unsafe extern "C" fn ListArchive(
         });
     }
 
-    let count = out.len() as c_int;
-    let ptr_out = out.as_mut_ptr();
-    std::mem::forget(out);
-
-    *file_count = count;
-    *files = ptr_out;
+    *file_count = out.len() as c_int;
+    // Never hand out a dangling pointer for a zero-length array
+    *files = if out.is_empty() {
+        ptr::null_mut()
+    } else {
+        Box::into_raw(out.into_boxed_slice()) as *mut ArchiveFileInfo
+    };
     0
 }
 
+/// Quick JSON summary of an archive (format, file count, sizes, creation date,
+/// encryption) read from its headers and metadata only; free with FreeCString
+#[export_name = "PeekArchive"]
+pub unsafe extern "C" fn PeekArchive(archive_path: *const c_char) -> *mut c_char {
+    if archive_path.is_null() {
+        set_last_error("Null pointer passed to PeekArchive".to_string());
+        return ptr::null_mut();
+    }
+
+    let archive_path = match CStr::from_ptr(archive_path).to_str() {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("Invalid archive path string".to_string());
+            return ptr::null_mut();
+        }
+    };
+
+    match openarc_core::archive_format::peek_archive(Path::new(archive_path)) {
+        Ok(peek) => match serde_json::to_string(&peek) {
+            Ok(json) => match CString::new(json) {
+                Ok(s) => s.into_raw(),
+                Err(_) => {
+                    set_last_error("Failed to allocate peek string".to_string());
+                    ptr::null_mut()
+                }
+            },
+            Err(e) => {
+                set_last_error(format!("Failed to serialize peek: {e}"));
+                ptr::null_mut()
+            }
+        },
+        Err(e) => {
+            set_last_error(format!("Failed to peek archive: {e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
 /// Archive record information for FFI
 #[repr(C)]
 #[derive(Debug)]
@@ -1021,6 +1694,138 @@ pub unsafe extern "C" fn UpdateArchiveDestination(
     }
 }
 
+/// Update archive description
+#[export_name = "UpdateArchiveDescription"]
+pub unsafe extern "C" fn UpdateArchiveDescription(
+    catalog_db_path: *const c_char,
+    archive_path: *const c_char,
+    description: *const c_char,
+) -> c_int {
+    if catalog_db_path.is_null() || archive_path.is_null() || description.is_null() {
+        set_last_error("Null pointer passed to UpdateArchiveDescription".to_string());
+        return -1;
+    }
+
+    let catalog_db_path = match CStr::from_ptr(catalog_db_path).to_str() {
+        Ok(s) => std::path::Path::new(s),
+        Err(_) => {
+            set_last_error("Invalid catalog database path string".to_string());
+            return -1;
+        }
+    };
+
+    let archive_path = match CStr::from_ptr(archive_path).to_str() {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("Invalid archive path string".to_string());
+            return -1;
+        }
+    };
+
+    let description = match CStr::from_ptr(description).to_str() {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("Invalid description string".to_string());
+            return -1;
+        }
+    };
+
+    match thread::spawn(move || -> Result<c_int> {
+        orchestrator::update_archive_description(catalog_db_path, archive_path, description)?;
+        Ok(0)
+    }).join() {
+        Ok(result) => match result {
+            Ok(code) => code,
+            Err(e) => {
+                set_last_error(format!("Failed to update archive description: {}", e));
+                -1
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during archive description update".to_string());
+            -1
+        }
+    }
+}
+
+/// Remove an archive record and its file mappings from the database, e.g. after
+/// the archive file was deleted; `archive_id` is `ArchiveRecordInfo::id`
+#[export_name = "DeleteArchiveRecord"]
+pub unsafe extern "C" fn DeleteArchiveRecord(catalog_db_path: *const c_char, archive_id: i64) -> c_int {
+    if catalog_db_path.is_null() {
+        set_last_error("Null pointer passed to DeleteArchiveRecord".to_string());
+        return -1;
+    }
+
+    let catalog_db_path = match CStr::from_ptr(catalog_db_path).to_str() {
+        Ok(s) => std::path::Path::new(s),
+        Err(_) => {
+            set_last_error("Invalid catalog database path string".to_string());
+            return -1;
+        }
+    };
+
+    match thread::spawn(move || -> Result<c_int> {
+        orchestrator::delete_archive_record(catalog_db_path, archive_id)?;
+        Ok(0)
+    }).join() {
+        Ok(result) => match result {
+            Ok(code) => code,
+            Err(e) => {
+                set_last_error(format!("Failed to delete archive record: {}", e));
+                -1
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during archive record deletion".to_string());
+            -1
+        }
+    }
+}
+
+/// Write every catalog entry to `output_path` as a JSON array, for auditing
+/// backup history outside the app
+#[export_name = "ExportCatalogJson"]
+pub unsafe extern "C" fn ExportCatalogJson(catalog_db_path: *const c_char, output_path: *const c_char) -> c_int {
+    if catalog_db_path.is_null() || output_path.is_null() {
+        set_last_error("Null pointer passed to ExportCatalogJson".to_string());
+        return -1;
+    }
+
+    let catalog_db_path = match CStr::from_ptr(catalog_db_path).to_str() {
+        Ok(s) => std::path::Path::new(s),
+        Err(_) => {
+            set_last_error("Invalid catalog database path string".to_string());
+            return -1;
+        }
+    };
+
+    let output_path = match CStr::from_ptr(output_path).to_str() {
+        Ok(s) => std::path::Path::new(s),
+        Err(_) => {
+            set_last_error("Invalid output path string".to_string());
+            return -1;
+        }
+    };
+
+    match thread::spawn(move || -> Result<c_int> {
+        orchestrator::export_catalog_json(catalog_db_path, output_path)?;
+        Ok(0)
+    }).join() {
+        Ok(result) => match result {
+            Ok(code) => code,
+            Err(e) => {
+                set_last_error(format!("Failed to export catalog: {}", e));
+                -1
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during catalog export".to_string());
+            -1
+        }
+    }
+}
+
 /// Get all archives from the database
 #[export_name = "GetAllArchives"]
 pub unsafe extern "C" fn GetAllArchives(
@@ -1047,73 +1852,121 @@ pub unsafe extern "C" fn GetAllArchives(
         // Create archive tracker using the same connection as the backup catalog
         let tracker = openarc_core::archive_tracker::ArchiveTracker::new(catalog.get_connection_mut())?;
         let archive_records = tracker.get_all_archives()?;
+        write_archive_records(archive_records, archive_count, archives);
+        Ok(0)
+    })();
 
-        // Allocate memory for the array of archive records
-        let mut archive_infos: Vec<ArchiveRecordInfo> = Vec::new();
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            set_last_error(format!("Failed to get all archives: {}", e));
+            -1
+        }
+    }
+}
+
+/// Hand tracker records to the caller as an `ArchiveRecordInfo` array
+///
+/// The array and its strings are released with `FreeArchivesArray`.
+unsafe fn write_archive_records(
+    archive_records: Vec<openarc_core::archive_tracker::ArchiveRecord>,
+    archive_count: *mut c_int,
+    archives: *mut *mut ArchiveRecordInfo,
+) {
+    // Allocate memory for the array of archive records
+    let mut archive_infos: Vec<ArchiveRecordInfo> = Vec::new();
+
+    for record in archive_records {
+        let archive_path_c = match CString::new(record.archive_path) {
+            Ok(s) => s.into_raw(),
+            Err(_) => ptr::null_mut(),
+        };
 
-        for record in archive_records {
-            let archive_path_c = match CString::new(record.archive_path) {
+        let original_location_c = match CString::new(record.original_location) {
+            Ok(s) => s.into_raw(),
+            Err(_) => ptr::null_mut(),
+        };
+
+        let destination_location_c = match record.destination_location {
+            Some(dest) => match CString::new(dest) {
                 Ok(s) => s.into_raw(),
                 Err(_) => ptr::null_mut(),
-            };
+            },
+            None => ptr::null_mut(),
+        };
 
-            let original_location_c = match CString::new(record.original_location) {
+        let description_c = match record.description {
+            Some(desc) => match CString::new(desc) {
                 Ok(s) => s.into_raw(),
                 Err(_) => ptr::null_mut(),
-            };
+            },
+            None => ptr::null_mut(),
+        };
 
-            let destination_location_c = match record.destination_location {
-                Some(dest) => match CString::new(dest) {
-                    Ok(s) => s.into_raw(),
-                    Err(_) => ptr::null_mut(),
-                },
-                None => ptr::null_mut(),
-            };
+        archive_infos.push(ArchiveRecordInfo {
+            id: record.id.unwrap_or(-1),
+            archive_path: archive_path_c,
+            archive_size: record.archive_size,
+            creation_date: record.creation_date,
+            original_location: original_location_c,
+            destination_location: destination_location_c,
+            description: description_c,
+            file_count: record.file_count,
+        });
+    }
 
-            let description_c = match record.description {
-                Some(desc) => match CString::new(desc) {
-                    Ok(s) => s.into_raw(),
-                    Err(_) => ptr::null_mut(),
-                },
-                None => ptr::null_mut(),
-            };
+    // Store the count
+    *archive_count = archive_infos.len() as c_int;
 
-            archive_infos.push(ArchiveRecordInfo {
-                id: record.id.unwrap_or(-1),
-                archive_path: archive_path_c,
-                archive_size: record.archive_size,
-                creation_date: record.creation_date,
-                original_location: original_location_c,
-                destination_location: destination_location_c,
-                description: description_c,
-                file_count: record.file_count,
-            });
-        }
-
-        // Store the count
-        *archive_count = archive_infos.len() as c_int;
-
-        // Allocate memory for the array and copy the data
-        if !archive_infos.is_empty() {
-            let boxed_array = Box::into_raw(archive_infos.into_boxed_slice());
-            *archives = boxed_array as *mut ArchiveRecordInfo;
-        } else {
-            *archives = ptr::null_mut();
+    // Allocate memory for the array and copy the data
+    if !archive_infos.is_empty() {
+        let boxed_array = Box::into_raw(archive_infos.into_boxed_slice());
+        *archives = boxed_array as *mut ArchiveRecordInfo;
+    } else {
+        *archives = ptr::null_mut();
+    }
+}
+
+/// Get archives created in `[start, end)` (Unix seconds), newest first
+#[export_name = "GetArchivesInRange"]
+pub unsafe extern "C" fn GetArchivesInRange(
+    catalog_db_path: *const c_char,
+    start: u64,
+    end: u64,
+    archive_count: *mut c_int,
+    archives: *mut *mut ArchiveRecordInfo,
+) -> c_int {
+    if catalog_db_path.is_null() || archive_count.is_null() || archives.is_null() {
+        set_last_error("Null pointer passed to GetArchivesInRange".to_string());
+        return -1;
+    }
+
+    let catalog_db_path = match CStr::from_ptr(catalog_db_path).to_str() {
+        Ok(s) => std::path::Path::new(s),
+        Err(_) => {
+            set_last_error("Invalid catalog database path string".to_string());
+            return -1;
         }
+    };
 
+    let result = (|| -> Result<c_int> {
+        let mut catalog = openarc_core::backup_catalog::BackupCatalog::new(catalog_db_path)?;
+        let tracker = openarc_core::archive_tracker::ArchiveTracker::new(catalog.get_connection_mut())?;
+        let archive_records = tracker.get_archives_in_range(start, end)?;
+        write_archive_records(archive_records, archive_count, archives);
         Ok(0)
     })();
 
     match result {
         Ok(code) => code,
         Err(e) => {
-            set_last_error(format!("Failed to get all archives: {}", e));
+            set_last_error(format!("Failed to get archives in range: {}", e));
             -1
         }
     }
 }
 
-/// Free the memory allocated by GetAllArchives
+/// Free the memory allocated by GetAllArchives or GetArchivesInRange
 #[export_name = "FreeArchivesArray"]
 pub unsafe extern "C" fn FreeArchivesArray(
     archives: *mut ArchiveRecordInfo,
@@ -1147,6 +2000,49 @@ pub unsafe extern "C" fn FreeArchivesArray(
     let _ = Box::from_raw(slice as *mut [ArchiveRecordInfo] as *mut [ArchiveRecordInfo]);
 }
 
+/// JSON savings summary for a catalog (original vs archived bytes); free with FreeCString
+#[export_name = "GetCatalogSavingsJson"]
+pub unsafe extern "C" fn GetCatalogSavingsJson(catalog_db_path: *const c_char) -> *mut c_char {
+    if catalog_db_path.is_null() {
+        set_last_error("Null pointer passed to GetCatalogSavingsJson".to_string());
+        return ptr::null_mut();
+    }
+
+    let catalog_db_path = match CStr::from_ptr(catalog_db_path).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid catalog database path string".to_string());
+            return ptr::null_mut();
+        }
+    };
+    if !catalog_db_path.exists() {
+        set_last_error(format!("Catalog not found: {}", catalog_db_path.display()));
+        return ptr::null_mut();
+    }
+
+    let report = openarc_core::backup_catalog::BackupCatalog::new(&catalog_db_path)
+        .and_then(|catalog| catalog.total_space_saved());
+    match report {
+        Ok(report) => match serde_json::to_string(&report) {
+            Ok(json) => match CString::new(json) {
+                Ok(s) => s.into_raw(),
+                Err(_) => {
+                    set_last_error("Failed to allocate savings string".to_string());
+                    ptr::null_mut()
+                }
+            },
+            Err(e) => {
+                set_last_error(format!("Failed to serialize savings: {e}"));
+                ptr::null_mut()
+            }
+        },
+        Err(e) => {
+            set_last_error(format!("Failed to compute catalog savings: {e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
 /// Encode a single image file to BPG
 #[export_name = "EncodeBpgFile"]
 pub unsafe extern "C" fn EncodeBpgFile(
@@ -1184,7 +2080,12 @@ pub unsafe extern "C" fn EncodeBpgFile(
             quality: compression_settings.bpg_quality as u8,
             lossless: compression_settings.bpg_lossless,
             bit_depth: compression_settings.bpg_bit_depth as u8,
-            chroma_format: compression_settings.bpg_chroma_format as u8,
+            // Auto only applies to archives; a single file gets 4:4:4
+            chroma_format: if compression_settings.bpg_chroma_format < 0 {
+                orchestrator::BPG_CHROMA_444 as u8
+            } else {
+                compression_settings.bpg_chroma_format as u8
+            },
             encoder_type: compression_settings.bpg_encoder_type as u8,
             compression_level: compression_settings.bpg_compression_level as u8,
         };
@@ -1206,6 +2107,85 @@ pub unsafe extern "C" fn EncodeBpgFile(
     }
 }
 
+/// Convert a single image to another format (BPG/AVIF/HEIC/JPEG/PNG/WebP)
+///
+/// Any input the viewer can decode is accepted. `quality` (1-100) drives JPEG,
+/// HEIC and AVIF; BPG uses the `bpg_*` fields of `settings`. Returns the output
+/// size in bytes, or -1 on error.
+#[export_name = "TranscodeImage"]
+pub unsafe extern "C" fn TranscodeImage(
+    input_path: *const c_char,
+    output_path: *const c_char,
+    target_format: *const c_char,
+    quality: c_int,
+    settings: *const CompressionSettings,
+) -> i64 {
+    if input_path.is_null() || output_path.is_null() || target_format.is_null() || settings.is_null() {
+        set_last_error("Null pointer passed to TranscodeImage".to_string());
+        return -1;
+    }
+
+    let input_path = match CStr::from_ptr(input_path).to_str() {
+        Ok(s) => std::path::PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid input path string".to_string());
+            return -1;
+        }
+    };
+
+    let output_path = match CStr::from_ptr(output_path).to_str() {
+        Ok(s) => std::path::PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid output path string".to_string());
+            return -1;
+        }
+    };
+
+    let target_name = CStr::from_ptr(target_format).to_string_lossy();
+    let target = match openarc_core::transcode::TranscodeTarget::from_name(&target_name) {
+        Some(t) => t,
+        None => {
+            set_last_error(format!(
+                "Unsupported target format: {} (expected bpg, avif, heic, jpeg, png or webp)",
+                target_name
+            ));
+            return -1;
+        }
+    };
+
+    let compression_settings = *settings;
+    let quality = quality.clamp(1, 100) as u8;
+
+    match thread::spawn(move || -> Result<u64> {
+        use openarc_core::bpg_wrapper::BpgConfig;
+
+        let config = BpgConfig {
+            quality: compression_settings.bpg_quality as u8,
+            lossless: compression_settings.bpg_lossless,
+            bit_depth: compression_settings.bpg_bit_depth as u8,
+            chroma_format: if compression_settings.bpg_chroma_format < 0 {
+                orchestrator::BPG_CHROMA_444 as u8
+            } else {
+                compression_settings.bpg_chroma_format as u8
+            },
+            encoder_type: compression_settings.bpg_encoder_type as u8,
+            compression_level: compression_settings.bpg_compression_level as u8,
+        };
+
+        openarc_core::transcode::transcode_image(&input_path, &output_path, target, quality, &config)
+    }).join() {
+        Ok(Ok(size)) => size as i64,
+        Ok(Err(e)) => {
+            set_last_error(format!("Failed to transcode image: {}", e));
+            -1
+        }
+        Err(_) => {
+            set_last_error("Thread panicked during image transcoding".to_string());
+            -1
+        }
+    }
+}
+
 /// Encode a single video file with FFmpeg
 #[export_name = "EncodeVideoFile"]
 pub unsafe extern "C" fn EncodeVideoFile(
@@ -1277,10 +2257,112 @@ pub unsafe extern "C" fn EncodeVideoFile(
     }
 }
 
+/// Generate a sprite sheet of `frames` evenly spaced frames from a video.
+/// tile_width of 0 uses the default (160px). Returns 1 if written, 0 if the
+/// video has fewer frames than requested (nothing written), -1 on error.
+#[export_name = "GenerateVideoSpriteSheet"]
+pub unsafe extern "C" fn GenerateVideoSpriteSheet(
+    input_path: *const c_char,
+    output_path: *const c_char,
+    frames: c_int,
+    tile_width: c_int,
+) -> c_int {
+    if input_path.is_null() || output_path.is_null() {
+        set_last_error("Null pointer passed to GenerateVideoSpriteSheet".to_string());
+        return -1;
+    }
+    if frames <= 0 {
+        set_last_error("Sprite frame count must be positive".to_string());
+        return -1;
+    }
+
+    let input_path = match CStr::from_ptr(input_path).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid input path string".to_string());
+            return -1;
+        }
+    };
+
+    let output_path = match CStr::from_ptr(output_path).to_str() {
+        Ok(s) => PathBuf::from(s),
+        Err(_) => {
+            set_last_error("Invalid output path string".to_string());
+            return -1;
+        }
+    };
+
+    match thread::spawn(move || -> Result<c_int> {
+        use openarc_core::codecs::ffmpeg::{FFmpegEncoder, FfmpegEncodeOptions, DEFAULT_SPRITE_TILE_WIDTH};
+
+        let tile_width = if tile_width > 0 { tile_width as u32 } else { DEFAULT_SPRITE_TILE_WIDTH };
+        let encoder = FFmpegEncoder::with_options(FfmpegEncodeOptions::default());
+        let info = encoder.generate_sprite_sheet(&input_path, &output_path, frames as u32, tile_width)?;
+        Ok(if info.is_some() { 1 } else { 0 })
+    }).join() {
+        Ok(result) => match result {
+            Ok(code) => code,
+            Err(e) => {
+                set_last_error(format!("Failed to generate sprite sheet: {}", e));
+                -1
+            }
+        },
+        Err(_) => {
+            set_last_error("Thread panicked during sprite sheet generation".to_string());
+            -1
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(error_code(&orchestrator::Cancelled.into()), OpenArcResult::Cancelled);
+        let mismatch: anyhow::Error = orchestrator::VerificationFailed("a.txt".to_string()).into();
+        assert_eq!(error_code(&mismatch.context("Failed to verify")), OpenArcResult::VerificationFailed);
+        let missing = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::NotFound)).context("Failed to open");
+        assert_eq!(error_code(&missing), OpenArcResult::IoError);
+        let corrupt = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::InvalidData));
+        assert_eq!(error_code(&corrupt), OpenArcResult::DecodeError);
+        assert_eq!(error_code(&anyhow::anyhow!("something else")), OpenArcResult::Failed);
+        let wrong = anyhow::Error::from(CryptoError::InvalidPassword).context("Failed to open archive");
+        assert_eq!(error_code(&wrong), OpenArcResult::InvalidPassword);
+        assert_eq!(error_code(&CryptoError::PasswordRequired.into()), OpenArcResult::InvalidPassword);
+        assert_eq!(error_code(&CryptoError::KeyDerivationFailed.into()), OpenArcResult::Failed);
+
+        unsafe {
+            assert_eq!(VerifyArchive(ptr::null()), OpenArcResult::NullArgument as c_int);
+            let gone = CString::new("/nonexistent/openarc/missing.tar.zst").unwrap();
+            assert_eq!(VerifyArchive(gone.as_ptr()), OpenArcResult::IoError as c_int);
+            assert!(!get_last_error_ptr().is_null());
+        }
+    }
+
+    #[test]
+    fn test_last_error_is_per_thread() {
+        let handles: Vec<_> = ["first thread failed", "second thread failed"]
+            .into_iter()
+            .map(|msg| {
+                thread::spawn(move || {
+                    set_last_error(msg.to_string());
+                    // Give the other thread a chance to overwrite a shared slot
+                    thread::sleep(std::time::Duration::from_millis(20));
+                    let ptr = get_last_error_ptr();
+                    assert!(!ptr.is_null());
+                    unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string()
+                })
+            })
+            .collect();
+        let seen: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(seen, ["first thread failed", "second thread failed"]);
+
+        // A thread that never failed has nothing to report
+        assert!(thread::spawn(|| get_last_error_ptr().is_null()).join().unwrap());
+    }
+
     #[test]
     fn test_file_type_detection() {
         assert_eq!(detect_file_type_ffi("test.jpg"), 1); // Image
@@ -1288,4 +2370,265 @@ mod tests {
         assert_eq!(detect_file_type_ffi("test.pdf"), 3); // Document
         assert_eq!(detect_file_type_ffi("test.xyz"), 0); // Unknown
     }
+
+    #[test]
+    fn test_list_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("openarc_ffi_list_{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("notes.txt"), "notes").unwrap();
+        fs::write(src.join("todo.txt"), "todo").unwrap();
+        let archive = dir.join("out.tar.zst");
+        let settings = orchestrator::OrchestratorSettings {
+            staging_dir: Some(dir.clone()),
+            enable_catalog: false,
+            ..orchestrator::OrchestratorSettings::default()
+        };
+        orchestrator::create_archive(&[src], &archive, settings, None).unwrap();
+
+        let path = CString::new(archive.to_string_lossy().as_ref()).unwrap();
+        let mut count: c_int = 0;
+        let mut files: *mut ArchiveFileInfo = ptr::null_mut();
+        assert_eq!(unsafe { ListArchive(path.as_ptr(), &mut count, &mut files) }, 0);
+        assert_eq!(count, 2);
+        let mut names: Vec<String> = unsafe { std::slice::from_raw_parts(files, count as usize) }
+            .iter()
+            .map(|f| unsafe { CStr::from_ptr(f.filename) }.to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert!(names[0].ends_with("notes.txt") && names[1].ends_with("todo.txt"));
+        unsafe { FreeArchiveFileInfoArray(files, count) };
+
+        // An empty archive lists as a null array with no entries
+        let empty_src = dir.join("empty");
+        fs::create_dir_all(&empty_src).unwrap();
+        let empty = dir.join("empty.tar.zst");
+        openarc_core::ZstdCodec::new(openarc_core::ZstdOptions::default())
+            .archive_dir_tar_zst(&empty_src, &empty)
+            .unwrap();
+        let path = CString::new(empty.to_string_lossy().as_ref()).unwrap();
+        let mut count: c_int = -1;
+        let mut files: *mut ArchiveFileInfo = ptr::NonNull::dangling().as_ptr();
+        assert_eq!(unsafe { ListArchive(path.as_ptr(), &mut count, &mut files) }, 0);
+        assert_eq!(count, 0);
+        assert!(files.is_null());
+        unsafe { FreeArchiveFileInfoArray(files, count) };
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    static CANCEL_TEST_TOKEN: std::sync::atomic::AtomicPtr<OpenArcCancelToken> =
+        std::sync::atomic::AtomicPtr::new(ptr::null_mut());
+
+    unsafe extern "C" fn cancel_after_a_few(progress: ProgressInfo) {
+        if progress.current_file >= 10 {
+            OpenArcCancelTokenCancel(CANCEL_TEST_TOKEN.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_cancel_token_stops_create_archive() {
+        let dir = std::env::temp_dir().join(format!("openarc_ffi_cancel_{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(&src).unwrap();
+        let inputs: Vec<CString> = (0..2000)
+            .map(|i| {
+                let p = src.join(format!("file{:04}.txt", i));
+                fs::write(&p, format!("synthetic input {}", i)).unwrap();
+                CString::new(p.to_string_lossy().as_ref()).unwrap()
+            })
+            .collect();
+        let input_ptrs: Vec<*const c_char> = inputs.iter().map(|c| c.as_ptr()).collect();
+        let archive = dir.join("out.tar.zst");
+        let output = CString::new(archive.to_string_lossy().as_ref()).unwrap();
+        let settings = CompressionSettings {
+            bpg_quality: 25,
+            bpg_lossless: false,
+            bpg_bit_depth: 8,
+            bpg_chroma_format: 0,
+            bpg_encoder_type: 0,
+            bpg_compression_level: 8,
+            video_codec: 0,
+            video_speed: 1,
+            video_crf: 23,
+            compression_level: 3,
+            enable_catalog: false,
+            enable_dedup: true,
+            skip_already_compressed_videos: true,
+            max_concurrent_videos: 0,
+        };
+
+        let token = OpenArcCancelTokenCreate();
+        CANCEL_TEST_TOKEN.store(token, Ordering::SeqCst);
+        let rc = unsafe {
+            CreateArchive(
+                output.as_ptr(),
+                input_ptrs.as_ptr(),
+                input_ptrs.len() as c_int,
+                &settings,
+                Some(cancel_after_a_few),
+                token,
+            )
+        };
+        CANCEL_TEST_TOKEN.store(ptr::null_mut(), Ordering::SeqCst);
+        unsafe { OpenArcCancelTokenFree(token) };
+
+        assert_eq!(rc, OPENARC_CANCELLED);
+        assert!(!archive.exists());
+
+        // Cancelling a null token is a no-op
+        unsafe { OpenArcCancelTokenCancel(ptr::null()) };
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_phone_backup_cancel_token() {
+        let dir = std::env::temp_dir().join(format!("openarc_ffi_phone_cancel_{}", std::process::id()));
+        let dcim = dir.join("phone").join("DCIM");
+        fs::create_dir_all(&dcim).unwrap();
+        for i in 0..5 {
+            fs::write(dcim.join(format!("note{}.txt", i)), format!("phone file {}", i)).unwrap();
+        }
+        let root = CString::new(dir.join("phone").to_string_lossy().as_ref()).unwrap();
+        let archive = dir.join("backup.tar.zst");
+        let output = CString::new(archive.to_string_lossy().as_ref()).unwrap();
+        let settings = CompressionSettings {
+            bpg_quality: 25,
+            bpg_lossless: false,
+            bpg_bit_depth: 8,
+            bpg_chroma_format: 0,
+            bpg_encoder_type: 0,
+            bpg_compression_level: 8,
+            video_codec: 0,
+            video_speed: 1,
+            video_crf: 23,
+            compression_level: 3,
+            enable_catalog: false,
+            enable_dedup: true,
+            skip_already_compressed_videos: true,
+            max_concurrent_videos: 0,
+        };
+
+        // A cancel issued before the call starts is honoured, not reset
+        let token = OpenArcCancelTokenCreate();
+        unsafe { OpenArcCancelTokenCancel(token) };
+        let rc = unsafe { PhoneArchivePendingFiles(root.as_ptr(), output.as_ptr(), &settings, None, token) };
+        unsafe { OpenArcCancelTokenFree(token) };
+        assert_eq!(rc, 0);
+        assert!(!archive.exists());
+        let (status, pending, _) = compute_phone_status(&dir.join("phone")).unwrap();
+        assert_eq!(pending.len(), 5);
+        assert_eq!(status.unarchived_files, 5);
+
+        // Without a token the pending files are archived and the count returned
+        let rc = unsafe { PhoneArchivePendingFiles(root.as_ptr(), output.as_ptr(), &settings, None, ptr::null()) };
+        assert_eq!(rc, 5);
+        assert!(archive.exists());
+        let (_, pending, _) = compute_phone_status(&dir.join("phone")).unwrap();
+        assert!(pending.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    static PHONE_FAIL_VICTIM: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+    unsafe extern "C" fn remove_victim_once(_progress: ProgressInfo) {
+        if let Some(victim) = PHONE_FAIL_VICTIM.lock().unwrap().take() {
+            fs::remove_file(victim).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_phone_backup_counts_only_archived_files() {
+        let dir = std::env::temp_dir().join(format!("openarc_ffi_phone_fail_{}", std::process::id()));
+        let dcim = dir.join("phone").join("DCIM");
+        fs::create_dir_all(&dcim).unwrap();
+        for i in 0..5 {
+            fs::write(dcim.join(format!("note{}.txt", i)), format!("phone file {}", i)).unwrap();
+        }
+        let root = CString::new(dir.join("phone").to_string_lossy().as_ref()).unwrap();
+        let archive = dir.join("backup.tar.zst");
+        let output = CString::new(archive.to_string_lossy().as_ref()).unwrap();
+        let settings = CompressionSettings {
+            bpg_quality: 25,
+            bpg_lossless: false,
+            bpg_bit_depth: 8,
+            bpg_chroma_format: 0,
+            bpg_encoder_type: 0,
+            bpg_compression_level: 8,
+            video_codec: 0,
+            video_speed: 1,
+            video_crf: 23,
+            compression_level: 3,
+            enable_catalog: false,
+            enable_dedup: false,
+            skip_already_compressed_videos: true,
+            max_concurrent_videos: 0,
+        };
+
+        // The first progress report comes after discovery, so the removed file is
+        // still in the chunk and fails when it is read (dedup off, or hashing would abort)
+        *PHONE_FAIL_VICTIM.lock().unwrap() = Some(dcim.join("note2.txt"));
+        let rc = unsafe {
+            PhoneArchivePendingFiles(root.as_ptr(), output.as_ptr(), &settings, Some(remove_victim_once), ptr::null())
+        };
+        assert_eq!(rc, 4);
+        assert!(archive.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_phone_chunk_archive_path() {
+        let out = Path::new("/backups/phone.tar.zst");
+        assert_eq!(phone_chunk_archive_path(out, 0), PathBuf::from("/backups/phone.tar.zst"));
+        assert_eq!(phone_chunk_archive_path(out, 2), PathBuf::from("/backups/phone_part3.tar.zst"));
+    }
+
+    #[test]
+    fn test_phone_dated_archive_path() {
+        assert_eq!(utc_date_string(0), "1970-01-01");
+        assert_eq!(utc_date_string(1_709_251_200), "2024-03-01");
+        assert_eq!(utc_date_string(951_782_400), "2000-02-29");
+
+        let out = Path::new("/nonexistent-openarc-test");
+        let mut taken = HashSet::new();
+        let day = 1_709_251_200;
+        assert_eq!(
+            phone_dated_archive_path(out, day, day + 3_600, &mut taken),
+            out.join("phone_2024-03-01.tar.zst")
+        );
+        assert_eq!(
+            phone_dated_archive_path(out, day, day + 3_600, &mut taken),
+            out.join("phone_2024-03-01_2.tar.zst")
+        );
+        assert_eq!(
+            phone_dated_archive_path(out, day, day + 86_400 * 40, &mut taken),
+            out.join("phone_2024-03-01_2024-04-10.tar.zst")
+        );
+    }
+
+    #[test]
+    fn test_phone_size_chunks() {
+        let dir = std::env::temp_dir().join(format!("openarc_ffi_chunks_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files: Vec<PathBuf> = [("a", 40), ("b", 40), ("c", 150), ("d", 10)]
+            .iter()
+            .map(|(name, len)| {
+                let p = dir.join(name);
+                fs::write(&p, vec![0u8; *len]).unwrap();
+                p
+            })
+            .collect();
+
+        // Files were written oldest first; the 150-byte one exceeds the limit and sits alone
+        let sizes: Vec<usize> = phone_size_chunks(&files, 100)
+            .iter()
+            .map(|(chunk, _, _)| chunk.len())
+            .collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+        assert_eq!(phone_size_chunks(&files, 0).len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
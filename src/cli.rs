@@ -54,6 +54,151 @@ pub enum Commands {
         /// Don't skip already compressed videos
         #[arg(long)]
         no_skip_compressed: bool,
+        
+        /// Also record per-chunk hashes (chunk size in MB) to locate corruption in large files
+        #[arg(long)]
+        hash_chunk_mb: Option<u64>,
+
+        /// Store a sprite sheet with this many frames per video under thumbs/
+        #[arg(long)]
+        video_sprite_frames: Option<u32>,
+
+        /// Keep the original video in the archive alongside the transcode
+        #[arg(long)]
+        keep_original_video: bool,
+
+        /// Apply EXIF orientation to image pixels before encoding
+        #[arg(long)]
+        bake_orientation: bool,
+
+        /// Hash algorithm for deduplication and HASHES.sha256
+        #[arg(long, default_value = "sha256", value_parser = ["sha256", "blake3"])]
+        hash: String,
+
+        /// Verify the archive against its embedded hashes after writing it
+        #[arg(long)]
+        verify: bool,
+
+        /// Write <output>.sha256 for checking the archive with sha256sum -c
+        #[arg(long)]
+        sidecar_checksum: bool,
+
+        /// Treat Live Photo clips like any other video (re-encode, no pairing)
+        #[arg(long)]
+        no_live_photos: bool,
+
+        /// What to do with files that can't be read
+        #[arg(long, default_value = "skip", value_parser = ["skip", "fail", "retry"])]
+        on_access_error: String,
+
+        /// What to do with files whose names clash ignoring case (IMG.jpg vs img.jpg)
+        #[arg(long, default_value = "rename", value_parser = ["rename", "fail", "skip"])]
+        on_case_collision: String,
+
+        /// Process inputs in path order so repeated runs produce identical archives
+        #[arg(long)]
+        sorted: bool,
+
+        /// JSON file of glob -> setting overrides (quality, chroma, video CRF, store as-is)
+        #[arg(long)]
+        rules: Option<PathBuf>,
+
+        /// Write separate images, videos and misc archives
+        #[arg(long)]
+        split_by_class: bool,
+
+        /// Record where photos were taken from their GPS EXIF
+        #[arg(long, default_value = "off", value_parser = ["off", "summary", "per-file"])]
+        locations: String,
+
+        /// Check every input is readable before encoding (abort with --on-access-error fail)
+        #[arg(long)]
+        preflight: bool,
+
+        /// Store catalog paths relative to this directory ("~" for the home directory)
+        #[arg(long)]
+        catalog_base: Option<PathBuf>,
+
+        /// Video encodes run at once; each HEVC encode can use several GB of RAM
+        #[arg(long, default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+        max_concurrent_videos: u32,
+
+        /// Images over 50 MB decoded at once
+        #[arg(long, default_value = "2", value_parser = clap::value_parser!(u32).range(1..))]
+        max_concurrent_heavy_images: u32,
+
+        /// Encoding threads before scaling down under memory pressure [default: 5]
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        encoding_threads: Option<u32>,
+
+        /// Fraction of RAM in use (0-1) above which encoding backs off [default: 0.85]
+        #[arg(long)]
+        memory_backoff: Option<f64>,
+
+        /// Store images as BPG or AVIF (AVIF needs libheif; falls back to BPG without it)
+        #[arg(long, default_value = "bpg", value_parser = ["bpg", "avif"])]
+        image_format: String,
+
+        /// AVIF quality (1-100) when --image-format avif
+        #[arg(long, default_value = "80", value_parser = clap::value_parser!(u8).range(1..=100))]
+        avif_quality: u8,
+
+        /// Omit the content checksum on the outer zstd frame (per-file hashes are still written)
+        #[arg(long)]
+        no_archive_checksum: bool,
+
+        /// Keep permissions, owner and xattrs of misc and stored files (Unix; read-only flag on Windows)
+        #[arg(long)]
+        preserve_metadata: bool,
+
+        /// Choose BPG chroma per image: 4:4:4 for screenshots and line art, 4:2:0 for photos
+        #[arg(long)]
+        auto_chroma: bool,
+
+        /// Only archive files matching this glob (repeatable; globs with '/' match the relative path)
+        #[arg(long = "include")]
+        include: Vec<String>,
+
+        /// Skip files matching this glob, e.g. '**/*.tmp' or '.DS_Store' (repeatable; wins over --include)
+        #[arg(long = "exclude")]
+        exclude: Vec<String>,
+
+        /// Skip files smaller than this many bytes
+        #[arg(long)]
+        min_size: Option<u64>,
+
+        /// Skip files larger than this many bytes
+        #[arg(long)]
+        max_size: Option<u64>,
+
+        /// Symbolic links in input folders: leave out, archive their targets, or keep as links
+        /// ('follow' archives whatever a link points to, so only use it on trusted trees)
+        #[arg(long, default_value = "skip", value_parser = ["skip", "follow", "preserve"])]
+        symlinks: String,
+
+        /// Split the archive into volumes of this many MB (name.tar.zst.001, .002, ...)
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        split_size_mb: Option<u64>,
+
+        /// Write a seekable archive so single files can be extracted without decompressing the rest
+        #[arg(long)]
+        seekable: bool,
+
+        /// Report how many files would be archived, skipped and deduplicated, then exit without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Stop at the first file that fails to process instead of archiving the rest
+        #[arg(long)]
+        fail_fast: bool,
+
+        /// Encrypt misc files with this cipher: aes-256, aes-128, blowfish or twofish-256
+        #[arg(long, requires = "password_file")]
+        encrypt: Option<String>,
+
+        /// File whose first line is the encryption password
+        #[arg(long, requires = "encrypt")]
+        password_file: Option<PathBuf>,
     },
     
     /// Extract an archive
@@ -65,12 +210,94 @@ pub enum Commands {
         /// Output directory
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Leave images as BPG instead of restoring their original formats
+        #[arg(long)]
+        no_decode: bool,
+
+        /// Quality for images restored as HEIC (1-100)
+        #[arg(long, default_value = "90", value_parser = clap::value_parser!(u8).range(1..=100))]
+        heic_quality: u8,
+
+        /// Quality for images restored as JPEG (1-100)
+        #[arg(long, default_value = "92", value_parser = clap::value_parser!(u8).range(1..=100))]
+        jpeg_quality: u8,
+
+        /// Check extracted files against the archive's HASHES.sha256
+        #[arg(long)]
+        verify: bool,
+
+        /// Restore permissions, xattrs and (when privileged) owners recorded with --preserve-metadata
+        #[arg(long)]
+        restore_metadata: bool,
+
+        /// File whose first line is the password of an encrypted archive
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+
+        /// Restore non-JPEG images as WebP instead of PNG/HEIC
+        #[arg(long)]
+        webp: bool,
+
+        /// Quality for images restored as lossy WebP (1-100)
+        #[arg(long, default_value = "85", value_parser = clap::value_parser!(u8).range(1..=100))]
+        webp_quality: u8,
+
+        /// Restore WebP images losslessly (ignores --webp-quality)
+        #[arg(long, requires = "webp")]
+        webp_lossless: bool,
     },
     
+    /// Re-encode an existing archive at different quality settings
+    Transcode {
+        /// Input archive file
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Output archive file
+        #[arg(short, long)]
+        output: PathBuf,
+        
+        /// BPG quality (0-51, lower = better quality, higher compression)
+        #[arg(long, default_value = "32")]
+        bpg_quality: i32,
+        
+        /// Video preset: 0=H264/Medium, 1=H265/Medium, 2=H264/Fast, 3=H265/Slow
+        #[arg(long, default_value = "1")]
+        video_preset: i32,
+        
+        /// Video CRF quality (lower = better, typical: 18-28)
+        #[arg(long, default_value = "28")]
+        video_crf: i32,
+    },
+    
+    /// Keep archiving new and changed files until Ctrl-C
+    Watch {
+        /// Files or directories to watch
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Directory for the rolling archives and their shared catalog
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// Seconds without changes before a batch is archived
+        #[arg(long, default_value = "2")]
+        debounce_secs: u64,
+    },
+
     /// List archive contents
     List {
         /// Archive file
         archive: PathBuf,
+
+        /// Print the listing as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+
+        /// Order entries by name, or by original size (largest first)
+        #[arg(long, default_value = "name", value_parser = ["name", "size"])]
+        sort_by: String,
     },
     
     /// Convert single image to BPG
@@ -1,9 +1,18 @@
 //! OpenArc - Media archiver for phone/camera files
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
-use openarc_core::orchestrator::{create_archive, OrchestratorSettings};
+use openarc_core::hash::HashAlgorithm;
+use openarc_core::location::LocationMode;
+use openarc_core::method_rules::MethodRules;
+use openarc_core::orchestrator::{
+    create_archive, extract_archive_with_decoding, is_cancelled, transcode_archive, watch_and_archive_with_options,
+    list_archive_contents, AccessErrorPolicy, CaseCollisionPolicy, ExtractionSettings, FileClass, ImageOutputFormat,
+    OrchestratorSettings, PreflightIssue, SymlinkPolicy, WatchOptions,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 mod cli;
@@ -24,16 +33,94 @@ fn main() -> Result<()> {
             no_catalog,
             no_dedup,
             no_skip_compressed,
+            hash_chunk_mb,
+            video_sprite_frames,
+            keep_original_video,
+            bake_orientation,
+            hash,
+            verify,
+            sidecar_checksum,
+            no_live_photos,
+            on_access_error,
+            on_case_collision,
+            sorted,
+            rules,
+            split_by_class,
+            locations,
+            preflight,
+            catalog_base,
+            max_concurrent_videos,
+            max_concurrent_heavy_images,
+            encoding_threads,
+            memory_backoff,
+            image_format,
+            avif_quality,
+            no_archive_checksum,
+            preserve_metadata,
+            auto_chroma,
+            include,
+            exclude,
+            min_size,
+            max_size,
+            symlinks,
+            split_size_mb,
+            seekable,
+            dry_run,
+            fail_fast,
+            encrypt,
+            password_file,
         } => {
             println!("OpenArc - Creating archive: {}", output.display());
             println!("Input sources: {} items", inputs.len());
             println!();
 
+            let hash_algo = HashAlgorithm::from_name(&hash)
+                .ok_or_else(|| anyhow::anyhow!("Unknown hash algorithm: {}", hash))?;
+            let on_access_error = AccessErrorPolicy::from_name(&on_access_error)
+                .ok_or_else(|| anyhow::anyhow!("Unknown access error policy: {}", on_access_error))?;
+            let on_case_collision = CaseCollisionPolicy::from_name(&on_case_collision)
+                .ok_or_else(|| anyhow::anyhow!("Unknown case collision policy: {}", on_case_collision))?;
+            let location_mode = LocationMode::from_name(&locations)
+                .ok_or_else(|| anyhow::anyhow!("Unknown location mode: {}", locations))?;
+            let image_output_format = ImageOutputFormat::from_name(&image_format)
+                .ok_or_else(|| anyhow::anyhow!("Unknown image format: {}", image_format))?;
+            let symlinks = SymlinkPolicy::from_name(&symlinks)
+                .ok_or_else(|| anyhow::anyhow!("Unknown symlink policy: {}", symlinks))?;
+            let method_rules = rules.as_deref().map(MethodRules::load).transpose()?;
+            let encryption = match (encrypt, password_file) {
+                (Some(spec), Some(path)) => Some((spec, read_password_file(&path)?)),
+                _ => None,
+            };
+            let encrypting = encryption.is_some();
+            let hash_chunk_size = hash_chunk_mb
+                .map(|mb| {
+                    mb.checked_mul(1024 * 1024)
+                        .ok_or_else(|| anyhow::anyhow!("--hash-chunk-mb {} is too large", mb))
+                })
+                .transpose()?;
+            let split_size = split_size_mb
+                .map(|mb| {
+                    mb.checked_mul(1024 * 1024)
+                        .ok_or_else(|| anyhow::anyhow!("--split-size-mb {} is too large", mb))
+                })
+                .transpose()?;
+
+            // First Ctrl-C stops cleanly (temp files removed, catalog untouched); a second one exits
+            let cancel_flag = Arc::new(AtomicBool::new(false));
+            let handler_flag = cancel_flag.clone();
+            ctrlc::set_handler(move || {
+                if handler_flag.swap(true, Ordering::SeqCst) {
+                    std::process::exit(130);
+                }
+                eprintln!("\nCancelling, cleaning up...");
+            })?;
+
             let settings = OrchestratorSettings {
                 bpg_quality,
                 bpg_lossless,
                 bpg_bit_depth: 8,
                 bpg_chroma_format: 1,
+                auto_chroma,
                 bpg_encoder_type: 0,
                 bpg_compression_level: 8,
                 video_preset,
@@ -45,6 +132,42 @@ fn main() -> Result<()> {
                 staging_dir: None,
                 heic_quality: 90,
                 jpeg_quality: 92,
+                hash_chunk_size,
+                video_sprite_frames,
+                keep_original_video,
+                bake_orientation,
+                hash_algo,
+                verify_after_create: verify,
+                emit_sidecar_checksum: sidecar_checksum,
+                preserve_live_photos: !no_live_photos,
+                on_access_error,
+                on_case_collision,
+                sorted,
+                method_rules,
+                split_by_class,
+                location_mode,
+                cancel_flag: Some(cancel_flag.clone()),
+                preflight_check: preflight,
+                catalog_path_base: catalog_base.map(expand_home),
+                catalog_path: None,
+                max_concurrent_videos: max_concurrent_videos as usize,
+                max_concurrent_heavy_images: max_concurrent_heavy_images as usize,
+                encoding_threads: encoding_threads.map(|n| n as usize),
+                memory_backoff_threshold: memory_backoff,
+                image_output_format,
+                avif_quality,
+                archive_checksum: !no_archive_checksum,
+                preserve_metadata,
+                include_globs: include,
+                exclude_globs: exclude,
+                min_file_size: min_size,
+                max_file_size: max_size,
+                symlinks,
+                split_size,
+                seekable,
+                dry_run,
+                fail_fast,
+                encryption,
             };
 
             println!("Settings:");
@@ -54,6 +177,7 @@ fn main() -> Result<()> {
             println!("  Catalog: {}", !no_catalog);
             println!("  Deduplication: {}", !no_dedup);
             println!("  Skip compressed videos: {}", !no_skip_compressed);
+            println!("  Hash: {} (verify after create: {})", hash_algo.name(), verify);
             println!();
 
             let pb = ProgressBar::new(100);
@@ -72,17 +196,112 @@ fn main() -> Result<()> {
             });
 
             println!("Processing files...");
-            let result = create_archive(&inputs, &output, settings, Some(progress_fn))?;
+            let result = match create_archive(&inputs, &output, settings, Some(progress_fn)) {
+                Ok(result) => result,
+                Err(e) if is_cancelled(&e) => {
+                    pb.abandon_with_message("Cancelled");
+                    eprintln!("Cancelled; no archive was written.");
+                    std::process::exit(130);
+                }
+                Err(e) => return Err(e),
+            };
 
             pb.finish_with_message("Complete");
             println!();
+            if dry_run {
+                println!("Dry run: nothing was written.");
+                println!("  Discovered: {} files", result.discovered_files.len());
+                println!(
+                    "  Would process: {} files ({} MB before compression)",
+                    result.planned_files,
+                    result.planned_bytes / 1_000_000
+                );
+                println!("  Skipped (catalog): {} files", result.skipped_by_catalog.len());
+                println!("  Already archived (catalog): {} files", result.referenced_from_catalog.len());
+                if result.dedup_groups > 0 {
+                    println!("  Dedup groups: {}", result.dedup_groups);
+                }
+                if !result.skipped_by_filter.is_empty() {
+                    println!("  Skipped (size filter): {} files", result.skipped_by_filter.len());
+                }
+                if !result.skipped_access_errors.is_empty() {
+                    println!("  Skipped (unreadable): {} files", result.skipped_access_errors.len());
+                }
+                if !result.skipped_case_collisions.is_empty() {
+                    println!("  Skipped (case clash): {} files", result.skipped_case_collisions.len());
+                }
+                return Ok(());
+            }
             println!("Archive creation complete!");
+            if encrypting && !result.processed.iter().any(|p| p.class == FileClass::Misc) {
+                println!("  Warning: no misc files to encrypt; media is never encrypted, so the archive is not");
+            }
             println!("  Discovered: {} files", result.discovered_files.len());
             println!("  Processed: {} files", result.processed.len());
             println!("  Skipped (catalog): {} files", result.skipped_by_catalog.len());
             if result.dedup_groups > 0 {
                 println!("  Dedup groups: {}", result.dedup_groups);
             }
+            let avoided = result.avoided_recompression();
+            if avoided > 0 {
+                println!("  Stored as-is (already compressed): {} files", avoided);
+            }
+            if !result.referenced_from_catalog.is_empty() {
+                println!("  Already archived (catalog): {} files", result.referenced_from_catalog.len());
+                for (path, archive) in &result.referenced_from_catalog {
+                    println!("    {} -> {}", path.display(), archive);
+                }
+            }
+            if !result.skipped_by_filter.is_empty() {
+                println!("  Skipped (size filter): {} files", result.skipped_by_filter.len());
+                for (path, len) in &result.skipped_by_filter {
+                    println!("    {} ({} bytes)", path.display(), len);
+                }
+            }
+            if !result.failed.is_empty() {
+                println!("  Failed: {} files", result.failed.len());
+                for (path, err) in &result.failed {
+                    println!("    {}: {}", path.display(), err);
+                }
+            }
+            if !result.skipped_access_errors.is_empty() {
+                println!("  Skipped (unreadable): {} files", result.skipped_access_errors.len());
+                for (path, err) in &result.skipped_access_errors {
+                    println!("    {}: {}", path.display(), err);
+                }
+            }
+            if !result.renamed_case_collisions.is_empty() {
+                println!("  Renamed (case clash): {} files", result.renamed_case_collisions.len());
+                for (path, name) in &result.renamed_case_collisions {
+                    println!("    {} -> {}", path.display(), name);
+                }
+            }
+            if !result.skipped_case_collisions.is_empty() {
+                println!("  Skipped (case clash): {} files", result.skipped_case_collisions.len());
+                for path in &result.skipped_case_collisions {
+                    println!("    {}", path.display());
+                }
+            }
+            if let Some(ref report) = result.preflight {
+                println!(
+                    "  Preflight: {} files, {} MB, {} issues",
+                    report.files_checked,
+                    report.total_bytes / 1_000_000,
+                    report.findings.len()
+                );
+                for finding in &report.findings {
+                    let issue = match &finding.issue {
+                        PreflightIssue::Unreadable(err) => format!("unreadable ({})", err),
+                        PreflightIssue::Empty => "empty".to_string(),
+                        PreflightIssue::Oversized(len) => format!("oversized ({} MB)", len / 1_000_000),
+                    };
+                    println!("    {}: {}", finding.path.display(), issue);
+                }
+            }
+            println!("  Hash algorithm: {}", hash_algo.name());
+            if verify {
+                println!("  Verification: passed");
+            }
 
             let total_original: u64 = result.processed.iter().map(|p| p.original_size).sum();
             let total_compressed: u64 = result.processed.iter().map(|p| p.output_size).sum();
@@ -97,21 +316,217 @@ fn main() -> Result<()> {
             println!("  Original size: {} MB", total_original / 1_000_000);
             println!("  Compressed size: {} MB", total_compressed / 1_000_000);
             println!("  Ratio: {:.2}%", ratio);
+
+            let report = result.size_report_by_class();
+            for (label, stats) in [("Images", report.images), ("Videos", report.videos), ("Misc", report.misc)] {
+                if stats.files > 0 {
+                    println!(
+                        "  {}: {} files, {} MB -> {} MB ({:.2}%)",
+                        label,
+                        stats.files,
+                        stats.original_bytes / 1_000_000,
+                        stats.output_bytes / 1_000_000,
+                        stats.ratio() * 100.0
+                    );
+                }
+            }
             println!();
-            println!("Output: {}", output.display());
+            if split_by_class {
+                println!("Output:");
+                for archive in &result.archives {
+                    println!("  {}", archive.display());
+                }
+            } else {
+                println!("Output: {}", output.display());
+            }
 
             Ok(())
         }
 
-        Commands::Extract { input, output } => {
+        Commands::Extract {
+            input,
+            output,
+            no_decode,
+            heic_quality,
+            jpeg_quality,
+            verify,
+            restore_metadata,
+            password_file,
+            webp,
+            webp_quality,
+            webp_lossless,
+        } => {
             println!("Extracting archive: {} to {}", input.display(), output.display());
-            println!("Note: Extraction not yet implemented in alpha version");
+            println!("  Decode images: {}", !no_decode);
+            if !no_decode {
+                println!("  HEIC quality: {}, JPEG quality: {}", heic_quality, jpeg_quality);
+            }
+            println!();
+
+            let settings = ExtractionSettings {
+                decode_images: !no_decode,
+                heic_quality,
+                jpeg_quality,
+                verify,
+                restore_metadata,
+                password: password_file.as_deref().map(read_password_file).transpose()?,
+                prefer_webp: webp,
+                webp_quality,
+                webp_lossless,
+                ..ExtractionSettings::default()
+            };
+
+            let pb = ProgressBar::new(100);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+
+            let pb_clone = pb.clone();
+            let progress_fn = Arc::new(move |current: usize, total: usize, msg: &str| {
+                pb_clone.set_length(total as u64);
+                pb_clone.set_position(current as u64);
+                pb_clone.set_message(msg.to_string());
+            });
+
+            // The level only matters when compressing; any value decodes
+            let result = match extract_archive_with_decoding(&input, &output, 3, settings, Some(progress_fn)) {
+                Ok(result) => result,
+                Err(e) => {
+                    // Returning the error makes the process exit non-zero
+                    pb.abandon_with_message("Failed");
+                    return Err(e.context(format!("Extraction of {} failed", input.display())));
+                }
+            };
+
+            pb.finish_with_message("Complete");
+            println!();
+            println!("Extraction complete!");
+            println!("  Files extracted: {}", result.files_extracted);
+            println!("  Total size: {} MB", result.total_size / 1_000_000);
+            println!("  Images decoded: {}", result.decoded_files);
+            if verify {
+                println!("  Files verified: {}", result.verified_files);
+            }
+
+            Ok(())
+        }
+
+        Commands::Transcode {
+            input,
+            output,
+            bpg_quality,
+            video_preset,
+            video_crf,
+        } => {
+            println!("Transcoding archive: {} -> {}", input.display(), output.display());
+            println!("  BPG quality: {}", bpg_quality);
+            println!("  Video preset: {} (CRF: {})", video_preset, video_crf);
+            println!();
+
+            let settings = OrchestratorSettings {
+                bpg_quality,
+                video_preset,
+                video_crf,
+                ..OrchestratorSettings::default()
+            };
+
+            let pb = ProgressBar::new(100);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+
+            let pb_clone = pb.clone();
+            let progress_fn = Arc::new(move |current: usize, total: usize, msg: &str| {
+                pb_clone.set_length(total as u64);
+                pb_clone.set_position(current as u64);
+                pb_clone.set_message(msg.to_string());
+            });
+
+            let result = transcode_archive(&input, &output, settings, Some(progress_fn))?;
+
+            pb.finish_with_message("Complete");
+            println!();
+            println!("Transcode complete!");
+            println!("  Images re-encoded: {}", result.images_reencoded);
+            println!("  Videos re-encoded: {}", result.videos_reencoded);
+            println!("  Original archive: {} MB", result.input_archive_size / 1_000_000);
+            println!("  New archive: {} MB", result.output_archive_size / 1_000_000);
+            println!("  Saved: {} MB", result.size_delta() / 1_000_000);
+
             Ok(())
         }
 
-        Commands::List { archive } => {
-            println!("Listing contents of: {}", archive.display());
-            println!("Note: Listing not yet implemented in alpha version");
+        Commands::Watch {
+            inputs,
+            output_dir,
+            debounce_secs,
+        } => {
+            let options = WatchOptions {
+                debounce: std::time::Duration::from_secs(debounce_secs),
+                ..WatchOptions::default()
+            };
+            let handle = watch_and_archive_with_options(&inputs, &output_dir, OrchestratorSettings::default(), options)?;
+            println!("Watching {} input(s), archiving into {}", inputs.len(), output_dir.display());
+            println!("Press Ctrl-C to stop");
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let handler_flag = stop.clone();
+            ctrlc::set_handler(move || handler_flag.store(true, Ordering::SeqCst))?;
+
+            let mut reported = 0;
+            while !stop.load(Ordering::SeqCst) && handle.is_running() {
+                let archives = handle.archives();
+                for archive in &archives[reported..] {
+                    println!("Archived changes to {}", archive.display());
+                }
+                reported = archives.len();
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+
+            let archives = handle.stop()?;
+            println!("Stopped; {} archive(s) written", archives.len());
+            Ok(())
+        }
+
+        Commands::List { archive, json, sort_by } => {
+            let mut files = list_archive_contents(&archive)?;
+            if sort_by == "size" {
+                files.sort_by(|a, b| b.original_size.cmp(&a.original_size).then_with(|| a.filename.cmp(&b.filename)));
+            } else {
+                files.sort_by(|a, b| a.filename.cmp(&b.filename));
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&files)?);
+                return Ok(());
+            }
+
+            let name_width = files.iter().map(|f| f.filename.len()).max().unwrap_or(0).max("Name".len());
+            println!("{:<name_width$}  {:>14}  {:>14}  Type", "Name", "Original", "Compressed");
+            for file in &files {
+                println!(
+                    "{:<name_width$}  {:>14}  {:>14}  {}",
+                    file.filename,
+                    file.original_size,
+                    file.compressed_size,
+                    file_type_label(file.file_type)
+                );
+            }
+            let original: u64 = files.iter().map(|f| f.original_size).sum();
+            let compressed: u64 = files.iter().map(|f| f.compressed_size).sum();
+            println!();
+            println!(
+                "{} files, {} MB original, {} MB compressed",
+                files.len(),
+                original / 1_000_000,
+                compressed / 1_000_000
+            );
             Ok(())
         }
 
@@ -122,3 +537,35 @@ fn main() -> Result<()> {
         }
     }
 }
+
+/// Display name for `ListedArchiveFile::file_type`
+fn file_type_label(file_type: i32) -> &'static str {
+    match file_type {
+        1 => "Image",
+        2 => "Video",
+        3 => "Document",
+        _ => "Unknown",
+    }
+}
+
+/// First line of `path`, without the line ending
+fn read_password_file(path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read password file {}", path.display()))?;
+    let password = content.lines().next().unwrap_or("");
+    if password.is_empty() {
+        anyhow::bail!("Password file {} is empty", path.display());
+    }
+    Ok(password.to_string())
+}
+
+/// Expand a leading `~` to the user's home directory
+fn expand_home(path: PathBuf) -> PathBuf {
+    let Ok(rest) = path.strip_prefix("~") else {
+        return path;
+    };
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(rest),
+        None => path,
+    }
+}
@@ -0,0 +1,105 @@
+//! Zstandard dictionary training.
+//!
+//! Small files that look alike (phone sidecars, JSON exports, thumbnails'
+//! metadata) compress poorly one by one because each frame starts with an
+//! empty history. A dictionary trained on a sample of them primes that history;
+//! pass it as `ZstdOptions::dict` for both compression and decompression.
+//!
+//! As a rule of thumb, give the trainer around 100x `dict_size` bytes of
+//! samples and aim for a dictionary of ~100 KiB.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Fewest samples zstd's trainer works with; it rejects smaller sets.
+pub const MIN_DICT_SAMPLES: usize = 5;
+
+/// Train a dictionary of at most `dict_size` bytes from `samples`.
+pub fn train_dictionary(samples: &[Vec<u8>], dict_size: usize) -> Result<Vec<u8>> {
+    let usable: Vec<&[u8]> = samples.iter().map(|s| s.as_slice()).filter(|s| !s.is_empty()).collect();
+    if usable.len() < MIN_DICT_SAMPLES {
+        return Err(anyhow!(
+            "Need at least {} non-empty samples to train a dictionary, got {}",
+            MIN_DICT_SAMPLES,
+            usable.len()
+        ));
+    }
+    if dict_size == 0 {
+        return Err(anyhow!("Dictionary size must be greater than zero"));
+    }
+
+    zstd::dict::from_samples(&usable, dict_size).context("zstd dictionary training failed")
+}
+
+/// Train a dictionary from the files under `dir` (recursively), one sample per file.
+///
+/// Files are read in path order so the same directory always yields the same dictionary.
+pub fn train_dictionary_from_dir<P: AsRef<Path>>(dir: P, dict_size: usize) -> Result<Vec<u8>> {
+    let dir = dir.as_ref();
+    let mut samples = Vec::new();
+    collect_samples(dir, &mut samples)?;
+    train_dictionary(&samples, dict_size).with_context(|| format!("Failed to train dictionary from {}", dir.display()))
+}
+
+fn collect_samples(dir: &Path, samples: &mut Vec<Vec<u8>>) -> Result<()> {
+    let mut children = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read dir: {}", dir.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    children.sort_by_key(|c| c.file_name());
+
+    for child in children {
+        let path = child.path();
+        let file_type = child.file_type()?;
+        if file_type.is_dir() {
+            collect_samples(&path, samples)?;
+        } else if file_type.is_file() {
+            samples.push(fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ZstdCodec, ZstdOptions};
+
+    fn sample(i: usize) -> Vec<u8> {
+        format!(
+            "{{\"device\":\"Pixel 7\",\"album\":\"Camera\",\"file\":\"IMG_{:05}.jpg\",\"width\":4032,\"height\":3024,\"iso\":{},\"favorite\":false}}",
+            i,
+            100 + (i * 37) % 700
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn trained_dict_round_trips() {
+        let samples: Vec<Vec<u8>> = (0..500).map(sample).collect();
+        let dict = train_dictionary(&samples, 4096).unwrap();
+        assert!(!dict.is_empty() && dict.len() <= 4096);
+
+        let with_dict = ZstdCodec::new(ZstdOptions {
+            dict: Some(dict),
+            ..ZstdOptions::default()
+        });
+        let input = sample(9999);
+        let compressed = with_dict.compress_bytes(&input).unwrap();
+        assert_eq!(with_dict.decompress_bytes(&compressed).unwrap(), input);
+
+        let plain = ZstdCodec::new(ZstdOptions::default()).compress_bytes(&input).unwrap();
+        assert!(compressed.len() < plain.len());
+    }
+
+    #[test]
+    fn too_few_samples_rejected() {
+        let samples: Vec<Vec<u8>> = (0..MIN_DICT_SAMPLES - 1).map(sample).collect();
+        assert!(train_dictionary(&samples, 4096).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("only.json"), sample(0)).unwrap();
+        assert!(train_dictionary_from_dir(dir.path(), 4096).is_err());
+    }
+}
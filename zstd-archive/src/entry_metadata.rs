@@ -0,0 +1,92 @@
+//! Permissions, ownership and extended attributes for tar entries.
+//!
+//! Files are usually staged (copied) before packing, which resets their owner
+//! and drops their xattrs, so the metadata is read from the original file and
+//! written into the tar header in place of the staged copy's.
+//!
+//! What is preserved per OS:
+//! - Unix: permission bits (including setuid/setgid/sticky), uid/gid and
+//!   extended attributes, stored as PAX `SCHILY.xattr.*` records like GNU tar
+//!   and bsdtar do. Ownership is only restored when the extracting process is
+//!   allowed to change it (normally root).
+//! - Windows: only the read-only attribute, as mode 0o444 or 0o644. Alternate
+//!   data streams and ACLs are not captured.
+
+use std::io;
+use std::path::Path;
+
+/// PAX record key prefix for extended attributes.
+pub const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
+
+/// Metadata to record in a file's tar header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntryMetadata {
+    pub mode: u32,
+    pub uid: u64,
+    pub gid: u64,
+    /// Extended attribute names and values (always empty on Windows).
+    pub xattrs: Vec<(String, Vec<u8>)>,
+}
+
+impl EntryMetadata {
+    /// Read the metadata of `path`.
+    #[cfg(unix)]
+    pub fn read(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let meta = std::fs::metadata(path)?;
+        let mut xattrs = Vec::new();
+        // Filesystems without xattr support simply report none
+        if let Ok(names) = xattr::list(path) {
+            for name in names {
+                if let Ok(Some(value)) = xattr::get(path, &name) {
+                    xattrs.push((name.to_string_lossy().into_owned(), value));
+                }
+            }
+        }
+
+        Ok(Self {
+            mode: meta.mode() & 0o7777,
+            uid: meta.uid() as u64,
+            gid: meta.gid() as u64,
+            xattrs,
+        })
+    }
+
+    /// Read the metadata of `path`.
+    #[cfg(not(unix))]
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let meta = std::fs::metadata(path)?;
+        let mode = if meta.permissions().readonly() { 0o444 } else { 0o644 };
+        Ok(Self {
+            mode,
+            ..Self::default()
+        })
+    }
+
+    /// Overwrite the mode and owner fields of `header`.
+    pub fn apply_to_header(&self, header: &mut tar::Header) {
+        header.set_mode(self.mode);
+        header.set_uid(self.uid);
+        header.set_gid(self.gid);
+    }
+
+    /// PAX records carrying the extended attributes.
+    pub fn pax_records(&self) -> Vec<(String, &[u8])> {
+        self.xattrs
+            .iter()
+            .map(|(name, value)| (format!("{}{}", PAX_XATTR_PREFIX, name), value.as_slice()))
+            .collect()
+    }
+}
+
+/// Give an unpacked file the owner from its header; silently skipped without the privilege.
+#[cfg(unix)]
+pub fn restore_owner(path: &Path, header: &tar::Header) {
+    if let (Ok(uid), Ok(gid)) = (header.uid(), header.gid()) {
+        let _ = std::os::unix::fs::lchown(path, Some(uid as u32), Some(gid as u32));
+    }
+}
+
+#[cfg(not(unix))]
+pub fn restore_owner(_path: &Path, _header: &tar::Header) {}
@@ -1,14 +1,42 @@
 use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "seekable")]
+pub mod seekable;
+#[cfg(feature = "seekable")]
+pub use seekable::{
+    is_seekable, seekable_archive_decoder, SeekFrame, SeekableDecoder, SeekableEncoder, DEFAULT_SEEKABLE_FRAME_SIZE,
+};
+
+pub mod split;
+pub use split::{SplitReader, SplitWriter};
+
+#[cfg(feature = "zdict")]
+pub mod dict;
+#[cfg(feature = "zdict")]
+pub use dict::{train_dictionary, train_dictionary_from_dir, MIN_DICT_SAMPLES};
+
+#[cfg(feature = "tar")]
+pub mod entry_metadata;
+#[cfg(feature = "tar")]
+pub use entry_metadata::EntryMetadata;
+
+#[cfg(feature = "tar")]
+pub mod safe_unpack;
+#[cfg(feature = "tar")]
+pub use safe_unpack::unpack_in_checked;
+
 /// Settings for zstd compression/decompression.
 ///
 /// Notes:
 /// - `include_checksum` adds a content checksum at the end of each frame. [web:38]
 /// - `threads` requires the `zstdmt` cargo feature to enable multithreaded compression. [web:38]
 /// - `dict` must be provided for both compression and decompression if used. [web:38][page:53]
+///   Build one with `train_dictionary` (`zdict` feature).
+///   `.tar.zst` archives carry a copy of it (see `DICT_FRAME_MAGIC`), so extraction finds it on its own.
 #[derive(Clone, Debug)]
 pub struct ZstdOptions {
     /// Compression level. Typical range is ~1-22 (zstd supports higher in some builds).
@@ -21,8 +49,14 @@ pub struct ZstdOptions {
     /// Enable long-distance matching (better ratio on some content, often slower).
     pub long_distance_matching: bool,
 
-    /// Multithreaded compression workers (0 = disabled).
-    pub threads: u32,
+    /// Log2 of the match window (`MIN_WINDOW_LOG..=MAX_WINDOW_LOG`); `None` keeps the level's default.
+    /// Raise it (e.g. 27-31) so long-distance matching can reach across large archives;
+    /// decompression needs about `1 << window_log` bytes of memory, and above 27 the
+    /// decoding codec must be given the same `window_log`.
+    pub window_log: Option<u32>,
+
+    /// Multithreaded compression workers.
+    pub threads: Threads,
 
     /// Optional dictionary bytes (same bytes must be used for decompression).
     pub dict: Option<Vec<u8>>,
@@ -32,6 +66,18 @@ pub struct ZstdOptions {
 
     /// Write output files atomically (write to temp file then rename).
     pub atomic_writes: bool,
+
+    /// Restore permissions, xattrs and (when privileged) ownership from tar headers on extraction.
+    pub preserve_metadata: bool,
+
+    /// Write `.tar.zst` archives as volumes of at most this many bytes (`name.tar.zst.001`, ...).
+    /// Extraction finds the volumes on its own. Volumes are written in place, not atomically.
+    pub split_size: Option<u64>,
+
+    /// Write `.tar.zst` archives in seekable format (needs the `seekable` feature): frames of
+    /// this many uncompressed bytes plus a seek table, so one entry can be read without
+    /// decompressing everything before it. Plain zstd decoders still read the whole archive.
+    pub seekable_frame_size: Option<usize>,
 }
 
 impl Default for ZstdOptions {
@@ -40,14 +86,65 @@ impl Default for ZstdOptions {
             level: 3,
             include_checksum: true,
             long_distance_matching: false,
-            threads: 0,
+            window_log: None,
+            threads: Threads::Fixed(0),
             dict: None,
             buffer_size: 1024 * 1024, // 1 MiB
             atomic_writes: true,
+            preserve_metadata: false,
+            split_size: None,
+            seekable_frame_size: None,
         }
     }
 }
 
+/// Skippable-frame magic for the dictionary copy written ahead of a `.tar.zst`'s data.
+/// zstd decoders skip the frame, so archives stay valid zstd streams.
+pub const DICT_FRAME_MAGIC: u32 = 0x184D_2A5E;
+
+/// Payload prefix identifying the frame as an OpenArc dictionary.
+const DICT_FRAME_TAG: &[u8; 8] = b"OADICT01";
+
+/// Smallest `window_log` zstd accepts.
+pub const MIN_WINDOW_LOG: u32 = 10;
+
+/// Largest `window_log` zstd decoders accept unless asked for more.
+const DEFAULT_WINDOW_LOG_MAX: u32 = 27;
+
+/// Largest `window_log` zstd accepts on this platform.
+pub const MAX_WINDOW_LOG: u32 = if cfg!(target_pointer_width = "64") { 31 } else { 30 };
+
+/// Upper bound for `Threads::Auto`; more workers mostly add memory, not speed.
+pub const MAX_AUTO_THREADS: u32 = 16;
+
+/// Worker count for multithreaded compression (needs the `zstdmt` feature).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Threads {
+    /// Exactly this many workers (0 = single-threaded, no zstdmt needed).
+    Fixed(u32),
+    /// One worker per available core, capped at `MAX_AUTO_THREADS`.
+    Auto,
+}
+
+impl Threads {
+    /// Number of workers to hand to zstd.
+    pub fn resolve(self) -> u32 {
+        match self {
+            Threads::Fixed(n) => n,
+            Threads::Auto => std::thread::available_parallelism()
+                .map(|n| n.get() as u32)
+                .unwrap_or(1)
+                .clamp(1, MAX_AUTO_THREADS),
+        }
+    }
+}
+
+impl From<u32> for Threads {
+    fn from(n: u32) -> Self {
+        Threads::Fixed(n)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ZstdCodec {
     opts: ZstdOptions,
@@ -105,14 +202,20 @@ impl ZstdCodec {
     /// Stream compression: reads from `reader`, writes compressed bytes into `writer`.
     /// Returns number of uncompressed bytes read from `reader`.
     pub fn compress_reader_to_writer<R: Read, W: Write>(&self, reader: R, writer: W) -> Result<u64> {
-        let mut reader = BufReader::with_capacity(self.opts.buffer_size, reader);
+        self.compress_stream(reader, writer, |_| {})
+    }
+
+    /// `compress_reader_to_writer`, calling `on_read` with the running input byte count
+    /// after every `buffer_size` chunk.
+    fn compress_stream<R: Read, W: Write>(&self, mut reader: R, writer: W, mut on_read: impl FnMut(u64)) -> Result<u64> {
         let writer = BufWriter::with_capacity(self.opts.buffer_size, writer);
 
         let mut encoder = self
             .make_encoder(writer)
             .context("Failed to create zstd encoder")?;
 
-        let bytes_in = io::copy(&mut reader, &mut encoder).context("Failed while streaming into zstd encoder")?;
+        let bytes_in = copy_chunks(&mut reader, &mut encoder, self.opts.buffer_size, &mut on_read)
+            .context("Failed while streaming into zstd encoder")?;
 
         // Required to finalize the compressed stream. [web:38]
         let mut writer = encoder.finish().context("Failed to finish zstd stream")?;
@@ -128,18 +231,70 @@ impl ZstdCodec {
         let mut decoder = self.make_decoder(reader).context("Failed to create zstd decoder")?;
 
         let mut writer = BufWriter::with_capacity(self.opts.buffer_size, writer);
-        let bytes_out = io::copy(&mut decoder, &mut writer).context("Failed while streaming from zstd decoder")?;
+        let bytes_out = copy_chunks(&mut decoder, &mut writer, self.opts.buffer_size, &mut |_| {})
+            .context("Failed while streaming from zstd decoder")?;
         writer.flush().context("Failed to flush output")?;
 
         Ok(bytes_out)
     }
 
+    /// Stream decompression into a callback instead of a writer.
+    /// `on_chunk` gets the output in `buffer_size` pieces (the last one may be shorter);
+    /// an error from it stops decompression and is returned as-is.
+    /// Returns number of uncompressed bytes produced.
+    pub fn decompress_stream_cb<R: Read>(
+        &self,
+        reader: R,
+        mut on_chunk: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<u64> {
+        let reader = BufReader::with_capacity(self.opts.buffer_size, reader);
+        let mut decoder = self.make_decoder(reader).context("Failed to create zstd decoder")?;
+
+        let mut buf = vec![0u8; self.opts.buffer_size.max(1)];
+        let mut total = 0u64;
+        loop {
+            // Fill the whole buffer so chunk sizes don't depend on zstd's block boundaries
+            let mut filled = 0;
+            while filled < buf.len() {
+                match decoder.read(&mut buf[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e).context("Failed while streaming from zstd decoder"),
+                }
+            }
+            if filled == 0 {
+                break;
+            }
+            on_chunk(&buf[..filled])?;
+            total += filled as u64;
+            if filled < buf.len() {
+                break;
+            }
+        }
+
+        Ok(total)
+    }
+
     /// Compress a file to a file.
     pub fn compress_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, output: Q) -> Result<()> {
+        self.compress_file_with_progress(input, output, |_, _| {})
+    }
+
+    /// Like `compress_file`, calling `progress(read, total)` with the input bytes compressed
+    /// so far after every `buffer_size` chunk; `total` is the input size when it can be read.
+    pub fn compress_file_with_progress<P, Q, F>(&self, input: P, output: Q, progress: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: Fn(u64, Option<u64>),
+    {
         let input = input.as_ref();
         let output = output.as_ref();
 
         let in_file = File::open(input).with_context(|| format!("Failed to open input file: {}", input.display()))?;
+        let total = in_file.metadata().ok().map(|m| m.len());
+        let on_read = |read: u64| progress(read, total);
 
         if let Some(parent) = output.parent() {
             fs::create_dir_all(parent)
@@ -148,14 +303,14 @@ impl ZstdCodec {
 
         if self.opts.atomic_writes {
             atomic_write(output, |tmp_file| {
-                self.compress_reader_to_writer(in_file, tmp_file)
+                self.compress_stream(in_file, tmp_file, on_read)
                     .with_context(|| format!("Failed to compress {} -> {}", input.display(), output.display()))?;
                 Ok(())
             })?;
         } else {
             let out_file =
                 File::create(output).with_context(|| format!("Failed to create output file: {}", output.display()))?;
-            self.compress_reader_to_writer(in_file, out_file)
+            self.compress_stream(in_file, out_file, on_read)
                 .with_context(|| format!("Failed to compress {} -> {}", input.display(), output.display()))?;
         }
 
@@ -164,10 +319,28 @@ impl ZstdCodec {
 
     /// Decompress a file to a file.
     pub fn decompress_file<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, output: Q) -> Result<()> {
+        self.decompress_file_with_progress(input, output, |_, _| {})
+    }
+
+    /// Like `decompress_file`, calling `progress(read, total)` with the compressed bytes
+    /// consumed so far (about every `buffer_size`); `total` is the input size when it can be read.
+    pub fn decompress_file_with_progress<P, Q, F>(&self, input: P, output: Q, progress: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: Fn(u64, Option<u64>),
+    {
         let input = input.as_ref();
         let output = output.as_ref();
 
         let in_file = File::open(input).with_context(|| format!("Failed to open input file: {}", input.display()))?;
+        let total = in_file.metadata().ok().map(|m| m.len());
+        let in_file = ProgressReader {
+            inner: in_file,
+            read: 0,
+            total: total.unwrap_or(0),
+            progress: |read: u64, _: u64| progress(read, total),
+        };
 
         if let Some(parent) = output.parent() {
             fs::create_dir_all(parent)
@@ -196,34 +369,89 @@ impl ZstdCodec {
     #[cfg(feature = "tar")]
     pub fn archive_dir_tar_zst<P: AsRef<Path>, Q: AsRef<Path>>(&self, src_dir: P, output: Q) -> Result<()> {
         let src_dir = src_dir.as_ref();
-        let output = output.as_ref();
+        self.write_tar_zst(output.as_ref(), |builder| {
+            builder
+                .append_dir_all(".", src_dir)
+                .with_context(|| format!("Failed to append dir: {}", src_dir.display()))
+        })
+    }
+
+    /// Like `archive_dir_tar_zst`, but files listed in `metadata` (keyed by path relative
+    /// to `src_dir`) get that mode, owner and xattrs in their tar header instead of the
+    /// on-disk file's. See [`entry_metadata`] for what each OS preserves.
+    #[cfg(feature = "tar")]
+    pub fn archive_dir_tar_zst_with_metadata<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        src_dir: P,
+        output: Q,
+        metadata: &HashMap<PathBuf, EntryMetadata>,
+    ) -> Result<()> {
+        let src_dir = src_dir.as_ref();
+        self.write_tar_zst(output.as_ref(), |builder| {
+            builder
+                .append_dir(".", src_dir)
+                .with_context(|| format!("Failed to append dir: {}", src_dir.display()))?;
+            append_tree(builder, src_dir, Path::new(""), metadata)
+        })
+    }
 
+    #[cfg(feature = "tar")]
+    fn write_tar_zst<F>(&self, output: &Path, fill: F) -> Result<()>
+    where
+        F: Fn(&mut tar::Builder<TarEncoder<BufWriter<ArchiveSink>>>) -> Result<()>,
+    {
         if let Some(parent) = output.parent() {
             fs::create_dir_all(parent)
                 .with_context(|| format!("Failed to create output directory: {}", parent.display()))?;
         }
 
-        let write_archive = |out_file: File| -> Result<()> {
-            let out_file = BufWriter::with_capacity(self.opts.buffer_size, out_file);
-            let encoder = self.make_encoder(out_file).context("Failed to create zstd encoder")?;
+        let write_archive = |sink: ArchiveSink| -> Result<ArchiveSink> {
+            let mut out_file = BufWriter::with_capacity(self.opts.buffer_size, sink);
+            if let Some(ref dict) = self.opts.dict {
+                write_dict_frame(&mut out_file, dict).context("Failed to write dictionary frame")?;
+            }
+            let encoder = match self.opts.seekable_frame_size {
+                None => TarEncoder::Stream(self.make_encoder(out_file).context("Failed to create zstd encoder")?),
+                #[cfg(feature = "seekable")]
+                Some(frame_size) => TarEncoder::Seekable(self.seekable_encoder(out_file, frame_size)),
+                #[cfg(not(feature = "seekable"))]
+                Some(_) => {
+                    return Err(anyhow!(
+                        "seekable_frame_size is set but zstd-archive was built without the seekable feature"
+                    ))
+                }
+            };
             let mut builder = tar::Builder::new(encoder);
 
-            builder
-                .append_dir_all(".", src_dir)
-                .with_context(|| format!("Failed to append dir: {}", src_dir.display()))?;
+            fill(&mut builder)?;
 
             // Finish TAR, then finish zstd. [web:38]
             let encoder = builder.into_inner().context("Failed to finalize tar builder")?;
-            let mut out = encoder.finish().context("Failed to finish zstd stream")?;
-            out.flush().context("Failed to flush zstd output")?;
-
-            Ok(())
+            let out = encoder.finish().context("Failed to finish zstd stream")?;
+            out.into_inner()
+                .map_err(|e| e.into_error())
+                .context("Failed to flush zstd output")
         };
 
-        if self.opts.atomic_writes {
-            atomic_write(output, |tmp_file| write_archive(tmp_file))?;
+        if let Some(volume_size) = self.opts.split_size {
+            let writer = SplitWriter::create(output, volume_size)
+                .with_context(|| format!("Failed to create first volume of {}", output.display()))?;
+            let written = write_archive(ArchiveSink::Split(writer)).and_then(|sink| sink.finish());
+            if written.is_err() {
+                for volume in split::volume_paths(output) {
+                    let _ = fs::remove_file(volume);
+                }
+                return written;
+            }
+            // A single-file archive of the same name would be read instead of the volumes
+            if output.is_file() {
+                fs::remove_file(output).with_context(|| format!("Failed to remove old {}", output.display()))?;
+            }
+        } else if self.opts.atomic_writes {
+            atomic_write(output, |tmp_file| write_archive(ArchiveSink::File(tmp_file)).map(|_| ()))?;
         } else {
-            write_archive(File::create(output).with_context(|| format!("Failed to create {}", output.display()))?)?;
+            let file = File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+            write_archive(ArchiveSink::File(file))?;
         }
 
         Ok(())
@@ -232,18 +460,50 @@ impl ZstdCodec {
     /// Optional: extract a `.tar.zst` archive into a directory.
     #[cfg(feature = "tar")]
     pub fn extract_tar_zst<P: AsRef<Path>, Q: AsRef<Path>>(&self, input: P, dst_dir: Q) -> Result<()> {
+        self.extract_tar_zst_with_progress(input, dst_dir, |_, _| {})
+    }
+
+    /// Like `extract_tar_zst`, calling `progress(read, total)` with compressed bytes consumed
+    /// out of the archive's size as extraction proceeds.
+    #[cfg(feature = "tar")]
+    pub fn extract_tar_zst_with_progress<P, Q, F>(&self, input: P, dst_dir: Q, progress: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: FnMut(u64, u64),
+    {
         let input = input.as_ref();
         let dst_dir = dst_dir.as_ref();
         fs::create_dir_all(dst_dir).with_context(|| format!("Failed to create {}", dst_dir.display()))?;
 
-        let in_file = File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+        // Multi-volume archives (`input.001`, `input.002`, ...) read as one stream
+        let in_file = SplitReader::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+        let total = in_file.len();
+        let in_file = ProgressReader {
+            inner: in_file,
+            read: 0,
+            total,
+            progress,
+        };
         let reader = BufReader::with_capacity(self.opts.buffer_size, in_file);
         let decoder = self.make_decoder(reader).context("Failed to create zstd decoder")?;
 
         let mut archive = tar::Archive::new(decoder);
-        archive
-            .unpack(dst_dir)
-            .with_context(|| format!("Failed to unpack into {}", dst_dir.display()))?;
+        archive.set_preserve_permissions(self.opts.preserve_metadata);
+        archive.set_unpack_xattrs(self.opts.preserve_metadata);
+        for entry in archive.entries().context("Failed to read tar entries")? {
+            let mut entry = entry.context("Failed to read tar entry")?;
+            // An entry that would land outside dst_dir fails the extraction
+            let target = unpack_in_checked(&mut entry, dst_dir)?;
+            if self.opts.preserve_metadata {
+                entry_metadata::restore_owner(&target, entry.header());
+            }
+        }
+
+        // tar stops at its end-of-archive marker; read the frame to the end so the
+        // content checksum is verified and a truncated file is an error, not a silent success
+        io::copy(&mut archive.into_inner(), &mut io::sink())
+            .context("Archive is truncated or fails its zstd checksum")?;
 
         Ok(())
     }
@@ -265,17 +525,33 @@ impl ZstdCodec {
         enc.long_distance_matching(self.opts.long_distance_matching)
             .context("Failed to set zstd long_distance_matching")?; // [web:38]
 
-        if self.opts.threads > 0 {
+        if let Some(window_log) = self.opts.window_log {
+            if !(MIN_WINDOW_LOG..=MAX_WINDOW_LOG).contains(&window_log) {
+                return Err(anyhow!(
+                    "window_log must be {}-{}, got {}",
+                    MIN_WINDOW_LOG,
+                    MAX_WINDOW_LOG,
+                    window_log
+                ));
+            }
+            enc.window_log(window_log).context("Failed to set zstd window_log")?;
+        }
+
+        if self.opts.threads != Threads::Fixed(0) {
             #[cfg(feature = "zstdmt")]
             {
-                enc.multithread(self.opts.threads)
+                enc.multithread(self.opts.threads.resolve())
                     .context("Failed to enable zstd multithread")?; // [web:38]
             }
             #[cfg(not(feature = "zstdmt"))]
             {
+                let requested = match self.opts.threads {
+                    Threads::Auto => "auto".to_string(),
+                    Threads::Fixed(n) => n.to_string(),
+                };
                 return Err(anyhow!(
-                    "threads={} requested but zstdmt feature is not enabled",
-                    self.opts.threads
+                    "threads={} requested but zstd-archive was built without the zstdmt feature",
+                    requested
                 ));
             }
         }
@@ -283,14 +559,230 @@ impl ZstdCodec {
         Ok(enc)
     }
 
-    fn make_decoder<R: io::BufRead>(&self, reader: R) -> Result<zstd::stream::read::Decoder<'static, R>> {
-        if let Some(ref dict) = self.opts.dict {
+    /// Decoder for `reader`, using the dictionary embedded in the stream if there is one
+    /// and `opts.dict` otherwise.
+    /// Windows past zstd's default limit (2^27) are only accepted up to `opts.window_log`,
+    /// so a crafted frame can't make a default decoder reserve gigabytes.
+    fn make_decoder<R: io::BufRead>(&self, mut reader: R) -> Result<zstd::stream::read::Decoder<'static, R>> {
+        let mut decoder = if let Some(dict) = read_embedded_dict(&mut reader)? {
+            zstd::stream::read::Decoder::with_dictionary(reader, &dict)
+                .context("Failed to create zstd decoder (embedded dictionary)")?
+        } else if let Some(ref dict) = self.opts.dict {
             zstd::stream::read::Decoder::with_dictionary(reader, dict)
-                .context("Failed to create zstd decoder (dictionary)") // [page:53]
+                .context("Failed to create zstd decoder (dictionary)")? // [page:53]
         } else {
-            zstd::stream::read::Decoder::with_buffer(reader).context("Failed to create zstd decoder")
+            zstd::stream::read::Decoder::with_buffer(reader).context("Failed to create zstd decoder")?
+        };
+        if let Some(window_log) = self.opts.window_log.filter(|&w| w > DEFAULT_WINDOW_LOG_MAX) {
+            decoder
+                .window_log_max(window_log.min(MAX_WINDOW_LOG))
+                .context("Failed to set zstd window_log_max")?;
+        }
+        Ok(decoder)
+    }
+}
+
+/// `io::copy` in `buffer_size` pieces, calling `on_chunk` with the running total after each.
+fn copy_chunks<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+    on_chunk: &mut impl FnMut(u64),
+) -> io::Result<u64> {
+    let mut buf = vec![0u8; buffer_size.max(1)];
+    let mut total = 0u64;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        total += n as u64;
+        on_chunk(total);
+    }
+}
+
+/// zstd stage of `write_tar_zst`: one stream, or independent frames plus a seek table
+/// when `seekable_frame_size` is set
+#[cfg(feature = "tar")]
+enum TarEncoder<W: Write> {
+    Stream(zstd::stream::write::Encoder<'static, W>),
+    #[cfg(feature = "seekable")]
+    Seekable(SeekableEncoder<W>),
+}
+
+#[cfg(feature = "tar")]
+impl<W: Write> TarEncoder<W> {
+    fn finish(self) -> Result<W> {
+        match self {
+            TarEncoder::Stream(e) => Ok(e.finish()?),
+            #[cfg(feature = "seekable")]
+            TarEncoder::Seekable(e) => e.finish(),
+        }
+    }
+}
+
+#[cfg(feature = "tar")]
+impl<W: Write> Write for TarEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            TarEncoder::Stream(e) => e.write(buf),
+            #[cfg(feature = "seekable")]
+            TarEncoder::Seekable(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            TarEncoder::Stream(e) => e.flush(),
+            #[cfg(feature = "seekable")]
+            TarEncoder::Seekable(e) => e.flush(),
+        }
+    }
+}
+
+/// Destination of `write_tar_zst`: one file, or volumes when `split_size` is set
+#[cfg(feature = "tar")]
+enum ArchiveSink {
+    File(File),
+    Split(SplitWriter),
+}
+
+#[cfg(feature = "tar")]
+impl ArchiveSink {
+    fn finish(self) -> Result<()> {
+        match self {
+            ArchiveSink::File(_) => Ok(()),
+            ArchiveSink::Split(w) => w.finish().map(|_| ()).context("Failed to close last volume"),
+        }
+    }
+}
+
+#[cfg(feature = "tar")]
+impl Write for ArchiveSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ArchiveSink::File(f) => f.write(buf),
+            ArchiveSink::Split(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ArchiveSink::File(f) => f.flush(),
+            ArchiveSink::Split(w) => w.flush(),
+        }
+    }
+}
+
+/// Reader that reports the running byte count after every read.
+struct ProgressReader<R, F> {
+    inner: R,
+    read: u64,
+    total: u64,
+    progress: F,
+}
+
+impl<R: Read, F: FnMut(u64, u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.read += n as u64;
+            (self.progress)(self.read, self.total);
+        }
+        Ok(n)
+    }
+}
+
+/// Append the contents of `root/rel` in name order, applying `metadata` overrides to files.
+#[cfg(feature = "tar")]
+fn append_tree<W: Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    rel: &Path,
+    metadata: &HashMap<PathBuf, EntryMetadata>,
+) -> Result<()> {
+    let dir = root.join(rel);
+    let mut children = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read dir: {}", dir.display()))?
+        .collect::<io::Result<Vec<_>>>()?;
+    children.sort_by_key(|c| c.file_name());
+
+    for child in children {
+        let child_rel = rel.join(child.file_name());
+        let name = Path::new(".").join(&child_rel);
+        let path = child.path();
+        let file_type = child.file_type()?;
+
+        if file_type.is_dir() {
+            builder
+                .append_dir(&name, &path)
+                .with_context(|| format!("Failed to append dir: {}", path.display()))?;
+            append_tree(builder, root, &child_rel, metadata)?;
+            continue;
+        }
+
+        match metadata.get(&child_rel) {
+            Some(meta) if file_type.is_file() => {
+                let records = meta.pax_records();
+                if !records.is_empty() {
+                    builder.append_pax_extensions(records.iter().map(|(key, value)| (key.as_str(), *value)))?;
+                }
+                let mut file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(&file.metadata()?);
+                meta.apply_to_header(&mut header);
+                builder
+                    .append_data(&mut header, &name, &mut file)
+                    .with_context(|| format!("Failed to append file: {}", path.display()))?;
+            }
+            _ => builder
+                .append_path_with_name(&path, &name)
+                .with_context(|| format!("Failed to append file: {}", path.display()))?,
         }
     }
+
+    Ok(())
+}
+
+/// Open a `.tar.zst` (or plain zstd) stream for reading, loading its embedded
+/// dictionary when it has one.
+pub fn archive_decoder<R: Read>(reader: R) -> Result<zstd::stream::read::Decoder<'static, BufReader<R>>> {
+    ZstdCodec::new(ZstdOptions::default()).make_decoder(BufReader::new(reader))
+}
+
+/// Write `dict` as a skippable frame: magic, payload length (both little-endian), tag, dictionary.
+fn write_dict_frame<W: Write>(writer: &mut W, dict: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(DICT_FRAME_TAG.len() + dict.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "dictionary too large"))?;
+    writer.write_all(&DICT_FRAME_MAGIC.to_le_bytes())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(DICT_FRAME_TAG)?;
+    writer.write_all(dict)
+}
+
+/// Consume and return the dictionary frame at the start of `reader`; anything else is left unread.
+fn read_embedded_dict<R: io::BufRead>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    let header = reader.fill_buf().context("Failed to read zstd stream header")?;
+    if header.len() < 16
+        || header[..4] != DICT_FRAME_MAGIC.to_le_bytes()
+        || &header[8..16] != DICT_FRAME_TAG.as_slice()
+    {
+        return Ok(None);
+    }
+    let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let dict_len = len
+        .checked_sub(DICT_FRAME_TAG.len())
+        .ok_or_else(|| anyhow!("Corrupt dictionary frame"))?;
+    reader.consume(16);
+
+    let mut dict = vec![0u8; dict_len];
+    reader
+        .read_exact(&mut dict)
+        .context("Archive is truncated inside its dictionary frame")?;
+    Ok(Some(dict))
 }
 
 /// Atomic file write helper (best-effort cross-platform).
@@ -360,4 +852,324 @@ mod tests {
 
         assert!(codec.decompress_bytes_limited(&compressed, 1024).is_err());
     }
+
+    #[test]
+    fn large_window_round_trips_and_bad_window_rejected() {
+        let codec = ZstdCodec::new(ZstdOptions {
+            long_distance_matching: true,
+            window_log: Some(28),
+            ..ZstdOptions::default()
+        });
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 253) as u8).collect();
+        let compressed = codec.compress_bytes(&data).unwrap();
+
+        // 2^28 is past zstd's default decoder limit; only a codec asking for it decodes
+        assert_eq!(codec.decompress_bytes(&compressed).unwrap(), data);
+        let plain = ZstdCodec::new(ZstdOptions::default());
+        assert!(plain.decompress_bytes(&compressed).is_err());
+
+        for window_log in [MIN_WINDOW_LOG - 1, MAX_WINDOW_LOG + 1] {
+            let bad = ZstdCodec::new(ZstdOptions {
+                window_log: Some(window_log),
+                ..ZstdOptions::default()
+            });
+            let err = bad.compress_bytes(&data).unwrap_err();
+            assert!(format!("{:#}", err).contains("window_log must be"));
+        }
+    }
+
+    #[test]
+    fn file_progress_reports_running_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.bin");
+        let data: Vec<u8> = (0..10_500u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&input, &data).unwrap();
+        let codec = ZstdCodec::new(ZstdOptions {
+            buffer_size: 1000,
+            ..ZstdOptions::default()
+        });
+
+        let compressed = dir.path().join("in.bin.zst");
+        let calls = std::cell::RefCell::new(Vec::new());
+        codec
+            .compress_file_with_progress(&input, &compressed, |read, total| calls.borrow_mut().push((read, total)))
+            .unwrap();
+        let calls = calls.into_inner();
+        assert_eq!(calls.len(), 11);
+        assert_eq!(calls[0], (1000, Some(10_500)));
+        assert_eq!(*calls.last().unwrap(), (10_500, Some(10_500)));
+
+        let output = dir.path().join("out.bin");
+        let compressed_len = fs::metadata(&compressed).unwrap().len();
+        let last = std::cell::Cell::new((0, None));
+        codec
+            .decompress_file_with_progress(&compressed, &output, |read, total| {
+                assert!(read >= last.get().0);
+                last.set((read, total));
+            })
+            .unwrap();
+        assert_eq!(last.get(), (compressed_len, Some(compressed_len)));
+        assert_eq!(fs::read(&output).unwrap(), data);
+    }
+
+    #[test]
+    fn stream_cb_chunks_by_buffer_size() {
+        let codec = ZstdCodec::new(ZstdOptions {
+            buffer_size: 1000,
+            ..ZstdOptions::default()
+        });
+        let data: Vec<u8> = (0..10_500u32).map(|i| (i % 251) as u8).collect();
+        let compressed = codec.compress_bytes(&data).unwrap();
+
+        let mut out = Vec::new();
+        let mut sizes = Vec::new();
+        let total = codec
+            .decompress_stream_cb(io::Cursor::new(&compressed), |chunk| {
+                sizes.push(chunk.len());
+                out.extend_from_slice(chunk);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(total, data.len() as u64);
+        assert_eq!(out, data);
+        assert_eq!(sizes.len(), 11);
+        assert!(sizes[..10].iter().all(|&n| n == 1000));
+        assert_eq!(sizes[10], 500);
+
+        // A failing callback stops the stream with its own error
+        let mut calls = 0;
+        let err = codec
+            .decompress_stream_cb(io::Cursor::new(&compressed), |_| {
+                calls += 1;
+                Err(anyhow!("consumer full"))
+            })
+            .unwrap_err();
+        assert_eq!(calls, 1);
+        assert_eq!(err.to_string(), "consumer full");
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn dict_archive_extracts_without_external_dict() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        for i in 0..20 {
+            fs::write(src.join(format!("note{}.txt", i)), format!("shopping list {}: milk, eggs, bread", i)).unwrap();
+        }
+
+        // Raw-content dictionary; zstd accepts any bytes as one
+        let dict = b"shopping list : milk, eggs, bread, butter, cheese".repeat(8);
+        let with_dict = ZstdCodec::new(ZstdOptions {
+            dict: Some(dict),
+            ..ZstdOptions::default()
+        });
+        let archive = dir.path().join("notes.tar.zst");
+        with_dict.archive_dir_tar_zst(&src, &archive).unwrap();
+
+        let out = dir.path().join("out");
+        ZstdCodec::new(ZstdOptions::default()).extract_tar_zst(&archive, &out).unwrap();
+        assert_eq!(fs::read_to_string(out.join("note7.txt")).unwrap(), "shopping list 7: milk, eggs, bread");
+
+        let mut decoder = archive_decoder(File::open(&archive).unwrap()).unwrap();
+        let mut tar_bytes = Vec::new();
+        decoder.read_to_end(&mut tar_bytes).unwrap();
+        assert!(!tar_bytes.is_empty());
+    }
+
+    #[test]
+    fn auto_threads_resolve_within_cap() {
+        let n = Threads::Auto.resolve();
+        assert!((1..=MAX_AUTO_THREADS).contains(&n));
+        assert_eq!(Threads::from(4).resolve(), 4);
+    }
+
+    #[test]
+    fn auto_threads_roundtrip_or_clear_error() {
+        let codec = ZstdCodec::new(ZstdOptions {
+            threads: Threads::Auto,
+            ..ZstdOptions::default()
+        });
+        let data = vec![7u8; 1024 * 256];
+        let compressed = codec.compress_bytes(&data);
+
+        #[cfg(feature = "zstdmt")]
+        assert_eq!(codec.decompress_bytes(&compressed.unwrap()).unwrap(), data);
+        #[cfg(not(feature = "zstdmt"))]
+        assert!(format!("{:#}", compressed.unwrap_err()).contains("zstdmt"));
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn extract_reports_byte_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("a.bin"), vec![1u8; 256 * 1024]).unwrap();
+
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let archive = dir.path().join("a.tar.zst");
+        codec.archive_dir_tar_zst(&src, &archive).unwrap();
+
+        let mut calls = Vec::new();
+        codec
+            .extract_tar_zst_with_progress(&archive, dir.path().join("out"), |read, total| calls.push((read, total)))
+            .unwrap();
+
+        let size = fs::metadata(&archive).unwrap().len();
+        assert!(!calls.is_empty());
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(calls.last().copied(), Some((size, size)));
+        assert!(dir.path().join("out").join("a.bin").exists());
+    }
+
+    #[cfg(all(feature = "tar", feature = "seekable"))]
+    #[test]
+    fn seekable_archive_reads_last_entry_only() {
+        use std::cell::Cell;
+        use std::io::{Seek, SeekFrom};
+        use std::rc::Rc;
+
+        struct CountingReader {
+            inner: File,
+            read: Rc<Cell<u64>>,
+        }
+        impl Read for CountingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.inner.read(buf)?;
+                self.read.set(self.read.get() + n as u64);
+                Ok(n)
+            }
+        }
+        impl Seek for CountingReader {
+            fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        // Incompressible, so the frames it fills are as large as the data
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let noise: Vec<u8> = (0..2 * 1024 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        fs::write(src.join("a.bin"), &noise).unwrap();
+        fs::write(src.join("z.txt"), b"last entry").unwrap();
+
+        let codec = ZstdCodec::new(ZstdOptions {
+            seekable_frame_size: Some(64 * 1024),
+            ..ZstdOptions::default()
+        });
+        let archive = dir.path().join("a.tar.zst");
+        // Name order puts z.txt after all of a.bin's frames
+        codec.archive_dir_tar_zst_with_metadata(&src, &archive, &HashMap::new()).unwrap();
+
+        let read = Rc::new(Cell::new(0u64));
+        let mut file = CountingReader {
+            inner: File::open(&archive).unwrap(),
+            read: read.clone(),
+        };
+        assert!(is_seekable(&mut file).unwrap());
+        let decoder = seekable_archive_decoder(file).unwrap();
+        assert!(decoder.frames().len() > 30);
+
+        let mut tar = tar::Archive::new(decoder);
+        let mut last = None;
+        for entry in tar.entries_with_seek().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.path().unwrap().ends_with("z.txt") {
+                let mut text = String::new();
+                entry.read_to_string(&mut text).unwrap();
+                last = Some(text);
+            }
+        }
+        assert_eq!(last.as_deref(), Some("last entry"));
+        let size = fs::metadata(&archive).unwrap().len();
+        assert!(read.get() < size / 4, "read {} of {} bytes", read.get(), size);
+
+        // Plain extraction reads straight through the frames and skips the seek table
+        let out = dir.path().join("out");
+        ZstdCodec::new(ZstdOptions::default()).extract_tar_zst(&archive, &out).unwrap();
+        assert_eq!(fs::read(out.join("a.bin")).unwrap(), noise);
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn extract_rejects_bad_checksum_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("a.txt"), b"checksummed content").unwrap();
+
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let archive = dir.path().join("a.tar.zst");
+        codec.archive_dir_tar_zst(&src, &archive).unwrap();
+        let bytes = fs::read(&archive).unwrap();
+
+        // Last four bytes are the frame's content checksum
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 0xFF;
+        let corrupt_path = dir.path().join("corrupt.tar.zst");
+        fs::write(&corrupt_path, &corrupt).unwrap();
+        assert!(codec.extract_tar_zst(&corrupt_path, dir.path().join("out1")).is_err());
+
+        let truncated_path = dir.path().join("truncated.tar.zst");
+        fs::write(&truncated_path, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(codec.extract_tar_zst(&truncated_path, dir.path().join("out2")).is_err());
+
+        // Without a checksum the archive still extracts
+        let plain = ZstdCodec::new(ZstdOptions {
+            include_checksum: false,
+            ..ZstdOptions::default()
+        });
+        let unchecked = dir.path().join("plain.tar.zst");
+        plain.archive_dir_tar_zst(&src, &unchecked).unwrap();
+        assert!(fs::metadata(&unchecked).unwrap().len() < bytes.len() as u64);
+        plain.extract_tar_zst(&unchecked, dir.path().join("out3")).unwrap();
+        assert!(dir.path().join("out3").join("a.txt").exists());
+    }
+
+    #[cfg(all(feature = "tar", unix))]
+    #[test]
+    fn metadata_overrides_roundtrip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("misc")).unwrap();
+        fs::write(src.join("misc").join("run.sh"), b"#!/bin/sh\n").unwrap();
+        fs::write(src.join("plain.txt"), b"plain").unwrap();
+
+        // Staged copy is 0644; the original it stands in for was 0750
+        fs::set_permissions(src.join("misc").join("run.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+        let original = EntryMetadata::read(&src.join("plain.txt")).unwrap();
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            PathBuf::from("misc/run.sh"),
+            EntryMetadata {
+                mode: 0o750,
+                ..original
+            },
+        );
+
+        let codec = ZstdCodec::new(ZstdOptions {
+            preserve_metadata: true,
+            ..ZstdOptions::default()
+        });
+        let archive = dir.path().join("a.tar.zst");
+        codec.archive_dir_tar_zst_with_metadata(&src, &archive, &metadata).unwrap();
+
+        let out = dir.path().join("out");
+        codec.extract_tar_zst(&archive, &out).unwrap();
+        let mode = fs::metadata(out.join("misc").join("run.sh")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o750);
+        assert_eq!(fs::read(out.join("plain.txt")).unwrap(), b"plain");
+    }
 }
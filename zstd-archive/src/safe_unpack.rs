@@ -0,0 +1,157 @@
+//! Extracting tar entries without letting them escape the destination.
+//!
+//! `tar::Entry::unpack_in` quietly skips entries with `..` components, so a
+//! crafted archive extracts "successfully" with files missing. Here such
+//! entries are an error instead: `..`, absolute paths and drive prefixes are
+//! rejected outright, and the part of the target that already exists is
+//! resolved so a symlink unpacked by an earlier entry can't redirect a later
+//! one outside the root.
+
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Where `entry_path` lands under `root`, or an error if it would leave it.
+pub fn checked_target(root: &Path, entry_path: &Path) -> Result<PathBuf> {
+    let mut target = root.to_path_buf();
+    for component in entry_path.components() {
+        match component {
+            Component::Normal(part) => target.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                bail!("Refusing to extract {}: path leaves the destination", entry_path.display())
+            }
+        }
+    }
+
+    let canonical_root = root
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", root.display()))?;
+    // The entry's own name is replaced on unpack; only its parents are followed
+    let mut existing = target.parent().unwrap_or(root);
+    while !existing.exists() {
+        existing = existing.parent().unwrap_or(root);
+    }
+    let resolved = existing
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", existing.display()))?;
+    if !resolved.starts_with(&canonical_root) {
+        bail!(
+            "Refusing to extract {}: {} leads outside the destination",
+            entry_path.display(),
+            existing.display()
+        );
+    }
+    Ok(target)
+}
+
+/// Unpack `entry` under `root` after checking its path (and a hard link's
+/// source) with `checked_target`; returns where it was written.
+pub fn unpack_in_checked<R: Read>(entry: &mut tar::Entry<'_, R>, root: &Path) -> Result<PathBuf> {
+    let path = entry.path().context("Failed to read tar entry path")?.into_owned();
+    let target = checked_target(root, &path)?;
+    if entry.header().entry_type().is_hard_link() {
+        if let Some(link) = entry.link_name().context("Failed to read tar link name")? {
+            checked_target(root, &link)?;
+        }
+    }
+    entry
+        .unpack_in(root)
+        .with_context(|| format!("Failed to unpack {}", target.display()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ZstdCodec, ZstdOptions};
+    use std::fs;
+
+    /// One tar entry with `name` and `link` written into the header as is,
+    /// bypassing the path checks of `tar::Builder`
+    fn raw_entry(name: &str, kind: tar::EntryType, link: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        let mut out = header.as_bytes().to_vec();
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512, 0);
+        out
+    }
+
+    fn write_tar_zst(path: &Path, entries: &[Vec<u8>]) {
+        let mut tar = entries.concat();
+        tar.extend_from_slice(&[0u8; 1024]);
+        fs::write(path, zstd::encode_all(&tar[..], 3).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn checked_target_rejects_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert_eq!(checked_target(root, Path::new("./a/b.txt")).unwrap(), root.join("a/b.txt"));
+        assert!(checked_target(root, Path::new("../evil.txt")).is_err());
+        assert!(checked_target(root, Path::new("a/../../evil.txt")).is_err());
+        assert!(checked_target(root, Path::new("/etc/passwd")).is_err());
+    }
+
+    #[test]
+    fn traversal_entries_fail_extraction() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let file = tar::EntryType::Regular;
+
+        let archive = dir.path().join("benign.tar.zst");
+        write_tar_zst(&archive, &[raw_entry("ok/fine.txt", file, "", b"fine")]);
+        codec.extract_tar_zst(&archive, dir.path().join("out0")).unwrap();
+        assert_eq!(fs::read(dir.path().join("out0/ok/fine.txt")).unwrap(), b"fine");
+
+        let archive = dir.path().join("dotdot.tar.zst");
+        write_tar_zst(
+            &archive,
+            &[raw_entry("ok.txt", file, "", b"ok"), raw_entry("../outside/evil.txt", file, "", b"evil")],
+        );
+        let err = codec.extract_tar_zst(&archive, dir.path().join("out1")).unwrap_err();
+        assert!(format!("{:#}", err).contains("leaves the destination"), "{:#}", err);
+        assert!(!outside.join("evil.txt").exists());
+
+        let archive = dir.path().join("absolute.tar.zst");
+        let absolute = outside.join("abs.txt");
+        write_tar_zst(&archive, &[raw_entry(absolute.to_str().unwrap(), file, "", b"evil")]);
+        assert!(codec.extract_tar_zst(&archive, dir.path().join("out2")).is_err());
+        assert!(!absolute.exists());
+
+        let archive = dir.path().join("hardlink.tar.zst");
+        write_tar_zst(&archive, &[raw_entry("link", tar::EntryType::Link, "../outside/target", b"")]);
+        assert!(codec.extract_tar_zst(&archive, dir.path().join("out3")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_parent_fails_extraction() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        let archive = dir.path().join("symlink.tar.zst");
+        write_tar_zst(
+            &archive,
+            &[
+                raw_entry("link", tar::EntryType::Symlink, outside.to_str().unwrap(), b""),
+                raw_entry("link/evil.txt", tar::EntryType::Regular, "", b"evil"),
+            ],
+        );
+
+        let err = ZstdCodec::new(ZstdOptions::default())
+            .extract_tar_zst(&archive, dir.path().join("out"))
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("leads outside the destination"), "{:#}", err);
+        assert!(!outside.join("evil.txt").exists());
+    }
+}
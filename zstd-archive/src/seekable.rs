@@ -0,0 +1,451 @@
+//! Zstandard seekable format.
+//!
+//! Input is cut into fixed-size pieces, each compressed as an independent zstd
+//! frame, followed by a seek table stored in a skippable frame:
+//!
+//! ```text
+//! [frame 0][frame 1]...[frame N-1]
+//! [u32 0x184D2A5E][u32 table_size]
+//!   N x ([u32 compressed_size][u32 decompressed_size])
+//!   [u32 N][u8 descriptor = 0][u32 0x8F92EAB1]
+//! ```
+//!
+//! This matches the layout of zstd's `contrib/seekable_format` (without
+//! per-frame checksums), so regular zstd decoders still read the whole stream
+//! and skip the table. `SeekableDecoder::read_at` only decompresses the frames
+//! covering the requested range. The frames may follow a prefix the table
+//! doesn't cover, such as the dictionary frame of a `.tar.zst`.
+
+use anyhow::{anyhow, Context, Result};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+
+use crate::{ZstdCodec, ZstdOptions};
+
+/// Default uncompressed size of each seekable frame (1 MiB).
+pub const DEFAULT_SEEKABLE_FRAME_SIZE: usize = 1024 * 1024;
+
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+const FOOTER_SIZE: u64 = 9;
+const ENTRY_SIZE: u64 = 8;
+
+/// Location of one frame in the compressed and decompressed streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeekFrame {
+    pub compressed_offset: u64,
+    pub compressed_size: u32,
+    pub decompressed_offset: u64,
+    pub decompressed_size: u32,
+}
+
+impl ZstdCodec {
+    /// Compress `reader` into seekable format with frames of `frame_size` uncompressed bytes.
+    /// Returns number of uncompressed bytes read from `reader`.
+    pub fn compress_seekable<R: Read, W: Write>(&self, mut reader: R, writer: W, frame_size: usize) -> Result<u64> {
+        let mut encoder = self.seekable_encoder(writer, frame_size);
+        let total_in = io::copy(&mut reader, &mut encoder).context("Failed to compress seekable input")?;
+        encoder.finish()?;
+        Ok(total_in)
+    }
+
+    /// Seekable counterpart of `compress_reader_to_writer`, with frames of
+    /// `opts.seekable_frame_size` (default `DEFAULT_SEEKABLE_FRAME_SIZE`) uncompressed bytes.
+    /// Returns number of uncompressed bytes read from `reader`.
+    pub fn compress_reader_to_writer_seekable<R: Read, W: Write>(&self, reader: R, writer: W) -> Result<u64> {
+        let frame_size = self.opts.seekable_frame_size.unwrap_or(DEFAULT_SEEKABLE_FRAME_SIZE);
+        self.compress_seekable(reader, writer, frame_size)
+    }
+
+    /// Writer producing seekable format into `writer`; call `finish` to append the seek table.
+    pub fn seekable_encoder<W: Write>(&self, writer: W, frame_size: usize) -> SeekableEncoder<W> {
+        let frame_size = frame_size.clamp(1, u32::MAX as usize);
+        SeekableEncoder {
+            writer,
+            codec: self.clone(),
+            frame_size,
+            buf: Vec::new(),
+            table: Vec::new(),
+        }
+    }
+
+    /// Open a seekable stream for random access (uses this codec's dictionary, if any).
+    pub fn seekable_decoder<R: Read + Seek>(&self, reader: R) -> Result<SeekableDecoder<R>> {
+        SeekableDecoder::with_codec(reader, self.clone())
+    }
+}
+
+/// Buffers input into frames of `frame_size` bytes, compressing each one independently.
+pub struct SeekableEncoder<W: Write> {
+    writer: W,
+    codec: ZstdCodec,
+    frame_size: usize,
+    buf: Vec<u8>,
+    /// (compressed, decompressed) size of every frame written so far
+    table: Vec<(u32, u32)>,
+}
+
+impl<W: Write> SeekableEncoder<W> {
+    /// Write the last partial frame and the seek table, returning the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.write_frame()?;
+
+        let table_size = self.table.len() as u64 * ENTRY_SIZE + FOOTER_SIZE;
+        let w = &mut self.writer;
+        w.write_all(&SKIPPABLE_MAGIC.to_le_bytes())?;
+        w.write_all(&(table_size as u32).to_le_bytes())?;
+        for (compressed, decompressed) in &self.table {
+            w.write_all(&compressed.to_le_bytes())?;
+            w.write_all(&decompressed.to_le_bytes())?;
+        }
+        w.write_all(&(self.table.len() as u32).to_le_bytes())?;
+        w.write_all(&[0u8])?;
+        w.write_all(&SEEKABLE_MAGIC.to_le_bytes())?;
+        w.flush().context("Failed to flush seekable output")?;
+
+        Ok(self.writer)
+    }
+
+    fn write_frame(&mut self) -> Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let mut encoder = self
+            .codec
+            .make_encoder(Vec::with_capacity(self.buf.len() / 2))
+            .context("Failed to create zstd encoder")?;
+        encoder.write_all(&self.buf).context("Failed to compress seekable frame")?;
+        let frame = encoder.finish().context("Failed to finish seekable frame")?;
+        if frame.len() > u32::MAX as usize {
+            return Err(anyhow!("Seekable frame too large ({} bytes)", frame.len()));
+        }
+
+        self.writer.write_all(&frame).context("Failed to write seekable frame")?;
+        self.table.push((frame.len() as u32, self.buf.len() as u32));
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for SeekableEncoder<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(self.frame_size - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == self.frame_size {
+            self.write_frame().map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{:#}", e)))?;
+        }
+        Ok(n)
+    }
+
+    /// Flushes the inner writer only; frames are cut at `frame_size` regardless.
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Random-access reader over a stream written by [`ZstdCodec::compress_seekable`].
+pub struct SeekableDecoder<R: Read + Seek> {
+    inner: R,
+    codec: ZstdCodec,
+    frames: Vec<SeekFrame>,
+    /// Most recently decompressed frame, reused by sequential small reads.
+    cached: Option<(usize, Vec<u8>)>,
+    /// Uncompressed position used by the `Read` and `Seek` impls.
+    pos: u64,
+}
+
+impl<R: Read + Seek> SeekableDecoder<R> {
+    pub fn new(inner: R) -> Result<Self> {
+        Self::with_codec(inner, ZstdCodec::new(crate::ZstdOptions::default()))
+    }
+
+    fn with_codec(mut inner: R, codec: ZstdCodec) -> Result<Self> {
+        let frames = read_seek_table(&mut inner)?;
+        Ok(Self {
+            inner,
+            codec,
+            frames,
+            cached: None,
+            pos: 0,
+        })
+    }
+
+    pub fn frames(&self) -> &[SeekFrame] {
+        &self.frames
+    }
+
+    /// Total uncompressed size of the stream.
+    pub fn decompressed_size(&self) -> u64 {
+        self.frames
+            .last()
+            .map(|f| f.decompressed_offset + f.decompressed_size as u64)
+            .unwrap_or(0)
+    }
+
+    /// Read up to `len` uncompressed bytes starting at `offset`.
+    /// Returns fewer bytes only when the range extends past the end of the stream.
+    pub fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = offset.saturating_add(len as u64).min(self.decompressed_size());
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let mut out = Vec::with_capacity((end - offset) as usize);
+        let mut idx = self.frame_index_for(offset);
+        let mut pos = offset;
+
+        while pos < end {
+            let frame = self.frames[idx];
+            let data = self.frame_data(idx)?;
+            let start_in_frame = (pos - frame.decompressed_offset) as usize;
+            let end_in_frame = ((end - frame.decompressed_offset) as usize).min(data.len());
+            out.extend_from_slice(&data[start_in_frame..end_in_frame]);
+            pos = frame.decompressed_offset + end_in_frame as u64;
+            idx += 1;
+        }
+
+        Ok(out)
+    }
+
+    fn frame_index_for(&self, offset: u64) -> usize {
+        // First frame whose end is beyond `offset`
+        self.frames
+            .partition_point(|f| f.decompressed_offset + f.decompressed_size as u64 <= offset)
+    }
+
+    fn frame_data(&mut self, idx: usize) -> Result<&[u8]> {
+        let cached = matches!(self.cached, Some((i, _)) if i == idx);
+        if !cached {
+            let frame = self.frames[idx];
+            let mut packed = vec![0u8; frame.compressed_size as usize];
+            self.inner
+                .seek(SeekFrom::Start(frame.compressed_offset))
+                .context("Failed to seek to zstd frame")?;
+            self.inner
+                .read_exact(&mut packed)
+                .context("Failed to read zstd frame")?;
+
+            let data = self
+                .codec
+                .decompress_bytes_limited(&packed, frame.decompressed_size as usize)
+                .with_context(|| format!("Failed to decompress seekable frame {}", idx))?;
+            if data.len() != frame.decompressed_size as usize {
+                return Err(anyhow!(
+                    "Seekable frame {} decompressed to {} bytes, expected {}",
+                    idx,
+                    data.len(),
+                    frame.decompressed_size
+                ));
+            }
+            self.cached = Some((idx, data));
+        }
+
+        Ok(self.cached.as_ref().map(|(_, d)| d.as_slice()).unwrap_or(&[]))
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.decompressed_size() {
+            return Ok(0);
+        }
+
+        let idx = self.frame_index_for(self.pos);
+        let start = (self.pos - self.frames[idx].decompressed_offset) as usize;
+        let data = self
+            .frame_data(idx)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:#}", e)))?;
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableDecoder<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(d) => self.decompressed_size().checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        };
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start of stream"))?;
+        Ok(self.pos)
+    }
+}
+
+/// True if `reader` ends with a seek table. Leaves `reader` positioned at the start.
+pub fn is_seekable<R: Read + Seek>(reader: &mut R) -> Result<bool> {
+    let total_len = reader.seek(SeekFrom::End(0)).context("Failed to seek to end")?;
+    let mut magic = [0u8; 4];
+    if total_len >= FOOTER_SIZE + 8 {
+        reader.seek(SeekFrom::End(-4))?;
+        reader.read_exact(&mut magic).context("Failed to read seek table footer")?;
+    }
+    reader.seek(SeekFrom::Start(0))?;
+    Ok(u32::from_le_bytes(magic) == SEEKABLE_MAGIC)
+}
+
+/// Open a `.tar.zst` written with `seekable_frame_size` for random access, loading its
+/// embedded dictionary when it has one.
+pub fn seekable_archive_decoder<R: Read + Seek>(mut reader: R) -> Result<SeekableDecoder<R>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let dict = crate::read_embedded_dict(&mut BufReader::new(&mut reader))?;
+    let codec = ZstdCodec::new(ZstdOptions {
+        dict,
+        ..ZstdOptions::default()
+    });
+    SeekableDecoder::with_codec(reader, codec)
+}
+
+fn read_seek_table<R: Read + Seek>(reader: &mut R) -> Result<Vec<SeekFrame>> {
+    let total_len = reader.seek(SeekFrom::End(0)).context("Failed to seek to end")?;
+    if total_len < FOOTER_SIZE + 8 {
+        return Err(anyhow!("Stream too short to contain a seek table"));
+    }
+
+    let mut footer = [0u8; FOOTER_SIZE as usize];
+    reader.seek(SeekFrom::Start(total_len - FOOTER_SIZE))?;
+    reader.read_exact(&mut footer).context("Failed to read seek table footer")?;
+
+    let num_frames = u32::from_le_bytes(footer[0..4].try_into().unwrap()) as u64;
+    let descriptor = footer[4];
+    let magic = u32::from_le_bytes(footer[5..9].try_into().unwrap());
+    if magic != SEEKABLE_MAGIC {
+        return Err(anyhow!("Not a seekable zstd stream (missing seek table)"));
+    }
+    let entry_size = if descriptor & 0x80 != 0 { ENTRY_SIZE + 4 } else { ENTRY_SIZE };
+
+    let table_size = num_frames * entry_size + FOOTER_SIZE;
+    let frame_start = total_len
+        .checked_sub(table_size + 8)
+        .ok_or_else(|| anyhow!("Seek table larger than stream"))?;
+
+    let mut header = [0u8; 8];
+    reader.seek(SeekFrom::Start(frame_start))?;
+    reader.read_exact(&mut header)?;
+    if u32::from_le_bytes(header[0..4].try_into().unwrap()) != SKIPPABLE_MAGIC
+        || u32::from_le_bytes(header[4..8].try_into().unwrap()) as u64 != table_size
+    {
+        return Err(anyhow!("Corrupt seek table header"));
+    }
+
+    let mut entries = vec![0u8; (num_frames * entry_size) as usize];
+    reader.read_exact(&mut entries).context("Failed to read seek table")?;
+
+    let sizes: Vec<(u32, u32)> = entries
+        .chunks_exact(entry_size as usize)
+        .map(|entry| {
+            (
+                u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+            )
+        })
+        .collect();
+
+    // Frames end where the table starts; anything before the first one is a prefix
+    let data_len: u64 = sizes.iter().map(|&(compressed, _)| compressed as u64).sum();
+    let mut compressed_offset = frame_start
+        .checked_sub(data_len)
+        .ok_or_else(|| anyhow!("Seek table does not match stream length"))?;
+
+    let mut frames = Vec::with_capacity(sizes.len());
+    let mut decompressed_offset = 0u64;
+    for (compressed_size, decompressed_size) in sizes {
+        frames.push(SeekFrame {
+            compressed_offset,
+            compressed_size,
+            decompressed_offset,
+            decompressed_size,
+        });
+        compressed_offset += compressed_size as u64;
+        decompressed_offset += decompressed_size as u64;
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZstdOptions;
+    use std::io::Cursor;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).flat_map(|i| format!("line {}\n", i).into_bytes()).take(len).collect()
+    }
+
+    #[test]
+    fn seekable_roundtrip_and_random_access() {
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let data = sample(300_000);
+
+        let mut compressed = Vec::new();
+        let n = codec
+            .compress_seekable(Cursor::new(&data), &mut compressed, 64 * 1024)
+            .unwrap();
+        assert_eq!(n, data.len() as u64);
+
+        // Plain decoders read through the frames and skip the seek table
+        assert_eq!(codec.decompress_bytes(&compressed).unwrap(), data);
+
+        let mut dec = codec.seekable_decoder(Cursor::new(compressed)).unwrap();
+        assert_eq!(dec.frames().len(), 5);
+        assert_eq!(dec.decompressed_size(), data.len() as u64);
+
+        // Tail read, a read spanning a frame boundary, and a read past the end
+        assert_eq!(dec.read_at(299_000, 1000).unwrap(), &data[299_000..]);
+        assert_eq!(dec.read_at(65_000, 2_000).unwrap(), &data[65_000..67_000]);
+        assert_eq!(dec.read_at(299_990, 100).unwrap(), &data[299_990..]);
+        assert!(dec.read_at(400_000, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn seekable_empty_input() {
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let mut compressed = Vec::new();
+        codec
+            .compress_seekable(Cursor::new(Vec::new()), &mut compressed, 1024)
+            .unwrap();
+
+        let mut dec = SeekableDecoder::new(Cursor::new(compressed)).unwrap();
+        assert_eq!(dec.decompressed_size(), 0);
+        assert!(dec.read_at(0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_plain_stream() {
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let compressed = codec.compress_bytes(b"not seekable").unwrap();
+        assert!(!is_seekable(&mut Cursor::new(&compressed)).unwrap());
+        assert!(SeekableDecoder::new(Cursor::new(compressed)).is_err());
+    }
+
+    #[test]
+    fn read_and_seek_across_frames() {
+        let codec = ZstdCodec::new(ZstdOptions::default());
+        let data = sample(10_000);
+
+        // Written in uneven pieces; frames are still cut every 1000 bytes
+        let mut encoder = codec.seekable_encoder(Vec::new(), 1000);
+        for piece in data.chunks(777) {
+            encoder.write_all(piece).unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+        assert!(is_seekable(&mut Cursor::new(&compressed)).unwrap());
+
+        let mut dec = codec.seekable_decoder(Cursor::new(compressed)).unwrap();
+        assert_eq!(dec.frames().len(), 10);
+        assert_eq!(dec.seek(SeekFrom::End(-1500)).unwrap(), 8500);
+        let mut tail = Vec::new();
+        dec.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, &data[8500..]);
+
+        dec.seek(SeekFrom::Start(990)).unwrap();
+        dec.seek(SeekFrom::Current(-90)).unwrap();
+        let mut middle = [0u8; 200];
+        dec.read_exact(&mut middle).unwrap();
+        assert_eq!(&middle[..], &data[900..1100]);
+        assert!(dec.seek(SeekFrom::Current(-2000)).is_err());
+    }
+}
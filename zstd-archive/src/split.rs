@@ -0,0 +1,272 @@
+//! Multi-volume archives.
+//!
+//! A split archive is the ordinary byte stream cut into numbered parts next to
+//! the name it would otherwise have: `photos.tar.zst.001`, `photos.tar.zst.002`,
+//! ... Every part but the last holds exactly the volume size. The parts can be
+//! joined with `cat` (or `copy /b`) into the single-file archive, and
+//! `SplitReader` reads them back as one (seekable) stream without doing so.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Path of volume `n` (1-based) of the archive named `base`: `base.001`, `base.002`, ...
+pub fn volume_path(base: &Path, n: u32) -> PathBuf {
+    let mut name = base.as_os_str().to_os_string();
+    name.push(format!(".{:03}", n));
+    PathBuf::from(name)
+}
+
+/// Existing volumes of `base`, in order; empty when `base` wasn't split
+pub fn volume_paths(base: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|n| volume_path(base, n))
+        .take_while(|p| p.is_file())
+        .collect()
+}
+
+/// The archive name behind `path`: `photos.tar.zst.001` gives `photos.tar.zst`,
+/// anything else is returned as-is.
+pub fn split_base(path: &Path) -> PathBuf {
+    match path.to_string_lossy().strip_suffix(".001") {
+        Some(base) => PathBuf::from(base),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Whether `path` names an archive, either as one file or as `path.001`, `path.002`, ...
+pub fn archive_exists(path: &Path) -> bool {
+    path.is_file() || volume_path(&split_base(path), 1).is_file()
+}
+
+/// Writer spreading its output over volumes of at most `volume_size` bytes.
+///
+/// Volumes are created as data arrives. Call `finish` to flush the last one
+/// and learn which files were written.
+pub struct SplitWriter {
+    base: PathBuf,
+    volume_size: u64,
+    current: Option<File>,
+    written_in_current: u64,
+    volumes: Vec<PathBuf>,
+}
+
+impl SplitWriter {
+    /// Start writing `base.001`; stale volumes of an earlier, longer split are removed.
+    pub fn create(base: &Path, volume_size: u64) -> io::Result<Self> {
+        if volume_size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "volume size must be at least 1 byte"));
+        }
+        for stale in volume_paths(base) {
+            fs::remove_file(stale)?;
+        }
+        let mut writer = Self {
+            base: base.to_path_buf(),
+            volume_size,
+            current: None,
+            written_in_current: 0,
+            volumes: Vec::new(),
+        };
+        // An empty stream still leaves one (empty) volume behind
+        writer.next_volume()?;
+        Ok(writer)
+    }
+
+    fn next_volume(&mut self) -> io::Result<()> {
+        if let Some(mut done) = self.current.take() {
+            done.flush()?;
+        }
+        let path = volume_path(&self.base, self.volumes.len() as u32 + 1);
+        self.current = Some(File::create(&path)?);
+        self.volumes.push(path);
+        self.written_in_current = 0;
+        Ok(())
+    }
+
+    /// Flush and close the last volume, returning every volume written in order.
+    pub fn finish(mut self) -> io::Result<Vec<PathBuf>> {
+        if let Some(mut last) = self.current.take() {
+            last.flush()?;
+            last.sync_all()?;
+        }
+        Ok(std::mem::take(&mut self.volumes))
+    }
+}
+
+impl Write for SplitWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.written_in_current >= self.volume_size {
+            self.next_volume()?;
+        }
+        let room = (self.volume_size - self.written_in_current).min(buf.len() as u64) as usize;
+        let n = self.current.as_mut().expect("volume open while writing").write(&buf[..room])?;
+        self.written_in_current += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some(f) => f.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reader over an archive that may be split into volumes.
+pub struct SplitReader {
+    volumes: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    next: usize,
+    current: Option<File>,
+    pos: u64,
+    len: u64,
+}
+
+impl SplitReader {
+    /// Open `path` as a single file if it exists, otherwise as the volumes
+    /// `path.001`, `path.002`, ...; `path` may also name the first volume itself.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let volumes = if path.is_file() && split_base(path) == path {
+            vec![path.to_path_buf()]
+        } else {
+            let volumes = volume_paths(&split_base(path));
+            if volumes.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{}: no such archive or volumes", path.display()),
+                ));
+            }
+            volumes
+        };
+        let sizes = volumes
+            .iter()
+            .map(|v| fs::metadata(v).map(|m| m.len()))
+            .collect::<io::Result<Vec<_>>>()?;
+        let len = sizes.iter().sum();
+        Ok(Self {
+            volumes,
+            sizes,
+            next: 0,
+            current: None,
+            pos: 0,
+            len,
+        })
+    }
+
+    /// Combined size of all volumes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Files being read, in order
+    pub fn volumes(&self) -> &[PathBuf] {
+        &self.volumes
+    }
+}
+
+impl Read for SplitReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.current.is_none() {
+                let Some(path) = self.volumes.get(self.next) else {
+                    return Ok(0);
+                };
+                self.current = Some(File::open(path)?);
+                self.next += 1;
+            }
+            let n = self.current.as_mut().expect("volume open while reading").read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            self.current = None;
+        }
+    }
+}
+
+impl Seek for SplitReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(d) => self.len.checked_add_signed(d),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start of archive"))?;
+
+        // Open the volume holding `target`; past the end, reads return EOF
+        self.current = None;
+        self.next = self.volumes.len();
+        let mut start = 0;
+        for (i, size) in self.sizes.iter().enumerate() {
+            if target < start + size {
+                let mut file = File::open(&self.volumes[i])?;
+                file.seek(SeekFrom::Start(target - start))?;
+                self.current = Some(file);
+                self.next = i + 1;
+                break;
+            }
+            start += size;
+        }
+        self.pos = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_rejoin() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("data.tar.zst");
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = SplitWriter::create(&base, 4096).unwrap();
+        writer.write_all(&data).unwrap();
+        let volumes = writer.finish().unwrap();
+        assert_eq!(volumes, vec![volume_path(&base, 1), volume_path(&base, 2), volume_path(&base, 3)]);
+        assert_eq!(fs::metadata(&volumes[0]).unwrap().len(), 4096);
+        assert_eq!(fs::metadata(&volumes[2]).unwrap().len(), 10_000 - 2 * 4096);
+        assert!(!base.exists());
+
+        // Opened by base name or by first volume
+        for path in [&base, &volumes[0]] {
+            let mut reader = SplitReader::open(path).unwrap();
+            assert_eq!(reader.len(), data.len() as u64);
+            let mut back = Vec::new();
+            reader.read_to_end(&mut back).unwrap();
+            assert_eq!(back, data);
+        }
+        assert!(archive_exists(&base));
+
+        // Seeks land in the right volume, including across a boundary
+        let mut reader = SplitReader::open(&base).unwrap();
+        let mut buf = [0u8; 100];
+        reader.seek(SeekFrom::Start(4050)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[4050..4150]);
+        assert_eq!(reader.seek(SeekFrom::End(-100)).unwrap(), 9900);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[9900..]);
+        assert_eq!(reader.seek(SeekFrom::Current(-5000)).unwrap(), 5000);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[5000..5100]);
+
+        // A shorter re-split leaves no stale third volume behind
+        let mut writer = SplitWriter::create(&base, 8192).unwrap();
+        writer.write_all(&data[..5000]).unwrap();
+        assert_eq!(writer.finish().unwrap().len(), 1);
+        assert_eq!(volume_paths(&base).len(), 1);
+    }
+}